@@ -0,0 +1,75 @@
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use buck::databases::{self, DEFAULT_DATABASES};
+use buck::engine::BuckDB;
+use buck::server::{serve, DEFAULT_ADDR};
+
+fn exit_with_error(message: String) -> ! {
+    eprintln!("[ERROR] {}", message);
+    std::process::exit(1);
+}
+
+fn main() {
+    // buck-server [addr] [--aof <path>] [--dir <path>] [--databases <count>] [--maxmemory <bytes>] [--maxmemory-policy <policy>]
+    //             [--key-max-length <bytes>] [--key-charset <chars>] [--key-prefix <prefix>]
+    let mut addr = DEFAULT_ADDR.to_owned();
+    let mut aof_path = None;
+    let mut dir = None;
+    let mut count = DEFAULT_DATABASES;
+    let mut config = Vec::new();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--aof" => match args.next() {
+                Some(path) => aof_path = Some(path),
+                None => exit_with_error("--aof requires a path".to_owned()),
+            },
+            "--dir" => match args.next() {
+                Some(path) => dir = Some(PathBuf::from(path)),
+                None => exit_with_error("--dir requires a path".to_owned()),
+            },
+            "--databases" => match args.next().and_then(|n| n.parse::<usize>().ok()) {
+                Some(n) if n > 0 => count = n,
+                _ => exit_with_error("--databases requires a positive count".to_owned()),
+            },
+            "--maxmemory" | "--maxmemory-policy" | "--key-max-length" | "--key-charset" | "--key-prefix" => match args.next() {
+                Some(value) => config.push((arg[2..].to_owned(), value)),
+                None => exit_with_error(format!("{} requires a value", arg)),
+            },
+            _ => addr = arg,
+        }
+    }
+
+    let mut databases = databases::new(count);
+
+    // only the first database is backed by the log
+    if let Some(path) = aof_path {
+        databases[0] = BuckDB::open(&path)
+            .unwrap_or_else(|e| exit_with_error(format!("Failed to open {}: {}", path, e)));
+        databases::share_memory(&mut databases);
+    }
+
+    // `SAVE` and `LOAD` can only reach files inside it
+    if let Some(dir) = &dir {
+        for db in databases.iter_mut() {
+            db.dir = dir.clone();
+        }
+    }
+
+    for (name, value) in &config {
+        databases::config_set(&mut databases, name, value)
+            .unwrap_or_else(|e| exit_with_error(e.to_string()));
+    }
+
+    let listener = TcpListener::bind(&addr)
+        .unwrap_or_else(|e| exit_with_error(format!("Failed to bind {}: {}", addr, e)));
+
+    println!("buck-server listening on {}", addr);
+
+    if let Err(e) = serve(listener, Arc::new(Mutex::new(databases))) {
+        exit_with_error(format!("Server stopped: {}", e));
+    }
+}
@@ -0,0 +1,155 @@
+//! blocking.rs
+//!
+//! Bookkeeping for clients blocked by `BLPOP`, `BRPOP` and `BLMOVE`.
+//!
+//! A client that finds every list empty registers a `BlockedPop` and waits.
+//! Each key keeps the clients blocked on it in FIFO order. When a write
+//! leaves a list behind for a key somebody waits on, the key is marked as
+//! ready, and `BuckDB::serve_blocked` hands its elements to the waiters in
+//! the order they blocked. The popped value is left in the waiter's mailbox
+//! and the waiting threads are woken through `wakeup`.
+
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::sync::{Arc, Condvar};
+
+use crate::errors::BuckEngineError;
+use crate::reply::BuckReply;
+use crate::types::list::ListSide;
+use crate::types::types::BuckTypes;
+
+/// What a blocked client is waiting to do once one of its keys has an element.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockedPop {
+    pub keys: Vec<String>,
+    /// The end of the list to pop from.
+    pub side: ListSide,
+    /// For `BLMOVE`, the list to push the element to, and at which end.
+    pub target: Option<(String, ListSide)>,
+}
+
+impl BlockedPop {
+    /// The reply for an element popped from `key`.
+    ///
+    /// `BLPOP` and `BRPOP` reply with the key and the element, `BLMOVE` only
+    /// with the element.
+    pub fn reply(&self, key: String, value: &BuckTypes) -> BuckReply {
+        match self.target {
+            Some(_) => BuckReply::from(value),
+            None => BuckReply::Array(vec![BuckReply::Bulk(key), BuckReply::from(value)]),
+        }
+    }
+}
+
+/// Result handed to a served client: the key it popped from and the element.
+pub type Served = Result<(String, BuckTypes), BuckEngineError>;
+
+#[derive(Debug, Clone, Default)]
+pub struct BlockedClients {
+    next_id: u64,
+    pops: HashMap<u64, BlockedPop>,
+    /// Blocked clients of every key, first come first served.
+    queues: HashMap<String, VecDeque<u64>>,
+    /// Keys that were written while somebody waits on them.
+    ready: BTreeSet<String>,
+    served: HashMap<u64, Served>,
+    wakeup: Arc<Condvar>,
+}
+
+impl BlockedClients {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Register a blocked client and return its id.
+    pub fn block(&mut self, pop: BlockedPop) -> u64 {
+        self.next_id += 1;
+        let id = self.next_id;
+
+        for key in &pop.keys {
+            let queue = self.queues.entry(key.clone()).or_default();
+            if !queue.contains(&id) {
+                queue.push_back(id);
+            }
+        }
+        self.pops.insert(id, pop);
+
+        id
+    }
+
+    /// Forget a blocked client, along with a result nobody picked up.
+    pub fn unblock(&mut self, id: u64) {
+        if let Some(pop) = self.pops.remove(&id) {
+            self.dequeue(id, &pop.keys);
+        }
+        self.served.remove(&id);
+    }
+
+    /// Take the result of a served client.
+    ///
+    /// ## Returns
+    ///
+    /// `None` while the client is still waiting.
+    pub fn take_served(&mut self, id: u64) -> Option<Served> {
+        self.served.remove(&id)
+    }
+
+    /// The condition variable blocked clients wait on.
+    ///
+    /// It must be used with the mutex that guards the database.
+    pub fn wakeup(&self) -> Arc<Condvar> {
+        Arc::clone(&self.wakeup)
+    }
+
+    pub fn is_blocked(&self, id: u64) -> bool {
+        self.pops.contains_key(&id)
+    }
+
+    /// Number of clients blocked on `key`.
+    pub fn waiting_on(&self, key: &str) -> usize {
+        self.queues.get(key).map_or(0, VecDeque::len)
+    }
+
+    /// Mark `key` as ready if somebody is blocked on it.
+    pub(crate) fn signal(&mut self, key: &str) {
+        if self.queues.contains_key(key) {
+            self.ready.insert(key.to_owned());
+        }
+    }
+
+    /// Mark every key somebody is blocked on as ready.
+    pub(crate) fn signal_all(&mut self) {
+        self.ready.extend(self.queues.keys().cloned());
+    }
+
+    pub(crate) fn next_ready(&mut self) -> Option<String> {
+        self.ready.pop_first()
+    }
+
+    /// The client that has been blocked on `key` the longest.
+    pub(crate) fn first(&self, key: &str) -> Option<(u64, &BlockedPop)> {
+        let id = *self.queues.get(key)?.front()?;
+
+        self.pops.get(&id).map(|pop| (id, pop))
+    }
+
+    /// Hand `result` to a blocked client and wake the waiting threads.
+    pub(crate) fn serve(&mut self, id: u64, result: Served) {
+        if let Some(pop) = self.pops.remove(&id) {
+            self.dequeue(id, &pop.keys);
+            self.served.insert(id, result);
+            self.wakeup.notify_all();
+        }
+    }
+
+    fn dequeue(&mut self, id: u64, keys: &[String]) {
+        for key in keys {
+            if let Some(queue) = self.queues.get_mut(key) {
+                queue.retain(|waiter| *waiter != id);
+
+                if queue.is_empty() {
+                    self.queues.remove(key);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,68 @@
+//! clock.rs
+//!
+//! Time source used by BuckDB for key expiration.
+//!
+//! The engine never reads the system time directly. It asks its `Clock`
+//! instead, so tests can swap in a `ManualClock` and move time forward
+//! deterministically.
+
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub trait Clock: fmt::Debug + Send + Sync {
+    /// Milliseconds since the Unix epoch.
+    fn now_millis(&self) -> u64;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_millis(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0)
+    }
+}
+
+/// A clock that only moves when told to. Clones share the same time.
+#[derive(Debug, Clone, Default)]
+pub struct ManualClock {
+    now: Arc<AtomicU64>,
+}
+
+impl ManualClock {
+    pub fn new(now_millis: u64) -> Self {
+        ManualClock {
+            now: Arc::new(AtomicU64::new(now_millis)),
+        }
+    }
+
+    pub fn advance(&self, millis: u64) {
+        self.now.fetch_add(millis, Ordering::SeqCst);
+    }
+
+    pub fn set(&self, now_millis: u64) {
+        self.now.store(now_millis, Ordering::SeqCst);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now_millis(&self) -> u64 {
+        self.now.load(Ordering::SeqCst)
+    }
+}
+
+/// When a key should expire, as given to `GETEX` or `SET`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expiry {
+    /// Milliseconds from now.
+    After(u64),
+    /// Milliseconds since the Unix epoch.
+    At(u64),
+    /// Never, the key loses its timeout.
+    Persist,
+}
@@ -0,0 +1,156 @@
+//! databases.rs
+//!
+//! Several independent databases in one process, for `SELECT`, `MOVE`,
+//! `SWAPDB` and `FLUSHALL`.
+//!
+//! Every database is a whole `BuckDB`, with its own committed data, staged
+//! writes and transaction state. A client picks one by its index with
+//! `SELECT` and every other command only sees that one, see `BuckSession`.
+//!
+//! The databases are passed around as a slice, so a lone `BuckDB` is simply
+//! a set of one database.
+//!
+//! The configuration is the same for every database: `CONFIG SET` changes
+//! all of them, and the `maxmemory` budget covers their keys together. It
+//! stays with the index across `SWAPDB`, like the append-only log.
+
+use crate::engine::BuckDB;
+use crate::errors::BuckEngineError;
+use crate::log::BuckLog;
+
+/// The number of databases a server holds unless told otherwise.
+pub const DEFAULT_DATABASES: usize = 16;
+
+/// `count` empty databases that share one memory budget.
+pub fn new(count: usize) -> Vec<BuckDB> {
+    let mut databases: Vec<BuckDB> = (0..count).map(|_| BuckDB::new()).collect();
+    share_memory(&mut databases);
+
+    databases
+}
+
+/// Count the keys of every database against the memory budget of the
+/// first one. Call it again after replacing a database.
+pub fn share_memory(databases: &mut [BuckDB]) {
+    if let Some((first, rest)) = databases.split_first_mut() {
+        for db in rest {
+            db.memory.share_budget(&first.memory);
+        }
+    }
+}
+
+/// Change a configuration parameter of every database, see
+/// `BuckDB::config_set`.
+///
+/// ## Syntax
+///
+/// >>> `CONFIG SET parameter value`
+pub fn config_set(databases: &mut [BuckDB], name: &str, value: &str) -> Result<BuckLog, BuckEngineError> {
+    // every database takes the same values, so only the first can refuse one
+    for db in databases.iter_mut() {
+        db.config_set(name, value)?;
+    }
+
+    Ok(BuckLog::ConfigSetOk)
+}
+
+/// The database at `index`.
+pub fn database(databases: &mut [BuckDB], index: usize) -> Result<&mut BuckDB, BuckEngineError> {
+    databases
+        .get_mut(index)
+        .ok_or(BuckEngineError::InvalidDatabase(index))
+}
+
+/// Move `key` from the database at `from` to the one at `to`, along with
+/// its timeout.
+///
+/// The key is staged out of one database and into the other, and each
+/// half takes effect when its own database commits.
+///
+/// ## Syntax
+///
+/// >>> `MOVE key db`
+///
+/// ## Returns
+///
+/// Whether the key was moved. Nothing is moved if the key does not exist,
+/// if it already exists in the destination, or if both are the same
+/// database.
+pub fn move_key(databases: &mut [BuckDB], key: &str, from: usize, to: usize) -> Result<bool, BuckEngineError> {
+    database(databases, from)?;
+    database(databases, to)?;
+
+    let [source, destination] = match databases.get_disjoint_mut([from, to]) {
+        Ok(pair) => pair,
+        // the same database
+        Err(_) => return Ok(false),
+    };
+
+    source.expire_if_needed(key);
+    destination.expire_if_needed(key);
+
+    let value = match source.get(key) {
+        Ok(value) => value.clone(),
+        Err(BuckEngineError::KeyNotFound(_)) => return Ok(false),
+        Err(e) => return Err(e),
+    };
+
+    match destination.get(key) {
+        Ok(_) => return Ok(false),
+        Err(BuckEngineError::KeyNotFound(_)) => {}
+        Err(e) => return Err(e),
+    }
+
+    // write the copy first, so that a failure leaves the key where it was
+    let deadline = source.expires.get(key).copied();
+    destination.insert(key.to_owned(), value)?;
+    destination.set_deadline(key, deadline);
+    source.remove(key)?;
+
+    Ok(true)
+}
+
+/// Swap the databases at `a` and `b`.
+///
+/// Clients connected to one database see the data of the other right
+/// away, staged writes included. Watched keys of both count as modified.
+/// An append-only log stays with its index and is rewritten with the
+/// committed data that moves in, so a restart finds the data where it was
+/// swapped to. If a log can not be rewritten, neither is and nothing is
+/// swapped.
+///
+/// ## Syntax
+///
+/// >>> `SWAPDB index1 index2`
+pub fn swap(databases: &mut [BuckDB], a: usize, b: usize) -> Result<(), BuckEngineError> {
+    database(databases, a)?;
+    database(databases, b)?;
+
+    if let Ok([first, second]) = databases.get_disjoint_mut([a, b]) {
+        first.rewrite_log_with(second)?;
+
+        if let Err(e) = second.rewrite_log_with(first) {
+            // put the data the first log held back, it is not swapped after all
+            let _ = first.rewrite_log_with(first);
+            return Err(e);
+        }
+
+        std::mem::swap(first, second);
+        first.swap_clients(second);
+    }
+
+    Ok(())
+}
+
+/// Remove every key of every database, see `BuckDB::flush_db`.
+///
+/// ## Syntax
+///
+/// >>> `FLUSHALL`
+pub fn flush_all(databases: &mut [BuckDB]) -> Result<(), BuckEngineError> {
+    for db in databases {
+        db.flush_db()?;
+    }
+
+    Ok(())
+}
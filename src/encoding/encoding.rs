@@ -1,6 +1,7 @@
 use std::collections::HashSet;
 
-use crate::types::{types::BuckTypes, sets::{BuckSets, Setable, EqFloat}};
+use crate::types::{hash::BuckHash, list::BuckList, types::BuckTypes, sets::{BuckSets, Setable, EqFloat}};
+use crate::types::sorted_set::BuckSortedSet;
 
 use super::errors::EncodingError;
 
@@ -67,10 +68,10 @@ pub fn takes_bytes(bytes: &mut &[u8]) -> Result<Vec<u8>, EncodingError> {
                 Some((i, 0x00)) => break i + 1,    // 0x00 0x00 -> terminator
                 Some((_, 0xff)) => decoded.push(0x00),     // 0x00 0xff is escape sequence for 0x00
                 Some((_, b)) => Err(EncodingError::UnexpectedEndOf(format!("Invalid byte escape {}", b)))?,
-                None => Err(EncodingError::UnexpectedEndOf(format!{"Unexpected end of bytes"}))?,
+                None => Err(EncodingError::UnexpectedEndOf("Unexpected end of bytes".to_owned()))?,
             }
             Some(b) => decoded.push(*b),
-            None => Err(EncodingError::UnexpectedEndOf(format!{"Unexpected end of bytes"}))?,
+            None => Err(EncodingError::UnexpectedEndOf("Unexpected end of bytes".to_owned()))?,
         }
     };
 
@@ -153,90 +154,333 @@ pub fn encode_length(len: usize) -> [u8; 4] {
     (len as u32).to_be_bytes()
 }
 
-pub fn encode_set(set: &BuckSets) -> Vec<u8> {
-    let mut encoded = Vec::new();
+pub fn take_length(bytes: &mut &[u8]) -> Result<usize, EncodingError> {
+    if bytes.len() < 4 {
+        return Err(EncodingError::InternalError(format!("Unable to decode length from {} bytes", bytes.len())));
+    }
 
-    // encode the length of the set
-    encoded.extend(encode_length(set.data.len()));
+    let len = u32::from_be_bytes(bytes[..4].try_into().unwrap()) as usize;
+    *bytes = &bytes[4..];
 
-    // encode each item in the set
-    for item in &set.data {
-        match item {
-            Setable::String(s) => {
-                encoded.push(0x01);
-                encoded.extend(encode_string(s));
-            }
-            Setable::Boolean(b) => {
-                encoded.push(0x02);
-                encoded.push(encode_boolean(*b));
-            }
-            Setable::Integer(i) => {
-                encoded.push(0x03);
-                encoded.extend(encode_integer(*i));
-            }
-            Setable::Float(f) => {
-                encoded.push(0x04);
-                encoded.extend(encode_float(f.0));
-            }
-            Setable::Empty => {
-                encoded.push(0x05);
-            }
+    Ok(len)
+}
+
+/// Version of the value format. It is written once in front of every value
+/// produced by `encode_type`, `encode_set`, `encode_list`, `encode_hash` and
+/// `encode_sorted_set`, and their decoders refuse values written with any
+/// other version.
+pub const FORMAT_VERSION: u8 = 0x01;
+
+/// Type tags shared by every encoder and decoder in this module,
+/// for both `BuckTypes` values and `Setable` set members.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum TypeTag {
+    Boolean = 0x01,
+    Float = 0x02,
+    Integer = 0x03,
+    String = 0x04,
+    Sets = 0x05,
+    List = 0x06,
+    Hash = 0x07,
+    Unknown = 0x08,
+    Empty = 0x09,
+    SortedSet = 0x0a,
+}
+
+impl TypeTag {
+    pub fn from_byte(byte: u8) -> Result<Self, EncodingError> {
+        match byte {
+            0x01 => Ok(TypeTag::Boolean),
+            0x02 => Ok(TypeTag::Float),
+            0x03 => Ok(TypeTag::Integer),
+            0x04 => Ok(TypeTag::String),
+            0x05 => Ok(TypeTag::Sets),
+            0x06 => Ok(TypeTag::List),
+            0x07 => Ok(TypeTag::Hash),
+            0x08 => Ok(TypeTag::Unknown),
+            0x09 => Ok(TypeTag::Empty),
+            0x0a => Ok(TypeTag::SortedSet),
+            _ => Err(EncodingError::InvalidTag(byte)),
         }
     }
+}
+
+fn take_tag(bytes: &mut &[u8]) -> Result<TypeTag, EncodingError> {
+    take_byte(bytes)
+        .ok_or_else(|| EncodingError::UnexpectedEndOf("Unexpected end of bytes".to_owned()))
+        .and_then(TypeTag::from_byte)
+}
+
+/// Takes the format version byte and checks that it is one this module can decode.
+pub fn take_version(bytes: &mut &[u8]) -> Result<(), EncodingError> {
+    match take_byte(bytes) {
+        Some(FORMAT_VERSION) => Ok(()),
+        Some(version) => Err(EncodingError::UnsupportedVersion(version)),
+        None => Err(EncodingError::UnexpectedEndOf("Unexpected end of bytes".to_owned())),
+    }
+}
+
+/// Encodes a set as its length followed by each tagged member.
+/// Members are written in the order of their encoding, so equal sets always encode to the same bytes.
+pub fn encode_set(set: &BuckSets) -> Vec<u8> {
+    let mut encoded = vec![FORMAT_VERSION];
+    write_set(&mut encoded, set);
 
     encoded
 }
 
 pub fn take_set(bytes: &mut &[u8]) -> Result<BuckSets, EncodingError> {
-    if bytes.len() < 4 {
-        return Err(EncodingError::InternalError(format!("Unable to decode set from {} bytes", bytes.len())));
+    take_version(bytes)?;
+    read_set(bytes)
+}
+
+/// Encodes a list as its length followed by each item, which may itself be any type.
+pub fn encode_list(list: &BuckList) -> Vec<u8> {
+    let mut encoded = vec![FORMAT_VERSION];
+    write_list(&mut encoded, list);
+
+    encoded
+}
+
+pub fn take_list(bytes: &mut &[u8]) -> Result<BuckList, EncodingError> {
+    take_version(bytes)?;
+    read_list(bytes)
+}
+
+/// Encodes a sorted set as its length followed by each member and its score.
+/// Members are written in score order, so equal sorted sets always encode to the same bytes.
+pub fn encode_sorted_set(set: &BuckSortedSet) -> Vec<u8> {
+    let mut encoded = vec![FORMAT_VERSION];
+    write_sorted_set(&mut encoded, set);
+
+    encoded
+}
+
+pub fn take_sorted_set(bytes: &mut &[u8]) -> Result<BuckSortedSet, EncodingError> {
+    take_version(bytes)?;
+    read_sorted_set(bytes)
+}
+
+/// Encodes a hash as its length followed by each field and value.
+/// Fields are written in sorted order, so equal hashes always encode to the same bytes.
+pub fn encode_hash(hash: &BuckHash) -> Vec<u8> {
+    let mut encoded = vec![FORMAT_VERSION];
+    write_hash(&mut encoded, hash);
+
+    encoded
+}
+
+pub fn take_hash(bytes: &mut &[u8]) -> Result<BuckHash, EncodingError> {
+    take_version(bytes)?;
+    read_hash(bytes)
+}
+
+pub fn encode_type(typ: &BuckTypes) -> Vec<u8> {
+    let mut encoded = vec![FORMAT_VERSION];
+    write_type(&mut encoded, typ);
+
+    encoded
+}
+
+/// Decodes a value written by `encode_type` and shortens the slice.
+pub fn take_type(bytes: &mut &[u8]) -> Result<BuckTypes, EncodingError> {
+    take_version(bytes)?;
+    read_type(bytes)
+}
+
+// The `write_*` / `read_*` helpers below handle the body of a value. Nested
+// values are written without their own version byte, since the version at
+// the front of the outermost value already covers them.
+
+fn write_type(encoded: &mut Vec<u8>, typ: &BuckTypes) {
+    match typ {
+        BuckTypes::Boolean(b) => {
+            encoded.push(TypeTag::Boolean as u8);
+            encoded.push(encode_boolean(*b));
+        }
+        BuckTypes::Float(f) => {
+            encoded.push(TypeTag::Float as u8);
+            encoded.extend(encode_float(*f));
+        }
+        BuckTypes::Integer(i) => {
+            encoded.push(TypeTag::Integer as u8);
+            encoded.extend(encode_integer(*i));
+        }
+        BuckTypes::String(s) => {
+            encoded.push(TypeTag::String as u8);
+            encoded.extend(encode_string(s));
+        }
+        BuckTypes::Sets(s) => {
+            encoded.push(TypeTag::Sets as u8);
+            write_set(encoded, s);
+        }
+        BuckTypes::List(l) => {
+            encoded.push(TypeTag::List as u8);
+            write_list(encoded, l);
+        }
+        BuckTypes::Hash(h) => {
+            encoded.push(TypeTag::Hash as u8);
+            write_hash(encoded, h);
+        }
+        BuckTypes::SortedSet(z) => {
+            encoded.push(TypeTag::SortedSet as u8);
+            write_sorted_set(encoded, z);
+        }
+        BuckTypes::Unknown(u) => {
+            encoded.push(TypeTag::Unknown as u8);
+            encoded.extend(encode_string(u));
+        }
+    }
+}
+
+fn read_type(bytes: &mut &[u8]) -> Result<BuckTypes, EncodingError> {
+    match take_tag(bytes)? {
+        TypeTag::Boolean => Ok(BuckTypes::Boolean(take_boolean(bytes)?)),
+        TypeTag::Float => Ok(BuckTypes::Float(take_float(bytes)?)),
+        TypeTag::Integer => Ok(BuckTypes::Integer(take_integer(bytes)?)),
+        TypeTag::String => Ok(BuckTypes::String(take_string(bytes)?)),
+        TypeTag::Sets => Ok(BuckTypes::Sets(read_set(bytes)?)),
+        TypeTag::List => Ok(BuckTypes::List(read_list(bytes)?)),
+        TypeTag::Hash => Ok(BuckTypes::Hash(read_hash(bytes)?)),
+        TypeTag::SortedSet => Ok(BuckTypes::SortedSet(read_sorted_set(bytes)?)),
+        TypeTag::Unknown => Ok(BuckTypes::Unknown(take_string(bytes)?)),
+        TypeTag::Empty => Err(EncodingError::InvalidTag(TypeTag::Empty as u8)),
+    }
+}
+
+fn write_setable(encoded: &mut Vec<u8>, item: &Setable) {
+    match item {
+        Setable::String(s) => {
+            encoded.push(TypeTag::String as u8);
+            encoded.extend(encode_string(s));
+        }
+        Setable::Boolean(b) => {
+            encoded.push(TypeTag::Boolean as u8);
+            encoded.push(encode_boolean(*b));
+        }
+        Setable::Integer(i) => {
+            encoded.push(TypeTag::Integer as u8);
+            encoded.extend(encode_integer(*i));
+        }
+        Setable::Float(f) => {
+            encoded.push(TypeTag::Float as u8);
+            encoded.extend(encode_float(f.0));
+        }
+        Setable::Empty => encoded.push(TypeTag::Empty as u8),
+    }
+}
+
+fn read_setable(bytes: &mut &[u8]) -> Result<Setable, EncodingError> {
+    match take_tag(bytes)? {
+        TypeTag::String => Ok(Setable::String(take_string(bytes)?)),
+        TypeTag::Boolean => Ok(Setable::Boolean(take_boolean(bytes)?)),
+        TypeTag::Integer => Ok(Setable::Integer(take_integer(bytes)?)),
+        TypeTag::Float => Ok(Setable::Float(take_float::<EqFloat>(bytes)?)),
+        TypeTag::Empty => Ok(Setable::Empty),
+        tag => Err(EncodingError::InvalidTag(tag as u8)),
+    }
+}
+
+fn write_set(encoded: &mut Vec<u8>, set: &BuckSets) {
+    let mut members: Vec<Vec<u8>> = set
+        .data
+        .iter()
+        .map(|item| {
+            let mut member = Vec::new();
+            write_setable(&mut member, item);
+            member
+        })
+        .collect();
+    members.sort();
+
+    encoded.extend(encode_length(members.len()));
+    for member in members {
+        encoded.extend(member);
     }
+}
 
-    let len = take_integer(bytes)? as usize;
+fn read_set(bytes: &mut &[u8]) -> Result<BuckSets, EncodingError> {
+    let len = take_length(bytes)?;
     let mut data = HashSet::new();
 
     for _ in 0..len {
-        match bytes[0] {
-            0x01 => {
-                *bytes = &bytes[1..];
-                let b = take_boolean(bytes)?;
-                data.insert(Setable::Boolean(b));
-            }
-            0x02 => {
-                *bytes = &bytes[1..];
-                let s = take_string(bytes)?;
-                data.insert(Setable::String(s));
-            }
-            0x03 => {
-                *bytes = &bytes[1..];
-                let i = take_integer(bytes)?;
-                data.insert(Setable::Integer(i));
-            }
-            0x04 => {
-                *bytes = &bytes[1..];
-                let ef: EqFloat = take_float(bytes)?;
-                data.insert(Setable::Float(ef));
-            }
-            0x05 => {
-                *bytes = &bytes[1..];
-                data.insert(Setable::Empty);
-            }
-            _ => {
-                return Err(EncodingError::InternalError(format!("Invalid set type: {}", bytes[0])));
-            }
-        }
+        data.insert(read_setable(bytes)?);
     }
 
     Ok(BuckSets { data })
 }
 
-pub fn encode_type(typ: &BuckTypes) -> Vec<u8> {
-    match typ {
-        BuckTypes::Boolean(b) => vec![0x01, encode_boolean(*b)],
-        BuckTypes::Float(f) => vec![&[0x02][..], &encode_float(*f)].concat(),
-        BuckTypes::Integer(i) => vec![&[0x03][..], &encode_integer(*i)].concat(),
-        BuckTypes::String(s) => vec![&[0x04][..], &encode_string(s)].concat(),
-        BuckTypes::Sets(s) => vec![&[0x05][..], &encode_set(s)].concat(),
-        _ => unimplemented!("Encoding for type {:?} is not implemented", typ),
-    }
-}
\ No newline at end of file
+fn write_list(encoded: &mut Vec<u8>, list: &BuckList) {
+    encoded.extend(encode_length(list.len()));
+
+    for item in &list.data {
+        write_type(encoded, item);
+    }
+}
+
+fn read_list(bytes: &mut &[u8]) -> Result<BuckList, EncodingError> {
+    let len = take_length(bytes)?;
+    let mut list = BuckList::new();
+
+    for _ in 0..len {
+        list.push(read_type(bytes)?);
+    }
+
+    Ok(list)
+}
+
+fn write_hash(encoded: &mut Vec<u8>, hash: &BuckHash) {
+    encoded.extend(encode_length(hash.len()));
+
+    let mut fields: Vec<(&String, &BuckTypes)> = hash.data.iter().collect();
+    fields.sort_by(|a, b| a.0.cmp(b.0));
+
+    for (field, value) in fields {
+        encoded.extend(encode_string(field));
+        write_type(encoded, value);
+    }
+}
+
+fn read_hash(bytes: &mut &[u8]) -> Result<BuckHash, EncodingError> {
+    let len = take_length(bytes)?;
+    let mut hash = BuckHash::new();
+
+    for _ in 0..len {
+        let field = take_string(bytes)?;
+        let value = read_type(bytes)?;
+        hash.insert(field, value);
+    }
+
+    Ok(hash)
+}
+
+fn write_sorted_set(encoded: &mut Vec<u8>, set: &BuckSortedSet) {
+    encoded.extend(encode_length(set.len()));
+
+    for (member, score) in set.iter() {
+        encoded.extend(encode_string(member));
+        encoded.extend(encode_float(score));
+    }
+}
+
+fn read_sorted_set(bytes: &mut &[u8]) -> Result<BuckSortedSet, EncodingError> {
+    let len = take_length(bytes)?;
+    let mut set = BuckSortedSet::new();
+
+    for _ in 0..len {
+        let member = take_string(bytes)?;
+        let score: f64 = take_float(bytes)?;
+
+        if score.is_nan() {
+            return Err(EncodingError::InternalError(format!(
+                "Invalid score for member {}",
+                member
+            )));
+        }
+
+        set.insert(member, score);
+    }
+
+    Ok(set)
+}
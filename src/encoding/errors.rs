@@ -4,6 +4,19 @@ use std::fmt;
 pub enum EncodingError {
     InternalError(String),
     UnexpectedEndOf(String),
+    InvalidTag(u8),
+    UnsupportedVersion(u8),
+}
+
+impl EncodingError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            EncodingError::InternalError(_) => "ENCODING_INTERNAL",
+            EncodingError::UnexpectedEndOf(_) => "ENCODING_UNEXPECTED_END",
+            EncodingError::InvalidTag(_) => "ENCODING_INVALID_TAG",
+            EncodingError::UnsupportedVersion(_) => "ENCODING_UNSUPPORTED_VERSION",
+        }
+    }
 }
 
 impl fmt::Display for EncodingError {
@@ -11,6 +24,10 @@ impl fmt::Display for EncodingError {
         match self {
             EncodingError::InternalError(msg) => write!(f, "{}", msg),
             EncodingError::UnexpectedEndOf(msg) => write!(f, "{}", msg),
+            EncodingError::InvalidTag(tag) => write!(f, "Invalid type tag: {:#04x}", tag),
+            EncodingError::UnsupportedVersion(version) => {
+                write!(f, "Unsupported format version: {:#04x}", version)
+            }
         }
     }
 }
\ No newline at end of file
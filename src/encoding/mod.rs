@@ -1,2 +1,3 @@
+#[allow(clippy::module_inception)]
 pub mod encoding;
 pub mod errors;
\ No newline at end of file
@@ -1,10 +1,29 @@
-use std::collections::{BTreeMap, HashMap};
-
+use std::collections::hash_map::RandomState;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::hash::BuildHasher;
+use std::mem;
+use std::ops::{Bound, RangeBounds};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::blocking::{BlockedClients, BlockedPop};
+use crate::clock::{Clock, Expiry, SystemClock};
+use crate::eviction::{parse_memory, EvictionPolicy, MemoryTracker};
+use crate::mvcc::{ReadView, VersionStore};
+use crate::parser::key_policy::{KeyCharset, KeyPolicy};
+use crate::range::{self, MergedRange};
+use crate::scan;
 use crate::sharding::hash::calculate_hash;
 use crate::sharding::shard::BuckDBShard;
+use crate::storage::aof::{AppendOnlyLog, LogEntry};
+use crate::storage::snapshot;
+use crate::storage::Keyspace;
+use crate::types::errors::BuckTypeError;
 use crate::types::hash::BuckHash;
-use crate::types::list::BuckList;
+use crate::types::list::{BuckList, ListSide};
 use crate::types::sets::{Setable, BuckSets};
+use crate::types::sorted_set::{Aggregate, BuckSortedSet, ScoreBound, ZAddFlags, ZRangeSpec};
+use crate::types::string::{self, SetOptions, MAX_STRING_LENGTH};
 use crate::types::types::BuckTypes;
 use crate::{errors::BuckEngineError, log::BuckLog};
 
@@ -23,6 +42,49 @@ pub struct BuckDB {
     pub status: TransactionStatus,
     pub shards: Vec<BuckDBShard>,
     pub is_shard_active: bool,
+    pub aof: Option<AppendOnlyLog>,
+    /// Absolute deadline, in milliseconds since the epoch, of every key that has one.
+    pub expires: HashMap<String, u64>,
+    /// Deadlines from before the first change of each key in this
+    /// transaction, `None` for keys that had none, so that `abort` can
+    /// restore them.
+    pub expires_backup: BTreeMap<String, Option<u64>>,
+    /// Committed keys that expired or were evicted since the last commit,
+    /// whose removal the next commit writes to the append-only log.
+    unlogged_deletes: BTreeSet<String>,
+    /// Keys changed since `start_recording`, `None` when not recording.
+    recording: Option<BTreeSet<String>>,
+    pub clock: Arc<dyn Clock>,
+    /// Estimated memory usage, the `maxmemory` budget and the eviction policy.
+    pub memory: MemoryTracker,
+    /// Every key, staged or committed, in the order `SCAN` visits them.
+    scan_order: BTreeSet<(u64, String)>,
+    /// Keys that are watched by at least one transaction.
+    watched: HashMap<String, WatchedKey>,
+    /// Bumped on every change to a watched key.
+    write_seq: u64,
+    /// Sequence number of the last commit.
+    pub commit_seq: u64,
+    /// Past committed values that open read views may still need.
+    pub versions: VersionStore,
+    /// Clients waiting in `BLPOP`, `BRPOP` or `BLMOVE`.
+    pub blocked: BlockedClients,
+    /// Rules every key a query names has to follow, none by default.
+    pub key_policy: KeyPolicy,
+    /// Directory the paths of `SAVE` and `LOAD` are resolved in.
+    pub dir: PathBuf,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct WatchedKey {
+    watchers: usize,
+    version: u64,
+}
+
+impl Default for BuckDB {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl BuckDB {
@@ -34,53 +96,517 @@ impl BuckDB {
             status: TransactionStatus::Uncommitted,
             shards: Vec::new(),
             is_shard_active: false,
+            aof: None,
+            expires: HashMap::new(),
+            expires_backup: BTreeMap::new(),
+            unlogged_deletes: BTreeSet::new(),
+            recording: None,
+            clock: Arc::new(SystemClock),
+            memory: MemoryTracker::new(),
+            scan_order: BTreeSet::new(),
+            watched: HashMap::new(),
+            write_seq: 0,
+            commit_seq: 0,
+            versions: VersionStore::new(),
+            blocked: BlockedClients::new(),
+            key_policy: KeyPolicy::default(),
+            dir: PathBuf::from("."),
         }
     }
 
+    /// Create an empty database that reads time from `clock`.
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
+        let mut db = BuckDB::new();
+        db.clock = clock;
+
+        db
+    }
+
+    /// Open a database backed by the append-only log at `path`.
+    ///
+    /// The log is replayed to rebuild the committed data and deadlines, and
+    /// every later commit is appended to it. Keys whose deadline passed while
+    /// the database was closed expire as soon as they are accessed.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, BuckEngineError> {
+        let aof = AppendOnlyLog::new(path);
+        let (data, expires) = aof
+            .replay()
+            .map_err(|e| BuckEngineError::PersistenceError(e.to_string()))?;
+
+        let mut db = BuckDB::new();
+        db.data = data;
+        db.expires = expires;
+        db.aof = Some(aof);
+        db.recompute_memory();
+
+        Ok(db)
+    }
+
     ///////// Transaction /////////
 
-    pub fn begin_transaction(&mut self) -> Result<BuckLog, ()> {
+    pub fn begin_transaction(&mut self) -> Result<BuckLog, BuckEngineError> {
         // clear the uncommitted data to ensure that the transaction is clean
-        self.uncommitted_data.clear();
+        let staged: Vec<String> = self.uncommitted_data.drain().map(|(key, _)| key).collect();
+        for key in &staged {
+            self.key_changed(key);
+        }
+        self.transaction_backup = Some(BTreeMap::new());
+        self.expires_backup.clear();
         self.status = TransactionStatus::Uncommitted;
 
         Ok(BuckLog::ClearTransactionOk)
     }
 
     pub fn commit(&mut self) -> Result<BuckLog, BuckEngineError> {
+        let keys = self.staged_keys();
+        self.commit_keys(&keys)
+    }
+
+    /// Commit the staged changes of `keys`, leaving the changes of every
+    /// other key staged.
+    ///
+    /// This is how a client commits its own writes while other clients
+    /// have writes of their own staged in the same database.
+    pub fn commit_keys(&mut self, keys: &BTreeSet<String>) -> Result<BuckLog, BuckEngineError> {
         if self.status == TransactionStatus::Committed {
             return Err(BuckEngineError::AlreadyCommitted);
         }
 
-        for (key, value) in self.uncommitted_data.drain() {
-            self.data.insert(key, value);
+        let keys: Vec<String> = keys.iter().filter(|key| self.is_staged(key)).cloned().collect();
+
+        // write the transaction to the log before it becomes visible in `data`
+        if let Some(aof) = &self.aof {
+            let entries = self.log_entries(&keys);
+
+            if !entries.is_empty() {
+                aof.append(&entries)
+                    .map_err(|e| BuckEngineError::PersistenceError(e.to_string()))?;
+            }
+        }
+
+        self.publish_commit(&keys);
+
+        for key in &keys {
+            if let Some(value) = self.uncommitted_data.remove(key) {
+                self.data.insert(key.clone(), value);
+                // the staged copy is gone, only the committed one is left
+                self.refresh_memory(key);
+            }
+            if let Some(backup) = self.transaction_backup.as_mut() {
+                backup.remove(key);
+            }
+            self.expires_backup.remove(key);
         }
 
         // update transaction status
-        self.status = TransactionStatus::Committed;
-        self.uncommitted_data.clear();
+        if !self.has_staged() {
+            self.status = TransactionStatus::Committed;
+            self.transaction_backup = Some(BTreeMap::new());
+        }
+        self.unlogged_deletes.clear();
+        self.versions.collect_garbage();
 
         Ok(BuckLog::TransactionOk)
     }
 
+    /// Every key with a staged write, an in-place change or a changed deadline.
+    fn staged_keys(&self) -> BTreeSet<String> {
+        self.uncommitted_data
+            .keys()
+            .chain(self.transaction_backup.iter().flat_map(|backup| backup.keys()))
+            .chain(self.expires_backup.keys())
+            .cloned()
+            .collect()
+    }
+
+    fn is_staged(&self, key: &str) -> bool {
+        self.uncommitted_data.contains_key(key)
+            || self.transaction_backup.as_ref().is_some_and(|backup| backup.contains_key(key))
+            || self.expires_backup.contains_key(key)
+    }
+
+    fn has_staged(&self) -> bool {
+        !self.uncommitted_data.is_empty()
+            || self.transaction_backup.as_ref().is_some_and(|backup| !backup.is_empty())
+            || !self.expires_backup.is_empty()
+    }
+
+    /// The final state of each of `keys`, and of the committed keys that
+    /// expired since the last commit, sorted by key.
+    fn log_entries(&self, keys: &[String]) -> Vec<LogEntry> {
+        let keys: BTreeSet<&String> = keys.iter().chain(self.unlogged_deletes.iter()).collect();
+
+        keys.into_iter()
+            .map(|key| match self.uncommitted_data.get(key).or_else(|| self.data.get(key)) {
+                Some(value) => LogEntry::Set(key.clone(), value.clone(), self.expires.get(key).copied()),
+                None => LogEntry::Delete(key.clone()),
+            })
+            .collect()
+    }
+
+    /// Throw away every change made since the last commit.
+    ///
+    /// Staged writes are dropped, committed values that were changed in place
+    /// are restored from `transaction_backup` and deadlines from
+    /// `expires_backup`.
     pub fn abort(&mut self) -> Result<BuckLog, BuckEngineError> {
         if self.transaction_backup.is_none() {
             return Err(BuckEngineError::NoBackup);
         }
 
-        if self.status == TransactionStatus::Abort {
+        let keys = self.staged_keys();
+        self.abort_keys(&keys)
+    }
+
+    /// Throw away the changes made to `keys` since the last commit, like
+    /// `abort`, leaving the changes of every other key staged.
+    pub fn abort_keys(&mut self, keys: &BTreeSet<String>) -> Result<BuckLog, BuckEngineError> {
+        let mut changed = Vec::new();
+
+        for key in keys {
+            let mut staged = self.uncommitted_data.remove(key).is_some();
+
             // restore the data from the backup
-            for (k, v) in self.transaction_backup.take().unwrap() {
-                self.data.insert(k, v);
+            if let Some(value) = self.transaction_backup.as_mut().and_then(|backup| backup.remove(key)) {
+                self.data.insert(key.clone(), value);
+                staged = true;
             }
 
-            self.uncommitted_data.clear();
-            self.status = TransactionStatus::Committed;
+            if let Some(deadline) = self.expires_backup.remove(key) {
+                match deadline {
+                    Some(deadline) => self.expires.insert(key.clone(), deadline),
+                    None => self.expires.remove(key),
+                };
+                staged = true;
+            }
+
+            if staged {
+                changed.push(key.clone());
+            }
+        }
+
+        self.status = match self.has_staged() {
+            true => TransactionStatus::Uncommitted,
+            false => TransactionStatus::Committed,
+        };
+        self.rebuild_shards()?;
+
+        for key in &changed {
+            self.key_changed(key);
+        }
+
+        Ok(BuckLog::RollbackOk)
+    }
+
+    /// Keep the committed value of `key` so that `abort` can restore it.
+    ///
+    /// Must be called before `data` is changed in place. Only the value
+    /// from before the first change is kept.
+    fn backup_committed(&mut self, key: &str) {
+        if let (Some(backup), Some(value)) = (self.transaction_backup.as_mut(), self.data.get(key)) {
+            if !backup.contains_key(key) {
+                backup.insert(key.to_owned(), value.clone());
+            }
+        }
+    }
+
+    /// Set or clear the deadline of `key`, keeping the previous one so that
+    /// `abort` can restore it. A changed deadline is part of the transaction.
+    pub(crate) fn set_deadline(&mut self, key: &str, deadline: Option<u64>) {
+        let previous = self.expires.get(key).copied();
+        if previous == deadline {
+            return;
+        }
+
+        self.expires_backup.entry(key.to_owned()).or_insert(previous);
+        match deadline {
+            Some(deadline) => self.expires.insert(key.to_owned(), deadline),
+            None => self.expires.remove(key),
+        };
+
+        if self.status == TransactionStatus::Committed {
+            self.status = TransactionStatus::Uncommitted;
+        }
+    }
+
+    /// Deadline of `key` as of the last commit.
+    fn committed_deadline(&self, key: &str) -> Option<u64> {
+        match self.expires_backup.get(key) {
+            Some(deadline) => *deadline,
+            None => self.expires.get(key).copied(),
+        }
+    }
+
+    ///////// Read views /////////
+
+    /// Open a view of the committed data as of the last commit.
+    ///
+    /// Later commits, staged writes and in-place changes are not visible
+    /// through the view. It is released when dropped.
+    pub fn read_view(&mut self) -> ReadView {
+        self.versions.open_view(self.commit_seq)
+    }
+
+    /// Get the value `key` had when `view` was opened.
+    pub fn get_at<'a>(&'a self, view: &ReadView, key: &str) -> Result<&'a BuckTypes, BuckEngineError> {
+        self.versions
+            .read(key, view.seq(), self.committed_value(key))
+            .ok_or_else(|| BuckEngineError::KeyNotFound(key.to_owned()))
+    }
+
+    /// Drop the past versions that no open view can see anymore.
+    ///
+    /// This also runs after every commit, so calling it is only needed to
+    /// release memory right after views are dropped.
+    pub fn collect_garbage(&mut self) -> usize {
+        self.versions.collect_garbage()
+    }
+
+    /// Latest committed value of `key`, ignoring staged writes and in-place
+    /// changes that are not committed yet.
+    fn committed_value(&self, key: &str) -> Option<&BuckTypes> {
+        self.transaction_backup
+            .as_ref()
+            .and_then(|backup| backup.get(key))
+            .or_else(|| self.data.get(key))
+    }
+
+    /// Start a new commit sequence number in which `keys` change, keeping
+    /// their previous committed values for the open views.
+    fn publish_commit(&mut self, keys: &[String]) {
+        self.commit_seq += 1;
+
+        if self.versions.oldest_view().is_none() {
+            return;
+        }
+
+        // a key can be both staged and changed in place
+        let keys: BTreeSet<&String> = keys.iter().collect();
+
+        for key in keys {
+            let previous = self.committed_value(key).cloned();
+            self.versions.record(key, previous, self.commit_seq);
+        }
+    }
+
+    ///////// Watch /////////
+
+    /// Start watching `key` and return its current version.
+    ///
+    /// The version changes whenever the key is written, deleted, expires or
+    /// gets a new timeout, so comparing it later tells whether the key was
+    /// touched in the meantime. Every call must be paired with `unwatch`.
+    pub fn watch(&mut self, key: &str) -> u64 {
+        let entry = self.watched.entry(key.to_owned()).or_default();
+        entry.watchers += 1;
+
+        entry.version
+    }
+
+    pub fn unwatch(&mut self, key: &str) {
+        if let Some(entry) = self.watched.get_mut(key) {
+            entry.watchers -= 1;
+
+            if entry.watchers == 0 {
+                self.watched.remove(key);
+            }
+        }
+    }
+
+    /// Current version of a watched key. Keys nobody watches are always `0`.
+    pub fn key_version(&self, key: &str) -> u64 {
+        self.watched.get(key).map_or(0, |entry| entry.version)
+    }
+
+    /// Give the watches, blocked clients and append-only logs of `self` and
+    /// `other` back to each other, after the two databases were swapped.
+    ///
+    /// Clients refer to a database by its index, so they stay where they
+    /// were while the data moves. Every watched key counts as modified, and
+    /// every blocked client gets another chance at the new data. A log is
+    /// replayed into the database at its index on startup, so it stays there
+    /// as well, and has to be rewritten with `rewrite_log_with` beforehand.
+    pub(crate) fn swap_clients(&mut self, other: &mut BuckDB) {
+        mem::swap(&mut self.watched, &mut other.watched);
+        mem::swap(&mut self.write_seq, &mut other.write_seq);
+        mem::swap(&mut self.blocked, &mut other.blocked);
+        mem::swap(&mut self.aof, &mut other.aof);
+
+        // so does the configuration, the data only brings its own usage
+        mem::swap(&mut self.memory.maxmemory, &mut other.memory.maxmemory);
+        mem::swap(&mut self.memory.policy, &mut other.memory.policy);
+        mem::swap(&mut self.key_policy, &mut other.key_policy);
+        mem::swap(&mut self.dir, &mut other.dir);
+
+        // the rewritten logs no longer hold the keys that expired
+        self.unlogged_deletes.clear();
+        other.unlogged_deletes.clear();
+
+        for db in [self, other] {
+            let keys: Vec<String> = db.watched.keys().cloned().collect();
+            for key in &keys {
+                db.signal_modified_key(key);
+            }
+            db.blocked.signal_all();
+        }
+    }
+
+    fn signal_modified_key(&mut self, key: &str) {
+        if let Some(entry) = self.watched.get_mut(key) {
+            self.write_seq += 1;
+            entry.version = self.write_seq;
+        }
+        if let Some(recording) = self.recording.as_mut() {
+            recording.insert(key.to_owned());
+        }
+        self.blocked.signal(key);
+    }
+
+    /// Start collecting the keys that change, so that a client can tell
+    /// its own writes apart from those of other clients.
+    pub fn start_recording(&mut self) {
+        self.recording = Some(BTreeSet::new());
+    }
+
+    /// Stop collecting and return the keys that changed since `start_recording`.
+    pub fn stop_recording(&mut self) -> BTreeSet<String> {
+        self.recording.take().unwrap_or_default()
+    }
+
+    ///////// Blocking /////////
+
+    /// Register a client that waits for an element of one of `pop.keys`.
+    ///
+    /// The client is served by `serve_blocked` once one of the lists gets an
+    /// element, and picks the result up with `BlockedClients::take_served`.
+    pub fn block(&mut self, pop: BlockedPop) -> u64 {
+        // a list that already has elements does not wait for another write
+        for key in &pop.keys {
+            if matches!(self.list(key), Ok(Some(_))) {
+                self.blocked.signal(key);
+            }
+        }
+
+        self.blocked.block(pop)
+    }
+
+    /// Hand the elements of every ready list to the clients blocked on it.
+    ///
+    /// Clients are served in the order they blocked. Call it after every
+    /// command, so that a pushed element goes to a waiting client before
+    /// anybody else can pop it.
+    ///
+    /// ## Returns
+    ///
+    /// The number of clients served.
+    pub fn serve_blocked(&mut self) -> usize {
+        let mut served = 0;
+
+        while let Some(key) = self.blocked.next_ready() {
+            while let Some((id, pop)) = self.blocked.first(&key) {
+                let pop = pop.clone();
+
+                // a key that holds something else by now keeps its clients waiting
+                if !matches!(self.list(&key), Ok(Some(_))) {
+                    break;
+                }
 
-            return Ok(BuckLog::RollbackOk);
+                let result = match self.move_element(&key, pop.side, pop.target.as_ref()) {
+                    Ok(Some(value)) => Ok((key.clone(), value)),
+                    Ok(None) => break,
+                    Err(e) => Err(e),
+                };
+
+                self.blocked.serve(id, result);
+                served += 1;
+            }
+        }
+
+        served
+    }
+
+    ///////// Snapshot /////////
+
+    /// Write the committed data to a snapshot file at `path`.
+    ///
+    /// Staged writes, changes made in place and deadlines are not part of
+    /// the snapshot until they are committed.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<BuckLog, BuckEngineError> {
+        let (data, expires) = self.committed_keyspace();
+
+        snapshot::save(path, &data, &expires)
+            .map_err(|e| BuckEngineError::PersistenceError(e.to_string()))?;
+
+        Ok(BuckLog::BackupOk)
+    }
+
+    /// Replace the committed data with the snapshot stored at `path`.
+    ///
+    /// The snapshot is fully verified before anything is replaced, so a
+    /// damaged file leaves the database untouched. Staged writes are dropped
+    /// and the deadlines become those stored in the snapshot.
+    pub fn load<P: AsRef<Path>>(&mut self, path: P) -> Result<BuckLog, BuckEngineError> {
+        let (data, expires) = snapshot::load(path)
+            .map_err(|e| BuckEngineError::PersistenceError(e.to_string()))?;
+
+        if let Some(aof) = &self.aof {
+            aof.rewrite(&data, &expires)
+                .map_err(|e| BuckEngineError::PersistenceError(e.to_string()))?;
+        }
+
+        let changed: Vec<String> = self
+            .data
+            .keys()
+            .chain(data.keys())
+            .chain(self.transaction_backup.iter().flat_map(|backup| backup.keys()))
+            .cloned()
+            .collect();
+        self.publish_commit(&changed);
+
+        let previous = mem::replace(&mut self.data, data);
+
+        self.uncommitted_data.clear();
+        self.expires = expires;
+        self.expires_backup.clear();
+        self.unlogged_deletes.clear();
+        self.transaction_backup = Some(BTreeMap::new());
+        self.status = TransactionStatus::Committed;
+        self.rebuild_shards()?;
+        self.recompute_memory();
+
+        for key in previous.keys().chain(self.data.keys()).cloned().collect::<Vec<_>>() {
+            self.signal_modified_key(&key);
+        }
+
+        Ok(BuckLog::LoadOk)
+    }
+
+    /// Replace the append-only log of `self` with the committed data and
+    /// deadlines of `other`, which is about to take its place.
+    pub(crate) fn rewrite_log_with(&self, other: &BuckDB) -> Result<(), BuckEngineError> {
+        if let Some(aof) = &self.aof {
+            let (data, expires) = other.committed_keyspace();
+            aof.rewrite(&data, &expires)
+                .map_err(|e| BuckEngineError::PersistenceError(e.to_string()))?;
         }
 
-        Err(BuckEngineError::AbortError)
+        Ok(())
+    }
+
+    /// The data and deadlines as of the last commit, without the changes
+    /// made in place since.
+    fn committed_keyspace(&self) -> Keyspace {
+        let mut data = self.data.clone();
+        for (key, value) in self.transaction_backup.iter().flatten() {
+            data.insert(key.clone(), value.clone());
+        }
+
+        let expires = data
+            .keys()
+            .filter_map(|key| self.committed_deadline(key).map(|deadline| (key.clone(), deadline)))
+            .collect();
+
+        (data, expires)
     }
 
     ///////// Query /////////
@@ -90,6 +616,11 @@ impl BuckDB {
     /// Newly added data is added to `uncommitted_data`,
     /// regardless of the transaction status.
     pub fn insert(&mut self, key: String, value: BuckTypes) -> Result<BuckLog, BuckEngineError> {
+        self.expire_if_needed(&key);
+        self.reserve_memory(&key)?;
+        // a new value replaces the old one, and with it any deadline
+        self.set_deadline(&key, None);
+
         match self.status {
             TransactionStatus::Committed => {
                 self.status = TransactionStatus::Uncommitted;
@@ -110,11 +641,22 @@ impl BuckDB {
             TransactionStatus::Abort => return self.abort(),
         }
 
+        self.track_write(&key);
         Ok(BuckLog::InsertOk(key))
     }
 
     /// Get a value from the database.
+    ///
+    /// Keys whose deadline has passed are reported as missing. Staged writes
+    /// are seen as well, so this is the read of whoever stages them: clients
+    /// go through `BuckSession`, which commits every query outside `MULTI`,
+    /// and only ever read what was committed. Use `read_view` for reads that
+    /// must not see staged writes at all.
     pub fn get(&self, key: &str) -> Result<&BuckTypes, BuckEngineError> {
+        if self.is_expired(key) {
+            return Err(BuckEngineError::KeyNotFound(key.to_owned()));
+        }
+
         match self.status {
             // if the transaction is uncommitted, check the uncommitted data first
             TransactionStatus::Uncommitted => match self.uncommitted_data.get(key) {
@@ -134,304 +676,2367 @@ impl BuckDB {
 
     /// Remove a value from the database.
     pub fn remove(&mut self, key: &str) -> Result<BuckLog, BuckEngineError> {
+        self.expire_if_needed(key);
+        self.set_deadline(key, None);
+
         if self.is_shard_active {
             self.with_shard(key, |shard| shard.remove(key))?;
         }
 
-        match self.status {
-            TransactionStatus::Committed => match self.data.remove(key) {
-                Some(_) => Ok(BuckLog::RemoveOk(key.to_owned())),
-                None => Err(BuckEngineError::KeyNotFound(key.to_owned())),
-            },
-            TransactionStatus::Uncommitted => match self.uncommitted_data.remove(key) {
-                Some(_) => Ok(BuckLog::RemoveOk(key.to_owned())),
-                None => Err(BuckEngineError::KeyNotFound(key.to_owned())),
-            },
-            TransactionStatus::Abort => self.abort(),
+        if self.status == TransactionStatus::Abort {
+            return self.abort();
         }
+
+        if self.status == TransactionStatus::Committed {
+            self.status = TransactionStatus::Uncommitted;
+        }
+
+        // the key may be staged, committed or both
+        self.backup_committed(key);
+        let staged = self.uncommitted_data.remove(key);
+        let committed = self.data.remove(key);
+
+        let result = match staged.or(committed) {
+            Some(_) => Ok(BuckLog::RemoveOk(key.to_owned())),
+            None => Err(BuckEngineError::KeyNotFound(key.to_owned())),
+        };
+
+        self.key_changed(key);
+        result
     }
 
     /// Update a value in the database.
     pub fn update(&mut self, key: &str, value: BuckTypes) -> Result<BuckLog, BuckEngineError> {
+        self.expire_if_needed(key);
+        self.reserve_memory(key)?;
+
         if self.is_shard_active {
             self.with_shard(key, |shard| shard.update(key, value.clone()))?;
         }
 
-        match self.status {
-            // TODO if apply update to uncommitted data, should change the status to uncommitted
-            TransactionStatus::Committed => match self.data.get_mut(key) {
-                Some(v) => {
-                    // exchange the previous value with the new value
-                    *v = value;
-                    Ok(BuckLog::UpdateOk(key.to_owned()))
+        let result = match self.status {
+            TransactionStatus::Committed => {
+                // the change is in place, but it still has to be committed
+                self.status = TransactionStatus::Uncommitted;
+                self.backup_committed(key);
+
+                match self.data.get_mut(key) {
+                    Some(v) => {
+                        // exchange the previous value with the new value
+                        *v = value;
+                        Ok(BuckLog::UpdateOk(key.to_owned()))
+                    }
+                    None => Err(BuckEngineError::KeyNotFound(key.to_owned())),
                 }
-                None => Err(BuckEngineError::KeyNotFound(key.to_owned())),
-            },
+            }
             TransactionStatus::Uncommitted => match self.uncommitted_data.get_mut(key) {
                 Some(v) => {
                     *v = value;
                     Ok(BuckLog::UpdateOk(key.to_owned()))
                 }
+                // a committed key is staged with its new value
+                None if self.data.contains_key(key) => {
+                    self.uncommitted_data.insert(key.to_owned(), value);
+                    Ok(BuckLog::UpdateOk(key.to_owned()))
+                }
                 None => Err(BuckEngineError::KeyNotFound(key.to_owned())),
             },
-            TransactionStatus::Abort => self.abort(),
-        }
-    }
+            TransactionStatus::Abort => return self.abort(),
+        };
 
-    ///////// Sharding /////////
+        self.track_write(key);
+        result
+    }
 
-    pub fn enable_sharding(&mut self, num_shards: usize) -> Result<BuckLog, ()> {
-        self.is_shard_active = true;
+    /// Increment the integer stored at key by `by`.
+    ///
+    /// A missing key is set to `0` before the operation. The key keeps its
+    /// deadline, if it has one.
+    ///
+    /// ## Returns
+    ///
+    /// The value of the key after the increment.
+    pub fn incr_by(&mut self, key: &str, by: i64) -> Result<i64, BuckEngineError> {
+        self.expire_if_needed(key);
+
+        let current = match self.get(key) {
+            Ok(BuckTypes::Integer(value)) => Some(*value),
+            Ok(_) => return Err(BuckTypeError::NotAnInteger(key.to_owned()).into()),
+            Err(BuckEngineError::KeyNotFound(_)) => None,
+            Err(e) => return Err(e),
+        };
 
-        for _ in 0..num_shards {
-            self.shards.push(BuckDBShard::new());
-        }
+        let value = current
+            .unwrap_or(0)
+            .checked_add(by)
+            .ok_or(BuckTypeError::Overflow)?;
 
-        Ok(BuckLog::ShardingEnableOk)
+        self.store_scalar(key, current.is_some(), BuckTypes::Integer(value))?;
+        Ok(value)
     }
 
-    // Only for testing (should be private later)
-    pub fn get_shard_data(&self, idx: usize) -> Option<&BuckDBShard> {
-        self.shards.get(idx)
-    }
+    /// Increment the number stored at key by `by`.
+    ///
+    /// Works like `incr_by`, the key holds a float afterwards.
+    pub fn incr_by_float(&mut self, key: &str, by: f64) -> Result<f64, BuckEngineError> {
+        self.expire_if_needed(key);
+
+        let current = match self.get(key) {
+            Ok(BuckTypes::Integer(value)) => Some(*value as f64),
+            Ok(BuckTypes::Float(value)) => Some(*value),
+            Ok(_) => return Err(BuckTypeError::NotAFloat(key.to_owned()).into()),
+            Err(BuckEngineError::KeyNotFound(_)) => None,
+            Err(e) => return Err(e),
+        };
 
-    /// query function handler for sharding
-    fn with_shard<F, R>(&mut self, key: &str, mut query_function: F) -> Result<R, BuckEngineError>
-    where
-        F: FnMut(&mut BuckDBShard) -> Result<R, BuckEngineError>,
-    {
-        if self.is_shard_active {
-            let shard_idx = calculate_hash(&key) as usize % self.shards.len();
-            let shard = &mut self.shards[shard_idx];
+        let value = current.unwrap_or(0.0) + by;
 
-            return query_function(shard);
+        if !value.is_finite() {
+            return Err(BuckTypeError::Overflow.into());
         }
 
-        Err(BuckEngineError::ShardingNotActive)
+        self.store_scalar(key, current.is_some(), BuckTypes::Float(value))?;
+        Ok(value)
     }
 
-    ///////// Type /////////
+    /// Write a new value for key, keeping the deadline of an existing key.
+    fn store_scalar(&mut self, key: &str, exists: bool, value: BuckTypes) -> Result<(), BuckEngineError> {
+        match exists {
+            true => self.update(key, value)?,
+            false => self.insert(key.to_owned(), value)?,
+        };
+
+        Ok(())
+    }
+
+    ///////// String /////////
 
-    /// Insert all the specified values at the head of the list stored at `key`.
+    /// Set key to hold `value`, as allowed by `options`.
     ///
-    /// `LPUSH key element | start...end`
+    /// Unlike `insert`, this can refuse to overwrite an existing key, or to
+    /// create a missing one, in a single step.
     ///
-    /// if `key` does not exist, it is create as empty list before performing the push operations.
-    pub fn l_push(&mut self, key: String, value: BuckTypes) -> Result<BuckLog, BuckEngineError> {
-        if self.status == TransactionStatus::Committed {
-            self.status = TransactionStatus::Uncommitted;
-        }
+    /// ## Syntax
+    ///
+    /// >>> `SET key value [NX|XX] [GET] [EX seconds|PX milliseconds|EXAT timestamp|PXAT milliseconds-timestamp|KEEPTTL]`
+    ///
+    /// ## Returns
+    ///
+    /// Whether the value was set, and with `GET` the old value, `None` if
+    /// there was no such key.
+    pub fn set(
+        &mut self,
+        key: String,
+        value: BuckTypes,
+        options: &SetOptions,
+    ) -> Result<(bool, Option<BuckTypes>), BuckEngineError> {
+        self.expire_if_needed(&key);
+
+        let exists = self.contains_key(&key);
+        let old = match options.get {
+            true => self.string_value(&key)?,
+            false => None,
+        };
 
-        if self.is_shard_active {
-            self.with_shard(&key, |shard| shard.insert(key.clone(), value.clone()))?;
+        if options.nx && exists || options.xx && !exists {
+            return Ok((false, old));
         }
 
-        // if key does not exist, create a new list
-        if !self.uncommitted_data.contains_key(&key) {
-            let mut list = BuckList::new();
-            list.push(value);
-            self.uncommitted_data
-                .insert(key.clone(), BuckTypes::List(list));
-
-            return Ok(BuckLog::InsertOk(key));
-        }
+        let deadline = match options.keep_ttl {
+            true => self.expires.get(&key).copied(),
+            false => None,
+        };
 
-        match self.uncommitted_data.get_mut(&key) {
-            Some(BuckTypes::List(list)) => {
-                list.push(value);
-                Ok(BuckLog::InsertOk(key))
-            }
-            _ => Err(BuckEngineError::KeyNotFound(key.to_owned())),
-        }
-    }
+        self.insert(key.clone(), value)?;
 
-    /// Removes and returns the first element of the list stored at `key`.
-    pub fn l_pop(&mut self, key: &str) -> Result<BuckLog, BuckEngineError> {
-        if self.status == TransactionStatus::Committed {
-            self.status = TransactionStatus::Uncommitted;
+        self.set_deadline(&key, deadline);
+        if let Some(expiry) = options.expiry {
+            self.set_expiry(&key, expiry)?;
         }
 
-        if self.is_shard_active {
-            self.with_shard(key, |shard| shard.remove(key))?;
-        }
+        Ok((true, old))
+    }
+
+    /// Replace the value stored at key with `new`, but only if it currently
+    /// is `expected`. An `expected` of `None` means the key must not exist.
+    ///
+    /// The key keeps its deadline, if it has one.
+    ///
+    /// ## Returns
+    ///
+    /// Whether the value was swapped.
+    pub fn compare_and_swap(
+        &mut self,
+        key: &str,
+        expected: Option<&BuckTypes>,
+        new: BuckTypes,
+    ) -> Result<bool, BuckEngineError> {
+        self.expire_if_needed(key);
+
+        let current = match self.get(key) {
+            Ok(value) => Some(value),
+            Err(BuckEngineError::KeyNotFound(_)) => None,
+            Err(e) => return Err(e),
+        };
+
+        if current != expected {
+            return Ok(false);
+        }
+
+        self.store_scalar(key, expected.is_some(), new)?;
+        Ok(true)
+    }
+
+    /// Append `value` to the string stored at key.
+    ///
+    /// A missing key is created as an empty string first.
+    ///
+    /// ## Returns
+    ///
+    /// The length of the string after the append, in bytes.
+    pub fn append(&mut self, key: &str, value: &str) -> Result<usize, BuckEngineError> {
+        self.expire_if_needed(key);
+
+        let current = self.text(key)?;
+        let exists = current.is_some();
+        let mut text = current.unwrap_or_default();
+
+        if text.len().saturating_add(value.len()) > MAX_STRING_LENGTH {
+            return Err(BuckTypeError::StringTooLong.into());
+        }
+        text.push_str(value);
+
+        let len = text.len();
+        self.store_scalar(key, exists, BuckTypes::String(text))?;
+        Ok(len)
+    }
+
+    /// Returns the length of the string stored at key in bytes, `0` if
+    /// there is no such key.
+    pub fn str_len(&mut self, key: &str) -> Result<usize, BuckEngineError> {
+        self.expire_if_needed(key);
+        self.touch(key);
+
+        Ok(self.text(key)?.map_or(0, |text| text.len()))
+    }
+
+    /// Returns the bytes of the string stored at key from `start` to `end`,
+    /// see `string::get_range`.
+    pub fn get_range(&mut self, key: &str, start: i64, end: i64) -> Result<String, BuckEngineError> {
+        self.expire_if_needed(key);
+        self.touch(key);
+
+        Ok(self
+            .text(key)?
+            .map(|text| string::get_range(&text, start, end))
+            .unwrap_or_default())
+    }
+
+    /// Overwrite the string stored at key from `offset` on with `value`,
+    /// see `string::set_range`.
+    ///
+    /// A missing key is created as an empty string first, unless `value`
+    /// is empty.
+    ///
+    /// ## Returns
+    ///
+    /// The length of the string after it was modified, in bytes.
+    pub fn set_range(&mut self, key: &str, offset: usize, value: &str) -> Result<usize, BuckEngineError> {
+        self.expire_if_needed(key);
+
+        let current = self.text(key)?;
+
+        if value.is_empty() {
+            return Ok(current.map_or(0, |text| text.len()));
+        }
+
+        let text = string::set_range(current.as_deref().unwrap_or_default(), offset, value)?;
+
+        let len = text.len();
+        self.store_scalar(key, current.is_some(), BuckTypes::String(text))?;
+        Ok(len)
+    }
+
+    /// Replace the string stored at key with `value`.
+    ///
+    /// Like `insert`, any timeout of the key is dropped.
+    ///
+    /// ## Returns
+    ///
+    /// The old value, `None` if there was no such key.
+    pub fn get_set(&mut self, key: String, value: BuckTypes) -> Result<Option<BuckTypes>, BuckEngineError> {
+        self.expire_if_needed(&key);
+
+        let old = self.string_value(&key)?;
+        self.insert(key, value)?;
+
+        Ok(old)
+    }
+
+    /// Remove the string stored at key.
+    ///
+    /// ## Returns
+    ///
+    /// The removed value, `None` if there was no such key.
+    pub fn get_del(&mut self, key: &str) -> Result<Option<BuckTypes>, BuckEngineError> {
+        self.expire_if_needed(key);
+
+        let value = self.string_value(key)?;
+        if value.is_some() {
+            self.remove(key)?;
+        }
+
+        Ok(value)
+    }
+
+    /// Get the string stored at key, and change its timeout if `expiry` is given.
+    ///
+    /// A deadline that is not in the future deletes the key, after its
+    /// value was read.
+    ///
+    /// ## Returns
+    ///
+    /// The value, `None` if there is no such key.
+    pub fn get_ex(&mut self, key: &str, expiry: Option<Expiry>) -> Result<Option<BuckTypes>, BuckEngineError> {
+        self.expire_if_needed(key);
+        self.touch(key);
+
+        let value = self.string_value(key)?;
+
+        if let (Some(_), Some(expiry)) = (&value, expiry) {
+            self.set_expiry(key, expiry)?;
+        }
+
+        Ok(value)
+    }
+
+    /// Returns the value of every given key, `None` for keys that do not
+    /// exist or do not hold a string.
+    pub fn m_get(&mut self, keys: &[String]) -> Result<Vec<Option<BuckTypes>>, BuckEngineError> {
+        let mut values = Vec::with_capacity(keys.len());
+
+        for key in keys {
+            self.expire_if_needed(key);
+            self.touch(key);
+
+            let value = match self.get(key) {
+                Ok(value) => string::as_text(value).map(|_| value.clone()),
+                Err(BuckEngineError::KeyNotFound(_)) => None,
+                Err(e) => return Err(e),
+            };
+            values.push(value);
+        }
+
+        Ok(values)
+    }
+
+    /// Insert every given key-value pair, like `insert`.
+    pub fn m_set(&mut self, pairs: Vec<(String, BuckTypes)>) -> Result<(), BuckEngineError> {
+        for (key, value) in pairs {
+            self.insert(key, value)?;
+        }
+
+        Ok(())
+    }
+
+    /// Insert every given key-value pair, but only if none of the keys exist.
+    ///
+    /// ## Returns
+    ///
+    /// Whether the pairs were inserted.
+    pub fn m_set_nx(&mut self, pairs: Vec<(String, BuckTypes)>) -> Result<bool, BuckEngineError> {
+        for (key, _) in &pairs {
+            self.expire_if_needed(key);
+
+            if self.contains_key(key) {
+                return Ok(false);
+            }
+        }
+
+        self.m_set(pairs)?;
+        Ok(true)
+    }
+
+    /// The text of the value stored at `key`, see `string::as_text`.
+    ///
+    /// `None` if there is no such key.
+    fn text(&self, key: &str) -> Result<Option<String>, BuckEngineError> {
+        match self.get(key) {
+            Ok(value) => match string::as_text(value) {
+                Some(text) => Ok(Some(text)),
+                None => Err(BuckEngineError::TypeNotSupported(key.to_owned())),
+            },
+            Err(BuckEngineError::KeyNotFound(_)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The value stored at `key`, as long as string commands can work on it.
+    fn string_value(&self, key: &str) -> Result<Option<BuckTypes>, BuckEngineError> {
+        match self.get(key) {
+            Ok(value) if string::as_text(value).is_some() => Ok(Some(value.clone())),
+            Ok(_) => Err(BuckEngineError::TypeNotSupported(key.to_owned())),
+            Err(BuckEngineError::KeyNotFound(_)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    ///////// Keyspace /////////
+
+    /// Returns every key matching the glob-style `pattern`, in order.
+    ///
+    /// ## Syntax
+    ///
+    /// >>> `KEYS pattern`
+    pub fn keys(&self, pattern: &str) -> Result<Vec<String>, BuckEngineError> {
+        Ok(self
+            .keyspace()?
+            .into_keys()
+            .filter(|key| scan::glob_match(pattern, key))
+            .cloned()
+            .collect())
+    }
+
+    /// Iterate the keys of the database, see `scan::scan`.
+    ///
+    /// Keys are taken from what `get` would see, so staged writes are
+    /// included. `type_name` filters like `pattern` does, after the page
+    /// was selected.
+    ///
+    /// ## Syntax
+    ///
+    /// >>> `SCAN cursor [MATCH pattern] [COUNT count] [TYPE type]`
+    ///
+    /// ## Returns
+    ///
+    /// The cursor to continue from, `0` once the iteration is over, and the
+    /// keys of this page.
+    pub fn scan(
+        &self,
+        cursor: u64,
+        pattern: Option<&str>,
+        count: usize,
+        type_name: Option<&str>,
+    ) -> Result<(u64, Vec<String>), BuckEngineError> {
+        if self.status == TransactionStatus::Abort {
+            return Err(BuckEngineError::AbortError);
+        }
+
+        // only the keys of the page are looked at, not the whole keyspace
+        let keys = self
+            .scan_order
+            .range((cursor, String::new())..)
+            .filter_map(|(hash, key)| Some((*hash, key, self.get(key).ok()?)));
+        let (cursor, keys) = scan::scan_ordered(keys, pattern, count);
+
+        Ok((
+            cursor,
+            keys.into_iter()
+                .filter(|(_, value)| type_name.is_none_or(|name| value.type_name() == name))
+                .map(|(key, _)| key.clone())
+                .collect(),
+        ))
+    }
+
+    /// Iterate the keys from `start` to `end` in order, with their values.
+    ///
+    /// Like `scan`, staged writes are included. The iterator can be walked
+    /// from either end, and an empty range is returned if `start` comes
+    /// after `end`.
+    ///
+    /// ## Syntax
+    ///
+    /// >>> `RANGE start end [LIMIT count] [REV]`
+    pub fn range(&self, start: Bound<&str>, end: Bound<&str>) -> Result<MergedRange<'_>, BuckEngineError> {
+        let staged = match self.status {
+            TransactionStatus::Committed => None,
+            TransactionStatus::Uncommitted => Some(&self.uncommitted_data),
+            TransactionStatus::Abort => return Err(BuckEngineError::AbortError),
+        };
+
+        let bounds = match range::is_valid_range(start, end) {
+            true => (start, end),
+            false => (Bound::Included(""), Bound::Excluded("")),
+        };
+
+        let mut staged: Vec<(&String, &BuckTypes)> = staged
+            .into_iter()
+            .flatten()
+            .filter(|(key, _)| bounds.contains(key.as_str()))
+            .collect();
+        staged.sort_by(|a, b| a.0.cmp(b.0));
+
+        Ok(MergedRange::new(
+            self.data.range::<str, _>(bounds),
+            staged,
+            |key| self.is_expired(key),
+        ))
+    }
+
+    /// Iterate the keys starting with `prefix` in order, see `range`.
+    ///
+    /// ## Syntax
+    ///
+    /// >>> `PREFIX prefix`
+    pub fn prefix(&self, prefix: &str) -> Result<MergedRange<'_>, BuckEngineError> {
+        let end = range::prefix_end(prefix);
+
+        self.range(
+            Bound::Included(prefix),
+            end.as_deref().map_or(Bound::Unbounded, Bound::Excluded),
+        )
+    }
+
+    /// Count how many of `keys` exist. A key given twice is counted twice.
+    ///
+    /// ## Syntax
+    ///
+    /// >>> `EXISTS key [key ...]`
+    pub fn exists(&mut self, keys: &[String]) -> Result<usize, BuckEngineError> {
+        let mut count = 0;
+
+        for key in keys {
+            self.expire_if_needed(key);
+
+            if self.key_exists(key)? {
+                count += 1;
+            }
+        }
+
+        Ok(count)
+    }
+
+    /// Record an access to each of `keys`, like a read would.
+    ///
+    /// ## Syntax
+    ///
+    /// >>> `TOUCH key [key ...]`
+    ///
+    /// ## Returns
+    ///
+    /// The number of keys that exist.
+    pub fn touch_keys(&mut self, keys: &[String]) -> Result<usize, BuckEngineError> {
+        for key in keys {
+            self.touch(key);
+        }
+
+        self.exists(keys)
+    }
+
+    /// Move the value of `key` to `new_key`, along with its timeout.
+    ///
+    /// Any value `new_key` had is overwritten. Like any other write, the
+    /// rename is staged until the next commit.
+    ///
+    /// ## Syntax
+    ///
+    /// >>> `RENAME key newkey`
+    pub fn rename(&mut self, key: &str, new_key: &str) -> Result<(), BuckEngineError> {
+        self.expire_if_needed(key);
+        self.expire_if_needed(new_key);
+
+        let value = self.get(key)?.clone();
+
+        if key != new_key {
+            self.move_key(key, new_key, value)?;
+        }
+
+        Ok(())
+    }
+
+    /// Rename `key` to `new_key`, but only if `new_key` does not exist.
+    ///
+    /// ## Syntax
+    ///
+    /// >>> `RENAMENX key newkey`
+    ///
+    /// ## Returns
+    ///
+    /// Whether the key was renamed.
+    pub fn rename_nx(&mut self, key: &str, new_key: &str) -> Result<bool, BuckEngineError> {
+        self.expire_if_needed(key);
+        self.expire_if_needed(new_key);
+
+        let value = self.get(key)?.clone();
+
+        if self.key_exists(new_key)? {
+            return Ok(false);
+        }
+
+        self.move_key(key, new_key, value)?;
+        Ok(true)
+    }
+
+    /// Copy the value of `source` to `destination`, along with its timeout.
+    ///
+    /// ## Syntax
+    ///
+    /// >>> `COPY source destination [REPLACE]`
+    ///
+    /// ## Returns
+    ///
+    /// Whether the value was copied. Nothing is copied if `source` does not
+    /// exist, if it is the same key as `destination`, or if `destination`
+    /// exists and `replace` is not set.
+    pub fn copy(&mut self, source: &str, destination: &str, replace: bool) -> Result<bool, BuckEngineError> {
+        self.expire_if_needed(source);
+        self.expire_if_needed(destination);
+
+        let value = match self.get(source) {
+            Ok(value) => value.clone(),
+            Err(BuckEngineError::KeyNotFound(_)) => return Ok(false),
+            Err(e) => return Err(e),
+        };
+
+        if source == destination || (!replace && self.key_exists(destination)?) {
+            return Ok(false);
+        }
+
+        let deadline = self.expires.get(source).copied();
+        self.insert(destination.to_owned(), value)?;
+        self.set_deadline(destination, deadline);
+
+        Ok(true)
+    }
+
+    /// Remove each of `keys` that exists, skipping the missing ones.
+    ///
+    /// ## Syntax
+    ///
+    /// >>> `UNLINK key [key ...]`
+    ///
+    /// ## Returns
+    ///
+    /// The number of keys that were removed.
+    pub fn unlink(&mut self, keys: &[String]) -> Result<usize, BuckEngineError> {
+        let mut count = 0;
+
+        for key in keys {
+            self.expire_if_needed(key);
+
+            if self.key_exists(key)? {
+                self.remove(key)?;
+                count += 1;
+            }
+        }
+
+        Ok(count)
+    }
+
+    /// The number of keys in the database, see `keys`.
+    ///
+    /// ## Syntax
+    ///
+    /// >>> `DBSIZE`
+    pub fn db_size(&self) -> Result<usize, BuckEngineError> {
+        Ok(self.keyspace()?.len())
+    }
+
+    /// Remove every key, committed or staged.
+    ///
+    /// Committed values are kept in `transaction_backup`, so the flush is
+    /// undone by `abort` and only takes effect on commit, like `remove`.
+    ///
+    /// ## Syntax
+    ///
+    /// >>> `FLUSHDB`
+    pub fn flush_db(&mut self) -> Result<(), BuckEngineError> {
+        if self.status == TransactionStatus::Abort {
+            return Err(BuckEngineError::AbortError);
+        }
+
+        let keys: Vec<String> = self
+            .data
+            .keys()
+            .chain(self.uncommitted_data.keys())
+            .cloned()
+            .collect();
+
+        for key in &keys {
+            self.backup_committed(key);
+            self.set_deadline(key, None);
+            self.data.remove(key);
+            self.uncommitted_data.remove(key);
+            self.key_changed(key);
+        }
+
+        if !keys.is_empty() {
+            self.status = TransactionStatus::Uncommitted;
+        }
+        self.rebuild_shards()
+    }
+
+    /// A key picked at random, `None` if the database is empty.
+    ///
+    /// ## Syntax
+    ///
+    /// >>> `RANDOMKEY`
+    pub fn random_key(&self) -> Result<Option<String>, BuckEngineError> {
+        let keyspace = self.keyspace()?;
+        if keyspace.is_empty() {
+            return Ok(None);
+        }
+
+        // the random hash keys of the standard library are enough here
+        let index = RandomState::new().hash_one(keyspace.len()) as usize % keyspace.len();
+
+        Ok(keyspace.into_keys().nth(index).cloned())
+    }
+
+    /// Whether `key` exists, failing instead of answering while aborting.
+    fn key_exists(&self, key: &str) -> Result<bool, BuckEngineError> {
+        match self.get(key) {
+            Ok(_) => Ok(true),
+            Err(BuckEngineError::KeyNotFound(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Stage `value` under `new_key` and remove `key`, moving its timeout along.
+    fn move_key(&mut self, key: &str, new_key: &str, value: BuckTypes) -> Result<(), BuckEngineError> {
+        let deadline = self.expires.get(key).copied();
+
+        self.remove(key)?;
+        self.insert(new_key.to_owned(), value)?;
+        self.set_deadline(new_key, deadline);
+
+        Ok(())
+    }
+
+    /// Committed keys merged with the staged ones, leaving out expired keys.
+    fn keyspace(&self) -> Result<BTreeMap<&String, &BuckTypes>, BuckEngineError> {
+        let mut keyspace: BTreeMap<&String, &BuckTypes> = BTreeMap::new();
+
+        match self.status {
+            TransactionStatus::Committed => keyspace.extend(self.data.iter()),
+            // a staged value hides the committed one
+            TransactionStatus::Uncommitted => {
+                keyspace.extend(self.data.iter());
+                keyspace.extend(self.uncommitted_data.iter());
+            }
+            TransactionStatus::Abort => return Err(BuckEngineError::AbortError),
+        }
+
+        keyspace.retain(|key, _| !self.is_expired(key));
+        Ok(keyspace)
+    }
+
+    ///////// Expiration /////////
+
+    /// Whether `key` has a deadline that has already passed.
+    pub fn is_expired(&self, key: &str) -> bool {
+        match self.expires.get(key) {
+            Some(deadline) => *deadline <= self.clock.now_millis(),
+            None => false,
+        }
+    }
+
+    /// Delete `key` if it has expired. Returns whether it was deleted.
+    ///
+    /// This is the lazy half of expiration: it runs whenever a key is accessed.
+    pub fn expire_if_needed(&mut self, key: &str) -> bool {
+        if !self.is_expired(key) {
+            return false;
+        }
+
+        self.delete_key(key);
+        true
+    }
+
+    /// Delete up to `max_keys` expired keys and return how many were deleted.
+    ///
+    /// This is the active half of expiration, so that keys which are never
+    /// accessed again still release their memory. It is meant to be run
+    /// periodically.
+    pub fn active_expire_cycle(&mut self, max_keys: usize) -> usize {
+        let now = self.clock.now_millis();
+        let expired: Vec<String> = self
+            .expires
+            .iter()
+            .filter(|(_, deadline)| **deadline <= now)
+            .map(|(key, _)| key.clone())
+            .take(max_keys)
+            .collect();
+
+        for key in &expired {
+            self.delete_key(key);
+        }
+
+        expired.len()
+    }
+
+    fn delete_key(&mut self, key: &str) {
+        // expiring or evicting a committed key takes effect right away
+        if self.committed_value(key).is_some() {
+            self.publish_commit(&[key.to_owned()]);
+
+            if self.aof.is_some() {
+                self.unlogged_deletes.insert(key.to_owned());
+            }
+        }
+        if let Some(backup) = self.transaction_backup.as_mut() {
+            backup.remove(key);
+        }
+        self.expires_backup.remove(key);
+
+        self.expires.remove(key);
+        self.data.remove(key);
+        self.uncommitted_data.remove(key);
+        self.refresh_memory(key);
+        self.signal_modified_key(key);
+
+        if self.is_shard_active {
+            // the shard may never have seen the key, so a miss is not an error
+            let _ = self.with_shard(key, |shard| shard.remove(key));
+        }
+    }
+
+    fn contains_key(&self, key: &str) -> bool {
+        self.get(key).is_ok()
+    }
+
+    /// Set a timeout on `key`, `millis` from now.
+    ///
+    /// A timeout that is not in the future deletes the key right away.
+    /// Otherwise the timeout is part of the transaction, so `abort` restores
+    /// the previous one.
+    ///
+    /// ## Returns
+    ///
+    /// `1` if the timeout was set, `0` if `key` does not exist.
+    pub fn expire(&mut self, key: &str, millis: i64) -> Result<BuckLog, BuckEngineError> {
+        self.expire_if_needed(key);
+
+        if !self.contains_key(key) {
+            return Ok(BuckLog::IntegerOk(0));
+        }
+
+        if millis <= 0 {
+            self.delete_key(key);
+            return Ok(BuckLog::IntegerOk(1));
+        }
+
+        let deadline = self.clock.now_millis().saturating_add(millis as u64);
+        self.set_deadline(key, Some(deadline));
+        self.signal_modified_key(key);
+
+        Ok(BuckLog::IntegerOk(1))
+    }
+
+    /// Apply `expiry` to `key`, which must exist.
+    ///
+    /// A deadline that is not in the future deletes the key right away.
+    fn set_expiry(&mut self, key: &str, expiry: Expiry) -> Result<(), BuckEngineError> {
+        let now = self.clock.now_millis();
+
+        let deadline = match expiry {
+            Expiry::After(millis) => now.saturating_add(millis),
+            Expiry::At(deadline) => deadline,
+            Expiry::Persist => {
+                self.persist(key)?;
+                return Ok(());
+            }
+        };
+
+        if deadline <= now {
+            self.delete_key(key);
+        } else {
+            self.set_deadline(key, Some(deadline));
+            self.signal_modified_key(key);
+        }
+
+        Ok(())
+    }
+
+    /// Remaining time to live of `key` in milliseconds.
+    ///
+    /// ## Returns
+    ///
+    /// `-2` if `key` does not exist, `-1` if it exists but has no timeout.
+    pub fn pttl(&self, key: &str) -> i64 {
+        if !self.contains_key(key) {
+            return -2;
+        }
+
+        match self.expires.get(key) {
+            // a deadline far enough out does not fit, it is as good as never
+            Some(deadline) => i64::try_from(deadline.saturating_sub(self.clock.now_millis())).unwrap_or(i64::MAX),
+            None => -1,
+        }
+    }
+
+    /// Remove the timeout of `key`. Like `expire`, this is undone by `abort`.
+    ///
+    /// ## Returns
+    ///
+    /// `1` if the timeout was removed, `0` if `key` does not exist or has no timeout.
+    pub fn persist(&mut self, key: &str) -> Result<BuckLog, BuckEngineError> {
+        self.expire_if_needed(key);
+
+        if !self.contains_key(key) {
+            return Ok(BuckLog::IntegerOk(0));
+        }
+
+        match self.expires.contains_key(key) {
+            true => {
+                self.set_deadline(key, None);
+                self.signal_modified_key(key);
+                Ok(BuckLog::IntegerOk(1))
+            }
+            false => Ok(BuckLog::IntegerOk(0)),
+        }
+    }
+
+    ///////// Memory /////////
+
+    /// Set the memory budget in bytes. `None` removes the limit.
+    ///
+    /// The budget is enforced on the next write, not right away.
+    pub fn set_maxmemory(&mut self, maxmemory: Option<usize>) {
+        self.memory.maxmemory = maxmemory;
+    }
+
+    pub fn set_eviction_policy(&mut self, policy: EvictionPolicy) {
+        self.memory.policy = policy;
+    }
+
+    /// Read a configuration parameter.
+    ///
+    /// Supported parameters are `maxmemory` (`0` means unlimited),
+    /// `maxmemory-policy`, and the key policy: `key-max-length` (`0` means
+    /// unlimited), `key-charset` and `key-prefix` (empty means none). The
+    /// data directory, `dir`, can be read but is only set on startup.
+    pub fn config_get(&self, name: &str) -> Result<BuckLog, BuckEngineError> {
+        let policy = &self.key_policy;
+
+        let value = match name {
+            "maxmemory" => self.memory.maxmemory.unwrap_or(0).to_string(),
+            "maxmemory-policy" => self.memory.policy.to_string(),
+            "key-max-length" => policy.max_length.unwrap_or(0).to_string(),
+            "key-charset" => policy.charset.as_ref().map_or("", KeyCharset::as_str).to_owned(),
+            "key-prefix" => policy.prefix.clone().unwrap_or_default(),
+            "dir" => self.dir.display().to_string(),
+            _ => return Err(BuckEngineError::InvalidConfig(name.to_owned())),
+        };
+
+        Ok(BuckLog::ConfigOk(name.to_owned(), value))
+    }
+
+    /// Change a configuration parameter. See `config_get`.
+    pub fn config_set(&mut self, name: &str, value: &str) -> Result<BuckLog, BuckEngineError> {
+        let invalid = || BuckEngineError::InvalidConfig(format!("{} {}", name, value));
+
+        match name {
+            "maxmemory" => {
+                let bytes = parse_memory(value).ok_or_else(invalid)?;
+                self.set_maxmemory(Some(bytes).filter(|bytes| *bytes > 0));
+            }
+            "maxmemory-policy" => {
+                let policy = EvictionPolicy::from_str(value).ok_or_else(invalid)?;
+                self.set_eviction_policy(policy);
+            }
+            "key-max-length" => {
+                let length = value.parse::<usize>().map_err(|_| invalid())?;
+                self.key_policy.max_length = Some(length).filter(|length| *length > 0);
+            }
+            "key-charset" => {
+                self.key_policy.charset = match value {
+                    "" => None,
+                    _ => Some(KeyCharset::parse(value).ok_or_else(invalid)?),
+                };
+            }
+            "key-prefix" => {
+                self.key_policy.prefix = Some(value.to_owned()).filter(|prefix| !prefix.is_empty());
+            }
+            _ => return Err(BuckEngineError::InvalidConfig(name.to_owned())),
+        }
+
+        Ok(BuckLog::ConfigSetOk)
+    }
+
+    /// Estimated number of bytes used by the stored keys and values.
+    pub fn used_memory(&self) -> usize {
+        self.memory.used_memory()
+    }
+
+    /// Lazily expire `key` and record an access to it for the eviction policy.
+    ///
+    /// Returns whether the key was deleted because it had expired.
+    pub fn touch(&mut self, key: &str) -> bool {
+        if self.expire_if_needed(key) {
+            return true;
+        }
+
+        self.memory.record_access(key, self.clock.now_millis());
+        false
+    }
+
+    /// Evict keys until the database fits in its budget again.
+    ///
+    /// Runs before every write that can grow the database. `key` is the key
+    /// about to be written and is never evicted.
+    fn reserve_memory(&mut self, key: &str) -> Result<(), BuckEngineError> {
+        while self.memory.is_over_budget() {
+            if self.memory.policy == EvictionPolicy::NoEviction {
+                return Err(BuckEngineError::OutOfMemory);
+            }
+
+            match self.memory.pick_victim(&self.expires, key) {
+                Some(victim) => self.delete_key(&victim),
+                // e.g. `volatile-lru` with no key that has a timeout
+                None => return Err(BuckEngineError::OutOfMemory),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Bytes used by `key`, counting both its staged and its committed value.
+    fn key_footprint(&self, key: &str) -> usize {
+        let value_size = |value: Option<&BuckTypes>| value.map_or(0, BuckTypes::estimated_size);
+        let values = value_size(self.data.get(key)) + value_size(self.uncommitted_data.get(key));
+
+        if values == 0 {
+            return 0;
+        }
+
+        mem::size_of::<String>() + key.len() + values
+    }
+
+    /// Account for the current size of `key`, and keep it in `scan_order`
+    /// for as long as it exists.
+    fn refresh_memory(&mut self, key: &str) {
+        let size = self.key_footprint(key);
+        self.memory.set_size(key, size);
+
+        let entry = (scan::position(key), key.to_owned());
+        if self.data.contains_key(key) || self.uncommitted_data.contains_key(key) {
+            self.scan_order.insert(entry);
+        } else {
+            self.scan_order.remove(&entry);
+        }
+    }
+
+    fn key_changed(&mut self, key: &str) {
+        self.refresh_memory(key);
+        self.signal_modified_key(key);
+    }
+
+    fn track_write(&mut self, key: &str) {
+        self.key_changed(key);
+        self.memory.record_access(key, self.clock.now_millis());
+    }
+
+    fn recompute_memory(&mut self) {
+        let keys: Vec<String> = self
+            .data
+            .keys()
+            .chain(self.uncommitted_data.keys())
+            .cloned()
+            .collect();
+
+        self.memory.clear();
+        self.scan_order.clear();
+        for key in &keys {
+            self.refresh_memory(key);
+        }
+    }
+
+    ///////// Sharding /////////
+
+    pub fn enable_sharding(&mut self, num_shards: usize) -> Result<BuckLog, BuckEngineError> {
+        if num_shards == 0 {
+            return Err(BuckEngineError::InvalidConfig(
+                "number of shards must be positive".to_owned(),
+            ));
+        }
+
+        self.is_shard_active = true;
+
+        for _ in 0..num_shards {
+            self.shards.push(BuckDBShard::new());
+        }
+
+        Ok(BuckLog::ShardingEnableOk)
+    }
+
+    // Only for testing (should be private later)
+    pub fn get_shard_data(&self, idx: usize) -> Option<&BuckDBShard> {
+        self.shards.get(idx)
+    }
+
+    /// query function handler for sharding
+    fn with_shard<F, R>(&mut self, key: &str, mut query_function: F) -> Result<R, BuckEngineError>
+    where
+        F: FnMut(&mut BuckDBShard) -> Result<R, BuckEngineError>,
+    {
+        if self.is_shard_active {
+            let shard_idx = calculate_hash(&key) as usize % self.shards.len();
+            let shard = &mut self.shards[shard_idx];
+
+            return query_function(shard);
+        }
+
+        Err(BuckEngineError::ShardingNotActive)
+    }
+
+    /// Make every shard mirror the current data again.
+    fn rebuild_shards(&mut self) -> Result<(), BuckEngineError> {
+        if !self.is_shard_active {
+            return Ok(());
+        }
+
+        for shard in self.shards.iter_mut() {
+            *shard = BuckDBShard::new();
+        }
+
+        let entries: Vec<(String, BuckTypes)> = self
+            .data
+            .iter()
+            .chain(self.uncommitted_data.iter())
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+
+        for (key, value) in entries {
+            self.with_shard(&key, |shard| shard.insert(key.clone(), value.clone()))?;
+        }
+
+        Ok(())
+    }
+
+    ///////// Type /////////
+
+    /// Insert `value` at the head of the list stored at `key`.
+    ///
+    /// `LPUSH key element | start...end`
+    ///
+    /// if `key` does not exist, it is create as empty list before performing the push operations.
+    ///
+    /// ## Returns
+    ///
+    /// The length of the list after the push.
+    pub fn l_push(&mut self, key: String, value: BuckTypes) -> Result<BuckLog, BuckEngineError> {
+        self.push_to_list(key, value, true)
+    }
+
+    /// Insert `value` at the tail of the list stored at `key`.
+    ///
+    /// `RPUSH key element | start...end`
+    ///
+    /// ## Returns
+    ///
+    /// The length of the list after the push.
+    pub fn r_push(&mut self, key: String, value: BuckTypes) -> Result<BuckLog, BuckEngineError> {
+        self.push_to_list(key, value, false)
+    }
+
+    fn push_to_list(
+        &mut self,
+        key: String,
+        value: BuckTypes,
+        at_head: bool,
+    ) -> Result<BuckLog, BuckEngineError> {
+        self.expire_if_needed(&key);
+        self.reserve_memory(&key)?;
+
+        let list = match self.staged_list(&key, true)? {
+            Some(list) => list,
+            None => return Err(BuckEngineError::KeyNotFound(key)),
+        };
+
+        match at_head {
+            true => list.push_front(value),
+            false => list.push_back(value),
+        }
+        let length = list.len();
+
+        self.collection_changed(&key)?;
+        Ok(BuckLog::LengthOk(length))
+    }
+
+    /// Remove and return up to `count` elements from the head of the list stored at `key`.
+    ///
+    /// A list that becomes empty is deleted.
+    ///
+    /// ## Returns
+    ///
+    /// The removed elements, none if `key` does not exist.
+    pub fn l_pop(&mut self, key: &str, count: usize) -> Result<Vec<BuckTypes>, BuckEngineError> {
+        self.pop_from_list(key, count, true)
+    }
+
+    /// Remove and return up to `count` elements from the tail of the list stored at `key`.
+    ///
+    /// ## Returns
+    ///
+    /// The removed elements, last one first, none if `key` does not exist.
+    pub fn r_pop(&mut self, key: &str, count: usize) -> Result<Vec<BuckTypes>, BuckEngineError> {
+        self.pop_from_list(key, count, false)
+    }
+
+    fn pop_from_list(
+        &mut self,
+        key: &str,
+        count: usize,
+        from_head: bool,
+    ) -> Result<Vec<BuckTypes>, BuckEngineError> {
+        self.expire_if_needed(key);
+
+        // popping nothing must not stage an untouched list
+        if count == 0 {
+            self.list(key)?;
+            return Ok(Vec::new());
+        }
+
+        let list = match self.staged_list(key, false)? {
+            Some(list) => list,
+            None => return Ok(Vec::new()),
+        };
+
+        let mut values = Vec::with_capacity(count.min(list.len()));
+        while values.len() < count {
+            let value = match from_head {
+                true => list.pop_front(),
+                false => list.pop_back(),
+            };
+
+            match value {
+                Some(value) => values.push(value),
+                None => break,
+            }
+        }
+
+        self.collection_changed(key)?;
+        Ok(values)
+    }
+
+    /// Pop an element from one end of `source` and push it to one end of `destination`.
+    ///
+    /// `LMOVE source destination LEFT|RIGHT LEFT|RIGHT`
+    ///
+    /// `source` and `destination` may be the same list, which rotates it.
+    ///
+    /// ## Returns
+    ///
+    /// The moved element, `None` if `source` does not exist.
+    pub fn l_move(
+        &mut self,
+        source: &str,
+        destination: String,
+        from: ListSide,
+        to: ListSide,
+    ) -> Result<Option<BuckTypes>, BuckEngineError> {
+        self.move_element(source, from, Some(&(destination, to)))
+    }
+
+    /// Pop an element from `source`, and push it to `target` if there is one.
+    fn move_element(
+        &mut self,
+        source: &str,
+        from: ListSide,
+        target: Option<&(String, ListSide)>,
+    ) -> Result<Option<BuckTypes>, BuckEngineError> {
+        // check the destination first, so that nothing is popped for nothing
+        if let Some((destination, _)) = target {
+            self.expire_if_needed(destination);
+            self.list(destination)?;
+        }
+
+        let value = match self.pop_from_list(source, 1, from == ListSide::Left)?.pop() {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+
+        if let Some((destination, to)) = target {
+            if let Err(e) = self.push_to_list(destination.clone(), value.clone(), *to == ListSide::Left) {
+                // put the element back where it came from
+                self.push_to_list(source.to_owned(), value, from == ListSide::Left)?;
+                return Err(e);
+            }
+        }
+
+        Ok(Some(value))
+    }
+
+    /// The elements of the list stored at `key`, from `start` to `stop` inclusive.
+    ///
+    /// Negative indices count from the tail, and out of range indices are
+    /// clamped to the list.
+    pub fn l_range(&self, key: &str, start: i64, stop: i64) -> Result<Vec<BuckTypes>, BuckEngineError> {
+        // an expired list reads as missing, it is left for the next write
+        // to delete so that readers can share the database
+        match self.list(key)? {
+            Some(list) => Ok(list.range(start, stop)),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// The element at `index` in the list stored at `key`.
+    ///
+    /// ## Returns
+    ///
+    /// `None` if `key` does not exist or `index` is out of range.
+    pub fn l_index(&mut self, key: &str, index: i64) -> Result<Option<BuckTypes>, BuckEngineError> {
+        self.expire_if_needed(key);
+
+        Ok(self.list(key)?.and_then(|list| list.get(index).cloned()))
+    }
+
+    /// Replace the element at `index` in the list stored at `key`.
+    pub fn l_set(&mut self, key: &str, index: i64, value: BuckTypes) -> Result<BuckLog, BuckEngineError> {
+        self.expire_if_needed(key);
+        self.reserve_memory(key)?;
+
+        // check before staging, so that a failed `LSET` changes nothing
+        match self.list(key)? {
+            Some(list) if list.get(index).is_none() => {
+                return Err(BuckTypeError::IndexOutOfRange(index).into())
+            }
+            Some(_) => {}
+            None => return Err(BuckEngineError::KeyNotFound(key.to_owned())),
+        }
+
+        if let Some(list) = self.staged_list(key, false)? {
+            list.set(index, value)?;
+        }
+
+        self.collection_changed(key)?;
+        Ok(BuckLog::UpdateOk(key.to_owned()))
+    }
+
+    /// Insert `value` before or after the first `pivot` in the list stored at `key`.
+    ///
+    /// ## Returns
+    ///
+    /// The length of the list after the insert, `-1` if `pivot` was not
+    /// found, and `0` if `key` does not exist.
+    pub fn l_insert(
+        &mut self,
+        key: &str,
+        before: bool,
+        pivot: &BuckTypes,
+        value: BuckTypes,
+    ) -> Result<i64, BuckEngineError> {
+        self.expire_if_needed(key);
+        self.reserve_memory(key)?;
+
+        match self.list(key)? {
+            Some(list) if !list.data.contains(pivot) => return Ok(-1),
+            Some(_) => {}
+            None => return Ok(0),
+        }
+
+        let length = match self.staged_list(key, false)? {
+            Some(list) => list.insert(pivot, value, before).map_or(-1, |len| len as i64),
+            None => 0,
+        };
+
+        self.collection_changed(key)?;
+        Ok(length)
+    }
+
+    /// Remove `count` occurrences of `value` from the list stored at `key`.
+    ///
+    /// A positive `count` removes from head to tail, a negative one from tail
+    /// to head, and `0` removes every occurrence.
+    ///
+    /// ## Returns
+    ///
+    /// The number of removed elements.
+    pub fn l_rem(&mut self, key: &str, count: i64, value: &BuckTypes) -> Result<usize, BuckEngineError> {
+        self.expire_if_needed(key);
+
+        match self.list(key)? {
+            Some(list) if list.data.contains(value) => {}
+            _ => return Ok(0),
+        }
+
+        let removed = match self.staged_list(key, false)? {
+            Some(list) => list.remove(count, value),
+            None => 0,
+        };
+
+        self.collection_changed(key)?;
+        Ok(removed)
+    }
+
+    /// Trim the list stored at `key` to the elements from `start` to `stop` inclusive.
+    ///
+    /// A list that becomes empty is deleted.
+    pub fn l_trim(&mut self, key: &str, start: i64, stop: i64) -> Result<BuckLog, BuckEngineError> {
+        self.expire_if_needed(key);
+
+        if self.list(key)?.is_none() {
+            return Ok(BuckLog::UpdateOk(key.to_owned()));
+        }
+
+        if let Some(list) = self.staged_list(key, false)? {
+            list.trim(start, stop);
+        }
+
+        self.collection_changed(key)?;
+        Ok(BuckLog::UpdateOk(key.to_owned()))
+    }
+
+    /// The list stored at `key`, `None` if there is no such key.
+    fn list(&self, key: &str) -> Result<Option<&BuckList>, BuckEngineError> {
+        match self.get(key) {
+            Ok(BuckTypes::List(list)) => Ok(Some(list)),
+            Ok(_) => Err(BuckEngineError::TypeNotSupported(key.to_owned())),
+            Err(BuckEngineError::KeyNotFound(_)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The staged copy of the list stored at `key`, ready to be changed.
+    ///
+    /// A list that is only committed is copied to `uncommitted_data` first,
+    /// so that the change is applied on commit and dropped on abort. With
+    /// `create`, a missing key is staged as an empty list.
+    fn staged_list(&mut self, key: &str, create: bool) -> Result<Option<&mut BuckList>, BuckEngineError> {
+        if self.status == TransactionStatus::Abort {
+            return Err(BuckEngineError::AbortError);
+        }
+
+        if !self.uncommitted_data.contains_key(key) {
+            let list = match self.list(key)? {
+                Some(list) => list.clone(),
+                None if create => BuckList::new(),
+                None => return Ok(None),
+            };
+
+            self.uncommitted_data.insert(key.to_owned(), BuckTypes::List(list));
+        }
+
+        self.status = TransactionStatus::Uncommitted;
+
+        match self.uncommitted_data.get_mut(key) {
+            Some(BuckTypes::List(list)) => Ok(Some(list)),
+            Some(_) => Err(BuckEngineError::TypeNotSupported(key.to_owned())),
+            None => Ok(None),
+        }
+    }
+
+    /// Bookkeeping after the staged collection at `key` changed.
+    ///
+    /// An empty collection is deleted, like every other command leaves it.
+    fn collection_changed(&mut self, key: &str) -> Result<(), BuckEngineError> {
+        let value = match self.uncommitted_data.get(key) {
+            Some(BuckTypes::List(list)) if list.is_empty() => None,
+            Some(BuckTypes::Sets(set)) if set.is_empty() => None,
+            Some(BuckTypes::Hash(hash)) if hash.is_empty() => None,
+            Some(BuckTypes::SortedSet(zset)) if zset.is_empty() => None,
+            Some(value) => Some(value.clone()),
+            None => return Ok(()),
+        };
+
+        match value {
+            Some(value) => {
+                if self.is_shard_active {
+                    self.with_shard(key, |shard| shard.insert(key.to_owned(), value.clone()))?;
+                }
+
+                self.track_write(key);
+            }
+            None => {
+                self.remove(key)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Add the specified members to the set stored at key.
+    /// Specified members that are already a member of this set are ignored.
+    /// 
+    /// If key does not exist, a new set is created before adding the specified members.
+    ///
+    /// An error is returned when the value stored at key is not a set.
+    pub fn s_add(&mut self, key: String, value: BuckTypes) -> Result<BuckLog, BuckEngineError> {
+        // sadd key value1 value2 ... | start...end
+
+        // check value type is `Setable` and wrap it into a `Setable` if it is.
+        let value = self.is_setable_value(value)?;
+        self.expire_if_needed(&key);
+        self.reserve_memory(&key)?;
+
+        // if key does not exist, create a new set
+        if let Some(set) = self.staged_sets(&key, true)? {
+            set.insert(&[value]);
+        }
+
+        self.collection_changed(&key)?;
+        Ok(BuckLog::InsertOk(key))
+    }
+
+    /// Remove the specified members from the set stored at key.
+    /// Specified members that are not a member of this set are ignored.
+    ///
+    /// If key does not exist, it is treated as an empty set and this command returns 0.
+    ///
+    /// An error is returned when the value stored at key is not a set.
+    ///
+    /// ## Returns
+    ///
+    /// Integer reply: the number of members that were removed from the set, not including non existing members.
+    pub fn s_rem(&mut self, key: String, value: BuckTypes) -> Result<BuckLog, BuckEngineError> {
+        // check value type is `Setable` and wrap it into a `Setable` if it is.
+        let value = self.is_setable_value(value)?;
+        self.expire_if_needed(&key);
+
+        match self.sets(&key)? {
+            Some(set) if set.is_member(&value) => {}
+            _ => return Ok(BuckLog::RemoveOk(key)),
+        }
+
+        if let Some(set) = self.staged_sets(&key, false)? {
+            set.remove(&[value]);
+        }
+
+        self.collection_changed(&key)?;
+        Ok(BuckLog::RemoveOk(key))
+    }
+
+    /// Returns the members of the set resulting from the intersection of all the given sets.
+    ///
+    /// Keys that do not exist are considered to be empty sets.
+    /// With one of the keys being an empty set, the resulting set is also empty
+    /// (since set intersection with an empty set always results in an empty set).
+    ///
+    /// ## Returns
+    ///
+    /// Array reply: list with members of the resulting set.
+    pub fn s_inter(&mut self, keys: &[String]) -> Result<BuckSets, BuckEngineError> {
+        let sets = self.sets_of(keys)?;
+
+        // a missing key is an empty set, and so is the intersection
+        let sets: Vec<&BuckSets> = match sets.into_iter().collect::<Option<Vec<_>>>() {
+            Some(sets) => sets,
+            None => return Ok(BuckSets::new()),
+        };
+
+        match sets.split_first() {
+            Some((first, others)) => Ok(first.intersection(others)),
+            None => Ok(BuckSets::new()),
+        }
+    }
+
+    /// Returns the members of the set resulting from the union of all the given sets.
+    ///
+    /// Keys that do not exist are considered to be empty sets.
+    pub fn s_union(&mut self, keys: &[String]) -> Result<BuckSets, BuckEngineError> {
+        let sets: Vec<&BuckSets> = self.sets_of(keys)?.into_iter().flatten().collect();
+
+        match sets.split_first() {
+            Some((first, others)) => Ok(first.union(others)),
+            None => Ok(BuckSets::new()),
+        }
+    }
+
+    /// Returns the members of the first set that are in none of the following sets.
+    ///
+    /// Keys that do not exist are considered to be empty sets.
+    pub fn s_diff(&mut self, keys: &[String]) -> Result<BuckSets, BuckEngineError> {
+        let sets = self.sets_of(keys)?;
+
+        match sets.split_first() {
+            Some((Some(first), others)) => {
+                let others: Vec<&BuckSets> = others.iter().flatten().copied().collect();
+
+                Ok(first.difference(&others))
+            }
+            _ => Ok(BuckSets::new()),
+        }
+    }
+
+    /// Returns the size of the intersection of all the given sets.
+    ///
+    /// A `limit` other than `0` stops counting once it is reached. The
+    /// intersection itself is never built.
+    pub fn s_inter_card(&mut self, keys: &[String], limit: usize) -> Result<usize, BuckEngineError> {
+        let sets = self.sets_of(keys)?;
+
+        // a missing key is an empty set, and so is the intersection
+        let sets: Vec<&BuckSets> = match sets.into_iter().collect::<Option<Vec<_>>>() {
+            Some(sets) => sets,
+            None => return Ok(0),
+        };
+
+        match sets.split_first() {
+            Some((first, others)) => Ok(first.intersection_card(others, limit)),
+            None => Ok(0),
+        }
+    }
+
+    /// Store the intersection of the given sets at `destination`.
+    ///
+    /// `destination` is overwritten whatever it holds, and deleted if the
+    /// result is empty.
+    ///
+    /// ## Returns
+    ///
+    /// The number of members in the resulting set.
+    pub fn s_inter_store(&mut self, destination: String, keys: &[String]) -> Result<usize, BuckEngineError> {
+        let set = self.s_inter(keys)?;
+
+        self.store_sets(destination, set)
+    }
+
+    /// Store the union of the given sets at `destination`, like `s_inter_store`.
+    pub fn s_union_store(&mut self, destination: String, keys: &[String]) -> Result<usize, BuckEngineError> {
+        let set = self.s_union(keys)?;
+
+        self.store_sets(destination, set)
+    }
+
+    /// Store the difference of the given sets at `destination`, like `s_inter_store`.
+    pub fn s_diff_store(&mut self, destination: String, keys: &[String]) -> Result<usize, BuckEngineError> {
+        let set = self.s_diff(keys)?;
+
+        self.store_sets(destination, set)
+    }
+
+    fn store_sets(&mut self, destination: String, set: BuckSets) -> Result<usize, BuckEngineError> {
+        let size = set.len();
+
+        match set.is_empty() {
+            true => {
+                self.expire_if_needed(&destination);
+                if self.get(&destination).is_ok() {
+                    self.remove(&destination)?;
+                }
+            }
+            false => {
+                self.insert(destination, BuckTypes::Sets(set))?;
+            }
+        }
+
+        Ok(size)
+    }
+
+    /// Returns if member is a member of the set stored at key.
+    ///
+    /// ## Returns
+    ///
+    /// `true` if the element is a member of the set.
+    ///
+    /// `false` if the element is not a member of the set, or if key does not exist.
+    pub fn s_is_member(&mut self, key: &str, value: BuckTypes) -> Result<bool, BuckEngineError> {
+        Ok(self.s_mis_member(key, vec![value])?.contains(&true))
+    }
+
+    /// Returns whether each of `values` is a member of the set stored at key.
+    pub fn s_mis_member(&mut self, key: &str, values: Vec<BuckTypes>) -> Result<Vec<bool>, BuckEngineError> {
+        self.expire_if_needed(key);
+        self.touch(key);
+
+        let values = values
+            .into_iter()
+            .map(|value| self.is_setable_value(value))
+            .collect::<Result<Vec<Setable>, BuckEngineError>>()?;
+
+        Ok(match self.sets(key)? {
+            Some(set) => values.iter().map(|value| set.is_member(value)).collect(),
+            None => vec![false; values.len()],
+        })
+    }
+
+    /// Returns all the members of the set stored at key, none if key does not exist.
+    pub fn s_members(&mut self, key: &str) -> Result<BuckSets, BuckEngineError> {
+        self.expire_if_needed(key);
+        self.touch(key);
+
+        Ok(self.sets(key)?.cloned().unwrap_or_default())
+    }
+
+    /// Returns the number of members of the set stored at key, `0` if key does not exist.
+    pub fn s_card(&mut self, key: &str) -> Result<usize, BuckEngineError> {
+        self.expire_if_needed(key);
+        self.touch(key);
+
+        Ok(self.sets(key)?.map_or(0, BuckSets::len))
+    }
+
+    /// Returns random members of the set stored at key, see `BuckSets::random_members`.
+    pub fn s_rand_member(&mut self, key: &str, count: i64) -> Result<Vec<Setable>, BuckEngineError> {
+        self.expire_if_needed(key);
+        self.touch(key);
+
+        Ok(self.sets(key)?.map_or(Ok(Vec::new()), |set| set.random_members(count))?)
+    }
+
+    /// Remove and return up to `count` random members of the set stored at key.
+    ///
+    /// A set that becomes empty is deleted.
+    pub fn s_pop(&mut self, key: &str, count: usize) -> Result<Vec<Setable>, BuckEngineError> {
+        self.expire_if_needed(key);
+
+        // popping nothing must not stage an untouched set
+        if count == 0 {
+            self.sets(key)?;
+            return Ok(Vec::new());
+        }
+
+        let popped = match self.staged_sets(key, false)? {
+            Some(set) => set.pop_random(count),
+            None => return Ok(Vec::new()),
+        };
+
+        self.collection_changed(key)?;
+        Ok(popped)
+    }
+
+    /// Move `value` from the set stored at `source` to the set stored at `destination`.
+    ///
+    /// ## Returns
+    ///
+    /// `true` if the member was moved, `false` if it is not a member of `source`.
+    pub fn s_move(&mut self, source: &str, destination: String, value: BuckTypes) -> Result<bool, BuckEngineError> {
+        let value = self.is_setable_value(value)?;
+        self.expire_if_needed(source);
+        self.expire_if_needed(&destination);
+
+        // both keys must hold sets before anything moves
+        self.sets(&destination)?;
+        match self.sets(source)? {
+            Some(set) if set.is_member(&value) => {}
+            _ => return Ok(false),
+        }
+
+        if source == destination {
+            return Ok(true);
+        }
+
+        self.reserve_memory(&destination)?;
+
+        if let Some(set) = self.staged_sets(source, false)? {
+            set.remove(std::slice::from_ref(&value));
+        }
+        self.collection_changed(source)?;
+
+        if let Some(set) = self.staged_sets(&destination, true)? {
+            set.insert(&[value]);
+        }
+        self.collection_changed(&destination)?;
+
+        Ok(true)
+    }
+
+    /// The set stored at `key`, `None` if there is no such key.
+    fn sets(&self, key: &str) -> Result<Option<&BuckSets>, BuckEngineError> {
+        match self.get(key) {
+            Ok(BuckTypes::Sets(set)) => Ok(Some(set)),
+            Ok(_) => Err(BuckEngineError::TypeNotSupported(key.to_owned())),
+            Err(BuckEngineError::KeyNotFound(_)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The sets stored at each of `keys`, failing if any of them holds another type.
+    fn sets_of(&mut self, keys: &[String]) -> Result<Vec<Option<&BuckSets>>, BuckEngineError> {
+        for key in keys {
+            self.expire_if_needed(key);
+            self.touch(key);
+        }
+
+        keys.iter().map(|key| self.sets(key)).collect()
+    }
+
+    /// The staged copy of the set stored at `key`, ready to be changed.
+    ///
+    /// Works like `staged_list`.
+    fn staged_sets(&mut self, key: &str, create: bool) -> Result<Option<&mut BuckSets>, BuckEngineError> {
+        if self.status == TransactionStatus::Abort {
+            return Err(BuckEngineError::AbortError);
+        }
+
+        if !self.uncommitted_data.contains_key(key) {
+            let set = match self.sets(key)? {
+                Some(set) => set.clone(),
+                None if create => BuckSets::new(),
+                None => return Ok(None),
+            };
+
+            self.uncommitted_data.insert(key.to_owned(), BuckTypes::Sets(set));
+        }
+
+        self.status = TransactionStatus::Uncommitted;
 
         match self.uncommitted_data.get_mut(key) {
-            Some(BuckTypes::List(list)) => {
-                let value = list.pop().unwrap();
-                Ok(BuckLog::ListPopOk(value.unwrap().to_string()))
-            }
-            _ => Err(BuckEngineError::KeyNotFound(key.to_owned())),
+            Some(BuckTypes::Sets(set)) => Ok(Some(set)),
+            Some(_) => Err(BuckEngineError::TypeNotSupported(key.to_owned())),
+            None => Ok(None),
         }
     }
 
-    /// Add the specified members to the set stored at key.
-    /// Specified members that are already a member of this set are ignored.
+    /// Push the specified fields to their respective values in the hash stored at key.
+    ///
+    /// This command overwrites the values of specified fields that exist in the hash. 
+    /// If key doesn't exist, a new key holding a hash is created.
     /// 
-    /// If key does not exist, a new set is created before adding the specified members.
+    /// ## Syntax
+    /// 
+    /// >>> `HSET key field:value [field:value ...]`
     ///
-    /// An error is returned when the value stored at key is not a set.
-    pub fn s_add(&mut self, key: String, value: BuckTypes) -> Result<BuckLog, BuckEngineError> {
-        // sadd key value1 value2 ... | start...end
+    /// ## Returns
+    ///
+    ///  Integer reply: The number of fields that were added.
+    /// 
+    /// ## Examples
+    /// 
+    /// ```text
+    /// HSET myhash field1 "Hello"
+    /// >>> (integer) 1
+    /// 
+    /// HSET myhash field2 "Hi" field3 "World"
+    /// >>> (integer) 2
+    /// ```
+    pub fn h_set(&mut self, key: String, fields: HashMap<String, BuckTypes>) -> Result<BuckLog, BuckEngineError> {
+        self.expire_if_needed(&key);
+        self.reserve_memory(&key)?;
 
-        // check value type is `Setable` and wrap it into a `Setable` if it is.
-        let value = self.is_setable_value(value)?;
+        let added = match self.staged_hash(&key, true)? {
+            Some(hash) => hash.hset(fields),
+            None => 0,
+        };
 
-        if self.status == TransactionStatus::Committed {
-            self.status = TransactionStatus::Uncommitted;
-        }
+        self.collection_changed(&key)?;
+        Ok(BuckLog::HSetOk(added))
+    }
 
-        if self.is_shard_active {
-            self.with_shard(&key, |shard| shard.insert_set_value_to_shard(&key, &value))?;
+    /// Set `field` in the hash stored at key, only if the field does not exist yet.
+    ///
+    /// ## Returns
+    ///
+    /// `true` if the field was set, `false` if it already existed.
+    pub fn h_set_nx(&mut self, key: String, field: &str, value: BuckTypes) -> Result<bool, BuckEngineError> {
+        self.expire_if_needed(&key);
+
+        // an existing field must not stage an untouched hash
+        if self.hash(&key)?.is_some_and(|hash| hash.data.contains_key(field)) {
+            return Ok(false);
         }
 
-        // if key does not exist, create a new set
-        if !self.uncommitted_data.contains_key(&key) {
-            let mut set = BuckSets::new();
-            set.insert(&[value.clone()]);
+        self.reserve_memory(&key)?;
+
+        let set = match self.staged_hash(&key, true)? {
+            Some(hash) => hash.set_if_absent(field, value),
+            None => false,
+        };
+
+        self.collection_changed(&key)?;
+        Ok(set)
+    }
+
+    /// Returns the value of `field` in the hash stored at key, `None` if either does not exist.
+    pub fn h_get(&mut self, key: &str, field: &str) -> Result<Option<BuckTypes>, BuckEngineError> {
+        Ok(self.h_mget(key, &[field])?.pop().flatten())
+    }
+
+    /// Returns the values of `fields` in the hash stored at key, in order.
+    pub fn h_mget(&mut self, key: &str, fields: &[&str]) -> Result<Vec<Option<BuckTypes>>, BuckEngineError> {
+        self.expire_if_needed(key);
+        self.touch(key);
+
+        Ok(match self.hash(key)? {
+            Some(hash) => fields.iter().map(|field| hash.hget(field).cloned()).collect(),
+            None => vec![None; fields.len()],
+        })
+    }
+
+    /// Returns all fields and values of the hash stored at key, sorted by field.
+    pub fn h_get_all(&mut self, key: &str) -> Result<Vec<(String, BuckTypes)>, BuckEngineError> {
+        self.expire_if_needed(key);
+        self.touch(key);
+
+        Ok(match self.hash(key)? {
+            Some(hash) => hash
+                .hget_all()
+                .into_iter()
+                .map(|(field, value)| (field.clone(), value.clone()))
+                .collect(),
+            None => Vec::new(),
+        })
+    }
+
+    /// Remove the specified fields from the hash stored at key.
+    ///
+    /// A hash that becomes empty is deleted.
+    ///
+    /// ## Returns
+    ///
+    /// The number of fields that were removed, not including non existing fields.
+    pub fn h_del(&mut self, key: &str, fields: &[&str]) -> Result<usize, BuckEngineError> {
+        self.expire_if_needed(key);
+
+        match self.hash(key)? {
+            Some(hash) if fields.iter().any(|field| hash.data.contains_key(*field)) => {}
+            _ => return Ok(0),
         }
 
-        match self.uncommitted_data.get_mut(&key) {
-            Some(BuckTypes::Sets(set)) => {
-                set.insert(&[value]);
-                Ok(BuckLog::InsertOk(key))
+        let removed = match self.staged_hash(key, false)? {
+            Some(hash) => hash.remove(fields),
+            None => 0,
+        };
+
+        self.collection_changed(key)?;
+        Ok(removed)
+    }
+
+    /// Returns if `field` is an existing field in the hash stored at key.
+    pub fn h_exists(&mut self, key: &str, field: &str) -> Result<bool, BuckEngineError> {
+        Ok(self.h_get(key, field)?.is_some())
+    }
+
+    /// Returns all field names in the hash stored at key, sorted.
+    pub fn h_keys(&mut self, key: &str) -> Result<Vec<String>, BuckEngineError> {
+        let fields = self.h_get_all(key)?;
+
+        Ok(fields.into_iter().map(|(field, _)| field).collect())
+    }
+
+    /// Returns all values in the hash stored at key, in the order of their fields.
+    pub fn h_vals(&mut self, key: &str) -> Result<Vec<BuckTypes>, BuckEngineError> {
+        let fields = self.h_get_all(key)?;
+
+        Ok(fields.into_iter().map(|(_, value)| value).collect())
+    }
+
+    /// Returns the number of fields in the hash stored at key, `0` if key does not exist.
+    pub fn h_len(&mut self, key: &str) -> Result<usize, BuckEngineError> {
+        self.expire_if_needed(key);
+        self.touch(key);
+
+        Ok(self.hash(key)?.map_or(0, BuckHash::len))
+    }
+
+    /// Returns the length of the value of `field` in the hash stored at key,
+    /// as it would be printed. `0` if either does not exist.
+    pub fn h_strlen(&mut self, key: &str, field: &str) -> Result<usize, BuckEngineError> {
+        Ok(self.h_get(key, field)?.map_or(0, |value| value.to_string().len()))
+    }
+
+    /// Increment the integer stored at `field` in the hash stored at key by `by`.
+    ///
+    /// A missing key or field is set to `0` before the operation.
+    ///
+    /// ## Returns
+    ///
+    /// The value of the field after the increment.
+    pub fn h_incr_by(&mut self, key: String, field: &str, by: i64) -> Result<i64, BuckEngineError> {
+        self.expire_if_needed(&key);
+
+        // a field that is not an integer must not stage an untouched hash
+        if let Some(Some(value)) = self.hash(&key)?.map(|hash| hash.hget(field)) {
+            if !matches!(value, BuckTypes::Integer(_)) {
+                return Err(BuckTypeError::NotAnInteger(field.to_owned()).into());
             }
-            _ => Err(BuckEngineError::TypeNotSupported(key.to_owned())),
         }
+
+        self.reserve_memory(&key)?;
+
+        let value = match self.staged_hash(&key, true)? {
+            Some(hash) => hash.increment_value(field, by)?,
+            None => return Err(BuckEngineError::KeyNotFound(key)),
+        };
+
+        self.collection_changed(&key)?;
+        Ok(value)
     }
 
-    /// Remove the specified members from the set stored at key.
-    /// Specified members that are not a member of this set are ignored.
-    ///
-    /// If key does not exist, it is treated as an empty set and this command returns 0.
+    /// Increment the number stored at `field` in the hash stored at key by `by`.
     ///
-    /// An error is returned when the value stored at key is not a set.
+    /// Works like `h_incr_by`, the field holds a float afterwards.
+    pub fn h_incr_by_float(&mut self, key: String, field: &str, by: f64) -> Result<f64, BuckEngineError> {
+        self.expire_if_needed(&key);
+
+        if let Some(Some(value)) = self.hash(&key)?.map(|hash| hash.hget(field)) {
+            if !matches!(value, BuckTypes::Integer(_) | BuckTypes::Float(_)) {
+                return Err(BuckTypeError::NotAFloat(field.to_owned()).into());
+            }
+        }
+
+        self.reserve_memory(&key)?;
+
+        let value = match self.staged_hash(&key, true)? {
+            Some(hash) => hash.increment_float(field, by)?,
+            None => return Err(BuckEngineError::KeyNotFound(key)),
+        };
+
+        self.collection_changed(&key)?;
+        Ok(value)
+    }
+
+    /// Iterate the fields of the hash stored at key, see `scan::scan`.
     ///
     /// ## Returns
     ///
-    /// Integer reply: the number of members that were removed from the set, not including non existing members.
-    pub fn s_rem(&mut self, key: String, value: BuckTypes) -> Result<BuckLog, BuckEngineError> {
-        // check value type is `Setable` and wrap it into a `Setable` if it is.
-        let value = self.is_setable_value(value)?;
+    /// The cursor to continue from, `0` once the iteration is over, and the
+    /// fields and values of this page.
+    pub fn h_scan(
+        &mut self,
+        key: &str,
+        cursor: u64,
+        pattern: Option<&str>,
+        count: usize,
+    ) -> Result<(u64, Vec<(String, BuckTypes)>), BuckEngineError> {
+        self.expire_if_needed(key);
+        self.touch(key);
+
+        let hash = match self.hash(key)? {
+            Some(hash) => hash,
+            None => return Ok((0, Vec::new())),
+        };
 
-        if self.status == TransactionStatus::Committed {
-            self.status = TransactionStatus::Uncommitted;
+        let (cursor, fields) = scan::scan(&hash.data, cursor, pattern, count);
+
+        Ok((
+            cursor,
+            fields
+                .into_iter()
+                .map(|(field, value)| (field.clone(), value.clone()))
+                .collect(),
+        ))
+    }
+
+    /// The hash stored at `key`, `None` if there is no such key.
+    fn hash(&self, key: &str) -> Result<Option<&BuckHash>, BuckEngineError> {
+        match self.get(key) {
+            Ok(BuckTypes::Hash(hash)) => Ok(Some(hash)),
+            Ok(_) => Err(BuckEngineError::TypeNotSupported(key.to_owned())),
+            Err(BuckEngineError::KeyNotFound(_)) => Ok(None),
+            Err(e) => Err(e),
         }
+    }
 
-        if self.is_shard_active {
-            self.with_shard(&key, |shard| shard.remove_set_key_value_from_shard(&key, &value))?;
+    /// The staged copy of the hash stored at `key`, ready to be changed.
+    ///
+    /// Works like `staged_list`.
+    fn staged_hash(&mut self, key: &str, create: bool) -> Result<Option<&mut BuckHash>, BuckEngineError> {
+        if self.status == TransactionStatus::Abort {
+            return Err(BuckEngineError::AbortError);
         }
 
-        match self.uncommitted_data.get_mut(&key) {
-            Some(BuckTypes::Sets(set)) => {
-                set.remove(&[value]);
-                Ok(BuckLog::RemoveOk(key))
-            }
-            _ => Err(BuckEngineError::TypeNotSupported(key.to_owned())),
+        if !self.uncommitted_data.contains_key(key) {
+            let hash = match self.hash(key)? {
+                Some(hash) => hash.clone(),
+                None if create => BuckHash::new(),
+                None => return Ok(None),
+            };
+
+            self.uncommitted_data.insert(key.to_owned(), BuckTypes::Hash(hash));
+        }
+
+        self.status = TransactionStatus::Uncommitted;
+
+        match self.uncommitted_data.get_mut(key) {
+            Some(BuckTypes::Hash(hash)) => Ok(Some(hash)),
+            Some(_) => Err(BuckEngineError::TypeNotSupported(key.to_owned())),
+            None => Ok(None),
         }
     }
 
-    /// Returns the members of the set resulting from the intersection of all the given sets.
+    /// Add the specified members with their scores to the sorted set stored at key.
     ///
-    /// Keys that do not exist are considered to be empty sets.
-    /// With one of the keys being an empty set, the resulting set is also empty
-    /// (since set intersection with an empty set always results in an empty set).
+    /// A member that is already in the sorted set gets its score updated, as
+    /// allowed by `flags`. If key does not exist, a new sorted set is created.
+    ///
+    /// ## Syntax
+    ///
+    /// >>> `ZADD key [NX|XX] [GT|LT] [CH] score member [score member ...]`
     ///
     /// ## Returns
     ///
-    /// Array reply: list with members of the resulting set.
-    pub fn s_inter(
+    /// Integer reply: the number of members added, or with `CH` the number
+    /// of members added or updated.
+    pub fn z_add(
         &mut self,
         key: String,
-        others: Vec<String>,
-    ) -> Result<BuckLog, BuckEngineError> {
-        // Retrieve the initial set for the provided key
-        let initial_set = match self.uncommitted_data.get(&key) {
-            Some(BuckTypes::Sets(set)) => set.clone(),
-            // if one of key does not exist, return empty set
-            None => return Ok(BuckLog::SetsIntersectionOk(key, vec![])),
-            // if the value is not a set, return error
-            _ => return Err(BuckEngineError::TypeNotSupported(key.to_owned())),
-        };
+        members: Vec<(f64, String)>,
+        flags: ZAddFlags,
+    ) -> Result<usize, BuckEngineError> {
+        self.expire_if_needed(&key);
+        self.reserve_memory(&key)?;
 
-        let mut other_sets: Vec<BuckSets> = Vec::new();
+        let mut count = 0;
 
-        for other_key in others {
-            match self.uncommitted_data.get(&other_key) {
-                Some(BuckTypes::Sets(set)) => other_sets.push(set.clone()),
-                // if one of key does not exist, return empty set
-                None => return Ok(BuckLog::SetsIntersectionOk(key, vec![])),
-                // if the value is not a set, return error
-                _ => return Err(BuckEngineError::TypeNotSupported(key.to_owned())),
+        if let Some(zset) = self.staged_zset(&key, !flags.xx)? {
+            for (score, member) in members {
+                let (added, changed) = zset.add(member, score, &flags);
+
+                if added || flags.ch && changed {
+                    count += 1;
+                }
             }
         }
 
-        let intersection = initial_set.intersection(&other_sets);
-        // let result = intersection.data.into_iter().collect();
-        let result = intersection.data.into_iter().map(|s| s.to_string()).collect();
-
-        Ok(BuckLog::SetsIntersectionOk(key, result))
+        self.collection_changed(&key)?;
+        Ok(count)
     }
 
-    /// Returns if member is a member of the set stored at key.
+    /// Remove the specified members from the sorted set stored at key.
     ///
-    /// ## Returns
+    /// A sorted set that becomes empty is deleted.
     ///
-    /// `1` if the element is a member of the set.
+    /// ## Returns
     ///
-    /// `0` if the element is not a member of the set, or if key does not exist.
-    pub fn s_is_member(
+    /// The number of members that were removed, not including non existing members.
+    pub fn z_rem(&mut self, key: &str, members: &[String]) -> Result<usize, BuckEngineError> {
+        self.expire_if_needed(key);
+
+        match self.zset(key)? {
+            Some(zset) if members.iter().any(|member| zset.score(member).is_some()) => {}
+            _ => return Ok(0),
+        }
+
+        let removed = match self.staged_zset(key, false)? {
+            Some(zset) => zset.remove(members),
+            None => 0,
+        };
+
+        self.collection_changed(key)?;
+        Ok(removed)
+    }
+
+    /// Returns the score of `member` in the sorted set stored at key, `None` if either does not exist.
+    pub fn z_score(&mut self, key: &str, member: &str) -> Result<Option<f64>, BuckEngineError> {
+        self.expire_if_needed(key);
+        self.touch(key);
+
+        Ok(self.zset(key)?.and_then(|zset| zset.score(member)))
+    }
+
+    /// Returns the rank of `member` in the sorted set stored at key, counting
+    /// from the lowest score or, with `reverse`, from the highest.
+    pub fn z_rank(
         &mut self,
-        key: String,
-        value: BuckTypes,
-    ) -> Result<BuckLog, BuckEngineError> {
-        unimplemented!()
+        key: &str,
+        member: &str,
+        reverse: bool,
+    ) -> Result<Option<usize>, BuckEngineError> {
+        self.expire_if_needed(key);
+        self.touch(key);
+
+        Ok(self.zset(key)?.and_then(|zset| zset.rank(member, reverse)))
     }
 
-    /// Push the specified fields to their respective values in the hash stored at key.
+    /// Returns the number of members in the sorted set stored at key, `0` if key does not exist.
+    pub fn z_card(&mut self, key: &str) -> Result<usize, BuckEngineError> {
+        self.expire_if_needed(key);
+        self.touch(key);
+
+        Ok(self.zset(key)?.map_or(0, BuckSortedSet::len))
+    }
+
+    /// Returns the members of the sorted set stored at key selected by `spec`,
+    /// with their scores.
+    pub fn z_range(
+        &mut self,
+        key: &str,
+        spec: &ZRangeSpec,
+    ) -> Result<Vec<(String, f64)>, BuckEngineError> {
+        self.expire_if_needed(key);
+        self.touch(key);
+
+        Ok(self
+            .zset(key)?
+            .map_or_else(Vec::new, |zset| zset.range(spec)))
+    }
+
+    /// Returns the number of members of the sorted set stored at key with a
+    /// score between `min` and `max`.
+    pub fn z_count(
+        &mut self,
+        key: &str,
+        min: &ScoreBound,
+        max: &ScoreBound,
+    ) -> Result<usize, BuckEngineError> {
+        self.expire_if_needed(key);
+        self.touch(key);
+
+        Ok(self.zset(key)?.map_or(0, |zset| zset.count(min, max)))
+    }
+
+    /// Increment the score of `member` in the sorted set stored at key by `by`.
     ///
-    /// This command overwrites the values of specified fields that exist in the hash. 
-    /// If key doesn't exist, a new key holding a hash is created.
-    /// 
-    /// ## Syntax
-    /// 
-    /// >>> `HSET key field:value [field:value ...]`
+    /// A missing key or member is added with a score of `0` first.
     ///
     /// ## Returns
     ///
-    ///  Integer reply: The number of fields that were added.
-    /// 
-    /// ## Examples
-    /// 
-    /// ```
-    /// HSET myhash field1 "Hello"
-    /// >>> (integer) 1
-    /// 
-    /// HSET myhash field2 "Hi" field3 "World"
-    /// >>> (integer) 2
-    /// ```
-    pub fn h_set(&mut self, key: String, fields: HashMap<String, BuckTypes>) -> Result<BuckLog, BuckEngineError> {
-        if self.status == TransactionStatus::Committed {
-            self.status = TransactionStatus::Uncommitted;
+    /// The new score of the member.
+    pub fn z_incr_by(
+        &mut self,
+        key: String,
+        by: f64,
+        member: &str,
+    ) -> Result<f64, BuckEngineError> {
+        self.expire_if_needed(&key);
+
+        // a score that would become `NaN` must not stage an untouched set
+        if let Some(score) = self.zset(&key)?.and_then(|zset| zset.score(member)) {
+            if (score + by).is_nan() {
+                return Err(BuckTypeError::ScoreIsNan(member.to_owned()).into());
+            }
         }
 
-        if self.is_shard_active {
-            self.with_shard(&key, |shard| shard.insert_hash_key_value_to_shard(&key, fields.clone()))?;
+        self.reserve_memory(&key)?;
+
+        let score = match self.staged_zset(&key, true)? {
+            Some(zset) => zset.increment(member, by)?,
+            None => return Err(BuckEngineError::KeyNotFound(key)),
+        };
+
+        self.collection_changed(&key)?;
+        Ok(score)
+    }
+
+    /// Remove and return up to `count` members with the lowest scores or,
+    /// with `max`, the highest, from the sorted set stored at key.
+    ///
+    /// A sorted set that becomes empty is deleted.
+    pub fn z_pop(
+        &mut self,
+        key: &str,
+        count: usize,
+        max: bool,
+    ) -> Result<Vec<(String, f64)>, BuckEngineError> {
+        self.expire_if_needed(key);
+
+        // popping nothing must not stage an untouched set
+        if count == 0 {
+            self.zset(key)?;
+            return Ok(Vec::new());
         }
 
-        match self.uncommitted_data.get_mut(&key) {
-            Some(BuckTypes::Hash(hash)) => {
-                hash.hset(fields);
-                let length = hash.data.len();
-                Ok(BuckLog::HSetOk(length))
+        let popped = match self.staged_zset(key, false)? {
+            Some(zset) => zset.pop(count, max),
+            None => return Ok(Vec::new()),
+        };
+
+        self.collection_changed(key)?;
+        Ok(popped)
+    }
+
+    /// Store the union of the sorted sets stored at `keys` at `destination`.
+    ///
+    /// The scores of each set are multiplied by its weight, `1` by default,
+    /// and combined with `aggregate` where a member is in several sets.
+    /// Keys that do not exist are considered to be empty sets.
+    ///
+    /// `destination` is overwritten whatever it holds, and deleted if the
+    /// result is empty.
+    ///
+    /// ## Returns
+    ///
+    /// The number of members in the resulting sorted set.
+    pub fn z_union_store(
+        &mut self,
+        destination: String,
+        keys: &[String],
+        weights: Option<&[f64]>,
+        aggregate: Aggregate,
+    ) -> Result<usize, BuckEngineError> {
+        let zsets = self.zsets_of(keys)?;
+        let weighted = weigh(&zsets, weights);
+        let zset = BuckSortedSet::union(&weighted, aggregate);
+
+        self.store_zset(destination, zset)
+    }
+
+    /// Store the intersection of the sorted sets stored at `keys` at
+    /// `destination`, like `z_union_store`.
+    pub fn z_inter_store(
+        &mut self,
+        destination: String,
+        keys: &[String],
+        weights: Option<&[f64]>,
+        aggregate: Aggregate,
+    ) -> Result<usize, BuckEngineError> {
+        let zsets = self.zsets_of(keys)?;
+
+        // a missing key is an empty set, and so is the intersection
+        let zset = match zsets.iter().any(Option::is_none) {
+            true => BuckSortedSet::new(),
+            false => BuckSortedSet::intersection(&weigh(&zsets, weights), aggregate),
+        };
+
+        self.store_zset(destination, zset)
+    }
+
+    fn store_zset(
+        &mut self,
+        destination: String,
+        zset: BuckSortedSet,
+    ) -> Result<usize, BuckEngineError> {
+        let size = zset.len();
+
+        match zset.is_empty() {
+            true => {
+                self.expire_if_needed(&destination);
+                if self.get(&destination).is_ok() {
+                    self.remove(&destination)?;
+                }
             }
-            None => {
-                let mut hash = BuckHash::new();
-                hash.hset(fields);
-                self.uncommitted_data.insert(key.clone(), BuckTypes::Hash(hash.clone()));
-                let length = hash.data.len();
-                Ok(BuckLog::HSetOk(length))
+            false => {
+                self.insert(destination, BuckTypes::SortedSet(zset))?;
             }
-            _ => Err(BuckEngineError::TypeNotSupported(key.to_owned())),
+        }
+
+        Ok(size)
+    }
+
+    /// The sorted set stored at `key`, `None` if there is no such key.
+    fn zset(&self, key: &str) -> Result<Option<&BuckSortedSet>, BuckEngineError> {
+        match self.get(key) {
+            Ok(BuckTypes::SortedSet(zset)) => Ok(Some(zset)),
+            Ok(_) => Err(BuckEngineError::TypeNotSupported(key.to_owned())),
+            Err(BuckEngineError::KeyNotFound(_)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The sorted sets stored at each of `keys`, failing if any of them holds another type.
+    fn zsets_of(
+        &mut self,
+        keys: &[String],
+    ) -> Result<Vec<Option<&BuckSortedSet>>, BuckEngineError> {
+        for key in keys {
+            self.expire_if_needed(key);
+            self.touch(key);
+        }
+
+        keys.iter().map(|key| self.zset(key)).collect()
+    }
+
+    /// The staged copy of the sorted set stored at `key`, ready to be changed.
+    ///
+    /// Works like `staged_list`.
+    fn staged_zset(
+        &mut self,
+        key: &str,
+        create: bool,
+    ) -> Result<Option<&mut BuckSortedSet>, BuckEngineError> {
+        if self.status == TransactionStatus::Abort {
+            return Err(BuckEngineError::AbortError);
+        }
+
+        if !self.uncommitted_data.contains_key(key) {
+            let zset = match self.zset(key)? {
+                Some(zset) => zset.clone(),
+                None if create => BuckSortedSet::new(),
+                None => return Ok(None),
+            };
+
+            self.uncommitted_data
+                .insert(key.to_owned(), BuckTypes::SortedSet(zset));
+        }
+
+        self.status = TransactionStatus::Uncommitted;
+
+        match self.uncommitted_data.get_mut(key) {
+            Some(BuckTypes::SortedSet(zset)) => Ok(Some(zset)),
+            Some(_) => Err(BuckEngineError::TypeNotSupported(key.to_owned())),
+            None => Ok(None),
         }
     }
 
@@ -445,9 +3050,14 @@ impl BuckDB {
     }
 
     pub fn get_collections_length(&self, key: String) -> Result<usize, BuckEngineError> {
+        if self.is_expired(&key) {
+            return self.get_length_from_value(None, key);
+        }
+
         match self.status {
             TransactionStatus::Uncommitted => {
-                self.get_length_from_value(self.uncommitted_data.get(&key), key)
+                let value = self.uncommitted_data.get(&key).or_else(|| self.data.get(&key));
+                self.get_length_from_value(value, key)
             }
             TransactionStatus::Committed => self.get_length_from_value(self.data.get(&key), key),
             _ => Err(BuckEngineError::AbortError),
@@ -463,6 +3073,7 @@ impl BuckDB {
             Some(BuckTypes::List(list)) => Ok(list.len()),
             Some(BuckTypes::Hash(hash)) => Ok(hash.len()),
             Some(BuckTypes::Sets(set)) => Ok(set.len()),
+            Some(BuckTypes::SortedSet(zset)) => Ok(zset.len()),
             Some(BuckTypes::String(string)) => Ok(string.len()),
             _ => Err(BuckEngineError::LengthNotSupported(key.to_owned())),
         }
@@ -470,17 +3081,25 @@ impl BuckDB {
 
     /// Get the type of a value in the database.
     pub fn type_of(&self, key: &str) -> Result<String, BuckEngineError> {
-        let value = self.get(key)?;
-
-        match value {
-            BuckTypes::String(_) => Ok("string".to_owned()),
-            BuckTypes::Integer(_) => Ok("integer".to_owned()),
-            BuckTypes::Float(_) => Ok("float".to_owned()),
-            BuckTypes::Boolean(_) => Ok("boolean".to_owned()),
-            BuckTypes::List(_) => Ok("list".to_owned()),
-            BuckTypes::Hash(_) => Ok("hash".to_owned()),
-            BuckTypes::Sets(_) => Ok("sets".to_owned()),
-            BuckTypes::Unknown(_) => Ok("unknown".to_owned()),
-        }
+        Ok(self.get(key)?.type_name().to_owned())
     }
 }
+
+/// Pair each of `zsets` with its weight, dropping the missing ones.
+fn weigh<'a>(
+    zsets: &[Option<&'a BuckSortedSet>],
+    weights: Option<&[f64]>,
+) -> Vec<(&'a BuckSortedSet, f64)> {
+    zsets
+        .iter()
+        .enumerate()
+        .filter_map(|(i, zset)| {
+            let weight = weights
+                .and_then(|weights| weights.get(i))
+                .copied()
+                .unwrap_or(1.0);
+
+            zset.map(|zset| (zset, weight))
+        })
+        .collect()
+}
@@ -1,6 +1,79 @@
+//! errors.rs
+//!
+//! `BuckError` is the root of the error hierarchy. Every layer keeps its own
+//! error enum (`BuckEngineError`, `BuckParserError`, `BuckTypeError` and
+//! `EncodingError`), and converts into `BuckError` on the way out to the
+//! caller.
+//!
+//! Each error has a stable code, such as `KEY_NOT_FOUND`, that clients can
+//! match on instead of the message. A code never changes once released.
+
 use std::fmt;
 
-#[derive(Debug, Eq, PartialEq)]
+use crate::encoding::errors::EncodingError;
+use crate::parser::errors::BuckParserError;
+use crate::types::errors::BuckTypeError;
+
+#[derive(Debug, PartialEq)]
+pub enum BuckError {
+    Engine(BuckEngineError),
+    Parser(BuckParserError),
+    Type(BuckTypeError),
+    Encoding(EncodingError),
+}
+
+impl BuckError {
+    /// The stable code of the error.
+    pub fn code(&self) -> &'static str {
+        match self {
+            BuckError::Engine(e) => e.code(),
+            BuckError::Parser(e) => e.code(),
+            BuckError::Type(e) => e.code(),
+            BuckError::Encoding(e) => e.code(),
+        }
+    }
+}
+
+impl fmt::Display for BuckError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuckError::Engine(e) => write!(f, "{}", e),
+            BuckError::Parser(e) => write!(f, "{}", e),
+            BuckError::Type(e) => write!(f, "{}", e),
+            BuckError::Encoding(e) => write!(f, "[Error] Encoding error: {}", e),
+        }
+    }
+}
+
+impl From<BuckEngineError> for BuckError {
+    fn from(e: BuckEngineError) -> Self {
+        match e {
+            // a type error only passes through the engine
+            BuckEngineError::TypeError(e) => BuckError::Type(e),
+            e => BuckError::Engine(e),
+        }
+    }
+}
+
+impl From<BuckParserError> for BuckError {
+    fn from(e: BuckParserError) -> Self {
+        BuckError::Parser(e)
+    }
+}
+
+impl From<BuckTypeError> for BuckError {
+    fn from(e: BuckTypeError) -> Self {
+        BuckError::Type(e)
+    }
+}
+
+impl From<EncodingError> for BuckError {
+    fn from(e: EncodingError) -> Self {
+        BuckError::Encoding(e)
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub enum BuckEngineError {
     KeyNotFound(String),
     ValueNotFound(String),
@@ -12,6 +85,49 @@ pub enum BuckEngineError {
     Unknown,
     LengthNotSupported(String),
     TypeNotSupported(String),
+    PersistenceError(String),
+    OutOfMemory,
+    InvalidConfig(String),
+    NestedTransaction,
+    NoTransaction(String),
+    WatchInsideTransaction,
+    WatchConflict(String),
+    SessionRequired(String),
+    InvalidDatabase(usize),
+    TypeError(BuckTypeError),
+}
+
+impl BuckEngineError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            BuckEngineError::KeyNotFound(_) => "KEY_NOT_FOUND",
+            BuckEngineError::ValueNotFound(_) => "VALUE_NOT_FOUND",
+            BuckEngineError::InvalidType => "INVALID_TYPE",
+            BuckEngineError::AlreadyCommitted => "ALREADY_COMMITTED",
+            BuckEngineError::NoBackup => "NO_BACKUP",
+            BuckEngineError::AbortError => "ABORT_FAILED",
+            BuckEngineError::ShardingNotActive => "SHARDING_NOT_ACTIVE",
+            BuckEngineError::Unknown => "UNKNOWN",
+            BuckEngineError::LengthNotSupported(_) => "LENGTH_NOT_SUPPORTED",
+            BuckEngineError::TypeNotSupported(_) => "WRONG_TYPE",
+            BuckEngineError::PersistenceError(_) => "PERSISTENCE_FAILED",
+            BuckEngineError::OutOfMemory => "OUT_OF_MEMORY",
+            BuckEngineError::InvalidConfig(_) => "INVALID_CONFIG",
+            BuckEngineError::NestedTransaction => "NESTED_TRANSACTION",
+            BuckEngineError::NoTransaction(_) => "NO_TRANSACTION",
+            BuckEngineError::WatchInsideTransaction => "WATCH_INSIDE_TRANSACTION",
+            BuckEngineError::WatchConflict(_) => "WATCH_CONFLICT",
+            BuckEngineError::SessionRequired(_) => "SESSION_REQUIRED",
+            BuckEngineError::InvalidDatabase(_) => "INVALID_DATABASE",
+            BuckEngineError::TypeError(e) => e.code(),
+        }
+    }
+}
+
+impl From<BuckTypeError> for BuckEngineError {
+    fn from(e: BuckTypeError) -> Self {
+        BuckEngineError::TypeError(e)
+    }
 }
 
 impl fmt::Display for BuckEngineError {
@@ -31,6 +147,34 @@ impl fmt::Display for BuckEngineError {
             BuckEngineError::TypeNotSupported(typ) => {
                 write!(f, "[Error] Type not supported: {}", typ)
             }
+            BuckEngineError::PersistenceError(msg) => {
+                write!(f, "[Error] Persistence failed: {}", msg)
+            }
+            BuckEngineError::InvalidConfig(msg) => {
+                write!(f, "[Error] Invalid configuration: {}", msg)
+            }
+            BuckEngineError::NestedTransaction => {
+                write!(f, "[Error] Transactions can not be nested")
+            }
+            BuckEngineError::NoTransaction(command) => {
+                write!(f, "[Error] {} without MULTI", command)
+            }
+            BuckEngineError::WatchInsideTransaction => {
+                write!(f, "[Error] WATCH inside a transaction is not allowed")
+            }
+            BuckEngineError::WatchConflict(key) => {
+                write!(f, "[Error] Transaction aborted, watched key changed: {}", key)
+            }
+            BuckEngineError::SessionRequired(command) => {
+                write!(f, "[Error] {} needs a client session", command)
+            }
+            BuckEngineError::InvalidDatabase(index) => {
+                write!(f, "[Error] Database index out of range: {}", index)
+            }
+            BuckEngineError::TypeError(e) => write!(f, "{}", e),
+            BuckEngineError::OutOfMemory => {
+                write!(f, "[Error] Out of memory: command not allowed when used memory > 'maxmemory'")
+            }
         }
     }
 }
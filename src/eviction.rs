@@ -0,0 +1,268 @@
+//! eviction.rs
+//!
+//! Memory accounting and key eviction for BuckDB.
+//!
+//! The engine keeps an estimate of how many bytes every key occupies. When a
+//! `maxmemory` budget is configured and a write would exceed it, keys are
+//! evicted according to the selected `EvictionPolicy` until the database fits
+//! again.
+//!
+//! Candidates are picked by scanning every eligible key, which is exact but
+//! linear in the number of keys. That is fine for the sizes buck targets.
+//!
+//! Databases can share one budget, see `MemoryTracker::share_budget`. The
+//! budget is then checked against the bytes used by all of them together,
+//! but keys are only ever evicted from the database being written to.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub enum EvictionPolicy {
+    /// Reject writes once the budget is reached.
+    #[default]
+    NoEviction,
+    /// Evict the least recently used key.
+    AllKeysLru,
+    /// Evict the least frequently used key.
+    AllKeysLfu,
+    /// Evict a random key.
+    AllKeysRandom,
+    /// Evict the least recently used key among those with a timeout.
+    VolatileLru,
+}
+
+impl EvictionPolicy {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<EvictionPolicy> {
+        match s.to_lowercase().as_str() {
+            "noeviction" => Some(EvictionPolicy::NoEviction),
+            "allkeys-lru" => Some(EvictionPolicy::AllKeysLru),
+            "allkeys-lfu" => Some(EvictionPolicy::AllKeysLfu),
+            "allkeys-random" => Some(EvictionPolicy::AllKeysRandom),
+            "volatile-lru" => Some(EvictionPolicy::VolatileLru),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EvictionPolicy::NoEviction => "noeviction",
+            EvictionPolicy::AllKeysLru => "allkeys-lru",
+            EvictionPolicy::AllKeysLfu => "allkeys-lfu",
+            EvictionPolicy::AllKeysRandom => "allkeys-random",
+            EvictionPolicy::VolatileLru => "volatile-lru",
+        }
+    }
+}
+
+impl fmt::Display for EvictionPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// How recently and how often a key has been used.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub struct KeyAccess {
+    /// Clock time of the last access, in milliseconds.
+    pub last_access: u64,
+    /// Number of accesses since the key was created.
+    pub frequency: u64,
+}
+
+#[derive(Debug)]
+pub struct MemoryTracker {
+    /// Upper bound on `total_memory`. `None` means unlimited.
+    pub maxmemory: Option<usize>,
+    pub policy: EvictionPolicy,
+    used_memory: usize,
+    /// Bytes used by every tracker that shares the budget, this one included.
+    shared: Arc<AtomicUsize>,
+    sizes: HashMap<String, usize>,
+    access: HashMap<String, KeyAccess>,
+    rng_state: u64,
+}
+
+// a clone has a budget of its own
+impl Clone for MemoryTracker {
+    fn clone(&self) -> Self {
+        MemoryTracker {
+            maxmemory: self.maxmemory,
+            policy: self.policy,
+            used_memory: self.used_memory,
+            shared: Arc::new(AtomicUsize::new(self.used_memory)),
+            sizes: self.sizes.clone(),
+            access: self.access.clone(),
+            rng_state: self.rng_state,
+        }
+    }
+}
+
+impl Drop for MemoryTracker {
+    fn drop(&mut self) {
+        self.shared.fetch_sub(self.used_memory, Ordering::Relaxed);
+    }
+}
+
+impl Default for MemoryTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryTracker {
+    pub fn new() -> Self {
+        MemoryTracker {
+            maxmemory: None,
+            policy: EvictionPolicy::NoEviction,
+            used_memory: 0,
+            shared: Arc::new(AtomicUsize::new(0)),
+            sizes: HashMap::new(),
+            access: HashMap::new(),
+            rng_state: 0x2545_f491_4f6c_dd1d,
+        }
+    }
+
+    /// Estimated number of bytes used by all tracked keys.
+    pub fn used_memory(&self) -> usize {
+        self.used_memory
+    }
+
+    /// Estimated number of bytes used by every tracker sharing the budget.
+    pub fn total_memory(&self) -> usize {
+        self.shared.load(Ordering::Relaxed)
+    }
+
+    /// Count the keys of `self` against the same budget as those of `other`.
+    pub fn share_budget(&mut self, other: &MemoryTracker) {
+        self.shared.fetch_sub(self.used_memory, Ordering::Relaxed);
+        self.shared = Arc::clone(&other.shared);
+        self.shared.fetch_add(self.used_memory, Ordering::Relaxed);
+    }
+
+    pub fn size_of(&self, key: &str) -> Option<usize> {
+        self.sizes.get(key).copied()
+    }
+
+    pub fn access_of(&self, key: &str) -> Option<KeyAccess> {
+        self.access.get(key).copied()
+    }
+
+    pub fn is_over_budget(&self) -> bool {
+        match self.maxmemory {
+            Some(max) => self.total_memory() > max,
+            None => false,
+        }
+    }
+
+    /// Record the current size of `key`. A size of zero stops tracking it.
+    pub fn set_size(&mut self, key: &str, size: usize) {
+        let previous = if size == 0 {
+            self.access.remove(key);
+            self.sizes.remove(key)
+        } else {
+            self.sizes.insert(key.to_owned(), size)
+        };
+
+        self.used_memory = self.used_memory - previous.unwrap_or(0) + size;
+        self.shared.fetch_sub(previous.unwrap_or(0), Ordering::Relaxed);
+        self.shared.fetch_add(size, Ordering::Relaxed);
+    }
+
+    /// Stop tracking `key` entirely.
+    pub fn forget(&mut self, key: &str) {
+        self.set_size(key, 0);
+    }
+
+    /// Stop tracking every key.
+    pub fn clear(&mut self) {
+        self.sizes.clear();
+        self.access.clear();
+        self.shared.fetch_sub(self.used_memory, Ordering::Relaxed);
+        self.used_memory = 0;
+    }
+
+    pub fn record_access(&mut self, key: &str, now: u64) {
+        if !self.sizes.contains_key(key) {
+            return;
+        }
+
+        let access = self.access.entry(key.to_owned()).or_default();
+        access.last_access = now;
+        access.frequency = access.frequency.saturating_add(1);
+    }
+
+    /// Pick the next key to evict under the current policy.
+    ///
+    /// `expires` holds the keys that have a timeout, which are the only
+    /// candidates for `volatile-lru`. `exclude` is never picked, so that the
+    /// key being written does not evict itself.
+    pub fn pick_victim(&mut self, expires: &HashMap<String, u64>, exclude: &str) -> Option<String> {
+        let roll = match self.policy {
+            EvictionPolicy::AllKeysRandom => self.next_random(),
+            _ => 0,
+        };
+        let candidates = self.sizes.keys().filter(|key| key.as_str() != exclude);
+
+        match self.policy {
+            EvictionPolicy::NoEviction => None,
+            EvictionPolicy::AllKeysLru => candidates
+                .min_by_key(|key| (self.access.get(*key).map(|a| a.last_access), *key))
+                .cloned(),
+            EvictionPolicy::AllKeysLfu => candidates
+                .min_by_key(|key| {
+                    let access = self.access.get(*key).copied().unwrap_or_default();
+                    (access.frequency, access.last_access, *key)
+                })
+                .cloned(),
+            EvictionPolicy::VolatileLru => candidates
+                .filter(|key| expires.contains_key(*key))
+                .min_by_key(|key| (self.access.get(*key).map(|a| a.last_access), *key))
+                .cloned(),
+            EvictionPolicy::AllKeysRandom => {
+                let mut keys: Vec<&String> = candidates.collect();
+                if keys.is_empty() {
+                    return None;
+                }
+
+                // sort so that the pick only depends on the random state
+                keys.sort();
+                let idx = (roll % keys.len() as u64) as usize;
+
+                Some(keys[idx].clone())
+            }
+        }
+    }
+
+    /// xorshift64*, plenty for picking eviction candidates.
+    fn next_random(&mut self) -> u64 {
+        let mut x = self.rng_state;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.rng_state = x;
+
+        x.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+}
+
+/// Parse a memory amount such as `1048576`, `512kb`, `100mb` or `1gb`.
+///
+/// Units are powers of 1024 and case insensitive.
+pub fn parse_memory(input: &str) -> Option<usize> {
+    let input = input.trim().to_lowercase();
+    let digits = input.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+
+    let multiplier: usize = match &input[digits.len()..] {
+        "" | "b" => 1,
+        "k" | "kb" => 1 << 10,
+        "m" | "mb" => 1 << 20,
+        "g" | "gb" => 1 << 30,
+        _ => return None,
+    };
+
+    digits.parse::<usize>().ok()?.checked_mul(multiplier)
+}
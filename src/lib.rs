@@ -1,7 +1,19 @@
+pub mod blocking;
+pub mod clock;
+pub mod databases;
 pub mod engine;
 pub mod errors;
+pub mod eviction;
 pub mod log;
+pub mod mvcc;
 pub mod parser;
+pub mod protocol;
+pub mod range;
+pub mod reply;
+pub mod scan;
+pub mod server;
+pub mod session;
 pub mod sharding;
+pub mod storage;
 pub mod types;
 pub mod encoding;
\ No newline at end of file
@@ -1,14 +1,16 @@
 //! log.rs
 //! 
-//! This module contains the logs that print out to the user when they execute
-//! a command.
-//! 
-//! For example, if the user types `insert key value` into CLI,
-//! BuckDB must print out `[log] key` to the user. Which is constructed by the
-//! BuckLog::InsertOk(String) enum.
+//! This module contains the logs that the engine returns when a command
+//! succeeds.
+//!
+//! A log is not rendered on its own: it converts into a `BuckReply`, and is
+//! displayed the way that reply is. For example, `insert key value` returns
+//! `BuckLog::InsertOk(String)`, which is shown as `OK`.
 
 use std::fmt;
 
+use crate::reply::BuckReply;
+
 #[derive(Debug, Eq, PartialEq)]
 pub enum BuckLog {
     InsertOk(String),
@@ -22,32 +24,23 @@ pub enum BuckLog {
     TransactionOk,
     RollbackOk,
     BackupOk,
+    LoadOk,
     TypeOk(String, String),
     ShardingEnableOk,
-    SetsIntersectionOk(String, Vec<String>),
     ClearOk,
+    PongOk,
+    IntegerOk(i64),
+    ConfigOk(String, String),
+    ConfigSetOk,
+    MultiOk,
+    QueuedOk,
+    WatchOk(Vec<String>),
+    UnwatchOk,
 }
 
+// A log is shown the same way as the reply it stands for.
 impl fmt::Display for BuckLog {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            BuckLog::InsertOk(key) => write!(f, "[log] {key}"),
-            BuckLog::GetOk(key) => write!(f, "{key}"),
-            BuckLog::RemoveOk(key) => write!(f, "[log] {key}"),
-            BuckLog::UpdateOk(key) => write!(f, "[log] {key}"),
-            BuckLog::ListPopOk(value) => write!(f, "(pop) {value}"),
-            BuckLog::HSetOk(length) => write!(f, "(integer) {length}"),
-            BuckLog::LengthOk(length) => write!(f, "(integer) {length}"),
-            BuckLog::ClearTransactionOk => write!(f, "[log] Transaction cleared"),
-            BuckLog::TransactionOk => write!(f, "[log] Transaction committed"),
-            BuckLog::RollbackOk => write!(f, "[log] Transaction rolled back"),
-            BuckLog::BackupOk => write!(f, "[Success] Database backed up"),
-            BuckLog::TypeOk(key, typ) => write!(f, "({typ}) {key}"),
-            BuckLog::ShardingEnableOk => write!(f, "[Success] Sharding enabled"),
-            BuckLog::SetsIntersectionOk(key, values) => {
-                write!(f, "({key}) {values:?}", values = values)
-            }
-            BuckLog::ClearOk => write!(f, ""),
-        }
+        write!(f, "{}", BuckReply::from(self))
     }
 }
@@ -1,10 +1,11 @@
 use std::io::{self, Write};
 
 use ansi_term::Color;
-use buck::{engine::BuckDB, parser::parse::parse_query};
+use buck::{engine::BuckDB, parser::parse::parse_query, session::BuckSession};
 
 fn main() {
     let mut db = BuckDB::new();
+    let mut session = BuckSession::new();
 
     println!("Enter 'exit' to quit.");
     loop {
@@ -18,9 +19,9 @@ fn main() {
 
         // Call Stack: input -> parse_query -> execute -> db -> Output
         match parse_query(input) {
-            Ok(query) => match query.execute(input, &mut db) {
-                Ok(log) => {
-                    println!("{}", log);
+            Ok(query) => match session.execute(query, input, &mut db) {
+                Ok(reply) => {
+                    println!("{}", reply);
                 }
                 Err(e) => {
                     eprintln!("[ERROR] {}", e);
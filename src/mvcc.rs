@@ -0,0 +1,165 @@
+//! mvcc.rs
+//!
+//! Multi-version storage behind `ReadView`s.
+//!
+//! Every commit gets a sequence number. A `ReadView` is pinned to the
+//! sequence number that was current when it was opened, and keeps seeing the
+//! committed data as of that point, no matter what is committed afterwards.
+//!
+//! `BuckDB::data` always holds the latest committed values. Older versions
+//! are only kept while some view may still need them: when a key changes and
+//! a view is open, the value it had is pushed onto that key's history. Once
+//! every view that could see a version is dropped, the version is garbage
+//! collected.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use crate::types::types::BuckTypes;
+
+/// A consistent, read-only view of the committed data.
+///
+/// The view stays pinned until it is dropped. It never sees staged writes,
+/// neither its own nor anybody else's.
+#[derive(Debug, Clone)]
+pub struct ReadView {
+    seq: u64,
+    _pin: Arc<()>,
+}
+
+impl ReadView {
+    /// The commit sequence number the view is pinned to.
+    pub fn seq(&self) -> u64 {
+        self.seq
+    }
+}
+
+/// A past value of a key. `None` means the key did not exist.
+#[derive(Debug, Clone, PartialEq)]
+struct Version {
+    /// Sequence number of the commit that made this value current.
+    seq: u64,
+    value: Option<BuckTypes>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct VersionStore {
+    /// One pin per sequence number that has open views. A pin is alive as
+    /// long as a `ReadView` holds a clone of it.
+    pins: BTreeMap<u64, Arc<()>>,
+    /// Past versions of every key that changed while views were open,
+    /// oldest first.
+    history: HashMap<String, Vec<Version>>,
+    /// Sequence number of the current value of keys that have a history.
+    /// Any other key has been unchanged since before the oldest view.
+    latest: HashMap<String, u64>,
+}
+
+impl VersionStore {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn open_view(&mut self, seq: u64) -> ReadView {
+        let pin = self.pins.entry(seq).or_default();
+
+        ReadView {
+            seq,
+            _pin: Arc::clone(pin),
+        }
+    }
+
+    /// Sequence number of the oldest view that is still open.
+    pub fn oldest_view(&mut self) -> Option<u64> {
+        self.pins.retain(|_, pin| Arc::strong_count(pin) > 1);
+        self.pins.keys().next().copied()
+    }
+
+    /// Record that `key` changes at commit `seq`, and had `previous` before.
+    ///
+    /// Nothing is kept if there is no open view to see the old value.
+    pub fn record(&mut self, key: &str, previous: Option<BuckTypes>, seq: u64) {
+        if self.oldest_view().is_none() {
+            return;
+        }
+
+        let since = self.latest.insert(key.to_owned(), seq).unwrap_or(0);
+        self.history.entry(key.to_owned()).or_default().push(Version {
+            seq: since,
+            value: previous,
+        });
+    }
+
+    /// Resolve `key` for a view pinned at `seq`.
+    ///
+    /// `current` is the latest committed value, which is the answer unless
+    /// the key changed after the view was opened.
+    pub fn read<'a>(
+        &'a self,
+        key: &str,
+        seq: u64,
+        current: Option<&'a BuckTypes>,
+    ) -> Option<&'a BuckTypes> {
+        match self.latest.get(key) {
+            Some(latest) if *latest > seq => self
+                .history
+                .get(key)?
+                .iter()
+                .rev()
+                .find(|version| version.seq <= seq)?
+                .value
+                .as_ref(),
+            _ => current,
+        }
+    }
+
+    /// Drop every version that no open view can see anymore.
+    ///
+    /// Returns the number of versions dropped.
+    pub fn collect_garbage(&mut self) -> usize {
+        let before = self.version_count();
+
+        if self.oldest_view().is_none() {
+            self.history.clear();
+            self.latest.clear();
+
+            return before;
+        }
+
+        let pins = &self.pins;
+        let latest = &mut self.latest;
+
+        self.history.retain(|key, versions| {
+            let current_since = latest.get(key).copied().unwrap_or(0);
+
+            // a version is visible to views pinned from its own sequence
+            // number up to the one that replaced it
+            let untils: Vec<u64> = versions
+                .iter()
+                .skip(1)
+                .map(|next| next.seq)
+                .chain(std::iter::once(current_since))
+                .collect();
+
+            let mut untils = untils.into_iter();
+            versions.retain(|version| {
+                let until = untils.next().unwrap_or(current_since);
+                pins.range(version.seq..until).next().is_some()
+            });
+
+            if versions.is_empty() {
+                latest.remove(key);
+                return false;
+            }
+
+            true
+        });
+
+        before - self.version_count()
+    }
+
+    /// Number of past versions currently kept.
+    pub fn version_count(&self) -> usize {
+        self.history.values().map(Vec::len).sum()
+    }
+}
@@ -0,0 +1,130 @@
+//! args.rs
+//!
+//! The arguments of a query, after its command.
+//!
+//! A query typed into the REPL is a single line, so its arguments are split
+//! on whitespace, and a key or value that contains whitespace is wrapped in
+//! quotes. Each word is then read for its type, so `10` is an integer and
+//! `[1, 2]` is a list.
+//!
+//! A request sent by a RESP client already comes as a list of bulk strings.
+//! Each of them is exactly one argument, taken as it is: quotes are kept,
+//! an empty argument stays empty and nothing is split.
+
+use std::ops::Deref;
+
+/// One argument of a query.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Word<'a> {
+    /// A word of a query line, which may be wrapped in quotes.
+    Text(&'a str),
+    /// An argument of a RESP request, taken byte for byte.
+    Literal(&'a str),
+}
+
+impl<'a> Word<'a> {
+    pub fn as_str(&self) -> &'a str {
+        match self {
+            Word::Text(word) | Word::Literal(word) => word,
+        }
+    }
+}
+
+impl Deref for Word<'_> {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+/// The arguments after the command of a query.
+#[derive(Debug, Clone, Copy)]
+pub enum Args<'a> {
+    /// The rest of a query line, empty if there is none.
+    Line(&'a str),
+    /// The arguments of a RESP request.
+    List(&'a [String]),
+}
+
+impl<'a> Args<'a> {
+    /// Every argument, with quoted strings kept together.
+    pub fn words(&self) -> Vec<Word<'a>> {
+        self.split(usize::MAX, false)
+    }
+
+    /// Like `words`, but a query line is split into at most `limit` words,
+    /// the last of which is the rest of the line. A RESP request is not
+    /// joined, so an argument too many is still one too many.
+    pub fn words_n(&self, limit: usize) -> Vec<Word<'a>> {
+        self.split(limit, false)
+    }
+
+    /// Like `words`, but bracketed values that contain whitespace are kept
+    /// together as well.
+    pub fn arguments(&self) -> Vec<Word<'a>> {
+        self.split(usize::MAX, true)
+    }
+
+    /// All of the arguments as a single one, like a file path.
+    ///
+    /// ## Returns
+    ///
+    /// `None` if there is no argument, or more than one in a RESP request.
+    pub fn rest(&self) -> Option<Word<'a>> {
+        match self {
+            Args::Line(line) => Some(line.trim()).filter(|line| !line.is_empty()).map(Word::Text),
+            Args::List([arg]) => Some(Word::Literal(arg)),
+            Args::List(_) => None,
+        }
+    }
+
+    fn split(&self, limit: usize, nested: bool) -> Vec<Word<'a>> {
+        match self {
+            Args::Line(line) => split_words(line, limit, nested).into_iter().map(Word::Text).collect(),
+            Args::List(args) => args.iter().map(|arg| Word::Literal(arg)).collect(),
+        }
+    }
+}
+
+// split a query line on whitespace, keeping quoted strings together, into at
+// most `limit` pieces, the last of which is the rest of the input after a
+// single whitespace character, as with `splitn`
+fn split_words(input: &str, limit: usize, nested: bool) -> Vec<&str> {
+    let mut args = Vec::new();
+    let mut start = None;
+    let mut quote = None;
+    let mut depth = 0usize;
+
+    for (i, c) in input.char_indices() {
+        match quote {
+            Some(delimiter) if c == delimiter => quote = None,
+            Some(_) => {}
+            None => match c {
+                '"' | '\'' if start.is_none() => quote = Some(c),
+                '[' | '{' | '(' if nested => depth += 1,
+                ']' | '}' | ')' if nested => depth = depth.saturating_sub(1),
+                c if c.is_whitespace() && depth == 0 => {
+                    if let Some(start) = start.take() {
+                        args.push(&input[start..i]);
+
+                        if args.len() + 1 == limit {
+                            args.push(&input[i + c.len_utf8()..]);
+                            return args;
+                        }
+                    }
+                    continue;
+                }
+                _ => {}
+            },
+        }
+
+        start.get_or_insert(i);
+    }
+
+    if let Some(start) = start {
+        args.push(&input[start..]);
+    }
+
+    args
+}
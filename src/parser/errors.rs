@@ -5,12 +5,32 @@ pub enum BuckParserError {
     UnknownQueryCommand,
     InvalidQueryCommand(String),
     InvalidKey(String),
+    KeyPolicyViolation(String),
     HashKeyIsEmpty(String),
     HashValueIsEmpty(String),
     HashValueIsNotInteger(String),
     InvalidSetType(String),
     InvalidRange(String),
     UpdateValueContainsSpace(String),
+    InvalidPath(String),
+}
+
+impl BuckParserError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            BuckParserError::UnknownQueryCommand => "UNKNOWN_COMMAND",
+            BuckParserError::InvalidQueryCommand(_) => "INVALID_COMMAND",
+            BuckParserError::InvalidKey(_) => "INVALID_KEY",
+            BuckParserError::KeyPolicyViolation(_) => "KEY_POLICY_VIOLATION",
+            BuckParserError::HashKeyIsEmpty(_) => "HASH_KEY_EMPTY",
+            BuckParserError::HashValueIsEmpty(_) => "HASH_VALUE_EMPTY",
+            BuckParserError::HashValueIsNotInteger(_) => "HASH_VALUE_NOT_INTEGER",
+            BuckParserError::InvalidSetType(_) => "INVALID_SET_TYPE",
+            BuckParserError::InvalidRange(_) => "INVALID_RANGE",
+            BuckParserError::UpdateValueContainsSpace(_) => "UPDATE_VALUE_CONTAINS_SPACE",
+            BuckParserError::InvalidPath(_) => "INVALID_PATH",
+        }
+    }
 }
 
 impl fmt::Display for BuckParserError {
@@ -21,6 +41,9 @@ impl fmt::Display for BuckParserError {
                 write!(f, "[Error] Invalid query command: {}", query)
             }
             BuckParserError::InvalidKey(key) => write!(f, "[Error] Invalid key: {}", key),
+            BuckParserError::KeyPolicyViolation(reason) => {
+                write!(f, "[Error] Key violates the key policy: {}", reason)
+            }
             BuckParserError::HashKeyIsEmpty(hash) => {
                 write!(f, "[Error] Invalid hash string. Value {} has no key", hash)
             }
@@ -42,6 +65,9 @@ impl fmt::Display for BuckParserError {
             BuckParserError::UpdateValueContainsSpace(key) => {
                 write!(f, "[Error] Update query value contains space: {}", key)
             }
+            BuckParserError::InvalidPath(path) => {
+                write!(f, "[Error] Path must stay inside the data directory: {}", path)
+            }
         }
     }
 }
@@ -0,0 +1,118 @@
+//! key_policy.rs
+//!
+//! An optional policy on the keys a database accepts.
+//!
+//! The parser takes any key that is not empty, wrapped in quotes if it
+//! contains whitespace. A server can narrow that down with a maximum
+//! length, a set of allowed characters and a namespace prefix that every
+//! key has to start with. Each rule is off until it is configured, see
+//! `BuckDB::config_set`.
+//!
+//! The policy is checked against every key a query names before it runs,
+//! see `BuckQuery::keys`. Patterns, prefixes and range bounds are not keys,
+//! and neither are hash fields.
+
+use std::fmt;
+
+use super::errors::BuckParserError;
+
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct KeyPolicy {
+    /// Maximum length of a key in bytes. `None` means unlimited.
+    pub max_length: Option<usize>,
+    /// Characters a key may be made of. `None` allows any.
+    pub charset: Option<KeyCharset>,
+    /// Namespace every key has to start with.
+    pub prefix: Option<String>,
+}
+
+impl KeyPolicy {
+    /// Check `key` against every configured rule.
+    pub fn check(&self, key: &str) -> Result<(), BuckParserError> {
+        let violation = |reason: String| {
+            Err(BuckParserError::KeyPolicyViolation(format!("{} {}", key, reason)))
+        };
+
+        if let Some(max_length) = self.max_length.filter(|max| key.len() > *max) {
+            return violation(format!("is longer than {} bytes", max_length));
+        }
+
+        if let Some(prefix) = self.prefix.as_ref().filter(|prefix| !key.starts_with(prefix.as_str())) {
+            return violation(format!("does not start with {}", prefix));
+        }
+
+        if let Some(charset) = &self.charset {
+            if let Some(c) = key.chars().find(|c| !charset.contains(*c)) {
+                return violation(format!("contains {:?}, which is not in {}", c, charset));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check every key in `keys`, and stop at the first violation.
+    pub fn check_all<'a>(&self, keys: impl IntoIterator<Item = &'a str>) -> Result<(), BuckParserError> {
+        keys.into_iter().try_for_each(|key| self.check(key))
+    }
+}
+
+/// A set of characters, like `a-zA-Z0-9:_-`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct KeyCharset {
+    spec: String,
+    ranges: Vec<(char, char)>,
+}
+
+impl KeyCharset {
+    /// Parse single characters and `first-last` ranges. A `-` that does not
+    /// sit between two characters stands for itself.
+    ///
+    /// ## Returns
+    ///
+    /// `None` if the set is empty or a range is backwards.
+    pub fn parse(spec: &str) -> Option<KeyCharset> {
+        let chars: Vec<char> = spec.chars().collect();
+        let mut ranges = Vec::new();
+        let mut i = 0;
+
+        while i < chars.len() {
+            match chars[i..] {
+                [first, '-', last, ..] => {
+                    if first > last {
+                        return None;
+                    }
+
+                    ranges.push((first, last));
+                    i += 3;
+                }
+                _ => {
+                    ranges.push((chars[i], chars[i]));
+                    i += 1;
+                }
+            }
+        }
+
+        if ranges.is_empty() {
+            return None;
+        }
+
+        Some(KeyCharset {
+            spec: spec.to_owned(),
+            ranges,
+        })
+    }
+
+    pub fn contains(&self, c: char) -> bool {
+        self.ranges.iter().any(|(first, last)| (*first..=*last).contains(&c))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.spec
+    }
+}
+
+impl fmt::Display for KeyCharset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.spec)
+    }
+}
@@ -1,4 +1,6 @@
+pub mod args;
 pub mod errors;
+pub mod key_policy;
 pub mod parse;
 pub mod query;
 pub mod tokens;
\ No newline at end of file
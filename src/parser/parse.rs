@@ -1,8 +1,15 @@
-use regex::Regex;
 use std::collections::HashMap;
-
+use std::ops::Bound;
+use std::path::{Component, Path};
+use std::time::Duration;
+
+use crate::clock::Expiry;
+use crate::types::list::ListSide;
+use crate::types::sorted_set::{Aggregate, LexBound, RangeBy, ScoreBound, ZAddFlags, ZRangeSpec};
+use crate::types::string::SetOptions;
 use crate::types::types::{parse_hash, parse_list, parse_sets, BuckTypes};
 
+use super::args::{Args, Word};
 use super::{errors::BuckParserError, query::BuckQuery, tokens::BuckTokens};
 
 pub type BuckParserResult = Result<BuckQuery, BuckParserError>;
@@ -27,7 +34,11 @@ pub fn get_value_type(value: &str) -> Result<BuckTypes, BuckParserError> {
         "false" => return Ok(BuckTypes::Boolean(false)),
         // string value must be wrapped in double quotes
         _ => {
-            if value.starts_with('"') && value.ends_with('"') || value.starts_with("'") && value.ends_with("'") {
+            let is_quoted = value.starts_with('"') && value.ends_with('"')
+                || value.starts_with('\'') && value.ends_with('\'');
+
+            // a lone quote is not a quoted empty string
+            if is_quoted && value.len() >= 2 {
                 return Ok(BuckTypes::String(value[1..value.len() - 1].to_owned()));
             }
 
@@ -48,16 +59,61 @@ pub fn get_value_type(value: &str) -> Result<BuckTypes, BuckParserError> {
     Ok(BuckTypes::Unknown(value.to_owned()))
 }
 
-fn is_valid_key(key: &str) -> bool {
-    let re = Regex::new(r"^[a-zA-Z][a-zA-Z0-9]*$").unwrap();
+// a value of a RESP request, which is only a number or a boolean if it
+// reads back the same, so `007` or `1.50` stay the strings they were sent as
+fn get_literal_type(value: &str) -> BuckTypes {
+    let typed = match value {
+        "true" => Some(BuckTypes::Boolean(true)),
+        "false" => Some(BuckTypes::Boolean(false)),
+        _ => value
+            .parse::<i64>()
+            .map(BuckTypes::Integer)
+            .or_else(|_| value.parse::<f64>().map(BuckTypes::Float))
+            .ok(),
+    };
+
+    match typed {
+        Some(typed) if typed.to_string() == value => typed,
+        _ => BuckTypes::String(value.to_owned()),
+    }
+}
+
+fn parse_value(value: Word) -> Result<BuckTypes, BuckParserError> {
+    match value {
+        Word::Text(value) => get_value_type(value),
+        Word::Literal(value) => Ok(get_literal_type(value)),
+    }
+}
+
+// a key, or a hash field name, which may hold any character but must not be
+// empty. A key that contains whitespace has to be wrapped in quotes.
+fn parse_key(key: Word) -> Result<String, BuckParserError> {
+    let parsed = parse_text(key);
+
+    if parsed.is_empty() {
+        return Err(BuckParserError::InvalidKey(key.to_string()));
+    }
+
+    Ok(parsed)
+}
 
-    re.is_match(key)
+fn parse_keys(keys: &[Word]) -> Result<Vec<String>, BuckParserError> {
+    keys.iter().map(|key| parse_key(*key)).collect()
 }
 
-fn get_invalid_keys(keys: Vec<String>) -> Vec<String> {
-    keys.into_iter()
-        .filter(|key| !is_valid_key(key))
-        .collect::<Vec<String>>()
+// the values of `LPUSH` or `SADD`, which are a range like `1..5` or words on
+// the rest of a query line, or one value per argument of a RESP request
+fn parse_values(values: &[Word]) -> Result<Vec<BuckTypes>, BuckParserError> {
+    let mut parsed = Vec::new();
+
+    for value in values {
+        match value {
+            Word::Text(value) => parsed.extend(parse_range(value)?),
+            Word::Literal(value) => parsed.push(get_literal_type(value)),
+        }
+    }
+
+    Ok(parsed)
 }
 
 fn parse_range(input: &str) -> Result<Vec<BuckTypes>, BuckParserError> {
@@ -83,12 +139,19 @@ fn parse_range(input: &str) -> Result<Vec<BuckTypes>, BuckParserError> {
 
     let values: Vec<BuckTypes> = input
         .split(' ')
-        .map(|s| get_value_type(s))
+        .map(get_value_type)
         .collect::<Result<Vec<BuckTypes>, BuckParserError>>()?;
 
     Ok(values)
 }
 
+// parse a numeric argument of `query`
+fn parse_number<T: std::str::FromStr>(query: &str, value: &str) -> Result<T, BuckParserError> {
+    value
+        .parse::<T>()
+        .map_err(|_| BuckParserError::InvalidQueryCommand(query.to_owned()))
+}
+
 // parse fields from a string
 // especially, This method could parse the value which contains whitespace
 fn parse_fields(value: &str) -> Result<HashMap<String, BuckTypes>, BuckParserError> {
@@ -113,18 +176,11 @@ fn parse_fields(value: &str) -> Result<HashMap<String, BuckTypes>, BuckParserErr
             let field_kv_pair: Vec<&str> = field_kv.splitn(2, ':').collect();
 
             if let (Some(name), Some(value)) = (field_kv_pair.first(), field_kv_pair.get(1)) {
-                if !is_valid_key(name) {
-                    return Err(BuckParserError::InvalidKey(name.to_string()));
-                }
-
-                let cleaned_value = if value.ends_with(' ') {
-                    &value[..value.len() - 1]
-                } else {
-                    value
-                };
+                let name = parse_key(Word::Text(name))?;
+                let cleaned_value = value.strip_suffix(' ').unwrap_or(value);
 
                 let value = get_value_type(cleaned_value)?;
-                parsed_fields.insert(name.to_string(), value);
+                parsed_fields.insert(name, value);
             }
 
             field_start = i + 1;
@@ -136,245 +192,1384 @@ fn parse_fields(value: &str) -> Result<HashMap<String, BuckTypes>, BuckParserErr
 
 pub fn parse_query(query: &str) -> BuckParserResult {
     let parts: Vec<&str> = query.splitn(2, ' ').collect();
-    let command = BuckTokens::from_str(parts[0]);
+    let args = Args::Line(parts.get(1).copied().unwrap_or(""));
+
+    parse_command(query, parts[0], args)
+}
 
-    match command {
-        BuckTokens::Get => handle_get(query, parts),
-        BuckTokens::Insert => handle_insert(query, parts),
-        BuckTokens::Update => handle_update(query, parts),
-        BuckTokens::Remove => handle_remove(query, parts),
+/// Parse a request of a RESP client, whose arguments are taken as they are.
+///
+/// Unlike a query line, an argument is never split or unquoted, so it may
+/// be empty or contain whitespace and quotes of either kind. A value is an
+/// integer, a float or a boolean only if it is written exactly the way
+/// buck prints it, and a string otherwise.
+pub fn parse_request(request: &[String]) -> BuckParserResult {
+    // only used to report errors
+    let query = request.join(" ");
+
+    match request.split_first() {
+        Some((command, args)) => parse_command(&query, command, Args::List(args)),
+        None => Err(BuckParserError::InvalidQueryCommand(query)),
+    }
+}
+
+fn parse_command(query: &str, command: &str, args: Args) -> BuckParserResult {
+    match BuckTokens::from_str(command) {
+        BuckTokens::Get => handle_get(query, args),
+        BuckTokens::Insert => handle_insert(query, args),
+        BuckTokens::Set => handle_set(query, args),
+        BuckTokens::Update => handle_update(query, args),
+        BuckTokens::Remove => handle_remove(query, args),
+        BuckTokens::Begin => Ok(BuckQuery::Begin),
         BuckTokens::Commit => Ok(BuckQuery::Commit),
         BuckTokens::Rollback => Ok(BuckQuery::Rollback),
-        BuckTokens::Shard => handle_shard(query, parts),
-        BuckTokens::Type => handle_type(query, parts),
+        BuckTokens::Shard => handle_shard(query, args),
+        BuckTokens::Type => handle_single_key(query, args).map(BuckQuery::Type),
 
         // list things
-        BuckTokens::LPush => handle_lpush(query, parts),
-        BuckTokens::LPop => handle_lpop(query, parts),
-        BuckTokens::SAdd => handle_sadd(query, parts),
-        BuckTokens::SRem => handle_srem(query, parts),
-        BuckTokens::SInter => handle_sinter(query, parts),
-        BuckTokens::HSet => handle_hset(query, parts),
-        BuckTokens::Length => handle_length(query, parts),
+        BuckTokens::LPush => handle_push(query, args, BuckQuery::LPush),
+        BuckTokens::RPush => handle_push(query, args, BuckQuery::RPush),
+        BuckTokens::LPop => handle_pop(query, args, BuckQuery::LPop),
+        BuckTokens::RPop => handle_pop(query, args, BuckQuery::RPop),
+        BuckTokens::LRange => handle_list_range(query, args, BuckQuery::LRange),
+        BuckTokens::LTrim => handle_list_range(query, args, BuckQuery::LTrim),
+        BuckTokens::LIndex => handle_lindex(query, args),
+        BuckTokens::LSet => handle_lset(query, args),
+        BuckTokens::LInsert => handle_linsert(query, args),
+        BuckTokens::LRem => handle_lrem(query, args),
+        BuckTokens::LMove => handle_lmove(query, args),
+        BuckTokens::BLPop => handle_blocking_pop(query, args, BuckQuery::BLPop),
+        BuckTokens::BRPop => handle_blocking_pop(query, args, BuckQuery::BRPop),
+        BuckTokens::BLMove => handle_blmove(query, args),
+        BuckTokens::SAdd => handle_members(query, args, BuckQuery::SAdd),
+        BuckTokens::SRem => handle_members(query, args, BuckQuery::SRem),
+        BuckTokens::SMIsMember => handle_members(query, args, BuckQuery::SMIsMember),
+        BuckTokens::SIsMember => handle_sismember(query, args),
+        BuckTokens::SMembers => handle_single_key(query, args).map(BuckQuery::SMembers),
+        BuckTokens::SCard => handle_single_key(query, args).map(BuckQuery::SCard),
+        BuckTokens::SInter => handle_set_keys(query, args, BuckQuery::SInter),
+        BuckTokens::SUnion => handle_set_keys(query, args, BuckQuery::SUnion),
+        BuckTokens::SDiff => handle_set_keys(query, args, BuckQuery::SDiff),
+        BuckTokens::SInterCard => handle_sintercard(query, args),
+        BuckTokens::SInterStore => handle_set_store(query, args, BuckQuery::SInterStore),
+        BuckTokens::SUnionStore => handle_set_store(query, args, BuckQuery::SUnionStore),
+        BuckTokens::SDiffStore => handle_set_store(query, args, BuckQuery::SDiffStore),
+        BuckTokens::SRandMember => handle_pop(query, args, BuckQuery::SRandMember),
+        BuckTokens::SPop => handle_pop(query, args, BuckQuery::SPop),
+        BuckTokens::SMove => handle_smove(query, args),
+        BuckTokens::HSet => handle_hset(query, args),
+        BuckTokens::HGet => handle_hash_field(query, args, BuckQuery::HGet),
+        BuckTokens::HExists => handle_hash_field(query, args, BuckQuery::HExists),
+        BuckTokens::HStrLen => handle_hash_field(query, args, BuckQuery::HStrLen),
+        BuckTokens::HMGet => handle_hash_fields(query, args, BuckQuery::HMGet),
+        BuckTokens::HDel => handle_hash_fields(query, args, BuckQuery::HDel),
+        BuckTokens::HGetAll => handle_single_key(query, args).map(BuckQuery::HGetAll),
+        BuckTokens::HKeys => handle_single_key(query, args).map(BuckQuery::HKeys),
+        BuckTokens::HVals => handle_single_key(query, args).map(BuckQuery::HVals),
+        BuckTokens::HLen => handle_single_key(query, args).map(BuckQuery::HLen),
+        BuckTokens::HSetNx => handle_hsetnx(query, args),
+        BuckTokens::HIncrBy => handle_hincrby(query, args, BuckQuery::HIncrBy),
+        BuckTokens::HIncrByFloat => handle_hincrby(query, args, BuckQuery::HIncrByFloat),
+        BuckTokens::HScan => handle_hscan(query, args),
+        BuckTokens::ZAdd => handle_zadd(query, args),
+        BuckTokens::ZRem => handle_zrem(query, args),
+        BuckTokens::ZScore => handle_zmember(query, args, BuckQuery::ZScore),
+        BuckTokens::ZRank => handle_zmember(query, args, BuckQuery::ZRank),
+        BuckTokens::ZRevRank => handle_zmember(query, args, BuckQuery::ZRevRank),
+        BuckTokens::ZRange => handle_zrange(query, args),
+        BuckTokens::ZRangeByScore => handle_zrange_by(query, args, "BYSCORE"),
+        BuckTokens::ZRangeByLex => handle_zrange_by(query, args, "BYLEX"),
+        BuckTokens::ZIncrBy => handle_zincrby(query, args),
+        BuckTokens::ZPopMin => handle_pop(query, args, BuckQuery::ZPopMin),
+        BuckTokens::ZPopMax => handle_pop(query, args, BuckQuery::ZPopMax),
+        BuckTokens::ZCount => handle_zcount(query, args),
+        BuckTokens::ZCard => handle_single_key(query, args).map(BuckQuery::ZCard),
+        BuckTokens::ZUnionStore => handle_zstore(query, args, BuckQuery::ZUnionStore),
+        BuckTokens::ZInterStore => handle_zstore(query, args, BuckQuery::ZInterStore),
+        BuckTokens::Incr => handle_single_key(query, args).map(|key| BuckQuery::IncrBy(key, 1)),
+        BuckTokens::Decr => handle_single_key(query, args).map(|key| BuckQuery::DecrBy(key, 1)),
+        BuckTokens::IncrBy => handle_incrby(query, args, BuckQuery::IncrBy),
+        BuckTokens::DecrBy => handle_incrby(query, args, BuckQuery::DecrBy),
+        BuckTokens::IncrByFloat => handle_incrby(query, args, BuckQuery::IncrByFloat),
+        BuckTokens::Append => handle_append(query, args),
+        BuckTokens::StrLen => handle_single_key(query, args).map(BuckQuery::StrLen),
+        BuckTokens::GetRange => handle_getrange(query, args),
+        BuckTokens::SetRange => handle_setrange(query, args),
+        BuckTokens::GetSet => handle_getset(query, args),
+        BuckTokens::GetDel => handle_single_key(query, args).map(BuckQuery::GetDel),
+        BuckTokens::GetEx => handle_getex(query, args),
+        BuckTokens::MGet => handle_set_keys(query, args, BuckQuery::MGet),
+        BuckTokens::MSet => handle_mset(query, args, BuckQuery::MSet),
+        BuckTokens::MSetNx => handle_mset(query, args, BuckQuery::MSetNx),
+        BuckTokens::Keys => handle_keys(query, args),
+        BuckTokens::Scan => handle_scan(query, args),
+        BuckTokens::Range => handle_range(query, args),
+        BuckTokens::Prefix => handle_prefix(query, args),
+        BuckTokens::Exists => handle_set_keys(query, args, BuckQuery::Exists),
+        BuckTokens::Touch => handle_set_keys(query, args, BuckQuery::Touch),
+        BuckTokens::Unlink => handle_set_keys(query, args, BuckQuery::Unlink),
+        BuckTokens::Rename => handle_rename(query, args, BuckQuery::Rename),
+        BuckTokens::RenameNx => handle_rename(query, args, BuckQuery::RenameNx),
+        BuckTokens::Copy => handle_copy(query, args),
+        BuckTokens::DbSize => handle_no_arguments(query, args, BuckQuery::DbSize),
+        BuckTokens::FlushDb => handle_no_arguments(query, args, BuckQuery::FlushDb),
+        BuckTokens::RandomKey => handle_no_arguments(query, args, BuckQuery::RandomKey),
+        BuckTokens::Select => handle_select(query, args),
+        BuckTokens::Move => handle_move(query, args),
+        BuckTokens::SwapDb => handle_swapdb(query, args),
+        BuckTokens::FlushAll => handle_no_arguments(query, args, BuckQuery::FlushAll),
+        BuckTokens::Length => handle_single_key(query, args).map(BuckQuery::Len),
         BuckTokens::Exit => Ok(BuckQuery::Exit),
         BuckTokens::Clear => Ok(BuckQuery::Clear),
+        BuckTokens::Ping => Ok(BuckQuery::Ping),
+        BuckTokens::Save => handle_save(query, args),
+        BuckTokens::Load => handle_load(query, args),
+        BuckTokens::Expire => handle_expire(query, args, false),
+        BuckTokens::PExpire => handle_expire(query, args, true),
+        BuckTokens::Ttl => handle_single_key(query, args).map(BuckQuery::Ttl),
+        BuckTokens::PTtl => handle_single_key(query, args).map(BuckQuery::PTtl),
+        BuckTokens::Persist => handle_single_key(query, args).map(BuckQuery::Persist),
+        BuckTokens::Config => handle_config(query, args),
+        BuckTokens::Watch => handle_watch(query, args),
+        BuckTokens::Unwatch => Ok(BuckQuery::Unwatch),
         _ => Err(BuckParserError::InvalidQueryCommand(query.to_owned())),
     }
 }
 
-fn handle_get(query: &str, parts: Vec<&str>) -> BuckParserResult {
-    if let Some(key) = parts.get(1) {
-        let keys: Vec<String> = key.split(' ').map(|s| s.to_string()).collect();
+fn handle_get(query: &str, args: Args) -> BuckParserResult {
+    Ok(BuckQuery::Get(parse_set_keys(query, &args.words())?))
+}
+
+fn handle_insert(query: &str, args: Args) -> BuckParserResult {
+    let key_value: Vec<Word> = args.words_n(2);
 
-        let invalid_keys = get_invalid_keys(keys.clone());
+    if let [key, value] = key_value[..] {
+        let key = parse_key(key)?;
 
-        if !invalid_keys.is_empty() {
-            return Err(BuckParserError::InvalidKey(invalid_keys.join(", ")));
-        }
+        let buck_type = parse_value(value);
 
-        return Ok(BuckQuery::Get(keys));
+        return Ok(BuckQuery::Insert(key, buck_type?));
     }
 
     Err(BuckParserError::InvalidQueryCommand(query.to_owned()))
 }
 
-fn handle_insert(query: &str, parts: Vec<&str>) -> BuckParserResult {
-    if let Some(key) = parts.get(1) {
-        let key_value: Vec<&str> = key.splitn(2, ' ').collect();
+// `SET key value [NX | XX] [GET] [EX seconds | PX milliseconds | EXAT unix-time-seconds | PXAT unix-time-milliseconds | KEEPTTL]`
+fn handle_set(query: &str, args: Args) -> BuckParserResult {
+    let args = args.arguments();
 
-        if let (Some(key), Some(value)) = (key_value.first(), key_value.get(1)) {
-            if !is_valid_key(key) {
-                return Err(BuckParserError::InvalidKey(key.to_string()));
-            }
+    if let [key, value, ref options @ ..] = args[..] {
+        let key = parse_key(key)?;
 
-            let buck_type = get_value_type(value);
+        let options = parse_set_options(query, options)?;
 
-            return Ok(BuckQuery::Insert(key.to_string(), buck_type?));
-        }
+        return Ok(BuckQuery::Set(key, parse_value(value)?, options));
     }
 
     Err(BuckParserError::InvalidQueryCommand(query.to_owned()))
 }
 
-fn handle_update(query: &str, parts: Vec<&str>) -> BuckParserResult {
-    if let Some(key) = parts.get(1) {
-        let key_value: Vec<&str> = key.splitn(2, ' ').collect();
-
-        if let (Some(key), Some(value)) = (key_value.first(), key_value.get(1)) {
-            if !is_valid_key(key) {
-                return Err(BuckParserError::InvalidKey(key.to_string()));
+fn parse_set_options(query: &str, args: &[Word]) -> Result<SetOptions, BuckParserError> {
+    let mut options = SetOptions::default();
+    let mut args = args.iter();
+
+    while let Some(option) = args.next() {
+        match option.to_uppercase().as_str() {
+            "NX" => options.nx = true,
+            "XX" => options.xx = true,
+            "GET" => options.get = true,
+            "KEEPTTL" => options.keep_ttl = true,
+            "EX" | "PX" | "EXAT" | "PXAT" if options.expiry.is_none() => {
+                let time = args
+                    .next()
+                    .ok_or_else(|| BuckParserError::InvalidQueryCommand(query.to_owned()))?;
+
+                options.expiry = Some(parse_expiry(query, option, time)?);
             }
+            _ => return Err(BuckParserError::InvalidQueryCommand(query.to_owned())),
+        }
+    }
+
+    if options.nx && options.xx || options.keep_ttl && options.expiry.is_some() {
+        return Err(BuckParserError::InvalidQueryCommand(query.to_owned()));
+    }
+
+    Ok(options)
+}
 
-            let buck_type = get_value_type(value)?;
-
-            if value.contains(' ') {
-                match buck_type {
-                    BuckTypes::String(_)
-                    | BuckTypes::Hash(_)
-                    | BuckTypes::Sets(_)
-                    | BuckTypes::List(_) => {}
-                    _ => {
-                        return Err(BuckParserError::UpdateValueContainsSpace(
-                            value.to_string(),
-                        ))
-                    }
+fn handle_update(query: &str, args: Args) -> BuckParserResult {
+    let key_value: Vec<Word> = args.words_n(2);
+
+    if let [key, value] = key_value[..] {
+        let key = parse_key(key)?;
+
+        let buck_type = parse_value(value)?;
+
+        if value.contains(' ') {
+            match buck_type {
+                BuckTypes::String(_)
+                | BuckTypes::Hash(_)
+                | BuckTypes::Sets(_)
+                | BuckTypes::List(_) => {}
+                _ => {
+                    return Err(BuckParserError::UpdateValueContainsSpace(
+                        value.to_string(),
+                    ))
                 }
             }
-
-            return Ok(BuckQuery::Update(key.to_string(), buck_type));
         }
+
+        return Ok(BuckQuery::Update(key, buck_type));
     }
 
     Err(BuckParserError::InvalidQueryCommand(query.to_owned()))
 }
 
-fn handle_remove(query: &str, parts: Vec<&str>) -> BuckParserResult {
-    if let Some(key) = parts.get(1) {
-        let keys: Vec<String> = key.split(' ').map(|s| s.to_string()).collect();
+fn handle_remove(query: &str, args: Args) -> BuckParserResult {
+    Ok(BuckQuery::Remove(parse_set_keys(query, &args.words())?))
+}
+
+fn handle_shard(query: &str, args: Args) -> BuckParserResult {
+    let n_shard = args.rest().map(|shard| shard.parse::<usize>());
+
+    if let Some(Ok(n_shard)) = n_shard {
+        return Ok(BuckQuery::Shard(n_shard));
+    }
 
-        let invalid_keys = get_invalid_keys(keys.clone());
+    Err(BuckParserError::InvalidQueryCommand(query.to_owned()))
+}
 
-        if !invalid_keys.is_empty() {
-            return Err(BuckParserError::InvalidKey(invalid_keys.join(", ")));
+// `LPUSH key values` or `RPUSH key values`
+fn handle_push(
+    query: &str,
+    args: Args,
+    push: fn(String, Vec<BuckTypes>) -> BuckQuery,
+) -> BuckParserResult {
+    let key_value: Vec<Word> = args.words_n(2);
+
+    if let [key, ref values @ ..] = key_value[..] {
+        if values.is_empty() {
+            return Err(BuckParserError::InvalidQueryCommand(query.to_owned()));
         }
 
-        return Ok(BuckQuery::Remove(keys));
+        let key = parse_key(key)?;
+
+        let values: Vec<BuckTypes> = parse_values(values)?;
+
+        return Ok(push(key, values));
+    }
+
+    Err(BuckParserError::InvalidQueryCommand(query.to_owned()))
+}
+
+// `LPOP key [count]`, `RPOP key [count]`, `SPOP key [count]`, `SRANDMEMBER key [count]`,
+// `ZPOPMIN key [count]` or `ZPOPMAX key [count]`
+fn handle_pop<T: std::str::FromStr>(
+    query: &str,
+    args: Args,
+    pop: fn(String, Option<T>) -> BuckQuery,
+) -> BuckParserResult {
+    let args: Vec<Word> = args.words();
+
+    let (key, count) = match args[..] {
+        [key] => (key, None),
+        [key, count] => (key, Some(parse_number(query, &count)?)),
+        _ => return Err(BuckParserError::InvalidQueryCommand(query.to_owned())),
+    };
+
+    let key = parse_key(key)?;
+
+    Ok(pop(key, count))
+}
+
+// `LRANGE key start stop` or `LTRIM key start stop`
+fn handle_list_range(
+    query: &str,
+    args: Args,
+    range: fn(String, i64, i64) -> BuckQuery,
+) -> BuckParserResult {
+    let args: Vec<Word> = args.words();
+
+    if let [key, start, stop] = args[..] {
+        let key = parse_key(key)?;
+
+        let start = parse_number(query, &start)?;
+        let stop = parse_number(query, &stop)?;
+
+        return Ok(range(key, start, stop));
     }
 
     Err(BuckParserError::InvalidQueryCommand(query.to_owned()))
 }
 
-fn handle_shard(query: &str, parts: Vec<&str>) -> BuckParserResult {
-    if let Some(shard) = parts.get(1) {
-        let n_shard = shard.trim().parse::<usize>();
+// `LINDEX key index`
+fn handle_lindex(query: &str, args: Args) -> BuckParserResult {
+    let args: Vec<Word> = args.words();
+
+    if let [key, index] = args[..] {
+        let key = parse_key(key)?;
+
+        return Ok(BuckQuery::LIndex(key, parse_number(query, &index)?));
+    }
+
+    Err(BuckParserError::InvalidQueryCommand(query.to_owned()))
+}
 
-        if let Ok(n_shard) = n_shard {
-            return Ok(BuckQuery::Shard(n_shard));
+// `LSET key index value`
+fn handle_lset(query: &str, args: Args) -> BuckParserResult {
+    let args: Vec<Word> = args.words_n(3);
+
+    if let [key, index, value] = args[..] {
+        let key = parse_key(key)?;
+
+        let index = parse_number(query, &index)?;
+
+        return Ok(BuckQuery::LSet(key, index, parse_value(value)?));
+    }
+
+    Err(BuckParserError::InvalidQueryCommand(query.to_owned()))
+}
+
+// `LINSERT key BEFORE|AFTER pivot value`
+fn handle_linsert(query: &str, args: Args) -> BuckParserResult {
+    let args: Vec<Word> = args.words_n(4);
+
+    if let [key, position, pivot, value] = args[..] {
+        let key = parse_key(key)?;
+
+        let before = match position.to_lowercase().as_str() {
+            "before" => true,
+            "after" => false,
+            _ => return Err(BuckParserError::InvalidQueryCommand(query.to_owned())),
+        };
+
+        return Ok(BuckQuery::LInsert(
+            key,
+            before,
+            parse_value(pivot)?,
+            parse_value(value)?,
+        ));
+    }
+
+    Err(BuckParserError::InvalidQueryCommand(query.to_owned()))
+}
+
+// `LREM key count value`
+fn handle_lrem(query: &str, args: Args) -> BuckParserResult {
+    let args: Vec<Word> = args.words_n(3);
+
+    if let [key, count, value] = args[..] {
+        let key = parse_key(key)?;
+
+        let count = parse_number(query, &count)?;
+
+        return Ok(BuckQuery::LRem(key, count, parse_value(value)?));
+    }
+
+    Err(BuckParserError::InvalidQueryCommand(query.to_owned()))
+}
+
+// `LEFT` or `RIGHT`, in any case
+fn parse_side(query: &str, value: &str) -> Result<ListSide, BuckParserError> {
+    match value.to_lowercase().as_str() {
+        "left" => Ok(ListSide::Left),
+        "right" => Ok(ListSide::Right),
+        _ => Err(BuckParserError::InvalidQueryCommand(query.to_owned())),
+    }
+}
+
+// timeout of a blocking command in seconds, `0` blocks forever
+fn parse_timeout(query: &str, value: &str) -> Result<Option<Duration>, BuckParserError> {
+    let seconds: f64 = parse_number(query, value)?;
+
+    if seconds == 0.0 {
+        return Ok(None);
+    }
+
+    Duration::try_from_secs_f64(seconds)
+        .map(Some)
+        .map_err(|_| BuckParserError::InvalidQueryCommand(query.to_owned()))
+}
+
+// `LMOVE source destination LEFT|RIGHT LEFT|RIGHT`
+fn handle_lmove(query: &str, args: Args) -> BuckParserResult {
+    parse_lmove(query, &args.words())
+}
+
+fn parse_lmove(query: &str, args: &[Word]) -> BuckParserResult {
+    if let [source, destination, from, to] = args[..] {
+        return Ok(BuckQuery::LMove(
+            parse_key(source)?,
+            parse_key(destination)?,
+            parse_side(query, &from)?,
+            parse_side(query, &to)?,
+        ));
+    }
+
+    Err(BuckParserError::InvalidQueryCommand(query.to_owned()))
+}
+
+// `BLPOP key [key ...] timeout` or `BRPOP key [key ...] timeout`
+fn handle_blocking_pop(
+    query: &str,
+    args: Args,
+    pop: fn(Vec<String>, Option<Duration>) -> BuckQuery,
+) -> BuckParserResult {
+    let mut keys = args.words();
+
+    let timeout = match keys.pop() {
+        Some(timeout) if !keys.is_empty() => parse_timeout(query, &timeout)?,
+        _ => return Err(BuckParserError::InvalidQueryCommand(query.to_owned())),
+    };
+
+    Ok(pop(parse_keys(&keys)?, timeout))
+}
+
+// `BLMOVE source destination LEFT|RIGHT LEFT|RIGHT timeout`
+fn handle_blmove(query: &str, args: Args) -> BuckParserResult {
+    let mut args = args.words();
+
+    if let Some(timeout) = args.pop() {
+        let timeout = parse_timeout(query, &timeout)?;
+
+        if let BuckQuery::LMove(source, destination, from, to) = parse_lmove(query, &args)? {
+            return Ok(BuckQuery::BLMove(source, destination, from, to, timeout));
         }
     }
 
     Err(BuckParserError::InvalidQueryCommand(query.to_owned()))
 }
 
-fn handle_type(query: &str, parts: Vec<&str>) -> BuckParserResult {
-    if let Some(key) = parts.get(1) {
-        if !is_valid_key(key) {
-            return Err(BuckParserError::InvalidKey(key.to_string()));
+// `SADD key values`, `SREM key values` or `SMISMEMBER key values`
+fn handle_members(
+    query: &str,
+    args: Args,
+    members: fn(String, Vec<BuckTypes>) -> BuckQuery,
+) -> BuckParserResult {
+    let key_value: Vec<Word> = args.words_n(2);
+
+    if let [key, ref values @ ..] = key_value[..] {
+        if values.is_empty() {
+            return Err(BuckParserError::InvalidQueryCommand(query.to_owned()));
         }
 
-        return Ok(BuckQuery::Type(key.to_string()));
+        let key = parse_key(key)?;
+
+        let values: Vec<BuckTypes> = parse_values(values)?;
+
+        return Ok(members(key, values));
     }
 
     Err(BuckParserError::InvalidQueryCommand(query.to_owned()))
 }
 
-fn handle_lpush(query: &str, parts: Vec<&str>) -> BuckParserResult {
-    if let Some(key) = parts.get(1) {
-        let key_value: Vec<&str> = key.splitn(2, ' ').collect();
+// `SISMEMBER key member`
+fn handle_sismember(query: &str, args: Args) -> BuckParserResult {
+    let args: Vec<Word> = args.words_n(2);
+
+    if let [key, member] = args[..] {
+        let key = parse_key(key)?;
+
+        return Ok(BuckQuery::SIsMember(key, parse_value(member)?));
+    }
+
+    Err(BuckParserError::InvalidQueryCommand(query.to_owned()))
+}
+
+// the keys of `SINTER`, `SUNION` or `SDIFF`
+fn parse_set_keys(query: &str, keys: &[Word]) -> Result<Vec<String>, BuckParserError> {
+    if keys.is_empty() {
+        return Err(BuckParserError::InvalidQueryCommand(query.to_owned()));
+    }
+
+    parse_keys(keys)
+}
+
+// `SINTER key [key ...]`, `SUNION key [key ...]`, `SDIFF key [key ...]`, `MGET key [key ...]`,
+// `EXISTS key [key ...]`, `TOUCH key [key ...]` or `UNLINK key [key ...]`
+fn handle_set_keys(
+    query: &str,
+    args: Args,
+    keys: fn(Vec<String>) -> BuckQuery,
+) -> BuckParserResult {
+    Ok(keys(parse_set_keys(query, &args.words())?))
+}
 
-        if let (Some(key), Some(value)) = (key_value.first(), key_value.get(1)) {
-            if !is_valid_key(key) {
-                return Err(BuckParserError::InvalidKey(key.to_string()));
+// `SINTERSTORE destination key [key ...]`, and the same for `SUNIONSTORE` and `SDIFFSTORE`
+fn handle_set_store(
+    query: &str,
+    args: Args,
+    store: fn(String, Vec<String>) -> BuckQuery,
+) -> BuckParserResult {
+    let mut keys = parse_set_keys(query, &args.words())?;
+
+    if keys.len() < 2 {
+        return Err(BuckParserError::InvalidQueryCommand(query.to_owned()));
+    }
+
+    let destination = keys.remove(0);
+
+    Ok(store(destination, keys))
+}
+
+// `SINTERCARD numkeys key [key ...] [LIMIT limit]`
+fn handle_sintercard(query: &str, args: Args) -> BuckParserResult {
+    let args: Vec<Word> = args.words();
+
+    if let Some((numkeys, rest)) = args.split_first() {
+        let numkeys: usize = parse_number(query, numkeys)?;
+
+        let limit = match rest.get(numkeys..) {
+            Some([]) => 0,
+            Some([option, limit]) if option.eq_ignore_ascii_case("limit") => {
+                parse_number(query, limit)?
             }
+            _ => return Err(BuckParserError::InvalidQueryCommand(query.to_owned())),
+        };
 
-            let values: Vec<BuckTypes> = parse_range(value)?;
+        if numkeys > 0 {
+            let keys = parse_set_keys(query, &rest[..numkeys])?;
 
-            return Ok(BuckQuery::LPush(key.to_string(), values));
+            return Ok(BuckQuery::SInterCard(keys, limit));
         }
     }
 
     Err(BuckParserError::InvalidQueryCommand(query.to_owned()))
 }
 
-fn handle_lpop(query: &str, parts: Vec<&str>) -> BuckParserResult {
-    if let Some(key) = parts.get(1) {
-        if !is_valid_key(key) {
-            return Err(BuckParserError::InvalidKey(key.to_string()));
+// `SMOVE source destination member`
+fn handle_smove(query: &str, args: Args) -> BuckParserResult {
+    let args: Vec<Word> = args.words_n(3);
+
+    if let [source, destination, member] = args[..] {
+        return Ok(BuckQuery::SMove(
+            parse_key(source)?,
+            parse_key(destination)?,
+            parse_value(member)?,
+        ));
+    }
+
+    Err(BuckParserError::InvalidQueryCommand(query.to_owned()))
+}
+
+fn handle_hset(query: &str, args: Args) -> BuckParserResult {
+    let key_value: Vec<Word> = args.words_n(2);
+
+    if let [key, ref fields @ ..] = key_value[..] {
+        if fields.is_empty() {
+            return Err(BuckParserError::InvalidQueryCommand(query.to_owned()));
+        }
+
+        let key = parse_key(key)?;
+
+        let mut parsed_fields = HashMap::new();
+
+        for field in fields {
+            match field {
+                Word::Text(fields) => parsed_fields.extend(parse_fields(fields)?),
+                // one `name:value` pair per argument
+                Word::Literal(field) => {
+                    let (name, value) = field
+                        .split_once(':')
+                        .ok_or_else(|| BuckParserError::InvalidQueryCommand(query.to_owned()))?;
+
+                    parsed_fields.insert(parse_key(Word::Literal(name))?, get_literal_type(value));
+                }
+            }
         }
 
-        return Ok(BuckQuery::LPop(key.to_string()));
+        return Ok(BuckQuery::HSet(key, parsed_fields));
+    }
+
+    Err(BuckParserError::InvalidQueryCommand(query.to_owned()))
+}
+
+// `HGET key field`, `HEXISTS key field` or `HSTRLEN key field`
+fn handle_hash_field(
+    query: &str,
+    args: Args,
+    field: fn(String, String) -> BuckQuery,
+) -> BuckParserResult {
+    let args: Vec<Word> = args.words();
+
+    if let [key, name] = args[..] {
+        return Ok(field(parse_key(key)?, parse_key(name)?));
+    }
+
+    Err(BuckParserError::InvalidQueryCommand(query.to_owned()))
+}
+
+// `HMGET key field [field ...]` or `HDEL key field [field ...]`
+fn handle_hash_fields(
+    query: &str,
+    args: Args,
+    fields: fn(String, Vec<String>) -> BuckQuery,
+) -> BuckParserResult {
+    let mut names = parse_keys(&args.words())?;
+
+    if names.len() >= 2 {
+        let key = names.remove(0);
+
+        return Ok(fields(key, names));
+    }
+
+    Err(BuckParserError::InvalidQueryCommand(query.to_owned()))
+}
+
+// `HSETNX key field value`
+fn handle_hsetnx(query: &str, args: Args) -> BuckParserResult {
+    let args: Vec<Word> = args.words_n(3);
+
+    if let [key, field, value] = args[..] {
+        return Ok(BuckQuery::HSetNx(
+            parse_key(key)?,
+            parse_key(field)?,
+            parse_value(value)?,
+        ));
+    }
+
+    Err(BuckParserError::InvalidQueryCommand(query.to_owned()))
+}
+
+// `HINCRBY key field increment` or `HINCRBYFLOAT key field increment`
+fn handle_hincrby<T: std::str::FromStr>(
+    query: &str,
+    args: Args,
+    increment: fn(String, String, T) -> BuckQuery,
+) -> BuckParserResult {
+    let args: Vec<Word> = args.words();
+
+    if let [key, field, by] = args[..] {
+        let by = parse_number(query, &by)?;
+
+        return Ok(increment(parse_key(key)?, parse_key(field)?, by));
+    }
+
+    Err(BuckParserError::InvalidQueryCommand(query.to_owned()))
+}
+
+// `INCRBY key increment`, `DECRBY key decrement` or `INCRBYFLOAT key increment`
+fn handle_incrby<T: std::str::FromStr>(
+    query: &str,
+    args: Args,
+    increment: fn(String, T) -> BuckQuery,
+) -> BuckParserResult {
+    let args: Vec<Word> = args.words();
+
+    if let [key, by] = args[..] {
+        let key = parse_key(key)?;
+
+        let by = parse_number(query, &by)?;
+
+        return Ok(increment(key, by));
+    }
+
+    Err(BuckParserError::InvalidQueryCommand(query.to_owned()))
+}
+
+// `APPEND key value`
+fn handle_append(query: &str, args: Args) -> BuckParserResult {
+    if let [key, value] = args.words_n(2)[..] {
+        let key = parse_key(key)?;
+
+        return Ok(BuckQuery::Append(key, parse_text(value)));
+    }
+
+    Err(BuckParserError::InvalidQueryCommand(query.to_owned()))
+}
+
+// `GETRANGE key start end`
+fn handle_getrange(query: &str, args: Args) -> BuckParserResult {
+    let args: Vec<Word> = args.words();
+
+    if let [key, start, end] = args[..] {
+        let key = parse_key(key)?;
+
+        return Ok(BuckQuery::GetRange(
+            key,
+            parse_number(query, &start)?,
+            parse_number(query, &end)?,
+        ));
+    }
+
+    Err(BuckParserError::InvalidQueryCommand(query.to_owned()))
+}
+
+// `SETRANGE key offset value`
+fn handle_setrange(query: &str, args: Args) -> BuckParserResult {
+    if let [key, offset, value] = args.words_n(3)[..] {
+        let key = parse_key(key)?;
+
+        return Ok(BuckQuery::SetRange(
+            key,
+            parse_number(query, &offset)?,
+            parse_text(value),
+        ));
     }
 
     Err(BuckParserError::InvalidQueryCommand(query.to_owned()))
 }
 
-fn handle_sadd(query: &str, parts: Vec<&str>) -> BuckParserResult {
-    if let Some(key) = parts.get(1) {
-        let key_value: Vec<&str> = key.splitn(2, ' ').collect();
+// `GETSET key value`
+fn handle_getset(query: &str, args: Args) -> BuckParserResult {
+    if let [key, value] = args.words_n(2)[..] {
+        let key = parse_key(key)?;
 
-        if let (Some(key), Some(value)) = (key_value.first(), key_value.get(1)) {
-            if !is_valid_key(key) {
-                return Err(BuckParserError::InvalidKey(key.to_string()));
+        return Ok(BuckQuery::GetSet(key, parse_value(value)?));
+    }
+
+    Err(BuckParserError::InvalidQueryCommand(query.to_owned()))
+}
+
+// `GETEX key [EX seconds | PX milliseconds | EXAT unix-time-seconds | PXAT unix-time-milliseconds | PERSIST]`
+fn handle_getex(query: &str, args: Args) -> BuckParserResult {
+    let args: Vec<Word> = args.words();
+
+    if let [key, ref options @ ..] = args[..] {
+        let key = parse_key(key)?;
+
+        let expiry = match options {
+            [] => None,
+            [option] if option.eq_ignore_ascii_case("PERSIST") => Some(Expiry::Persist),
+            [option, time] => Some(parse_expiry(query, option, time)?),
+            _ => return Err(BuckParserError::InvalidQueryCommand(query.to_owned())),
+        };
+
+        return Ok(BuckQuery::GetEx(key, expiry));
+    }
+
+    Err(BuckParserError::InvalidQueryCommand(query.to_owned()))
+}
+
+// `EX seconds`, `PX milliseconds`, `EXAT unix-time-seconds` or `PXAT unix-time-milliseconds`
+fn parse_expiry(query: &str, option: &str, time: &str) -> Result<Expiry, BuckParserError> {
+    let time: u64 = parse_number(query, time)?;
+
+    // like `EXPIRE`, but a time that is not positive is a mistake here
+    let millis = |seconds: u64| {
+        seconds
+            .checked_mul(1000)
+            .filter(|_| seconds > 0)
+            .ok_or_else(|| BuckParserError::InvalidQueryCommand(query.to_owned()))
+    };
+
+    match option.to_uppercase().as_str() {
+        "EX" => Ok(Expiry::After(millis(time)?)),
+        "PX" if time > 0 => Ok(Expiry::After(time)),
+        "EXAT" => Ok(Expiry::At(millis(time)?)),
+        "PXAT" if time > 0 => Ok(Expiry::At(time)),
+        _ => Err(BuckParserError::InvalidQueryCommand(query.to_owned())),
+    }
+}
+
+// `MSET key value [key value ...]` or `MSETNX key value [key value ...]`
+fn handle_mset(
+    query: &str,
+    args: Args,
+    set: fn(Vec<(String, BuckTypes)>) -> BuckQuery,
+) -> BuckParserResult {
+    let args = args.arguments();
+
+    if args.is_empty() || !args.len().is_multiple_of(2) {
+        return Err(BuckParserError::InvalidQueryCommand(query.to_owned()));
+    }
+
+    let pairs = args
+        .chunks(2)
+        .map(|pair| Ok((parse_key(pair[0])?, parse_value(pair[1])?)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(set(pairs))
+}
+
+// `HSCAN key cursor [MATCH pattern] [COUNT count]`
+fn handle_hscan(query: &str, args: Args) -> BuckParserResult {
+    let args: Vec<Word> = args.words();
+
+    if let [key, cursor, ref options @ ..] = args[..] {
+        let key = parse_key(key)?;
+
+        let cursor = parse_number(query, &cursor)?;
+        let (pattern, count) = parse_scan_options(query, options)?;
+
+        return Ok(BuckQuery::HScan(key, cursor, pattern, count));
+    }
+
+    Err(BuckParserError::InvalidQueryCommand(query.to_owned()))
+}
+
+// `[MATCH pattern] [COUNT count]` in any order, `COUNT` defaults to 10
+// `KEYS pattern`
+fn handle_keys(query: &str, args: Args) -> BuckParserResult {
+    match args.rest() {
+        Some(pattern) => Ok(BuckQuery::Keys(parse_text(pattern))),
+        _ => Err(BuckParserError::InvalidQueryCommand(query.to_owned())),
+    }
+}
+
+// `SCAN cursor [MATCH pattern] [COUNT count] [TYPE type]`
+fn handle_scan(query: &str, args: Args) -> BuckParserResult {
+    let args: Vec<Word> = args.words();
+
+    if let [cursor, ref options @ ..] = args[..] {
+        let cursor = parse_number(query, &cursor)?;
+
+        // `TYPE` is only known to `SCAN`, the rest is shared with `HSCAN`
+        let mut type_name = None;
+        let mut rest = Vec::new();
+
+        for option in options.chunks(2) {
+            match option {
+                [name, value] if name.eq_ignore_ascii_case("type") => {
+                    type_name = Some(value.to_lowercase());
+                }
+                _ => rest.extend_from_slice(option),
             }
+        }
+
+        let (pattern, count) = parse_scan_options(query, &rest)?;
+
+        return Ok(BuckQuery::Scan(cursor, pattern, count, type_name));
+    }
+
+    Err(BuckParserError::InvalidQueryCommand(query.to_owned()))
+}
+
+// `RANGE start end [LIMIT count] [REV]`
+fn handle_range(query: &str, args: Args) -> BuckParserResult {
+    let args: Vec<Word> = args.words();
+
+    if let [start, end, ref options @ ..] = args[..] {
+        let start = parse_key_bound(&start, "-");
+        let end = parse_key_bound(&end, "+");
 
-            let values: Vec<BuckTypes> = parse_range(value)?;
+        let mut limit = None;
+        let mut rev = false;
+        let mut options = options.iter();
 
-            return Ok(BuckQuery::SAdd(key.to_string(), values));
+        while let Some(option) = options.next() {
+            match option.to_uppercase().as_str() {
+                "REV" => rev = true,
+                "LIMIT" => {
+                    let count = options
+                        .next()
+                        .ok_or_else(|| BuckParserError::InvalidQueryCommand(query.to_owned()))?;
+
+                    limit = Some(parse_number(query, count)?);
+                }
+                _ => return Err(BuckParserError::InvalidQueryCommand(query.to_owned())),
+            }
         }
+
+        return Ok(BuckQuery::Range(start, end, limit, rev));
     }
 
     Err(BuckParserError::InvalidQueryCommand(query.to_owned()))
 }
 
-fn handle_srem(query: &str, parts: Vec<&str>) -> BuckParserResult {
-    if let Some(key) = parts.get(1) {
-        let key_value: Vec<&str> = key.splitn(2, ' ').collect();
+// `key` or `[key` for an inclusive bound, `(key` for an exclusive one, and
+// `open` for no bound at all
+fn parse_key_bound(value: &str, open: &str) -> Bound<String> {
+    if value == open {
+        return Bound::Unbounded;
+    }
+
+    match (value.strip_prefix('['), value.strip_prefix('(')) {
+        (Some(key), _) => Bound::Included(key.to_owned()),
+        (_, Some(key)) => Bound::Excluded(key.to_owned()),
+        _ => Bound::Included(value.to_owned()),
+    }
+}
+
+// `PREFIX prefix`
+fn handle_prefix(query: &str, args: Args) -> BuckParserResult {
+    let args: Vec<Word> = args.words();
+
+    match args[..] {
+        [prefix] => Ok(BuckQuery::Prefix(parse_text(prefix))),
+        _ => Err(BuckParserError::InvalidQueryCommand(query.to_owned())),
+    }
+}
+
+// `RENAME key newkey` or `RENAMENX key newkey`
+fn handle_rename(
+    query: &str,
+    args: Args,
+    rename: fn(String, String) -> BuckQuery,
+) -> BuckParserResult {
+    let keys = parse_set_keys(query, &args.words())?;
+
+    match &keys[..] {
+        [key, new_key] => Ok(rename(key.clone(), new_key.clone())),
+        _ => Err(BuckParserError::InvalidQueryCommand(query.to_owned())),
+    }
+}
+
+// `COPY source destination [REPLACE]`
+fn handle_copy(query: &str, args: Args) -> BuckParserResult {
+    let args: Vec<Word> = args.words();
+
+    let replace = match args[..] {
+        [_, _] => false,
+        [_, _, option] if option.eq_ignore_ascii_case("replace") => true,
+        _ => return Err(BuckParserError::InvalidQueryCommand(query.to_owned())),
+    };
+
+    let keys = parse_keys(&args[..2])?;
+
+    Ok(BuckQuery::Copy(keys[0].clone(), keys[1].clone(), replace))
+}
 
-        if let (Some(key), Some(value)) = (key_value.first(), key_value.get(1)) {
-            if !is_valid_key(key) {
-                return Err(BuckParserError::InvalidKey(key.to_string()));
+// `DBSIZE`, `FLUSHDB`, `FLUSHALL` or `RANDOMKEY`
+fn handle_no_arguments(query: &str, args: Args, command: BuckQuery) -> BuckParserResult {
+    match args.words().is_empty() {
+        true => Ok(command),
+        false => Err(BuckParserError::InvalidQueryCommand(query.to_owned())),
+    }
+}
+
+// `SELECT index`
+fn handle_select(query: &str, args: Args) -> BuckParserResult {
+    let args: Vec<Word> = args.words();
+
+    match args[..] {
+        [index] => Ok(BuckQuery::Select(parse_number(query, &index)?)),
+        _ => Err(BuckParserError::InvalidQueryCommand(query.to_owned())),
+    }
+}
+
+// `MOVE key db`
+fn handle_move(query: &str, args: Args) -> BuckParserResult {
+    let args: Vec<Word> = args.words();
+
+    match args[..] {
+        [key, index] => Ok(BuckQuery::Move(parse_key(key)?, parse_number(query, &index)?)),
+        _ => Err(BuckParserError::InvalidQueryCommand(query.to_owned())),
+    }
+}
+
+// `SWAPDB index1 index2`
+fn handle_swapdb(query: &str, args: Args) -> BuckParserResult {
+    let args: Vec<Word> = args.words();
+
+    match args[..] {
+        [a, b] => Ok(BuckQuery::SwapDb(parse_number(query, &a)?, parse_number(query, &b)?)),
+        _ => Err(BuckParserError::InvalidQueryCommand(query.to_owned())),
+    }
+}
+
+fn parse_scan_options(
+    query: &str,
+    options: &[Word],
+) -> Result<(Option<String>, usize), BuckParserError> {
+    let mut pattern = None;
+    let mut count = 10;
+
+    for option in options.chunks(2) {
+        match option {
+            [name, value] if name.eq_ignore_ascii_case("match") => {
+                pattern = Some(value.to_string());
+            }
+            [name, value] if name.eq_ignore_ascii_case("count") => {
+                count = parse_number(query, value)?;
             }
+            _ => return Err(BuckParserError::InvalidQueryCommand(query.to_owned())),
+        }
+    }
+
+    if count == 0 {
+        return Err(BuckParserError::InvalidQueryCommand(query.to_owned()));
+    }
+
+    Ok((pattern, count))
+}
 
-            let values: Vec<BuckTypes> = parse_range(value)?;
+// a score of a sorted set, `inf` and `-inf` included but not `nan`
+fn parse_score(query: &str, value: &str) -> Result<f64, BuckParserError> {
+    let score: f64 = parse_number(query, value)?;
 
-            return Ok(BuckQuery::SRem(key.to_string(), values));
+    if score.is_nan() {
+        return Err(BuckParserError::InvalidQueryCommand(query.to_owned()));
+    }
+
+    Ok(score)
+}
+
+// a string argument, like a member of a sorted set, which may be wrapped in
+// quotes on a query line
+fn parse_text(value: Word) -> String {
+    let value = match value {
+        Word::Text(value) => value,
+        Word::Literal(value) => return value.to_owned(),
+    };
+
+    let is_quoted = value.len() >= 2
+        && (value.starts_with('"') && value.ends_with('"')
+            || value.starts_with('\'') && value.ends_with('\''));
+
+    match is_quoted {
+        true => value[1..value.len() - 1].to_owned(),
+        false => value.to_owned(),
+    }
+}
+
+// `score` or `(score` for an exclusive bound
+fn parse_score_bound(query: &str, value: &str) -> Result<ScoreBound, BuckParserError> {
+    match value.strip_prefix('(') {
+        Some(score) => Ok(ScoreBound::Exclusive(parse_score(query, score)?)),
+        None => Ok(ScoreBound::Inclusive(parse_score(query, value)?)),
+    }
+}
+
+// `[member`, `(member`, or `-` and `+` for no bound
+fn parse_lex_bound(query: &str, value: &str) -> Result<LexBound, BuckParserError> {
+    if let Some(member) = value.strip_prefix('[') {
+        return Ok(LexBound::Inclusive(member.to_owned()));
+    }
+
+    if let Some(member) = value.strip_prefix('(') {
+        return Ok(LexBound::Exclusive(member.to_owned()));
+    }
+
+    match value {
+        "-" => Ok(LexBound::Min),
+        "+" => Ok(LexBound::Max),
+        _ => Err(BuckParserError::InvalidQueryCommand(query.to_owned())),
+    }
+}
+
+// `ZADD key [NX|XX] [GT|LT] [CH] score member [score member ...]`
+fn handle_zadd(query: &str, args: Args) -> BuckParserResult {
+    let args: Vec<Word> = args.words();
+
+    if let [key, ref rest @ ..] = args[..] {
+        let key = parse_key(key)?;
+
+        let mut flags = ZAddFlags::default();
+        let mut rest = rest;
+
+        while let Some((flag, others)) = rest.split_first() {
+            match flag.to_lowercase().as_str() {
+                "nx" => flags.nx = true,
+                "xx" => flags.xx = true,
+                "gt" => flags.gt = true,
+                "lt" => flags.lt = true,
+                "ch" => flags.ch = true,
+                _ => break,
+            }
+
+            rest = others;
         }
+
+        let conflicting =
+            flags.nx && (flags.xx || flags.gt || flags.lt) || flags.gt && flags.lt;
+
+        if conflicting || rest.is_empty() || rest.len() % 2 != 0 {
+            return Err(BuckParserError::InvalidQueryCommand(query.to_owned()));
+        }
+
+        let members = rest
+            .chunks(2)
+            .map(|pair| Ok((parse_score(query, &pair[0])?, parse_text(pair[1]))))
+            .collect::<Result<Vec<(f64, String)>, BuckParserError>>()?;
+
+        return Ok(BuckQuery::ZAdd(key, flags, members));
     }
 
     Err(BuckParserError::InvalidQueryCommand(query.to_owned()))
 }
 
-fn handle_sinter(query: &str, parts: Vec<&str>) -> BuckParserResult {
-    if let Some(key) = parts.get(1) {
-        let keys: Vec<String> = key.split(' ').map(|s| s.to_string()).collect();
+// `ZREM key member [member ...]`
+fn handle_zrem(query: &str, args: Args) -> BuckParserResult {
+    let args: Vec<Word> = args.words();
 
-        let invalid_keys = get_invalid_keys(keys.clone());
+    if let [key, ref members @ ..] = args[..] {
+        let key = parse_key(key)?;
 
-        if !invalid_keys.is_empty() {
-            return Err(BuckParserError::InvalidKey(invalid_keys.join(", ")));
+        if !members.is_empty() {
+            let members = members.iter().map(|member| parse_text(*member)).collect();
+
+            return Ok(BuckQuery::ZRem(key, members));
         }
+    }
 
-        return Ok(BuckQuery::SInter("".to_string(), keys));
+    Err(BuckParserError::InvalidQueryCommand(query.to_owned()))
+}
+
+// `ZSCORE key member`, `ZRANK key member` or `ZREVRANK key member`
+fn handle_zmember(
+    query: &str,
+    args: Args,
+    member: fn(String, String) -> BuckQuery,
+) -> BuckParserResult {
+    let args: Vec<Word> = args.words();
+
+    if let [key, name] = args[..] {
+        let key = parse_key(key)?;
+
+        return Ok(member(key, parse_text(name)));
     }
 
     Err(BuckParserError::InvalidQueryCommand(query.to_owned()))
 }
 
-fn handle_length(query: &str, parts: Vec<&str>) -> BuckParserResult {
-    if let Some(key) = parts.get(1) {
-        if !is_valid_key(key) {
-            return Err(BuckParserError::InvalidKey(key.to_string()));
+// `ZRANGE key start stop [BYSCORE|BYLEX] [REV] [LIMIT offset count] [WITHSCORES]`
+fn handle_zrange(query: &str, args: Args) -> BuckParserResult {
+    let args: Vec<Word> = args.words();
+
+    parse_zrange(query, &args)
+}
+
+// `ZRANGEBYSCORE key min max [WITHSCORES] [LIMIT offset count]` or
+// `ZRANGEBYLEX key min max [LIMIT offset count]`
+fn handle_zrange_by(query: &str, args: Args, by: &'static str) -> BuckParserResult {
+    let mut args: Vec<Word> = args.words();
+
+    // the same as `ZRANGE key min max BYSCORE ...` or `ZRANGE key min max BYLEX ...`
+    if args.len() >= 3 {
+        args.insert(3, Word::Text(by));
+
+        return parse_zrange(query, &args);
+    }
+
+    Err(BuckParserError::InvalidQueryCommand(query.to_owned()))
+}
+
+fn parse_zrange(query: &str, args: &[Word]) -> BuckParserResult {
+    let invalid = || BuckParserError::InvalidQueryCommand(query.to_owned());
+
+    let (key, start, stop, options) = match args {
+        [key, start, stop, options @ ..] => (*key, *start, *stop, options),
+        _ => return Err(invalid()),
+    };
+
+    let key = parse_key(key)?;
+
+    let (mut by_score, mut by_lex, mut rev, mut with_scores) = (false, false, false, false);
+    let mut limit = None;
+    let mut options = options.iter();
+
+    while let Some(option) = options.next() {
+        match option.to_lowercase().as_str() {
+            "byscore" => by_score = true,
+            "bylex" => by_lex = true,
+            "rev" => rev = true,
+            "withscores" => with_scores = true,
+            "limit" => {
+                let (offset, count) = match (options.next(), options.next()) {
+                    (Some(offset), Some(count)) => (offset, count),
+                    _ => return Err(invalid()),
+                };
+
+                // a negative count returns every member after the offset
+                let count = match parse_number::<i64>(query, count)? {
+                    count if count < 0 => usize::MAX,
+                    count => count as usize,
+                };
+
+                limit = Some((parse_number(query, offset)?, count));
+            }
+            _ => return Err(invalid()),
         }
+    }
+
+    // with `REV`, a score or lex range is given from the maximum down
+    let (min, max) = match rev {
+        true => (stop, start),
+        false => (start, stop),
+    };
+
+    let by = match (by_score, by_lex) {
+        (false, false) if limit.is_none() => {
+            RangeBy::Index(parse_number(query, &start)?, parse_number(query, &stop)?)
+        }
+        (true, false) => RangeBy::Score(
+            parse_score_bound(query, &min)?,
+            parse_score_bound(query, &max)?,
+        ),
+        (false, true) if !with_scores => {
+            RangeBy::Lex(parse_lex_bound(query, &min)?, parse_lex_bound(query, &max)?)
+        }
+        _ => return Err(invalid()),
+    };
+
+    Ok(BuckQuery::ZRange(
+        key,
+        ZRangeSpec {
+            by,
+            rev,
+            limit,
+            with_scores,
+        },
+    ))
+}
 
-        return Ok(BuckQuery::Len(key.to_string()));
+// `ZINCRBY key increment member`
+fn handle_zincrby(query: &str, args: Args) -> BuckParserResult {
+    let args: Vec<Word> = args.words();
+
+    if let [key, by, member] = args[..] {
+        let key = parse_key(key)?;
+
+        return Ok(BuckQuery::ZIncrBy(
+            key,
+            parse_score(query, &by)?,
+            parse_text(member),
+        ));
     }
 
     Err(BuckParserError::InvalidQueryCommand(query.to_owned()))
 }
 
-fn handle_hset(query: &str, parts: Vec<&str>) -> BuckParserResult {
-    if let Some(key) = parts.get(1) {
-        let key_value: Vec<&str> = key.splitn(2, ' ').collect();
+// `ZCOUNT key min max`
+fn handle_zcount(query: &str, args: Args) -> BuckParserResult {
+    let args: Vec<Word> = args.words();
+
+    if let [key, min, max] = args[..] {
+        let key = parse_key(key)?;
+
+        return Ok(BuckQuery::ZCount(
+            key,
+            parse_score_bound(query, &min)?,
+            parse_score_bound(query, &max)?,
+        ));
+    }
+
+    Err(BuckParserError::InvalidQueryCommand(query.to_owned()))
+}
+
+// `ZUNIONSTORE destination numkeys key [key ...] [WEIGHTS weight ...] [AGGREGATE SUM|MIN|MAX]`,
+// and the same for `ZINTERSTORE`
+fn handle_zstore(
+    query: &str,
+    args: Args,
+    store: fn(String, Vec<String>, Option<Vec<f64>>, Aggregate) -> BuckQuery,
+) -> BuckParserResult {
+    let invalid = || BuckParserError::InvalidQueryCommand(query.to_owned());
+
+    let args: Vec<Word> = args.words();
+
+    if let [destination, numkeys, ref rest @ ..] = args[..] {
+        let numkeys: usize = parse_number(query, &numkeys)?;
+
+        if numkeys == 0 || rest.len() < numkeys {
+            return Err(invalid());
+        }
 
-        if let (Some(key), Some(value)) = (key_value.first(), key_value.get(1)) {
-            if !is_valid_key(key) {
-                return Err(BuckParserError::InvalidKey(key.to_string()));
+        let keys = parse_keys(&rest[..numkeys])?;
+        let destination = parse_key(destination)?;
+
+        let mut weights = None;
+        let mut aggregate = Aggregate::default();
+        let mut options = &rest[numkeys..];
+
+        while let Some((option, others)) = options.split_first() {
+            match option.to_lowercase().as_str() {
+                "weights" if others.len() >= numkeys => {
+                    let parsed = others[..numkeys]
+                        .iter()
+                        .map(|weight| parse_score(query, weight))
+                        .collect::<Result<Vec<f64>, BuckParserError>>()?;
+
+                    weights = Some(parsed);
+                    options = &others[numkeys..];
+                }
+                "aggregate" => {
+                    aggregate = match others.first().map(|a| a.to_lowercase()).as_deref() {
+                        Some("sum") => Aggregate::Sum,
+                        Some("min") => Aggregate::Min,
+                        Some("max") => Aggregate::Max,
+                        _ => return Err(invalid()),
+                    };
+                    options = &others[1..];
+                }
+                _ => return Err(invalid()),
             }
+        }
+
+        return Ok(store(destination, keys, weights, aggregate));
+    }
+
+    Err(invalid())
+}
+
+// parse a file path, which may be wrapped in quotes if it contains whitespace.
+// The path is resolved in the data directory, so it may not leave it.
+fn parse_path(query: &str, args: Args) -> Result<String, BuckParserError> {
+    let path = args
+        .rest()
+        .map(parse_text)
+        .filter(|path| !path.is_empty())
+        .ok_or_else(|| BuckParserError::InvalidQueryCommand(query.to_owned()))?;
+
+    let escapes = Path::new(&path)
+        .components()
+        .any(|component| !matches!(component, Component::Normal(_) | Component::CurDir));
+
+    if escapes {
+        return Err(BuckParserError::InvalidPath(path));
+    }
 
-            let parsed_fields = parse_fields(value)?;
-            return Ok(BuckQuery::HSet(key.to_string(), parsed_fields));
+    Ok(path)
+}
+
+// `SAVE path`
+fn handle_save(query: &str, args: Args) -> BuckParserResult {
+    parse_path(query, args).map(BuckQuery::Save)
+}
+
+// `LOAD path`
+fn handle_load(query: &str, args: Args) -> BuckParserResult {
+    parse_path(query, args).map(BuckQuery::Load)
+}
+
+// parse a query that takes exactly one key
+fn handle_single_key(query: &str, args: Args) -> Result<String, BuckParserError> {
+    let args: Vec<Word> = args.words();
+
+    match args[..] {
+        [key] => parse_key(key),
+        _ => Err(BuckParserError::InvalidQueryCommand(query.to_owned())),
+    }
+}
+
+fn handle_expire(query: &str, args: Args, is_millis: bool) -> BuckParserResult {
+    let key_value: Vec<Word> = args.words();
+
+    if let [key, timeout] = key_value[..] {
+        let key = parse_key(key)?;
+
+        let timeout = timeout.parse::<i64>().map_err(|_| {
+            BuckParserError::InvalidQueryCommand(query.to_owned())
+        })?;
+
+        return match is_millis {
+            true => Ok(BuckQuery::PExpire(key, timeout)),
+            false => Ok(BuckQuery::Expire(key, timeout)),
+        };
+    }
+
+    Err(BuckParserError::InvalidQueryCommand(query.to_owned()))
+}
+
+fn handle_watch(query: &str, args: Args) -> BuckParserResult {
+    Ok(BuckQuery::Watch(parse_set_keys(query, &args.words())?))
+}
+
+/// `CONFIG GET parameter` or `CONFIG SET parameter value`, where the value may
+/// be wrapped in quotes, like `CONFIG SET key-prefix ""` to clear it
+fn handle_config(query: &str, args: Args) -> BuckParserResult {
+    let args: Vec<Word> = args.words();
+
+    match args[..] {
+        [sub, name] if sub.eq_ignore_ascii_case("get") => {
+            return Ok(BuckQuery::ConfigGet(name.to_lowercase()));
+        }
+        [sub, name, value] if sub.eq_ignore_ascii_case("set") => {
+            return Ok(BuckQuery::ConfigSet(name.to_lowercase(), parse_text(value)));
         }
+        _ => {}
     }
 
     Err(BuckParserError::InvalidQueryCommand(query.to_owned()))
-}
\ No newline at end of file
+}
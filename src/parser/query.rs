@@ -1,140 +1,842 @@
 use std::collections::HashMap;
+use std::ops::Bound;
+use std::time::Duration;
 
+use crate::blocking::BlockedPop;
+use crate::clock::Expiry;
+use crate::types::errors::BuckTypeError;
+use crate::types::list::ListSide;
+use crate::types::sets::BuckSets;
+use crate::types::sorted_set::{Aggregate, ScoreBound, ZAddFlags, ZRangeSpec};
+use crate::types::string::SetOptions;
 use crate::types::types::BuckTypes;
-use crate::{engine::BuckDB, errors::BuckEngineError, log::BuckLog};
+use crate::reply::BuckReply;
+use crate::errors::{BuckEngineError, BuckError};
+use crate::{engine::BuckDB, parser::errors::BuckParserError};
 
 #[derive(Debug, PartialEq)]
 pub enum BuckQuery {
     Get(Vec<String>),
     Insert(String, BuckTypes),
+    Set(String, BuckTypes, SetOptions),
     Update(String, BuckTypes),
     Remove(Vec<String>),
     Shard(usize),
     Type(String),
+    // counter things
+    IncrBy(String, i64),
+    DecrBy(String, i64),
+    IncrByFloat(String, f64),
+    // string things
+    Append(String, String),
+    StrLen(String),
+    GetRange(String, i64, i64),
+    // key, offset, value
+    SetRange(String, usize, String),
+    GetSet(String, BuckTypes),
+    GetDel(String),
+    GetEx(String, Option<Expiry>),
+    MGet(Vec<String>),
+    MSet(Vec<(String, BuckTypes)>),
+    MSetNx(Vec<(String, BuckTypes)>),
+    // keyspace things
+    Keys(String),
+    // cursor, pattern, count and type
+    Scan(u64, Option<String>, usize, Option<String>),
+    // start, end, limit and whether to go from the end
+    Range(Bound<String>, Bound<String>, Option<usize>, bool),
+    Prefix(String),
+    Exists(Vec<String>),
+    Touch(Vec<String>),
+    Rename(String, String),
+    RenameNx(String, String),
+    // source, destination and whether to replace it
+    Copy(String, String, bool),
+    Unlink(Vec<String>),
+    DbSize,
+    FlushDb,
+    RandomKey,
+    // database things
+    Select(usize),
+    // key and the database to move it to
+    Move(String, usize),
+    SwapDb(usize, usize),
+    FlushAll,
     // list things
     LPush(String, Vec<BuckTypes>),
-    LPop(String),
+    RPush(String, Vec<BuckTypes>),
+    LPop(String, Option<usize>),
+    RPop(String, Option<usize>),
+    LRange(String, i64, i64),
+    LIndex(String, i64),
+    LSet(String, i64, BuckTypes),
+    // key, whether to insert before the pivot, pivot, value
+    LInsert(String, bool, BuckTypes, BuckTypes),
+    LRem(String, i64, BuckTypes),
+    LTrim(String, i64, i64),
+    // source, destination, the end to pop from, the end to push to
+    LMove(String, String, ListSide, ListSide),
+    // keys and timeout, `None` blocks forever
+    BLPop(Vec<String>, Option<Duration>),
+    BRPop(Vec<String>, Option<Duration>),
+    BLMove(String, String, ListSide, ListSide, Option<Duration>),
     // sets type things
     SAdd(String, Vec<BuckTypes>),
     SRem(String, Vec<BuckTypes>),
-    SInter(String, Vec<String>),
+    SInter(Vec<String>),
+    SUnion(Vec<String>),
+    SDiff(Vec<String>),
+    // keys and the limit, `0` for none
+    SInterCard(Vec<String>, usize),
+    // destination and keys
+    SInterStore(String, Vec<String>),
+    SUnionStore(String, Vec<String>),
+    SDiffStore(String, Vec<String>),
+    SIsMember(String, BuckTypes),
+    SMIsMember(String, Vec<BuckTypes>),
+    SMembers(String),
+    SCard(String),
+    SRandMember(String, Option<i64>),
+    SPop(String, Option<usize>),
+    // source, destination, member
+    SMove(String, String, BuckTypes),
     // hash type things
     HSet(String, HashMap<String, BuckTypes>),
+    HGet(String, String),
+    HMGet(String, Vec<String>),
+    HGetAll(String),
+    HDel(String, Vec<String>),
+    HExists(String, String),
+    HKeys(String),
+    HVals(String),
+    HLen(String),
+    HSetNx(String, String, BuckTypes),
+    HIncrBy(String, String, i64),
+    HIncrByFloat(String, String, f64),
+    HStrLen(String, String),
+    // key, cursor, pattern and count
+    HScan(String, u64, Option<String>, usize),
+    // sorted set type things
+    // key, flags, and scores with their members
+    ZAdd(String, ZAddFlags, Vec<(f64, String)>),
+    ZRem(String, Vec<String>),
+    ZScore(String, String),
+    ZRank(String, String),
+    ZRevRank(String, String),
+    ZRange(String, ZRangeSpec),
+    // key, increment, member
+    ZIncrBy(String, f64, String),
+    ZPopMin(String, Option<usize>),
+    ZPopMax(String, Option<usize>),
+    ZCount(String, ScoreBound, ScoreBound),
+    ZCard(String),
+    // destination, keys, weights and how to combine scores
+    ZUnionStore(String, Vec<String>, Option<Vec<f64>>, Aggregate),
+    ZInterStore(String, Vec<String>, Option<Vec<f64>>, Aggregate),
     // for all collection types
     Len(String),
     //TODO Commit and Rollback may be take db name as argument
+    Begin,
     Commit,
     Rollback,
+    Watch(Vec<String>),
+    Unwatch,
     Exit,
     Clear,
+    Ping,
+    Save(String),
+    Load(String),
+    // expiration things
+    Expire(String, i64),
+    PExpire(String, i64),
+    Ttl(String),
+    PTtl(String),
+    Persist(String),
+    // configuration things
+    ConfigGet(String),
+    ConfigSet(String, String),
     Unknown,
 }
 
 impl BuckQuery {
-    pub fn execute(self, query: &str, db: &mut BuckDB) -> Result<BuckLog, BuckEngineError> {
+    /// The pop a blocking command waits for, and how long it may wait.
+    ///
+    /// ## Returns
+    ///
+    /// `None` for commands that never block.
+    pub fn blocked_pop(&self) -> Option<(BlockedPop, Option<Duration>)> {
+        let (keys, side, target, timeout) = match self {
+            BuckQuery::BLPop(keys, timeout) => (keys.clone(), ListSide::Left, None, timeout),
+            BuckQuery::BRPop(keys, timeout) => (keys.clone(), ListSide::Right, None, timeout),
+            BuckQuery::BLMove(source, destination, from, to, timeout) => (
+                vec![source.clone()],
+                *from,
+                Some((destination.clone(), *to)),
+                timeout,
+            ),
+            _ => return None,
+        };
+
+        Some((BlockedPop { keys, side, target }, *timeout))
+    }
+
+    /// Every key the query names, so that they can be checked against a
+    /// `KeyPolicy` before it runs.
+    ///
+    /// Patterns, prefixes and range bounds are not keys, and neither are
+    /// hash fields or the members of a collection.
+    pub fn keys(&self) -> Vec<&str> {
+        let keys: Vec<&String> = match self {
+            BuckQuery::Get(keys)
+            | BuckQuery::Remove(keys)
+            | BuckQuery::MGet(keys)
+            | BuckQuery::Exists(keys)
+            | BuckQuery::Touch(keys)
+            | BuckQuery::Unlink(keys)
+            | BuckQuery::BLPop(keys, _)
+            | BuckQuery::BRPop(keys, _)
+            | BuckQuery::SInter(keys)
+            | BuckQuery::SUnion(keys)
+            | BuckQuery::SDiff(keys)
+            | BuckQuery::SInterCard(keys, _)
+            | BuckQuery::Watch(keys) => keys.iter().collect(),
+            BuckQuery::SInterStore(destination, keys)
+            | BuckQuery::SUnionStore(destination, keys)
+            | BuckQuery::SDiffStore(destination, keys)
+            | BuckQuery::ZUnionStore(destination, keys, ..)
+            | BuckQuery::ZInterStore(destination, keys, ..) => {
+                std::iter::once(destination).chain(keys).collect()
+            }
+            BuckQuery::MSet(pairs) | BuckQuery::MSetNx(pairs) => {
+                pairs.iter().map(|(key, _)| key).collect()
+            }
+            BuckQuery::Rename(source, destination)
+            | BuckQuery::RenameNx(source, destination)
+            | BuckQuery::Copy(source, destination, _)
+            | BuckQuery::LMove(source, destination, ..)
+            | BuckQuery::BLMove(source, destination, ..)
+            | BuckQuery::SMove(source, destination, _) => vec![source, destination],
+            BuckQuery::Insert(key, ..)
+            | BuckQuery::Set(key, ..)
+            | BuckQuery::Update(key, ..)
+            | BuckQuery::Type(key)
+            | BuckQuery::IncrBy(key, _)
+            | BuckQuery::DecrBy(key, _)
+            | BuckQuery::IncrByFloat(key, _)
+            | BuckQuery::Append(key, _)
+            | BuckQuery::StrLen(key)
+            | BuckQuery::GetRange(key, ..)
+            | BuckQuery::SetRange(key, ..)
+            | BuckQuery::GetSet(key, _)
+            | BuckQuery::GetDel(key)
+            | BuckQuery::GetEx(key, _)
+            | BuckQuery::Move(key, _)
+            | BuckQuery::LPush(key, _)
+            | BuckQuery::RPush(key, _)
+            | BuckQuery::LPop(key, _)
+            | BuckQuery::RPop(key, _)
+            | BuckQuery::LRange(key, ..)
+            | BuckQuery::LIndex(key, _)
+            | BuckQuery::LSet(key, ..)
+            | BuckQuery::LInsert(key, ..)
+            | BuckQuery::LRem(key, ..)
+            | BuckQuery::LTrim(key, ..)
+            | BuckQuery::SAdd(key, _)
+            | BuckQuery::SRem(key, _)
+            | BuckQuery::SIsMember(key, _)
+            | BuckQuery::SMIsMember(key, _)
+            | BuckQuery::SMembers(key)
+            | BuckQuery::SCard(key)
+            | BuckQuery::SRandMember(key, _)
+            | BuckQuery::SPop(key, _)
+            | BuckQuery::HSet(key, _)
+            | BuckQuery::HGet(key, _)
+            | BuckQuery::HMGet(key, _)
+            | BuckQuery::HGetAll(key)
+            | BuckQuery::HDel(key, _)
+            | BuckQuery::HExists(key, _)
+            | BuckQuery::HKeys(key)
+            | BuckQuery::HVals(key)
+            | BuckQuery::HLen(key)
+            | BuckQuery::HSetNx(key, ..)
+            | BuckQuery::HIncrBy(key, ..)
+            | BuckQuery::HIncrByFloat(key, ..)
+            | BuckQuery::HStrLen(key, _)
+            | BuckQuery::HScan(key, ..)
+            | BuckQuery::ZAdd(key, ..)
+            | BuckQuery::ZRem(key, _)
+            | BuckQuery::ZScore(key, _)
+            | BuckQuery::ZRank(key, _)
+            | BuckQuery::ZRevRank(key, _)
+            | BuckQuery::ZRange(key, _)
+            | BuckQuery::ZIncrBy(key, ..)
+            | BuckQuery::ZPopMin(key, _)
+            | BuckQuery::ZPopMax(key, _)
+            | BuckQuery::ZCount(key, ..)
+            | BuckQuery::ZCard(key)
+            | BuckQuery::Len(key)
+            | BuckQuery::Expire(key, _)
+            | BuckQuery::PExpire(key, _)
+            | BuckQuery::Ttl(key)
+            | BuckQuery::PTtl(key)
+            | BuckQuery::Persist(key) => vec![key],
+            BuckQuery::Shard(_)
+            | BuckQuery::Keys(_)
+            | BuckQuery::Scan(..)
+            | BuckQuery::Range(..)
+            | BuckQuery::Prefix(_)
+            | BuckQuery::DbSize
+            | BuckQuery::FlushDb
+            | BuckQuery::RandomKey
+            | BuckQuery::Select(_)
+            | BuckQuery::SwapDb(..)
+            | BuckQuery::FlushAll
+            | BuckQuery::Begin
+            | BuckQuery::Commit
+            | BuckQuery::Rollback
+            | BuckQuery::Unwatch
+            | BuckQuery::Exit
+            | BuckQuery::Clear
+            | BuckQuery::Ping
+            | BuckQuery::Save(_)
+            | BuckQuery::Load(_)
+            | BuckQuery::ConfigGet(_)
+            | BuckQuery::ConfigSet(..)
+            | BuckQuery::Unknown => Vec::new(),
+        };
+
+        keys.into_iter().map(String::as_str).collect()
+    }
+
+    /// Run the query against `db`, once every key it names passed the key
+    /// policy of `db`.
+    ///
+    /// ## Returns
+    ///
+    /// The typed reply to the query. A single `GET` returns the value itself,
+    /// or `Nil` if the key does not exist; several keys return an array.
+    ///
+    /// Any failure is returned as a `BuckError`, this never panics.
+    pub fn execute(self, _query: &str, db: &mut BuckDB) -> Result<BuckReply, BuckError> {
+        db.key_policy.check_all(self.keys())?;
+
         match self {
             BuckQuery::Get(keys) => {
                 let mut results = Vec::new();
 
-                for key in keys {
-                    let value = db.get(&key).unwrap();
-                    results.push(format!("{}: {}", key, value));
+                for key in &keys {
+                    db.touch(key);
+                    let value = match db.get(key) {
+                        Ok(value) => BuckReply::from(value),
+                        Err(BuckEngineError::KeyNotFound(_)) => BuckReply::Nil,
+                        Err(e) => return Err(e.into()),
+                    };
+                    results.push(value);
+                }
+
+                if results.len() == 1 {
+                    return Ok(results.remove(0));
                 }
 
-                Ok(BuckLog::GetOk(results.join("\n")))
+                Ok(BuckReply::Array(results))
             }
-            BuckQuery::Insert(key, value) => {
-                db.insert(key, value).unwrap();
+            BuckQuery::Insert(key, value) => Ok(db.insert(key, value)?.into()),
+            BuckQuery::Set(key, value, options) => {
+                let (is_set, old) = db.set(key, value, &options)?;
 
-                Ok(BuckLog::InsertOk(query.to_owned()))
+                match (options.get, is_set) {
+                    (true, _) => Ok(old.as_ref().map_or(BuckReply::Nil, BuckReply::from)),
+                    (false, true) => Ok(BuckReply::ok()),
+                    (false, false) => Ok(BuckReply::Nil),
+                }
             }
             BuckQuery::Remove(keys) => {
                 for key in keys {
-                    db.remove(&key).unwrap();
+                    db.remove(&key)?;
                 }
 
-                Ok(BuckLog::RemoveOk(query.to_owned()))
+                Ok(BuckReply::ok())
+            }
+            BuckQuery::Update(key, value) => Ok(db.update(&key, value)?.into()),
+            BuckQuery::IncrBy(key, by) => Ok(BuckReply::Integer(db.incr_by(&key, by)?)),
+            BuckQuery::DecrBy(key, by) => {
+                // `i64::MIN` has no positive counterpart
+                let by = by.checked_neg().ok_or(BuckTypeError::Overflow)?;
+
+                Ok(BuckReply::Integer(db.incr_by(&key, by)?))
+            }
+            BuckQuery::IncrByFloat(key, by) => {
+                Ok(BuckReply::Float(db.incr_by_float(&key, by)?))
+            }
+
+            // string things
+            BuckQuery::Append(key, value) => Ok(BuckReply::Integer(db.append(&key, &value)? as i64)),
+            BuckQuery::StrLen(key) => Ok(BuckReply::Integer(db.str_len(&key)? as i64)),
+            BuckQuery::GetRange(key, start, end) => {
+                Ok(BuckReply::Bulk(db.get_range(&key, start, end)?))
+            }
+            BuckQuery::SetRange(key, offset, value) => {
+                Ok(BuckReply::Integer(db.set_range(&key, offset, &value)? as i64))
+            }
+            BuckQuery::GetSet(key, value) => {
+                Ok(db.get_set(key, value)?.as_ref().map_or(BuckReply::Nil, BuckReply::from))
+            }
+            BuckQuery::GetDel(key) => {
+                Ok(db.get_del(&key)?.as_ref().map_or(BuckReply::Nil, BuckReply::from))
+            }
+            BuckQuery::GetEx(key, expiry) => {
+                Ok(db.get_ex(&key, expiry)?.as_ref().map_or(BuckReply::Nil, BuckReply::from))
+            }
+            BuckQuery::MGet(keys) => Ok(BuckReply::Array(
+                db.m_get(&keys)?
+                    .iter()
+                    .map(|value| value.as_ref().map_or(BuckReply::Nil, BuckReply::from))
+                    .collect(),
+            )),
+            BuckQuery::MSet(pairs) => {
+                db.m_set(pairs)?;
+
+                Ok(BuckReply::ok())
+            }
+            BuckQuery::MSetNx(pairs) => Ok(BuckReply::Integer(db.m_set_nx(pairs)? as i64)),
+
+            // keyspace things
+            BuckQuery::Keys(pattern) => Ok(BuckReply::Array(
+                db.keys(&pattern)?.into_iter().map(BuckReply::Bulk).collect(),
+            )),
+            BuckQuery::Scan(cursor, pattern, count, type_name) => {
+                let (cursor, keys) = db.scan(cursor, pattern.as_deref(), count, type_name.as_deref())?;
+
+                Ok(BuckReply::Array(vec![
+                    BuckReply::Bulk(cursor.to_string()),
+                    BuckReply::Array(keys.into_iter().map(BuckReply::Bulk).collect()),
+                ]))
+            }
+            BuckQuery::Range(start, end, limit, rev) => {
+                let entries = db.range(start.as_ref().map(String::as_str), end.as_ref().map(String::as_str))?;
+                let limit = limit.unwrap_or(usize::MAX);
+
+                Ok(match rev {
+                    true => entries_reply(entries.rev().take(limit)),
+                    false => entries_reply(entries.take(limit)),
+                })
+            }
+            BuckQuery::Prefix(prefix) => Ok(entries_reply(db.prefix(&prefix)?)),
+            BuckQuery::Exists(keys) => Ok(BuckReply::Integer(db.exists(&keys)? as i64)),
+            BuckQuery::Touch(keys) => Ok(BuckReply::Integer(db.touch_keys(&keys)? as i64)),
+            BuckQuery::Rename(key, new_key) => {
+                db.rename(&key, &new_key)?;
+
+                Ok(BuckReply::ok())
+            }
+            BuckQuery::RenameNx(key, new_key) => {
+                Ok(BuckReply::Integer(db.rename_nx(&key, &new_key)? as i64))
+            }
+            BuckQuery::Copy(source, destination, replace) => {
+                Ok(BuckReply::Integer(db.copy(&source, &destination, replace)? as i64))
+            }
+            BuckQuery::Unlink(keys) => Ok(BuckReply::Integer(db.unlink(&keys)? as i64)),
+            BuckQuery::DbSize => Ok(BuckReply::Integer(db.db_size()? as i64)),
+            BuckQuery::FlushDb => {
+                db.flush_db()?;
+
+                Ok(BuckReply::ok())
+            }
+            BuckQuery::RandomKey => Ok(db.random_key()?.map_or(BuckReply::Nil, BuckReply::Bulk)),
+
+            // database things
+            // with a single database there is nothing else to select or move to,
+            // `BuckSession` handles these for a set of databases
+            BuckQuery::Select(_) => {
+                Err(BuckEngineError::SessionRequired("SELECT".to_owned()).into())
+            }
+            BuckQuery::Move(..) => Err(BuckEngineError::SessionRequired("MOVE".to_owned()).into()),
+            BuckQuery::SwapDb(..) => {
+                Err(BuckEngineError::SessionRequired("SWAPDB".to_owned()).into())
             }
-            BuckQuery::Update(key, value) => {
-                db.update(&key, value).unwrap();
+            BuckQuery::FlushAll => {
+                db.flush_db()?;
 
-                Ok(BuckLog::UpdateOk(query.to_owned()))
+                Ok(BuckReply::ok())
             }
             BuckQuery::Type(key) => {
-                let typ = db.type_of(&key).unwrap();
+                db.touch(&key);
+                let typ = db.type_of(&key)?;
 
-                Ok(BuckLog::TypeOk(key, typ.to_string()))
+                Ok(BuckReply::Status(typ))
             }
+            // queueing and watching are handled by `BuckSession`,
+            // without one these only act on the staged writes
+            BuckQuery::Begin => Ok(db.begin_transaction()?.into()),
             BuckQuery::Commit => {
-                db.commit().unwrap();
+                db.commit()?;
 
-                Ok(BuckLog::TransactionOk)
+                Ok(BuckReply::ok())
             }
-            BuckQuery::Rollback => {
-                db.abort().unwrap();
-
-                Ok(BuckLog::RollbackOk)
+            BuckQuery::Rollback => Ok(db.abort()?.into()),
+            BuckQuery::Watch(_) => {
+                Err(BuckEngineError::SessionRequired("WATCH".to_owned()).into())
             }
+            BuckQuery::Unwatch => Ok(BuckReply::ok()),
             BuckQuery::Exit => {
                 std::process::exit(0);
             }
             BuckQuery::Clear => {
                 print!("{}[2J", 27 as char);
 
-                Ok(BuckLog::ClearOk)
+                Ok(BuckReply::ok())
             },
+            BuckQuery::Ping => Ok(BuckReply::Status("PONG".to_owned())),
+            BuckQuery::Save(path) => Ok(db.save(db.dir.join(path))?.into()),
+            BuckQuery::Load(path) => Ok(db.load(db.dir.join(path))?.into()),
+
+            // expiration things
+            BuckQuery::Expire(key, seconds) => {
+                Ok(db.expire(&key, seconds.saturating_mul(1000))?.into())
+            }
+            BuckQuery::PExpire(key, millis) => Ok(db.expire(&key, millis)?.into()),
+            BuckQuery::Ttl(key) => {
+                db.expire_if_needed(&key);
+
+                // round to the nearest second, like the remaining time is usually shown
+                let ttl = match db.pttl(&key) {
+                    millis if millis >= 0 => millis.saturating_add(500) / 1000,
+                    code => code,
+                };
+
+                Ok(BuckReply::Integer(ttl))
+            }
+            BuckQuery::PTtl(key) => {
+                db.expire_if_needed(&key);
+
+                Ok(BuckReply::Integer(db.pttl(&key)))
+            }
+            BuckQuery::Persist(key) => Ok(db.persist(&key)?.into()),
+
+            // configuration things
+            BuckQuery::ConfigGet(name) => Ok(db.config_get(&name)?.into()),
+            BuckQuery::ConfigSet(name, value) => Ok(db.config_set(&name, &value)?.into()),
             BuckQuery::Shard(num_shards) => {
-                db.enable_sharding(num_shards).unwrap();
+                db.enable_sharding(num_shards)?;
 
-                Ok(BuckLog::ShardingEnableOk)
+                Ok(BuckReply::ok())
             }
 
             // list things
             BuckQuery::LPush(key, values) => {
+                let mut length = BuckReply::Integer(0);
+                for value in values {
+                    length = db.l_push(key.clone(), value)?.into();
+                }
+
+                Ok(length)
+            }
+            BuckQuery::RPush(key, values) => {
+                let mut length = BuckReply::Integer(0);
                 for value in values {
-                    db.l_push(key.clone(), value).unwrap();
+                    length = db.r_push(key.clone(), value)?.into();
                 }
 
-                Ok(BuckLog::InsertOk(query.to_owned()))
+                Ok(length)
+            }
+            BuckQuery::LPop(key, count) => {
+                let values = db.l_pop(&key, count.unwrap_or(1))?;
+
+                Ok(popped_reply(values, count))
+            }
+            BuckQuery::RPop(key, count) => {
+                let values = db.r_pop(&key, count.unwrap_or(1))?;
+
+                Ok(popped_reply(values, count))
+            }
+            BuckQuery::LRange(key, start, stop) => {
+                let values = db.l_range(&key, start, stop)?;
+
+                Ok(BuckReply::Array(values.iter().map(BuckReply::from).collect()))
+            }
+            BuckQuery::LIndex(key, index) => match db.l_index(&key, index)? {
+                Some(value) => Ok(BuckReply::from(&value)),
+                None => Ok(BuckReply::Nil),
+            },
+            BuckQuery::LSet(key, index, value) => {
+                db.l_set(&key, index, value)?;
+
+                Ok(BuckReply::ok())
             }
-            BuckQuery::LPop(key) => {
-                let value = db.l_pop(&key).unwrap();
+            BuckQuery::LInsert(key, before, pivot, value) => {
+                Ok(BuckReply::Integer(db.l_insert(&key, before, &pivot, value)?))
+            }
+            BuckQuery::LRem(key, count, value) => {
+                Ok(BuckReply::Integer(db.l_rem(&key, count, &value)? as i64))
+            }
+            BuckQuery::LTrim(key, start, stop) => {
+                db.l_trim(&key, start, stop)?;
 
-                Ok(BuckLog::GetOk(format!("{}: {}", key, value)))
+                Ok(BuckReply::ok())
+            }
+            BuckQuery::LMove(source, destination, from, to) => {
+                match db.l_move(&source, destination, from, to)? {
+                    Some(value) => Ok(BuckReply::from(&value)),
+                    None => Ok(BuckReply::Nil),
+                }
+            }
+            // the blocking commands only try once here, waiting for an
+            // element is up to the caller, see `blocked_pop`
+            query @ (BuckQuery::BLPop(..) | BuckQuery::BRPop(..) | BuckQuery::BLMove(..)) => {
+                let (pop, _) = match query.blocked_pop() {
+                    Some(blocked) => blocked,
+                    None => return Ok(BuckReply::Nil),
+                };
+
+                for key in &pop.keys {
+                    let value = match &pop.target {
+                        Some((destination, to)) => db.l_move(key, destination.clone(), pop.side, *to)?,
+                        None => match pop.side {
+                            ListSide::Left => db.l_pop(key, 1)?.pop(),
+                            ListSide::Right => db.r_pop(key, 1)?.pop(),
+                        },
+                    };
+
+                    if let Some(value) = value {
+                        return Ok(pop.reply(key.clone(), &value));
+                    }
+                }
+
+                Ok(BuckReply::Nil)
             }
             // sets type things
             BuckQuery::SAdd(key, values) => {
                 for value in values {
-                    db.s_add(key.clone(), value).unwrap();
+                    db.s_add(key.clone(), value)?;
+                }
+
+                Ok(BuckReply::ok())
+            }
+            BuckQuery::SInter(keys) => Ok(BuckReply::from(&db.s_inter(&keys)?)),
+            BuckQuery::SUnion(keys) => Ok(BuckReply::from(&db.s_union(&keys)?)),
+            BuckQuery::SDiff(keys) => Ok(BuckReply::from(&db.s_diff(&keys)?)),
+            BuckQuery::SInterCard(keys, limit) => {
+                Ok(BuckReply::Integer(db.s_inter_card(&keys, limit)? as i64))
+            }
+            BuckQuery::SInterStore(destination, keys) => {
+                Ok(BuckReply::Integer(db.s_inter_store(destination, &keys)? as i64))
+            }
+            BuckQuery::SUnionStore(destination, keys) => {
+                Ok(BuckReply::Integer(db.s_union_store(destination, &keys)? as i64))
+            }
+            BuckQuery::SDiffStore(destination, keys) => {
+                Ok(BuckReply::Integer(db.s_diff_store(destination, &keys)? as i64))
+            }
+            BuckQuery::SIsMember(key, value) => {
+                Ok(BuckReply::Integer(db.s_is_member(&key, value)? as i64))
+            }
+            BuckQuery::SMIsMember(key, values) => {
+                let members = db.s_mis_member(&key, values)?;
+
+                Ok(BuckReply::Array(
+                    members.into_iter().map(|member| BuckReply::Integer(member as i64)).collect(),
+                ))
+            }
+            BuckQuery::SMembers(key) => Ok(BuckReply::from(&db.s_members(&key)?)),
+            BuckQuery::SCard(key) => Ok(BuckReply::Integer(db.s_card(&key)? as i64)),
+            BuckQuery::SRandMember(key, count) => {
+                let members = db.s_rand_member(&key, count.unwrap_or(1))?;
+
+                match (count, members.first()) {
+                    (None, None) => Ok(BuckReply::Nil),
+                    (None, Some(member)) => Ok(BuckReply::from(member)),
+                    // members may repeat, so this is not a set
+                    (Some(_), _) => Ok(BuckReply::Array(members.iter().map(BuckReply::from).collect())),
                 }
+            }
+            BuckQuery::SPop(key, count) => {
+                let members = db.s_pop(&key, count.unwrap_or(1))?;
+
+                match (count, members.first()) {
+                    (None, None) => Ok(BuckReply::Nil),
+                    (None, Some(member)) => Ok(BuckReply::from(member)),
+                    (Some(_), _) => {
+                        let mut set = BuckSets::new();
+                        set.insert(&members);
 
-                Ok(BuckLog::InsertOk(query.to_owned()))
+                        Ok(BuckReply::from(&set))
+                    }
+                }
             }
-            BuckQuery::SInter(target, others) => {
-                let target = db.s_inter(target, others.clone()).unwrap();
-                
-                Ok(BuckLog::SetsIntersectionOk(target.to_string(), others))
+            BuckQuery::SMove(source, destination, value) => {
+                Ok(BuckReply::Integer(db.s_move(&source, destination, value)? as i64))
             }
             BuckQuery::SRem(key, values) => {
                 for value in values {
-                    db.s_rem(key.clone(), value).unwrap();
+                    db.s_rem(key.clone(), value)?;
                 }
 
-                Ok(BuckLog::RemoveOk(query.to_owned()))
+                Ok(BuckReply::ok())
             }
             BuckQuery::Len(key) => {
-                let length = db.get_collections_length(key.clone()).unwrap();
+                db.touch(&key);
+                let length = db.get_collections_length(key.clone())?;
+
+                Ok(BuckReply::Integer(length as i64))
+            }
+            // hash type things
+            BuckQuery::HSet(key, fields) => Ok(db.h_set(key, fields)?.into()),
+            BuckQuery::HGet(key, field) => match db.h_get(&key, &field)? {
+                Some(value) => Ok(BuckReply::from(&value)),
+                None => Ok(BuckReply::Nil),
+            },
+            BuckQuery::HMGet(key, fields) => {
+                let fields: Vec<&str> = fields.iter().map(String::as_str).collect();
+                let values = db.h_mget(&key, &fields)?;
+
+                Ok(BuckReply::Array(
+                    values
+                        .iter()
+                        .map(|value| value.as_ref().map_or(BuckReply::Nil, BuckReply::from))
+                        .collect(),
+                ))
+            }
+            BuckQuery::HGetAll(key) => {
+                let fields = db.h_get_all(&key)?;
 
-                Ok(BuckLog::LengthOk(length))
+                Ok(BuckReply::Map(
+                    fields
+                        .iter()
+                        .map(|(field, value)| (BuckReply::Bulk(field.clone()), BuckReply::from(value)))
+                        .collect(),
+                ))
+            }
+            BuckQuery::HDel(key, fields) => {
+                let fields: Vec<&str> = fields.iter().map(String::as_str).collect();
+
+                Ok(BuckReply::Integer(db.h_del(&key, &fields)? as i64))
+            }
+            BuckQuery::HExists(key, field) => {
+                Ok(BuckReply::Integer(db.h_exists(&key, &field)? as i64))
+            }
+            BuckQuery::HKeys(key) => {
+                let fields = db.h_keys(&key)?;
+
+                Ok(BuckReply::Array(fields.into_iter().map(BuckReply::Bulk).collect()))
+            }
+            BuckQuery::HVals(key) => {
+                let values = db.h_vals(&key)?;
+
+                Ok(BuckReply::Array(values.iter().map(BuckReply::from).collect()))
+            }
+            BuckQuery::HLen(key) => Ok(BuckReply::Integer(db.h_len(&key)? as i64)),
+            BuckQuery::HSetNx(key, field, value) => {
+                Ok(BuckReply::Integer(db.h_set_nx(key, &field, value)? as i64))
             }
-            BuckQuery::HSet(key, fields) => {
-                db.h_set(key.clone(), fields).unwrap();
-                let length = db.get_collections_length(key.clone()).unwrap();
-                Ok(BuckLog::HSetOk(length))
+            BuckQuery::HIncrBy(key, field, by) => {
+                Ok(BuckReply::Integer(db.h_incr_by(key, &field, by)?))
             }
-            _ => {
-                unimplemented!("Not implemented yet")
+            BuckQuery::HIncrByFloat(key, field, by) => {
+                Ok(BuckReply::Float(db.h_incr_by_float(key, &field, by)?))
             }
+            BuckQuery::HStrLen(key, field) => {
+                Ok(BuckReply::Integer(db.h_strlen(&key, &field)? as i64))
+            }
+            BuckQuery::HScan(key, cursor, pattern, count) => {
+                let (cursor, fields) = db.h_scan(&key, cursor, pattern.as_deref(), count)?;
+
+                // fields and values are flattened, like `HGETALL` over RESP2
+                let items = fields
+                    .iter()
+                    .flat_map(|(field, value)| [BuckReply::Bulk(field.clone()), BuckReply::from(value)])
+                    .collect();
+
+                Ok(BuckReply::Array(vec![
+                    BuckReply::Bulk(cursor.to_string()),
+                    BuckReply::Array(items),
+                ]))
+            }
+
+            // sorted set type things
+            BuckQuery::ZAdd(key, flags, members) => {
+                Ok(BuckReply::Integer(db.z_add(key, members, flags)? as i64))
+            }
+            BuckQuery::ZRem(key, members) => {
+                Ok(BuckReply::Integer(db.z_rem(&key, &members)? as i64))
+            }
+            BuckQuery::ZScore(key, member) => match db.z_score(&key, &member)? {
+                Some(score) => Ok(BuckReply::Float(score)),
+                None => Ok(BuckReply::Nil),
+            },
+            BuckQuery::ZRank(key, member) => Ok(rank_reply(db.z_rank(&key, &member, false)?)),
+            BuckQuery::ZRevRank(key, member) => Ok(rank_reply(db.z_rank(&key, &member, true)?)),
+            BuckQuery::ZRange(key, spec) => {
+                let members = db.z_range(&key, &spec)?;
+
+                Ok(scored_reply(members, spec.with_scores))
+            }
+            BuckQuery::ZIncrBy(key, by, member) => {
+                Ok(BuckReply::Float(db.z_incr_by(key, by, &member)?))
+            }
+            BuckQuery::ZPopMin(key, count) => Ok(scored_reply(
+                db.z_pop(&key, count.unwrap_or(1), false)?,
+                true,
+            )),
+            BuckQuery::ZPopMax(key, count) => Ok(scored_reply(
+                db.z_pop(&key, count.unwrap_or(1), true)?,
+                true,
+            )),
+            BuckQuery::ZCount(key, min, max) => {
+                Ok(BuckReply::Integer(db.z_count(&key, &min, &max)? as i64))
+            }
+            BuckQuery::ZCard(key) => Ok(BuckReply::Integer(db.z_card(&key)? as i64)),
+            BuckQuery::ZUnionStore(destination, keys, weights, aggregate) => {
+                let size = db.z_union_store(destination, &keys, weights.as_deref(), aggregate)?;
+
+                Ok(BuckReply::Integer(size as i64))
+            }
+            BuckQuery::ZInterStore(destination, keys, weights, aggregate) => {
+                let size = db.z_inter_store(destination, &keys, weights.as_deref(), aggregate)?;
+
+                Ok(BuckReply::Integer(size as i64))
+            }
+            BuckQuery::Unknown => Err(BuckParserError::UnknownQueryCommand.into()),
         }
     }
 }
+
+// without a count a pop replies with the element itself, with a count
+// always with an array
+fn popped_reply(values: Vec<BuckTypes>, count: Option<usize>) -> BuckReply {
+    match (count, values.first()) {
+        (_, None) => BuckReply::Nil,
+        (None, Some(value)) => BuckReply::from(value),
+        (Some(_), Some(_)) => BuckReply::Array(values.iter().map(BuckReply::from).collect()),
+    }
+}
+
+// keys and their values, flattened like `HGETALL` over RESP2
+fn entries_reply<'a>(entries: impl Iterator<Item = (&'a String, &'a BuckTypes)>) -> BuckReply {
+    BuckReply::Array(
+        entries
+            .flat_map(|(key, value)| [BuckReply::Bulk(key.clone()), BuckReply::from(value)])
+            .collect(),
+    )
+}
+
+fn rank_reply(rank: Option<usize>) -> BuckReply {
+    match rank {
+        Some(rank) => BuckReply::Integer(rank as i64),
+        None => BuckReply::Nil,
+    }
+}
+
+// members of a sorted set, each followed by its score if asked for
+fn scored_reply(members: Vec<(String, f64)>, with_scores: bool) -> BuckReply {
+    let mut items = Vec::new();
+
+    for (member, score) in members {
+        items.push(BuckReply::Bulk(member));
+
+        if with_scores {
+            items.push(BuckReply::Float(score));
+        }
+    }
+
+    BuckReply::Array(items)
+}
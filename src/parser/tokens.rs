@@ -7,6 +7,7 @@
 pub enum BuckTokens {
     Get,
     Insert,
+    Set,
     Remove,
     Update,
     Type,
@@ -16,37 +17,233 @@ pub enum BuckTokens {
     Clear,
     Shard,
     LPush,
+    RPush,
     LPop,
+    RPop,
+    LRange,
+    LIndex,
+    LSet,
+    LInsert,
+    LRem,
+    LTrim,
+    LMove,
+    BLPop,
+    BRPop,
+    BLMove,
     SAdd,
     SRem,
     SInter,
+    SUnion,
+    SDiff,
+    SInterCard,
+    SInterStore,
+    SUnionStore,
+    SDiffStore,
+    SIsMember,
+    SMIsMember,
+    SMembers,
+    SCard,
+    SRandMember,
+    SPop,
+    SMove,
     HSet,
+    HGet,
+    HMGet,
+    HGetAll,
+    HDel,
+    HExists,
+    HKeys,
+    HVals,
+    HLen,
+    HSetNx,
+    HIncrBy,
+    HIncrByFloat,
+    HStrLen,
+    HScan,
+    ZAdd,
+    ZRem,
+    ZScore,
+    ZRank,
+    ZRevRank,
+    ZRange,
+    ZRangeByScore,
+    ZRangeByLex,
+    ZIncrBy,
+    ZPopMin,
+    ZPopMax,
+    ZCount,
+    ZCard,
+    ZUnionStore,
+    ZInterStore,
+    Incr,
+    Decr,
+    IncrBy,
+    DecrBy,
+    IncrByFloat,
+    Append,
+    StrLen,
+    GetRange,
+    SetRange,
+    GetSet,
+    GetDel,
+    GetEx,
+    MGet,
+    MSet,
+    MSetNx,
+    Keys,
+    Scan,
+    Range,
+    Prefix,
+    Exists,
+    Touch,
+    Rename,
+    RenameNx,
+    Copy,
+    Unlink,
+    DbSize,
+    FlushDb,
+    RandomKey,
+    Select,
+    Move,
+    SwapDb,
+    FlushAll,
     Length,
+    Ping,
+    Save,
+    Load,
+    Expire,
+    PExpire,
+    Ttl,
+    PTtl,
+    Persist,
+    Config,
+    Begin,
+    Watch,
+    Unwatch,
     Unknown,
 }
 
 impl BuckTokens {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(token: &str) -> Self {
         let token = token.to_lowercase();
 
         match token.as_str() {
             "get" => BuckTokens::Get,
             "insert" => BuckTokens::Insert,
-            "remove" => BuckTokens::Remove,
+            "set" => BuckTokens::Set,
+            "remove" | "del" => BuckTokens::Remove,
             "update" => BuckTokens::Update,
             "type" => BuckTokens::Type,
-            "commit" => BuckTokens::Commit,
-            "rollback" => BuckTokens::Rollback,
-            "exit" => BuckTokens::Exit,
+            "begin" | "multi" => BuckTokens::Begin,
+            "commit" | "exec" => BuckTokens::Commit,
+            "rollback" | "discard" => BuckTokens::Rollback,
+            "watch" => BuckTokens::Watch,
+            "unwatch" => BuckTokens::Unwatch,
+            "exit" | "quit" => BuckTokens::Exit,
             "clear" => BuckTokens::Clear,
             "shard" => BuckTokens::Shard,
             "lpush" => BuckTokens::LPush,
+            "rpush" => BuckTokens::RPush,
             "lpop" => BuckTokens::LPop,
+            "rpop" => BuckTokens::RPop,
+            "lrange" => BuckTokens::LRange,
+            "lindex" => BuckTokens::LIndex,
+            "lset" => BuckTokens::LSet,
+            "linsert" => BuckTokens::LInsert,
+            "lrem" => BuckTokens::LRem,
+            "ltrim" => BuckTokens::LTrim,
+            "lmove" => BuckTokens::LMove,
+            "blpop" => BuckTokens::BLPop,
+            "brpop" => BuckTokens::BRPop,
+            "blmove" => BuckTokens::BLMove,
             "sadd" => BuckTokens::SAdd,
             "srem" => BuckTokens::SRem,
             "sinter" => BuckTokens::SInter,
+            "sunion" => BuckTokens::SUnion,
+            "sdiff" => BuckTokens::SDiff,
+            "sintercard" => BuckTokens::SInterCard,
+            "sinterstore" => BuckTokens::SInterStore,
+            "sunionstore" => BuckTokens::SUnionStore,
+            "sdiffstore" => BuckTokens::SDiffStore,
+            "sismember" => BuckTokens::SIsMember,
+            "smismember" => BuckTokens::SMIsMember,
+            "smembers" => BuckTokens::SMembers,
+            "scard" => BuckTokens::SCard,
+            "srandmember" => BuckTokens::SRandMember,
+            "spop" => BuckTokens::SPop,
+            "smove" => BuckTokens::SMove,
             "hset" => BuckTokens::HSet,
+            "hget" => BuckTokens::HGet,
+            "hmget" => BuckTokens::HMGet,
+            "hgetall" => BuckTokens::HGetAll,
+            "hdel" => BuckTokens::HDel,
+            "hexists" => BuckTokens::HExists,
+            "hkeys" => BuckTokens::HKeys,
+            "hvals" => BuckTokens::HVals,
+            "hlen" => BuckTokens::HLen,
+            "hsetnx" => BuckTokens::HSetNx,
+            "hincrby" => BuckTokens::HIncrBy,
+            "hincrbyfloat" => BuckTokens::HIncrByFloat,
+            "hstrlen" => BuckTokens::HStrLen,
+            "hscan" => BuckTokens::HScan,
+            "zadd" => BuckTokens::ZAdd,
+            "zrem" => BuckTokens::ZRem,
+            "zscore" => BuckTokens::ZScore,
+            "zrank" => BuckTokens::ZRank,
+            "zrevrank" => BuckTokens::ZRevRank,
+            "zrange" => BuckTokens::ZRange,
+            "zrangebyscore" => BuckTokens::ZRangeByScore,
+            "zrangebylex" => BuckTokens::ZRangeByLex,
+            "zincrby" => BuckTokens::ZIncrBy,
+            "zpopmin" => BuckTokens::ZPopMin,
+            "zpopmax" => BuckTokens::ZPopMax,
+            "zcount" => BuckTokens::ZCount,
+            "zcard" => BuckTokens::ZCard,
+            "zunionstore" => BuckTokens::ZUnionStore,
+            "zinterstore" => BuckTokens::ZInterStore,
+            "incr" => BuckTokens::Incr,
+            "decr" => BuckTokens::Decr,
+            "incrby" => BuckTokens::IncrBy,
+            "decrby" => BuckTokens::DecrBy,
+            "incrbyfloat" => BuckTokens::IncrByFloat,
+            "append" => BuckTokens::Append,
+            "strlen" => BuckTokens::StrLen,
+            "getrange" => BuckTokens::GetRange,
+            "setrange" => BuckTokens::SetRange,
+            "getset" => BuckTokens::GetSet,
+            "getdel" => BuckTokens::GetDel,
+            "getex" => BuckTokens::GetEx,
+            "mget" => BuckTokens::MGet,
+            "mset" => BuckTokens::MSet,
+            "msetnx" => BuckTokens::MSetNx,
+            "keys" => BuckTokens::Keys,
+            "scan" => BuckTokens::Scan,
+            "range" => BuckTokens::Range,
+            "prefix" => BuckTokens::Prefix,
+            "exists" => BuckTokens::Exists,
+            "touch" => BuckTokens::Touch,
+            "rename" => BuckTokens::Rename,
+            "renamenx" => BuckTokens::RenameNx,
+            "copy" => BuckTokens::Copy,
+            "unlink" => BuckTokens::Unlink,
+            "dbsize" => BuckTokens::DbSize,
+            "flushdb" => BuckTokens::FlushDb,
+            "randomkey" => BuckTokens::RandomKey,
+            "select" => BuckTokens::Select,
+            "move" => BuckTokens::Move,
+            "swapdb" => BuckTokens::SwapDb,
+            "flushall" => BuckTokens::FlushAll,
             "len" => BuckTokens::Length,
+            "ping" => BuckTokens::Ping,
+            "save" => BuckTokens::Save,
+            "load" => BuckTokens::Load,
+            "expire" => BuckTokens::Expire,
+            "pexpire" => BuckTokens::PExpire,
+            "ttl" => BuckTokens::Ttl,
+            "pttl" => BuckTokens::PTtl,
+            "persist" => BuckTokens::Persist,
+            "config" => BuckTokens::Config,
             _ => BuckTokens::Unknown,
         }
     }
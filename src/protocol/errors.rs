@@ -0,0 +1,50 @@
+use std::fmt;
+
+#[derive(Debug, PartialEq)]
+pub enum RespError {
+    InvalidPrefix(u8),
+    InvalidLength(String),
+    InvalidInteger(String),
+    InvalidUtf8,
+    NotAnArray,
+    UnbalancedQuotes,
+    LineTooLong,
+}
+
+impl RespError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            RespError::InvalidPrefix(_) => "INVALID_PREFIX",
+            RespError::InvalidLength(_) => "INVALID_LENGTH",
+            RespError::InvalidInteger(_) => "INVALID_INTEGER",
+            RespError::InvalidUtf8 => "INVALID_UTF8",
+            RespError::NotAnArray => "NOT_AN_ARRAY",
+            RespError::UnbalancedQuotes => "UNBALANCED_QUOTES",
+            RespError::LineTooLong => "LINE_TOO_LONG",
+        }
+    }
+}
+
+impl fmt::Display for RespError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RespError::InvalidPrefix(byte) => {
+                write!(f, "[Error] Protocol error: invalid type prefix '{}'", *byte as char)
+            }
+            RespError::InvalidLength(len) => {
+                write!(f, "[Error] Protocol error: invalid length {}", len)
+            }
+            RespError::InvalidInteger(int) => {
+                write!(f, "[Error] Protocol error: invalid integer {}", int)
+            }
+            RespError::InvalidUtf8 => write!(f, "[Error] Protocol error: invalid UTF-8"),
+            RespError::NotAnArray => {
+                write!(f, "[Error] Protocol error: expected an array of bulk strings")
+            }
+            RespError::UnbalancedQuotes => {
+                write!(f, "[Error] Protocol error: unbalanced quotes in request")
+            }
+            RespError::LineTooLong => write!(f, "[Error] Protocol error: too big request line"),
+        }
+    }
+}
@@ -0,0 +1,341 @@
+//! resp.rs
+//!
+//! This module implements the part of the RESP2 protocol that `buck-server`
+//! needs in order to talk to stock Redis clients such as `redis-cli`.
+//!
+//! Requests arrive either as arrays of bulk strings
+//! (`*2\r\n$3\r\nGET\r\n$3\r\nkey\r\n`) or as inline commands (`GET key\r\n`).
+//! Their arguments go to `parse_request` as they are, so a key or value may
+//! hold whitespace, quotes or nothing at all. The result of the query is then
+//! encoded as a RESP reply.
+//!
+//! Keys and values are text, so a request that is not valid UTF-8 is refused.
+
+use crate::errors::BuckError;
+use crate::log::BuckLog;
+use crate::reply::BuckReply;
+
+use super::errors::RespError;
+
+/// Largest bulk string a client may send, like the `proto-max-bulk-len` of Redis.
+pub const MAX_BULK_LEN: usize = 512 * 1024 * 1024;
+/// Largest number of elements in an array a client may send.
+pub const MAX_ARRAY_LEN: usize = 1024 * 1024;
+/// Longest inline command or length line a client may send, like the
+/// `PROTO_INLINE_MAX_SIZE` of Redis.
+pub const MAX_INLINE_LEN: usize = 64 * 1024;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RespValue {
+    SimpleString(String),
+    Error(String),
+    Integer(i64),
+    BulkString(Option<String>),
+    Array(Option<Vec<RespValue>>),
+}
+
+pub fn encode(value: &RespValue) -> Vec<u8> {
+    let mut encoded = Vec::new();
+
+    match value {
+        RespValue::SimpleString(s) => {
+            encoded.push(b'+');
+            encoded.extend(s.as_bytes());
+            encoded.extend(b"\r\n");
+        }
+        RespValue::Error(e) => {
+            encoded.push(b'-');
+            encoded.extend(e.as_bytes());
+            encoded.extend(b"\r\n");
+        }
+        RespValue::Integer(i) => {
+            encoded.extend(format!(":{}\r\n", i).as_bytes());
+        }
+        RespValue::BulkString(None) => encoded.extend(b"$-1\r\n"),
+        RespValue::BulkString(Some(s)) => {
+            encoded.extend(format!("${}\r\n", s.len()).as_bytes());
+            encoded.extend(s.as_bytes());
+            encoded.extend(b"\r\n");
+        }
+        RespValue::Array(None) => encoded.extend(b"*-1\r\n"),
+        RespValue::Array(Some(items)) => {
+            encoded.extend(format!("*{}\r\n", items.len()).as_bytes());
+            for item in items {
+                encoded.extend(encode(item));
+            }
+        }
+    }
+
+    encoded
+}
+
+/// Takes a single `\r\n` terminated line from a byte slice and shortens it.
+///
+/// Returns `None` and leaves the slice untouched if no full line is buffered yet.
+fn take_line<'a>(bytes: &mut &'a [u8]) -> Option<&'a [u8]> {
+    let end = bytes.windows(2).position(|w| w == b"\r\n")?;
+    let line = &bytes[..end];
+    *bytes = &bytes[end + 2..];
+
+    Some(line)
+}
+
+/// Like `take_line`, for a line of a request that may be at most
+/// `MAX_INLINE_LEN` bytes long. Only that much is searched for its end, so a
+/// client sending a long line a few bytes at a time costs no more than that.
+fn take_request_line<'a>(bytes: &mut &'a [u8]) -> Result<Option<&'a [u8]>, RespError> {
+    let window = &bytes[..bytes.len().min(MAX_INLINE_LEN + 2)];
+
+    match take_line(&mut &window[..]) {
+        Some(line) => {
+            *bytes = &bytes[line.len() + 2..];
+            Ok(Some(line))
+        }
+        None if window.len() == MAX_INLINE_LEN + 2 => Err(RespError::LineTooLong),
+        None => Ok(None),
+    }
+}
+
+fn parse_integer(line: &[u8]) -> Result<i64, RespError> {
+    let text = std::str::from_utf8(line).map_err(|_| RespError::InvalidUtf8)?;
+
+    text.parse::<i64>()
+        .map_err(|_| RespError::InvalidInteger(text.to_owned()))
+}
+
+// a length of at most `max`, or `-1` for a null value
+fn parse_length(line: &[u8], max: usize) -> Result<Option<usize>, RespError> {
+    match parse_integer(line)? {
+        -1 => Ok(None),
+        n if n >= 0 && n as u64 <= max as u64 => Ok(Some(n as usize)),
+        n => Err(RespError::InvalidLength(n.to_string())),
+    }
+}
+
+/// Decodes a single RESP value from the front of a byte slice and shortens the slice.
+///
+/// Returns `Ok(None)` without consuming anything if the value is not fully buffered yet.
+pub fn take_value(bytes: &mut &[u8]) -> Result<Option<RespValue>, RespError> {
+    let mut rest = *bytes;
+
+    let prefix = match rest.first() {
+        Some(prefix) => *prefix,
+        None => return Ok(None),
+    };
+    rest = &rest[1..];
+
+    let line = match take_line(&mut rest) {
+        Some(line) => line,
+        None => return Ok(None),
+    };
+
+    let value = match prefix {
+        b'+' => RespValue::SimpleString(to_string(line)?),
+        b'-' => RespValue::Error(to_string(line)?),
+        b':' => RespValue::Integer(parse_integer(line)?),
+        b'$' => match take_bulk(line, &mut rest)? {
+            Some(value) => value,
+            None => return Ok(None),
+        },
+        b'*' => match parse_length(line, MAX_ARRAY_LEN)? {
+            None => RespValue::Array(None),
+            Some(len) => {
+                // every element takes at least a few bytes, so a length the
+                // buffer can not hold yet is not worth reserving for
+                let mut items = Vec::with_capacity(len.min(rest.len()));
+
+                for _ in 0..len {
+                    match take_value(&mut rest)? {
+                        Some(item) => items.push(item),
+                        None => return Ok(None),
+                    }
+                }
+
+                RespValue::Array(Some(items))
+            }
+        },
+        _ => return Err(RespError::InvalidPrefix(prefix)),
+    };
+
+    *bytes = rest;
+    Ok(Some(value))
+}
+
+// the data of a bulk string whose length `line` has been taken already
+fn take_bulk(line: &[u8], bytes: &mut &[u8]) -> Result<Option<RespValue>, RespError> {
+    let len = match parse_length(line, MAX_BULK_LEN)? {
+        Some(len) => len,
+        None => return Ok(Some(RespValue::BulkString(None))),
+    };
+
+    let end = len
+        .checked_add(2)
+        .ok_or_else(|| RespError::InvalidLength(len.to_string()))?;
+
+    if bytes.len() < end {
+        return Ok(None);
+    }
+
+    if &bytes[len..end] != b"\r\n" {
+        return Err(RespError::InvalidLength(len.to_string()));
+    }
+
+    let s = to_string(&bytes[..len])?;
+    *bytes = &bytes[end..];
+
+    Ok(Some(RespValue::BulkString(Some(s))))
+}
+
+/// Decodes a client request from the front of a byte slice and shortens the slice.
+///
+/// A request is either an array of bulk strings or an inline command, which is
+/// split on whitespace outside of quotes. Returns `Ok(None)` if the request is not fully buffered yet.
+///
+/// Unlike `take_value`, this never recurses: an array nested in a request is
+/// refused right away, however deep it goes.
+pub fn take_request(bytes: &mut &[u8]) -> Result<Option<Vec<String>>, RespError> {
+    let mut rest = *bytes;
+
+    let args = match rest.first() {
+        None => return Ok(None),
+        Some(b'*') => {
+            rest = &rest[1..];
+
+            let line = match take_request_line(&mut rest)? {
+                Some(line) => line,
+                None => return Ok(None),
+            };
+            let len = parse_length(line, MAX_ARRAY_LEN)?.ok_or(RespError::NotAnArray)?;
+
+            // every element takes at least a few bytes, so a length the
+            // buffer can not hold yet is not worth reserving for
+            let mut args = Vec::with_capacity(len.min(rest.len()));
+
+            for _ in 0..len {
+                match rest.first() {
+                    None => return Ok(None),
+                    Some(b'$') => rest = &rest[1..],
+                    Some(_) => return Err(RespError::NotAnArray),
+                }
+
+                let line = match take_request_line(&mut rest)? {
+                    Some(line) => line,
+                    None => return Ok(None),
+                };
+
+                match take_bulk(line, &mut rest)? {
+                    Some(RespValue::BulkString(Some(arg))) => args.push(arg),
+                    Some(_) => return Err(RespError::NotAnArray),
+                    None => return Ok(None),
+                }
+            }
+
+            args
+        }
+        Some(_) => match take_request_line(&mut rest)? {
+            Some(line) => split_inline(&to_string(line)?)?,
+            None => return Ok(None),
+        },
+    };
+
+    *bytes = rest;
+    Ok(Some(args))
+}
+
+// split an inline command on whitespace, like `redis-cli` does. An argument
+// may be wrapped in double quotes, in which a backslash escapes the next
+// character, or in single quotes, and the quotes are not part of it.
+fn split_inline(line: &str) -> Result<Vec<String>, RespError> {
+    let mut args = Vec::new();
+    let mut chars = line.chars().peekable();
+
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+        let mut arg = String::new();
+
+        match chars.peek() {
+            None => return Ok(args),
+            Some(&quote) if quote == '"' || quote == '\'' => {
+                chars.next();
+
+                loop {
+                    match chars.next() {
+                        Some(c) if c == quote => break,
+                        Some('\\') if quote == '"' => match chars.next() {
+                            Some('n') => arg.push('\n'),
+                            Some('r') => arg.push('\r'),
+                            Some('t') => arg.push('\t'),
+                            Some(c) => arg.push(c),
+                            None => return Err(RespError::UnbalancedQuotes),
+                        },
+                        Some(c) => arg.push(c),
+                        None => return Err(RespError::UnbalancedQuotes),
+                    }
+                }
+
+                // a closing quote has to end the argument
+                if chars.peek().is_some_and(|c| !c.is_whitespace()) {
+                    return Err(RespError::UnbalancedQuotes);
+                }
+            }
+            Some(_) => {
+                while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                    arg.push(c);
+                }
+            }
+        }
+
+        args.push(arg);
+    }
+}
+
+fn to_string(bytes: &[u8]) -> Result<String, RespError> {
+    String::from_utf8(bytes.to_vec()).map_err(|_| RespError::InvalidUtf8)
+}
+
+/// Builds an error reply from one of the `[Error] ...` messages used across buck.
+///
+/// `code` is the first word of the reply, which clients read as the kind
+/// of error: the `code()` of the error, or `ERR` for a generic one.
+pub fn error_reply(code: &str, message: &str) -> RespValue {
+    let message = message.trim_start_matches("[Error] ");
+
+    RespValue::Error(format!("{} {}", code, message))
+}
+
+impl From<BuckReply> for RespValue {
+    fn from(reply: BuckReply) -> Self {
+        match reply {
+            BuckReply::Nil => RespValue::BulkString(None),
+            BuckReply::Status(status) => RespValue::SimpleString(status),
+            BuckReply::Integer(n) => RespValue::Integer(n),
+            BuckReply::Boolean(b) => RespValue::Integer(b as i64),
+            BuckReply::Float(n) => RespValue::BulkString(Some(n.to_string())),
+            BuckReply::Bulk(value) => RespValue::BulkString(Some(value)),
+            BuckReply::Error(code, message) => error_reply(code, &message),
+            BuckReply::Array(items) | BuckReply::Set(items) => {
+                RespValue::Array(Some(items.into_iter().map(RespValue::from).collect()))
+            }
+            // RESP2 has no map type, so fields and values are flattened
+            BuckReply::Map(pairs) => RespValue::Array(Some(
+                pairs
+                    .into_iter()
+                    .flat_map(|(field, value)| [RespValue::from(field), RespValue::from(value)])
+                    .collect(),
+            )),
+        }
+    }
+}
+
+impl From<BuckLog> for RespValue {
+    fn from(log: BuckLog) -> Self {
+        RespValue::from(BuckReply::from(log))
+    }
+}
+
+impl From<&BuckError> for RespValue {
+    fn from(error: &BuckError) -> Self {
+        error_reply(error.code(), &error.to_string())
+    }
+}
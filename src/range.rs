@@ -0,0 +1,166 @@
+//! range.rs
+//!
+//! Ordered iteration over a range of keys, for `RANGE` and `PREFIX`.
+//!
+//! Committed keys are read lazily from the `BTreeMap` behind
+//! `BuckDB::data`. Staged keys live in a `HashMap`, so the ones inside the
+//! range are sorted up front, which is cheap as long as a transaction stays
+//! small. The two are merged in key order, a staged value hiding the
+//! committed value of the same key.
+
+use std::collections::btree_map;
+use std::ops::Bound;
+use std::vec;
+
+use crate::types::types::BuckTypes;
+
+type Entry<'a> = (&'a String, &'a BuckTypes);
+
+/// The entries of a range of keys, in key order, from either end.
+pub struct MergedRange<'a> {
+    committed: Side<'a, btree_map::Range<'a, String, BuckTypes>>,
+    staged: Side<'a, vec::IntoIter<Entry<'a>>>,
+    hidden: Box<dyn Fn(&str) -> bool + 'a>,
+}
+
+impl<'a> MergedRange<'a> {
+    /// Merge `committed` with `staged`, which must be sorted by key, leaving
+    /// out the keys for which `hidden` is true.
+    pub fn new(
+        committed: btree_map::Range<'a, String, BuckTypes>,
+        staged: Vec<Entry<'a>>,
+        hidden: impl Fn(&str) -> bool + 'a,
+    ) -> Self {
+        MergedRange {
+            committed: Side::new(committed),
+            staged: Side::new(staged.into_iter()),
+            hidden: Box::new(hidden),
+        }
+    }
+
+    fn visible(&self, entry: Entry<'a>) -> Option<Entry<'a>> {
+        (!(self.hidden)(entry.0)).then_some(entry)
+    }
+}
+
+impl<'a> Iterator for MergedRange<'a> {
+    type Item = Entry<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let entry = match (self.committed.peek_front(), self.staged.peek_front()) {
+                (None, None) => return None,
+                (Some(committed), Some(staged)) if committed.0 < staged.0 => {
+                    self.committed.take_front()
+                }
+                (Some(committed), Some(staged)) if committed.0 == staged.0 => {
+                    self.committed.take_front();
+                    self.staged.take_front()
+                }
+                (Some(_), None) => self.committed.take_front(),
+                (_, Some(_)) => self.staged.take_front(),
+            };
+
+            if let Some(entry) = entry.and_then(|entry| self.visible(entry)) {
+                return Some(entry);
+            }
+        }
+    }
+}
+
+impl DoubleEndedIterator for MergedRange<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        loop {
+            let entry = match (self.committed.peek_back(), self.staged.peek_back()) {
+                (None, None) => return None,
+                (Some(committed), Some(staged)) if committed.0 > staged.0 => {
+                    self.committed.take_back()
+                }
+                (Some(committed), Some(staged)) if committed.0 == staged.0 => {
+                    self.committed.take_back();
+                    self.staged.take_back()
+                }
+                (Some(_), None) => self.committed.take_back(),
+                (_, Some(_)) => self.staged.take_back(),
+            };
+
+            if let Some(entry) = entry.and_then(|entry| self.visible(entry)) {
+                return Some(entry);
+            }
+        }
+    }
+}
+
+/// One of the two sorted inputs, with room to look ahead from either end.
+struct Side<'a, I> {
+    iter: I,
+    front: Option<Entry<'a>>,
+    back: Option<Entry<'a>>,
+}
+
+impl<'a, I: DoubleEndedIterator<Item = Entry<'a>>> Side<'a, I> {
+    fn new(iter: I) -> Self {
+        Side {
+            iter,
+            front: None,
+            back: None,
+        }
+    }
+
+    fn peek_front(&mut self) -> Option<Entry<'a>> {
+        if self.front.is_none() {
+            // once the inner iterator is used up, the back is all that is left
+            self.front = self.iter.next().or_else(|| self.back.take());
+        }
+
+        self.front
+    }
+
+    fn peek_back(&mut self) -> Option<Entry<'a>> {
+        if self.back.is_none() {
+            self.back = self.iter.next_back().or_else(|| self.front.take());
+        }
+
+        self.back
+    }
+
+    fn take_front(&mut self) -> Option<Entry<'a>> {
+        self.peek_front();
+        self.front.take()
+    }
+
+    fn take_back(&mut self) -> Option<Entry<'a>> {
+        self.peek_back();
+        self.back.take()
+    }
+}
+
+/// The smallest string that is greater than every string starting with
+/// `prefix`, `None` if there is no such string.
+pub fn prefix_end(prefix: &str) -> Option<String> {
+    let mut chars: Vec<char> = prefix.chars().collect();
+
+    while let Some(last) = chars.pop() {
+        // skip over the surrogate range, which is not made of chars
+        let next = (last as u32 + 1..=char::MAX as u32).find_map(char::from_u32);
+
+        if let Some(next) = next {
+            chars.push(next);
+            return Some(chars.into_iter().collect());
+        }
+    }
+
+    None
+}
+
+/// Whether `start..end` can be handed to `BTreeMap::range` without it
+/// panicking.
+pub fn is_valid_range(start: Bound<&str>, end: Bound<&str>) -> bool {
+    match (start, end) {
+        (Bound::Excluded(start), Bound::Excluded(end)) => start < end,
+        (Bound::Included(start) | Bound::Excluded(start), Bound::Included(end) | Bound::Excluded(end)) => {
+            start <= end
+        }
+        _ => true,
+    }
+}
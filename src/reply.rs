@@ -0,0 +1,187 @@
+//! reply.rs
+//!
+//! This module contains `BuckReply`, the structured result of a query.
+//!
+//! `BuckQuery::execute` returns a `BuckReply` instead of preformatted text,
+//! so that embedders get typed values and protocol front-ends can encode
+//! them directly. The `Display` implementation is the human readable
+//! rendering that the REPL prints, and `BuckLog` is displayed through it.
+
+use std::fmt;
+
+use crate::errors::BuckError;
+use crate::log::BuckLog;
+use crate::types::sets::{BuckSets, Setable};
+use crate::types::types::BuckTypes;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BuckReply {
+    /// A missing value.
+    Nil,
+    /// A short status message such as `OK` or `PONG`.
+    Status(String),
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    /// A string value.
+    Bulk(String),
+    /// An ordered sequence of replies.
+    Array(Vec<BuckReply>),
+    /// Field and value pairs.
+    Map(Vec<(BuckReply, BuckReply)>),
+    /// An unordered collection of distinct replies.
+    Set(Vec<BuckReply>),
+    /// The `code()` of an error and its message.
+    Error(&'static str, String),
+}
+
+impl BuckReply {
+    pub fn ok() -> Self {
+        BuckReply::Status("OK".to_owned())
+    }
+
+    fn render(&self, f: &mut fmt::Formatter<'_>, indent: usize) -> fmt::Result {
+        match self {
+            BuckReply::Nil => write!(f, "(nil)"),
+            BuckReply::Status(status) => write!(f, "{}", status),
+            BuckReply::Integer(n) => write!(f, "(integer) {}", n),
+            BuckReply::Float(n) => write!(f, "(float) {}", n),
+            BuckReply::Boolean(b) => write!(f, "(boolean) {}", b),
+            BuckReply::Bulk(s) => write!(f, "\"{}\"", s),
+            BuckReply::Error(_, e) => write!(f, "(error) {}", e),
+            BuckReply::Array(items) if items.is_empty() => write!(f, "(empty array)"),
+            BuckReply::Set(items) if items.is_empty() => write!(f, "(empty set)"),
+            BuckReply::Map(items) if items.is_empty() => write!(f, "(empty map)"),
+            BuckReply::Array(items) | BuckReply::Set(items) => {
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "\n{:indent$}", "", indent = indent)?;
+                    }
+
+                    let marker = format!("{}) ", i + 1);
+                    write!(f, "{}", marker)?;
+                    item.render(f, indent + marker.len())?;
+                }
+
+                Ok(())
+            }
+            BuckReply::Map(items) => {
+                for (i, (field, value)) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "\n{:indent$}", "", indent = indent)?;
+                    }
+
+                    let marker = format!("{}# ", i + 1);
+                    write!(f, "{}", marker)?;
+                    field.render(f, indent + marker.len())?;
+                    write!(f, " => ")?;
+                    value.render(f, indent + marker.len())?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+impl fmt::Display for BuckReply {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.render(f, 0)
+    }
+}
+
+impl From<&BuckTypes> for BuckReply {
+    fn from(value: &BuckTypes) -> Self {
+        match value {
+            BuckTypes::String(s) | BuckTypes::Unknown(s) => BuckReply::Bulk(s.clone()),
+            BuckTypes::Integer(n) => BuckReply::Integer(*n),
+            BuckTypes::Float(n) => BuckReply::Float(*n),
+            BuckTypes::Boolean(b) => BuckReply::Boolean(*b),
+            BuckTypes::List(list) => BuckReply::Array(list.data.iter().map(BuckReply::from).collect()),
+            BuckTypes::Hash(hash) => {
+                let mut fields: Vec<(&String, &BuckTypes)> = hash.data.iter().collect();
+                fields.sort_by(|a, b| a.0.cmp(b.0));
+
+                BuckReply::Map(
+                    fields
+                        .into_iter()
+                        .map(|(field, value)| (BuckReply::Bulk(field.clone()), BuckReply::from(value)))
+                        .collect(),
+                )
+            }
+            BuckTypes::Sets(set) => BuckReply::from(set),
+            BuckTypes::SortedSet(zset) => BuckReply::Map(
+                zset.iter()
+                    .map(|(member, score)| {
+                        (BuckReply::Bulk(member.clone()), BuckReply::Float(score))
+                    })
+                    .collect(),
+            ),
+        }
+    }
+}
+
+// members are sorted, so that the reply does not depend on the hash order
+impl From<&BuckSets> for BuckReply {
+    fn from(set: &BuckSets) -> Self {
+        let mut members: Vec<BuckReply> = set.data.iter().map(BuckReply::from).collect();
+        members.sort_by_key(|member| member.to_string());
+
+        BuckReply::Set(members)
+    }
+}
+
+impl From<&Setable> for BuckReply {
+    fn from(member: &Setable) -> Self {
+        match member {
+            Setable::String(s) => BuckReply::Bulk(s.clone()),
+            Setable::Integer(n) => BuckReply::Integer(*n),
+            Setable::Float(n) => BuckReply::Float(n.0),
+            Setable::Boolean(b) => BuckReply::Boolean(*b),
+            Setable::Empty => BuckReply::Nil,
+        }
+    }
+}
+
+impl From<&BuckLog> for BuckReply {
+    fn from(log: &BuckLog) -> Self {
+        match log {
+            BuckLog::InsertOk(_)
+            | BuckLog::RemoveOk(_)
+            | BuckLog::UpdateOk(_)
+            | BuckLog::ClearTransactionOk
+            | BuckLog::TransactionOk
+            | BuckLog::RollbackOk
+            | BuckLog::BackupOk
+            | BuckLog::LoadOk
+            | BuckLog::ShardingEnableOk
+            | BuckLog::ClearOk
+            | BuckLog::ConfigSetOk
+            | BuckLog::MultiOk
+            | BuckLog::WatchOk(_)
+            | BuckLog::UnwatchOk => BuckReply::ok(),
+            BuckLog::PongOk => BuckReply::Status("PONG".to_owned()),
+            BuckLog::QueuedOk => BuckReply::Status("QUEUED".to_owned()),
+            BuckLog::GetOk(value) | BuckLog::ListPopOk(value) => BuckReply::Bulk(value.clone()),
+            BuckLog::HSetOk(n) | BuckLog::LengthOk(n) => BuckReply::Integer(*n as i64),
+            BuckLog::IntegerOk(n) => BuckReply::Integer(*n),
+            BuckLog::TypeOk(_, typ) => BuckReply::Status(typ.clone()),
+            BuckLog::ConfigOk(name, value) => BuckReply::Map(vec![(
+                BuckReply::Bulk(name.clone()),
+                BuckReply::Bulk(value.clone()),
+            )]),
+        }
+    }
+}
+
+impl From<BuckLog> for BuckReply {
+    fn from(log: BuckLog) -> Self {
+        BuckReply::from(&log)
+    }
+}
+
+impl From<&BuckError> for BuckReply {
+    fn from(error: &BuckError) -> Self {
+        BuckReply::Error(error.code(), error.to_string())
+    }
+}
@@ -0,0 +1,196 @@
+//! scan.rs
+//!
+//! Cursor based iteration for `SCAN` and `HSCAN`, and glob patterns for
+//! `MATCH` and `KEYS`.
+//!
+//! Entries are visited in the order of the hash of their name, and the
+//! cursor handed back to the client is the hash of the next entry to
+//! visit. Since that order does not depend on what else is in the
+//! collection, an entry that is present for the whole iteration is returned
+//! exactly once, however the collection changes in between. Entries added
+//! or removed during the iteration may or may not be returned.
+//!
+//! A cursor of `0` starts an iteration, and is returned once it is over.
+
+use crate::sharding::hash::calculate_hash;
+
+/// Where `name` comes in the order entries are visited in.
+pub fn position(name: &str) -> u64 {
+    calculate_hash(name)
+}
+
+/// Visit up to `count` entries, starting at `cursor`.
+///
+/// Entries whose name does not match `pattern` count as visited, but are
+/// left out of the result, so a page may come back empty before the
+/// iteration is over.
+///
+/// `entries` may come in any order. Only the entries of the page are
+/// sorted, so a page costs a pass over the collection; a collection that
+/// is iterated a lot keeps its names in order instead, see `scan_ordered`.
+///
+/// ## Returns
+///
+/// The cursor to continue from, and the matching entries of this page.
+pub fn scan<'a, T>(
+    entries: impl IntoIterator<Item = (&'a String, T)>,
+    cursor: u64,
+    pattern: Option<&str>,
+    count: usize,
+) -> (u64, Vec<(&'a String, T)>) {
+    let count = count.max(1);
+    let order = |a: &(u64, &String, T), b: &(u64, &String, T)| (a.0, a.1).cmp(&(b.0, b.1));
+
+    let mut pending: Vec<(u64, &'a String, T)> = entries
+        .into_iter()
+        .map(|(name, value)| (position(name), name, value))
+        .filter(|(hash, _, _)| *hash >= cursor)
+        .collect();
+
+    if pending.len() > count {
+        // the first `count` entries, and the ones sharing a hash with the
+        // last of them, make the page; the first entry after them is where
+        // the next page starts
+        pending.select_nth_unstable_by(count - 1, order);
+        let last = pending[..count].iter().map(|(hash, _, _)| *hash).max().unwrap_or(0);
+
+        let mut rest = pending.split_off(count);
+        let ties = rest.iter().filter(|(hash, _, _)| *hash == last).count();
+        rest.sort_unstable_by_key(|(hash, _, _)| *hash != last);
+
+        let next = rest.split_off(ties).into_iter().min_by(order);
+        pending.extend(rest);
+        pending.extend(next);
+    }
+    pending.sort_by(order);
+
+    scan_ordered(pending, pattern, count)
+}
+
+/// Like `scan`, for `entries` that are already in the order they are
+/// visited in and start at the cursor.
+///
+/// Only as many entries as the page needs are taken from `entries`.
+pub fn scan_ordered<'a, T>(
+    entries: impl IntoIterator<Item = (u64, &'a String, T)>,
+    pattern: Option<&str>,
+    count: usize,
+) -> (u64, Vec<(&'a String, T)>) {
+    let mut page = Vec::new();
+    let mut next = 0;
+
+    for (hash, name, value) in entries {
+        // entries sharing a hash can not be told apart by a cursor, so they
+        // always go out in the same page
+        if page.len() >= count.max(1) && Some(hash) != page.last().map(|(h, _, _)| *h) {
+            next = hash;
+            break;
+        }
+
+        page.push((hash, name, value));
+    }
+
+    let page = page
+        .into_iter()
+        .filter(|(_, name, _)| pattern.is_none_or(|pattern| glob_match(pattern, name)))
+        .map(|(_, name, value)| (name, value))
+        .collect();
+
+    (next, page)
+}
+
+/// Match `text` against a glob-style `pattern`.
+///
+/// `*` matches any run of characters, `?` any single one, `[abc]`, `[a-z]`
+/// and `[^a]` a character class, and `\` escapes the next character.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    // where the last `*` was, and how much of the text it has taken so far
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if pattern.get(p) == Some(&'*') {
+            star = Some((p, t));
+            p += 1;
+            continue;
+        }
+
+        if let Some(len) = match_one(&pattern[p.min(pattern.len())..], text[t]) {
+            p += len;
+            t += 1;
+            continue;
+        }
+
+        match star {
+            // let the last `*` take one more character
+            Some((star_p, star_t)) => {
+                p = star_p + 1;
+                t = star_t + 1;
+                star = Some((star_p, star_t + 1));
+            }
+            None => return false,
+        }
+    }
+
+    pattern[p.min(pattern.len())..].iter().all(|c| *c == '*')
+}
+
+/// Match a single character against the start of `pattern`.
+///
+/// ## Returns
+///
+/// How much of the pattern was used, `None` if `c` does not match.
+fn match_one(pattern: &[char], c: char) -> Option<usize> {
+    match pattern {
+        [] | ['*', ..] => None,
+        ['?', ..] => Some(1),
+        ['\\', escaped, ..] => (*escaped == c).then_some(2),
+        ['[', class @ ..] => match class.iter().position(|item| *item == ']') {
+            // a `]` right after `[` or `[^` is part of the class
+            Some(end) if end > 0 && !(end == 1 && class[0] == '^') => {
+                let (negated, items) = match class[0] {
+                    '^' => (true, &class[1..end]),
+                    _ => (false, &class[..end]),
+                };
+
+                (in_class(items, c) != negated).then_some(end + 2)
+            }
+            // an unterminated class is a literal `[`
+            _ => (c == '[').then_some(1),
+        },
+        [literal, ..] => (*literal == c).then_some(1),
+    }
+}
+
+fn in_class(items: &[char], c: char) -> bool {
+    let mut i = 0;
+
+    while i < items.len() {
+        match items[i..] {
+            ['\\', escaped, ..] => {
+                if escaped == c {
+                    return true;
+                }
+                i += 2;
+            }
+            [low, '-', high, ..] => {
+                if (low.min(high)..=low.max(high)).contains(&c) {
+                    return true;
+                }
+                i += 3;
+            }
+            [item, ..] => {
+                if item == c {
+                    return true;
+                }
+                i += 1;
+            }
+            [] => break,
+        }
+    }
+
+    false
+}
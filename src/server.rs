@@ -0,0 +1,236 @@
+//! server.rs
+//!
+//! TCP front-end for BuckDB that speaks RESP2, so that `redis-cli` and other
+//! stock Redis clients can talk to buck.
+//!
+//! Every connection is served by its own thread, and all of them share the
+//! same databases behind a mutex. Each client starts out on database `0`
+//! and may switch with `SELECT`.
+//!
+//! A client in `BLPOP`, `BRPOP` or `BLMOVE` that finds nothing to pop waits
+//! on the database's condition variable, which releases the mutex until
+//! another client pushes an element or the timeout expires.
+
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::blocking::BlockedPop;
+use crate::engine::BuckDB;
+use crate::errors::BuckError;
+use crate::log::BuckLog;
+use crate::parser::parse::parse_request;
+use crate::parser::query::BuckQuery;
+use crate::protocol::resp::{encode, error_reply, take_request, RespValue};
+use crate::reply::BuckReply;
+use crate::session::BuckSession;
+
+pub const DEFAULT_ADDR: &str = "127.0.0.1:6379";
+
+/// How often the background sweep looks for expired keys.
+const EXPIRE_SWEEP_INTERVAL: Duration = Duration::from_millis(100);
+/// Upper bound on keys deleted per sweep, so the lock is never held for long.
+const EXPIRE_SWEEP_KEYS: usize = 200;
+/// How often a blocked client checks whether its connection is still open.
+const BLOCKED_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Accept connections forever, serving each one on a new thread.
+pub fn serve(listener: TcpListener, db: Arc<Mutex<Vec<BuckDB>>>) -> io::Result<()> {
+    spawn_expire_sweeper(Arc::clone(&db));
+
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let db = Arc::clone(&db);
+
+        thread::spawn(move || {
+            if let Err(e) = handle_client(stream, db) {
+                eprintln!("[ERROR] Connection closed: {}", e);
+            }
+        });
+    }
+
+    Ok(())
+}
+
+/// Periodically delete expired keys that no client touches anymore.
+fn spawn_expire_sweeper(db: Arc<Mutex<Vec<BuckDB>>>) {
+    thread::spawn(move || loop {
+        thread::sleep(EXPIRE_SWEEP_INTERVAL);
+
+        let mut databases = db.lock().unwrap_or_else(PoisonError::into_inner);
+        for db in databases.iter_mut() {
+            db.active_expire_cycle(EXPIRE_SWEEP_KEYS);
+        }
+    });
+}
+
+/// Read requests from a single client until it disconnects or sends `QUIT`.
+pub fn handle_client(stream: TcpStream, db: Arc<Mutex<Vec<BuckDB>>>) -> io::Result<()> {
+    let mut session = BuckSession::new();
+    let result = serve_session(stream, &db, &mut session);
+
+    // release the client's watches even if the connection broke
+    let mut databases = db.lock().unwrap_or_else(PoisonError::into_inner);
+    session.close_in(&mut databases);
+
+    result
+}
+
+fn serve_session(
+    mut stream: TcpStream,
+    db: &Mutex<Vec<BuckDB>>,
+    session: &mut BuckSession,
+) -> io::Result<()> {
+    let mut buffer: Vec<u8> = Vec::new();
+    let mut chunk = [0u8; 4096];
+
+    loop {
+        let n = stream.read(&mut chunk)?;
+        if n == 0 {
+            return Ok(());
+        }
+        buffer.extend_from_slice(&chunk[..n]);
+
+        loop {
+            let mut bytes = buffer.as_slice();
+
+            let args = match take_request(&mut bytes) {
+                Ok(Some(args)) => args,
+                Ok(None) => break,
+                Err(e) => {
+                    // the stream can not be resynchronized after a protocol error
+                    stream.write_all(&encode(&error_reply(e.code(), &e.to_string())))?;
+                    return Ok(());
+                }
+            };
+
+            let consumed = buffer.len() - bytes.len();
+            buffer.drain(..consumed);
+
+            if args.is_empty() {
+                continue;
+            }
+
+            match execute_request(&args, db, session, &stream) {
+                Some(reply) => stream.write_all(&encode(&reply))?,
+                None => {
+                    stream.write_all(&encode(&RespValue::SimpleString("OK".to_owned())))?;
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+/// Execute a single request against the shared databases.
+///
+/// Returns `None` if the client asked to close the connection.
+fn execute_request(
+    args: &[String],
+    db: &Mutex<Vec<BuckDB>>,
+    session: &mut BuckSession,
+    stream: &TcpStream,
+) -> Option<RespValue> {
+    let input = args.join(" ");
+
+    let query = match parse_request(args) {
+        Ok(query) => query,
+        Err(e) => return Some(RespValue::from(&BuckError::from(e))),
+    };
+
+    match query {
+        // `exit` would stop the whole server and `clear` only makes sense in a terminal
+        BuckQuery::Exit => None,
+        BuckQuery::Clear => Some(RespValue::from(BuckLog::ClearOk)),
+        query => {
+            let mut databases = db.lock().unwrap_or_else(PoisonError::into_inner);
+
+            // inside a transaction the command is only queued
+            let blocked = match session.in_transaction() {
+                true => None,
+                false => query.blocked_pop(),
+            };
+
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                session.execute_in(query, &input, &mut databases)
+            }));
+
+            match (result, blocked) {
+                // nothing to pop yet, wait for it
+                (Ok(Ok(BuckReply::Nil)), Some((pop, timeout))) => {
+                    Some(wait_for_pop(databases, session, pop, timeout, stream))
+                }
+                (Ok(Ok(reply)), _) => Some(RespValue::from(reply)),
+                (Ok(Err(e)), _) => Some(RespValue::from(&e)),
+                (Err(_), _) => Some(error_reply("ERR", "Internal error while executing query")),
+            }
+        }
+    }
+}
+
+/// Block until another client pushes an element for `pop` to the database
+/// `session` selected, or `timeout` expires.
+///
+/// Clients blocked on the same key are served in the order they blocked.
+/// A client that disconnects stops waiting, so that it is never handed an
+/// element nobody would receive.
+fn wait_for_pop(
+    mut databases: MutexGuard<Vec<BuckDB>>,
+    session: &mut BuckSession,
+    pop: BlockedPop,
+    timeout: Option<Duration>,
+    stream: &TcpStream,
+) -> RespValue {
+    let index = session.selected();
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    let wakeup = databases[index].blocked.wakeup();
+    let id = databases[index].block(pop.clone());
+    session.serve_blocked_in(&mut databases);
+
+    loop {
+        if let Some(served) = databases[index].blocked.take_served(id) {
+            return match served {
+                Ok((key, value)) => RespValue::from(pop.reply(key, &value)),
+                Err(e) => RespValue::from(&BuckError::from(e)),
+            };
+        }
+
+        if is_disconnected(stream) {
+            break;
+        }
+
+        let now = Instant::now();
+        let wait = match deadline {
+            Some(deadline) if deadline <= now => break,
+            Some(deadline) => (deadline - now).min(BLOCKED_POLL_INTERVAL),
+            None => BLOCKED_POLL_INTERVAL,
+        };
+
+        databases = match wakeup.wait_timeout(databases, wait) {
+            Ok((databases, _)) => databases,
+            Err(poisoned) => poisoned.into_inner().0,
+        };
+    }
+
+    databases[index].blocked.unblock(id);
+
+    RespValue::from(BuckReply::Nil)
+}
+
+/// Whether the peer closed the connection, without consuming any input.
+fn is_disconnected(stream: &TcpStream) -> bool {
+    if stream.set_nonblocking(true).is_err() {
+        return true;
+    }
+
+    let closed = match stream.peek(&mut [0u8; 1]) {
+        Ok(0) => true,
+        Ok(_) => false,
+        Err(e) => e.kind() != io::ErrorKind::WouldBlock,
+    };
+
+    stream.set_nonblocking(false).is_err() || closed
+}
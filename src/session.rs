@@ -0,0 +1,328 @@
+//! session.rs
+//!
+//! Per-client state for multi-command transactions.
+//!
+//! `MULTI` (or `BEGIN`) opens a transaction, after which queries are queued
+//! instead of executed. `EXEC` (or `COMMIT`) runs the whole queue and commits
+//! it in one go, and `DISCARD` (or `ROLLBACK`) drops it.
+//!
+//! Outside of a transaction, every query is committed as soon as it ran, or
+//! rolled back if it failed. No write of a client is ever left staged where
+//! other clients could read it, so `EXEC` and `DISCARD` without `MULTI` are
+//! errors.
+//!
+//! `WATCH key...` adds optimistic locking on top: if any watched key changes
+//! before `EXEC`, the transaction is aborted without running anything.
+//!
+//! Blocking commands such as `BLPOP` only try once here. Waiting for an
+//! element is up to the front-end, see `BuckDB::block`.
+//!
+//! A session also remembers which database its client selected, when it
+//! runs against a set of databases, see `databases`.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::slice;
+
+use crate::databases;
+use crate::engine::{BuckDB, TransactionStatus};
+use crate::errors::{BuckEngineError, BuckError};
+use crate::log::BuckLog;
+use crate::parser::query::BuckQuery;
+use crate::reply::BuckReply;
+
+#[derive(Debug, Default)]
+pub struct BuckSession {
+    /// Queued queries and their input, `Some` while a transaction is open.
+    queue: Option<Vec<(String, BuckQuery)>>,
+    /// Watched keys, with their database and their version at the time
+    /// they were watched.
+    watched: Vec<(usize, String, u64)>,
+    /// Index of the selected database.
+    selected: usize,
+}
+
+impl BuckSession {
+    pub fn new() -> Self {
+        BuckSession {
+            queue: None,
+            watched: Vec::new(),
+            selected: 0,
+        }
+    }
+
+    pub fn in_transaction(&self) -> bool {
+        self.queue.is_some()
+    }
+
+    /// Index of the database the client selected, `0` unless it sent `SELECT`.
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    /// Execute `query` for this client.
+    ///
+    /// Afterwards, clients blocked on a list that got elements are served,
+    /// before any other command can take them.
+    pub fn execute(
+        &mut self,
+        query: BuckQuery,
+        input: &str,
+        db: &mut BuckDB,
+    ) -> Result<BuckReply, BuckError> {
+        self.execute_in(query, input, slice::from_mut(db))
+    }
+
+    /// Like `execute`, against the selected one of `databases`.
+    pub fn execute_in(
+        &mut self,
+        query: BuckQuery,
+        input: &str,
+        databases: &mut [BuckDB],
+    ) -> Result<BuckReply, BuckError> {
+        let reply = self.dispatch(query, input, databases);
+        self.serve_blocked_in(databases);
+
+        reply
+    }
+
+    /// Serve the clients blocked in `databases`, committing the elements
+    /// they are handed right away.
+    pub fn serve_blocked_in(&mut self, databases: &mut [BuckDB]) {
+        // serving a client can not fail, it is handed the error instead
+        let _ = self.commit_now(databases, |_, databases| {
+            for db in databases.iter_mut() {
+                db.serve_blocked();
+            }
+
+            Ok(())
+        });
+    }
+
+    /// Call `run` and commit every key it changed, or roll them back if it
+    /// fails, in every database it changed.
+    ///
+    /// Only the keys `run` changed are committed or rolled back, so writes
+    /// that were staged through `BuckDB` directly stay staged.
+    fn commit_now<T>(
+        &mut self,
+        databases: &mut [BuckDB],
+        run: impl FnOnce(&mut Self, &mut [BuckDB]) -> Result<T, BuckError>,
+    ) -> Result<T, BuckError> {
+        for db in databases.iter_mut() {
+            db.start_recording();
+        }
+
+        let result = run(self, databases);
+
+        let changed: BTreeMap<usize, BTreeSet<String>> = databases
+            .iter_mut()
+            .map(BuckDB::stop_recording)
+            .enumerate()
+            .filter(|(_, keys)| !keys.is_empty())
+            .collect();
+
+        if result.is_err() {
+            for (index, keys) in &changed {
+                databases[*index].abort_keys(keys)?;
+            }
+            return result;
+        }
+
+        for (index, keys) in &changed {
+            let db = &mut databases[*index];
+
+            // keys that expired in the meantime are gone already
+            if db.status != TransactionStatus::Uncommitted {
+                continue;
+            }
+
+            if let Err(e) = db.commit_keys(keys) {
+                db.abort_keys(keys)?;
+                return Err(e.into());
+            }
+        }
+
+        result
+    }
+
+    fn dispatch(
+        &mut self,
+        query: BuckQuery,
+        input: &str,
+        databases: &mut [BuckDB],
+    ) -> Result<BuckReply, BuckError> {
+        match query {
+            BuckQuery::Begin => Ok(self.multi()?.into()),
+            BuckQuery::Commit => self.exec(databases),
+            BuckQuery::Rollback => Ok(self.discard(databases)?.into()),
+            BuckQuery::Watch(keys) => Ok(self.watch(keys, databases)?.into()),
+            BuckQuery::Unwatch => {
+                self.unwatch_all(databases);
+
+                Ok(BuckReply::from(BuckLog::UnwatchOk))
+            }
+            // these act on the terminal or the connection, not on the data
+            BuckQuery::Exit | BuckQuery::Clear => {
+                query.execute(input, databases::database(databases, self.selected)?)
+            }
+            query => match self.queue.as_mut() {
+                Some(queue) => {
+                    queue.push((input.to_owned(), query));
+
+                    Ok(BuckReply::from(BuckLog::QueuedOk))
+                }
+                None => self.commit_now(databases, |session, databases| session.run(query, input, databases)),
+            },
+        }
+    }
+
+    /// Run a query right away, the ones that work across databases included.
+    fn run(
+        &mut self,
+        query: BuckQuery,
+        input: &str,
+        databases: &mut [BuckDB],
+    ) -> Result<BuckReply, BuckError> {
+        match query {
+            BuckQuery::Select(index) => {
+                databases::database(databases, index)?;
+                self.selected = index;
+
+                Ok(BuckReply::ok())
+            }
+            BuckQuery::Move(key, to) => {
+                // the key has to follow the key policy of both databases
+                for index in [self.selected, to] {
+                    databases::database(databases, index)?.key_policy.check(&key)?;
+                }
+
+                let moved = databases::move_key(databases, &key, self.selected, to)?;
+
+                Ok(BuckReply::Integer(moved as i64))
+            }
+            BuckQuery::SwapDb(a, b) => {
+                databases::swap(databases, a, b)?;
+
+                Ok(BuckReply::ok())
+            }
+            BuckQuery::FlushAll => {
+                databases::flush_all(databases)?;
+
+                Ok(BuckReply::ok())
+            }
+            BuckQuery::ConfigSet(name, value) => Ok(databases::config_set(databases, &name, &value)?.into()),
+            query => query.execute(input, databases::database(databases, self.selected)?),
+        }
+    }
+
+    /// Drop the open transaction and every watch. Call it when the client goes away.
+    pub fn close(&mut self, db: &mut BuckDB) {
+        self.close_in(slice::from_mut(db));
+    }
+
+    /// Like `close`, for a client of `databases`.
+    pub fn close_in(&mut self, databases: &mut [BuckDB]) {
+        self.queue = None;
+        self.unwatch_all(databases);
+    }
+
+    fn multi(&mut self) -> Result<BuckLog, BuckEngineError> {
+        if self.in_transaction() {
+            return Err(BuckEngineError::NestedTransaction);
+        }
+
+        self.queue = Some(Vec::new());
+
+        Ok(BuckLog::MultiOk)
+    }
+
+    /// Run the queued queries and commit them.
+    ///
+    /// Nothing runs if a watched key has changed. If one of the queries
+    /// fails, everything the queue changed is rolled back, so either the
+    /// whole queue is applied or none of it is.
+    ///
+    /// Every database the queue wrote to is committed, and only the keys it
+    /// changed.
+    ///
+    /// ## Returns
+    ///
+    /// An array with the reply of every queued query, in order.
+    fn exec(&mut self, databases: &mut [BuckDB]) -> Result<BuckReply, BuckError> {
+        let queue = match self.queue.take() {
+            Some(queue) => queue,
+            None => return Err(BuckEngineError::NoTransaction("EXEC".to_owned()).into()),
+        };
+
+        // a watched key whose deadline passed counts as changed
+        for (index, key, _) in &self.watched {
+            if let Some(db) = databases.get_mut(*index) {
+                db.expire_if_needed(key);
+            }
+        }
+
+        let conflict = self
+            .watched
+            .iter()
+            .find(|(index, key, version)| {
+                databases.get(*index).is_none_or(|db| db.key_version(key) != *version)
+            })
+            .map(|(_, key, _)| key.clone());
+
+        self.unwatch_all(databases);
+
+        if let Some(key) = conflict {
+            return Err(BuckEngineError::WatchConflict(key).into());
+        }
+
+        let mut replies = Vec::with_capacity(queue.len());
+
+        self.commit_now(databases, |session, databases| {
+            for (input, query) in queue {
+                replies.push(session.run(query, &input, databases)?);
+            }
+
+            Ok(())
+        })?;
+
+        Ok(BuckReply::Array(replies))
+    }
+
+    /// Drop the queued queries.
+    fn discard(&mut self, databases: &mut [BuckDB]) -> Result<BuckLog, BuckEngineError> {
+        if self.queue.take().is_none() {
+            return Err(BuckEngineError::NoTransaction("DISCARD".to_owned()));
+        }
+
+        self.unwatch_all(databases);
+
+        Ok(BuckLog::RollbackOk)
+    }
+
+    fn watch(&mut self, keys: Vec<String>, databases: &mut [BuckDB]) -> Result<BuckLog, BuckEngineError> {
+        if self.in_transaction() {
+            return Err(BuckEngineError::WatchInsideTransaction);
+        }
+
+        let index = self.selected;
+        let db = databases::database(databases, index)?;
+
+        for key in &keys {
+            // a key that is already watched keeps its original version
+            if !self.watched.iter().any(|(watched_index, watched, _)| *watched_index == index && watched == key) {
+                let version = db.watch(key);
+                self.watched.push((index, key.clone(), version));
+            }
+        }
+
+        Ok(BuckLog::WatchOk(keys))
+    }
+
+    fn unwatch_all(&mut self, databases: &mut [BuckDB]) {
+        for (index, key, _) in self.watched.drain(..) {
+            if let Some(db) = databases.get_mut(index) {
+                db.unwatch(&key);
+            }
+        }
+    }
+}
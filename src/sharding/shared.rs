@@ -0,0 +1,223 @@
+//! shared.rs
+//!
+//! A `BuckDB` handle that can be shared between threads.
+//!
+//! The key space is split over a fixed number of partitions, each one a
+//! `BuckDB` behind its own `RwLock`. A key always lives in the partition
+//! picked by `calculate_hash`, so a write only locks that partition, and
+//! reads of any partition run concurrently with each other.
+//!
+//! Every partition keeps its own staged writes. `commit` and `rollback` walk
+//! all of them, but they are not atomic across partitions: a reader may see
+//! one partition committed before the next.
+
+use std::collections::HashMap;
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::clock::Clock;
+use crate::engine::{BuckDB, TransactionStatus};
+use crate::errors::BuckEngineError;
+use crate::log::BuckLog;
+use crate::sharding::hash::calculate_hash;
+use crate::types::types::BuckTypes;
+
+#[derive(Debug)]
+pub struct SharedBuckDB {
+    partitions: Vec<RwLock<BuckDB>>,
+}
+
+impl SharedBuckDB {
+    /// Create a database split over `num_partitions` partitions.
+    ///
+    /// ## Panics
+    ///
+    /// If `num_partitions` is zero.
+    pub fn new(num_partitions: usize) -> Self {
+        assert!(num_partitions > 0, "SharedBuckDB needs at least one partition");
+
+        SharedBuckDB {
+            partitions: (0..num_partitions).map(|_| RwLock::new(BuckDB::new())).collect(),
+        }
+    }
+
+    /// Like `new`, with every partition reading time from `clock`.
+    pub fn with_clock(num_partitions: usize, clock: Arc<dyn Clock>) -> Self {
+        let db = SharedBuckDB::new(num_partitions);
+
+        for partition in &db.partitions {
+            partition
+                .write()
+                .unwrap_or_else(PoisonError::into_inner)
+                .clock = Arc::clone(&clock);
+        }
+
+        db
+    }
+
+    pub fn num_partitions(&self) -> usize {
+        self.partitions.len()
+    }
+
+    /// Index of the partition that owns `key`.
+    pub fn partition_of(&self, key: &str) -> usize {
+        calculate_hash(key) as usize % self.partitions.len()
+    }
+
+    /// Run `f` with shared access to the partition that owns `key`.
+    pub fn read<F, R>(&self, key: &str, f: F) -> R
+    where
+        F: FnOnce(&BuckDB) -> R,
+    {
+        f(&self.read_partition(self.partition_of(key)))
+    }
+
+    /// Run `f` with exclusive access to the partition that owns `key`.
+    ///
+    /// Only that partition is locked, so writes to keys owned by other
+    /// partitions go on in parallel.
+    pub fn write<F, R>(&self, key: &str, f: F) -> R
+    where
+        F: FnOnce(&mut BuckDB) -> R,
+    {
+        f(&mut self.write_partition(self.partition_of(key)))
+    }
+
+    ///////// Query /////////
+
+    pub fn insert(&self, key: String, value: BuckTypes) -> Result<BuckLog, BuckEngineError> {
+        self.write(&key.clone(), |db| db.insert(key, value))
+    }
+
+    /// Get a copy of the value stored at `key`.
+    pub fn get(&self, key: &str) -> Result<BuckTypes, BuckEngineError> {
+        self.read(key, |db| db.get(key).cloned())
+    }
+
+    pub fn remove(&self, key: &str) -> Result<BuckLog, BuckEngineError> {
+        self.write(key, |db| db.remove(key))
+    }
+
+    pub fn update(&self, key: &str, value: BuckTypes) -> Result<BuckLog, BuckEngineError> {
+        self.write(key, |db| db.update(key, value))
+    }
+
+    pub fn type_of(&self, key: &str) -> Result<String, BuckEngineError> {
+        self.read(key, |db| db.type_of(key))
+    }
+
+    pub fn l_push(&self, key: String, value: BuckTypes) -> Result<BuckLog, BuckEngineError> {
+        self.write(&key.clone(), |db| db.l_push(key, value))
+    }
+
+    pub fn r_push(&self, key: String, value: BuckTypes) -> Result<BuckLog, BuckEngineError> {
+        self.write(&key.clone(), |db| db.r_push(key, value))
+    }
+
+    pub fn l_pop(&self, key: &str, count: usize) -> Result<Vec<BuckTypes>, BuckEngineError> {
+        self.write(key, |db| db.l_pop(key, count))
+    }
+
+    pub fn r_pop(&self, key: &str, count: usize) -> Result<Vec<BuckTypes>, BuckEngineError> {
+        self.write(key, |db| db.r_pop(key, count))
+    }
+
+    pub fn l_range(&self, key: &str, start: i64, stop: i64) -> Result<Vec<BuckTypes>, BuckEngineError> {
+        self.read(key, |db| db.l_range(key, start, stop))
+    }
+
+    pub fn s_add(&self, key: String, value: BuckTypes) -> Result<BuckLog, BuckEngineError> {
+        self.write(&key.clone(), |db| db.s_add(key, value))
+    }
+
+    pub fn s_rem(&self, key: String, value: BuckTypes) -> Result<BuckLog, BuckEngineError> {
+        self.write(&key.clone(), |db| db.s_rem(key, value))
+    }
+
+    pub fn h_set(
+        &self,
+        key: String,
+        fields: HashMap<String, BuckTypes>,
+    ) -> Result<BuckLog, BuckEngineError> {
+        self.write(&key.clone(), |db| db.h_set(key, fields))
+    }
+
+    pub fn get_collections_length(&self, key: &str) -> Result<usize, BuckEngineError> {
+        self.read(key, |db| db.get_collections_length(key.to_owned()))
+    }
+
+    pub fn expire(&self, key: &str, millis: i64) -> Result<BuckLog, BuckEngineError> {
+        self.write(key, |db| db.expire(key, millis))
+    }
+
+    pub fn pttl(&self, key: &str) -> i64 {
+        self.read(key, |db| db.pttl(key))
+    }
+
+    ///////// Transaction /////////
+
+    /// Commit the staged writes of every partition.
+    ///
+    /// The partitions are committed one after the other, each under its own
+    /// lock, so a reader may see some of them committed and others not yet.
+    /// If one of them fails, the ones before it stay committed.
+    pub fn commit(&self) -> Result<BuckLog, BuckEngineError> {
+        for idx in 0..self.partitions.len() {
+            let mut db = self.write_partition(idx);
+
+            if db.status != TransactionStatus::Committed {
+                db.commit()?;
+            }
+        }
+
+        Ok(BuckLog::TransactionOk)
+    }
+
+    /// Throw away the uncommitted changes of every partition.
+    ///
+    /// Like `commit`, this goes one partition at a time, so it is not atomic
+    /// across partitions either.
+    pub fn rollback(&self) -> Result<BuckLog, BuckEngineError> {
+        for idx in 0..self.partitions.len() {
+            self.write_partition(idx).abort()?;
+        }
+
+        Ok(BuckLog::RollbackOk)
+    }
+
+    ///////// Maintenance /////////
+
+    /// Number of keys, staged or committed, over all partitions.
+    pub fn len(&self) -> usize {
+        (0..self.partitions.len())
+            .map(|idx| {
+                let db = self.read_partition(idx);
+
+                db.data
+                    .keys()
+                    .chain(db.uncommitted_data.keys().filter(|key| !db.data.contains_key(*key)))
+                    .filter(|key| !db.is_expired(key))
+                    .count()
+            })
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Run an active expire cycle on every partition, each bounded by `max_keys`.
+    pub fn active_expire_cycle(&self, max_keys: usize) -> usize {
+        (0..self.partitions.len())
+            .map(|idx| self.write_partition(idx).active_expire_cycle(max_keys))
+            .sum()
+    }
+
+    // a panic while holding a lock must not take every other client down
+    fn read_partition(&self, idx: usize) -> RwLockReadGuard<'_, BuckDB> {
+        self.partitions[idx].read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write_partition(&self, idx: usize) -> RwLockWriteGuard<'_, BuckDB> {
+        self.partitions[idx].write().unwrap_or_else(PoisonError::into_inner)
+    }
+}
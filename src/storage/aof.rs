@@ -0,0 +1,289 @@
+//! aof.rs
+//!
+//! Append-only log of committed transactions.
+//!
+//! Every `BuckDB::commit` appends one record holding the final state of each
+//! key the transaction touched: the value it holds, or a delete marker if it
+//! was removed. A record is framed as
+//!
+//! ```text
+//! | payload length (u32, BE) | crc32 of payload (u32, BE) | payload |
+//! ```
+//!
+//! and the payload is the number of entries followed by each entry: a tag
+//! byte, the key and, for a set, the value, written with the order-preserving
+//! encoders in `encoding::encoding`. A set of a key that has a deadline is
+//! tagged on its own and followed by the absolute deadline in milliseconds
+//! since the epoch (u64, BE).
+//!
+//! On startup the log is replayed to rebuild `BuckDB::data` and
+//! `BuckDB::expires`. If the process
+//! died in the middle of an append, the final record is torn: it is shorter
+//! than its header claims, or its checksum does not match. Replay stops at
+//! that record and truncates the file back to the last complete one. A
+//! record that claims to run past the end of the file while a complete
+//! record follows it has a damaged length instead, and is corruption.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use crate::encoding::encoding::{encode_length, encode_string, encode_type, take_string, take_type};
+use crate::encoding::errors::EncodingError;
+use crate::types::types::BuckTypes;
+
+use super::errors::StorageError;
+use super::Keyspace;
+use super::snapshot::{take_deadline, with_suffix};
+
+const HEADER_LEN: usize = 8;
+
+const SET_TAG: u8 = 0;
+const DELETE_TAG: u8 = 1;
+const SET_WITH_DEADLINE_TAG: u8 = 2;
+
+/// The state of one key after a commit.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LogEntry {
+    /// The key holds the value, until its deadline if it has one.
+    Set(String, BuckTypes, Option<u64>),
+    /// The key was removed.
+    Delete(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct AppendOnlyLog {
+    path: PathBuf,
+}
+
+impl AppendOnlyLog {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        AppendOnlyLog {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append one committed transaction to the log and flush it to disk.
+    pub fn append(&self, entries: &[LogEntry]) -> Result<(), StorageError> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+
+        file.write_all(&frame_record(entries))?;
+        file.sync_data()?;
+
+        Ok(())
+    }
+
+    /// Replay every complete record in the log, in commit order.
+    ///
+    /// A torn final record is dropped and the file is truncated to the end of
+    /// the last complete record, so that later appends start from a clean tail.
+    /// A damaged record followed by more data is reported as corruption instead.
+    ///
+    /// ## Returns
+    ///
+    /// The committed keyspace and the deadlines of its keys.
+    pub fn replay(&self) -> Result<Keyspace, StorageError> {
+        let mut data = BTreeMap::new();
+        let mut expires = HashMap::new();
+
+        let bytes = match fs::read(&self.path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok((data, expires)),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut offset = 0;
+
+        while offset < bytes.len() {
+            let rest = &bytes[offset..];
+
+            if rest.len() < HEADER_LEN {
+                break;
+            }
+
+            let len = u32::from_be_bytes(rest[0..4].try_into().unwrap()) as usize;
+            let checksum = u32::from_be_bytes(rest[4..8].try_into().unwrap());
+
+            if rest.len() < HEADER_LEN + len {
+                // only the last record can be cut short by a crash, so if a
+                // whole record follows, it is the length that is broken
+                if holds_record(&rest[HEADER_LEN..]) {
+                    return Err(StorageError::Corrupted(format!(
+                        "invalid length in record at offset {}",
+                        offset
+                    )));
+                }
+
+                break;
+            }
+
+            let payload = &rest[HEADER_LEN..HEADER_LEN + len];
+            let is_last = rest.len() == HEADER_LEN + len;
+
+            if crc32fast::hash(payload) != checksum {
+                if is_last {
+                    break;
+                }
+
+                return Err(StorageError::Corrupted(format!(
+                    "checksum mismatch in record at offset {}",
+                    offset
+                )));
+            }
+
+            for entry in decode_record(payload)? {
+                match entry {
+                    LogEntry::Set(key, value, deadline) => {
+                        match deadline {
+                            Some(deadline) => expires.insert(key.clone(), deadline),
+                            None => expires.remove(&key),
+                        };
+                        data.insert(key, value);
+                    }
+                    LogEntry::Delete(key) => {
+                        expires.remove(&key);
+                        data.remove(&key);
+                    }
+                }
+            }
+
+            offset += HEADER_LEN + len;
+        }
+
+        if offset < bytes.len() {
+            let file = OpenOptions::new().write(true).open(&self.path)?;
+            file.set_len(offset as u64)?;
+            file.sync_all()?;
+        }
+
+        Ok((data, expires))
+    }
+
+    /// Replace the whole log with a single record holding `data` and the
+    /// deadlines of its keys.
+    ///
+    /// Used when the committed data is swapped out wholesale, e.g. by `LOAD`,
+    /// so that replaying the log no longer yields keys that are gone.
+    pub fn rewrite(&self, data: &BTreeMap<String, BuckTypes>, expires: &HashMap<String, u64>) -> Result<(), StorageError> {
+        let tmp_path = with_suffix(&self.path, ".rewrite");
+        let entries: Vec<LogEntry> = data
+            .iter()
+            .map(|(k, v)| LogEntry::Set(k.clone(), v.clone(), expires.get(k).copied()))
+            .collect();
+
+        let mut file = File::create(&tmp_path)?;
+        if !entries.is_empty() {
+            file.write_all(&frame_record(&entries))?;
+        }
+        file.sync_all()?;
+
+        fs::rename(&tmp_path, &self.path)?;
+
+        Ok(())
+    }
+}
+
+// whether a complete record with a valid checksum starts anywhere in `bytes`
+fn holds_record(bytes: &[u8]) -> bool {
+    (0..bytes.len()).any(|start| {
+        let rest = &bytes[start..];
+        if rest.len() < HEADER_LEN {
+            return false;
+        }
+
+        let len = u32::from_be_bytes(rest[0..4].try_into().unwrap()) as usize;
+        let checksum = u32::from_be_bytes(rest[4..8].try_into().unwrap());
+
+        // an empty payload is not a record, and its checksum is all zeros
+        len > 0
+            && rest.len() >= HEADER_LEN + len
+            && crc32fast::hash(&rest[HEADER_LEN..HEADER_LEN + len]) == checksum
+            && decode_record(&rest[HEADER_LEN..HEADER_LEN + len]).is_ok()
+    })
+}
+
+/// Encode one transaction as a framed, checksummed record.
+pub fn frame_record(entries: &[LogEntry]) -> Vec<u8> {
+    let payload = encode_record(entries);
+
+    let mut framed = Vec::with_capacity(HEADER_LEN + payload.len());
+    framed.extend(encode_length(payload.len()));
+    framed.extend(crc32fast::hash(&payload).to_be_bytes());
+    framed.extend(payload);
+
+    framed
+}
+
+fn encode_record(entries: &[LogEntry]) -> Vec<u8> {
+    let mut encoded = Vec::new();
+    encoded.extend(encode_length(entries.len()));
+
+    for entry in entries {
+        match entry {
+            LogEntry::Set(key, value, None) => {
+                encoded.push(SET_TAG);
+                encoded.extend(encode_string(key));
+                encoded.extend(encode_type(value));
+            }
+            LogEntry::Set(key, value, Some(deadline)) => {
+                encoded.push(SET_WITH_DEADLINE_TAG);
+                encoded.extend(encode_string(key));
+                encoded.extend(encode_type(value));
+                encoded.extend(deadline.to_be_bytes());
+            }
+            LogEntry::Delete(key) => {
+                encoded.push(DELETE_TAG);
+                encoded.extend(encode_string(key));
+            }
+        }
+    }
+
+    encoded
+}
+
+fn decode_record(mut payload: &[u8]) -> Result<Vec<LogEntry>, StorageError> {
+    if payload.len() < 4 {
+        return Err(EncodingError::UnexpectedEndOf("Unexpected end of record".to_owned()).into());
+    }
+
+    let count = u32::from_be_bytes(payload[0..4].try_into().unwrap()) as usize;
+    payload = &payload[4..];
+
+    let mut entries = Vec::with_capacity(count);
+    for _ in 0..count {
+        let (tag, rest) = payload.split_first().ok_or_else(|| {
+            EncodingError::UnexpectedEndOf("Unexpected end of record".to_owned())
+        })?;
+        payload = rest;
+
+        let key = take_string(&mut payload)?;
+
+        let entry = match *tag {
+            SET_TAG => LogEntry::Set(key, take_type(&mut payload)?, None),
+            SET_WITH_DEADLINE_TAG => {
+                let value = take_type(&mut payload)?;
+                LogEntry::Set(key, value, Some(take_deadline(&mut payload)?))
+            }
+            DELETE_TAG => LogEntry::Delete(key),
+            tag => return Err(StorageError::Corrupted(format!("unknown entry tag {}", tag))),
+        };
+        entries.push(entry);
+    }
+
+    if !payload.is_empty() {
+        return Err(StorageError::Corrupted(format!(
+            "{} trailing bytes in record",
+            payload.len()
+        )));
+    }
+
+    Ok(entries)
+}
@@ -0,0 +1,33 @@
+use std::fmt;
+use std::io;
+
+use crate::encoding::errors::EncodingError;
+
+#[derive(Debug, PartialEq)]
+pub enum StorageError {
+    Io(String),
+    Corrupted(String),
+    Encoding(EncodingError),
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::Io(msg) => write!(f, "[Error] I/O error: {}", msg),
+            StorageError::Corrupted(msg) => write!(f, "[Error] Corrupted file: {}", msg),
+            StorageError::Encoding(e) => write!(f, "[Error] Encoding error: {}", e),
+        }
+    }
+}
+
+impl From<io::Error> for StorageError {
+    fn from(e: io::Error) -> Self {
+        StorageError::Io(e.to_string())
+    }
+}
+
+impl From<EncodingError> for StorageError {
+    fn from(e: EncodingError) -> Self {
+        StorageError::Encoding(e)
+    }
+}
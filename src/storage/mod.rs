@@ -0,0 +1,11 @@
+pub mod aof;
+pub mod errors;
+pub mod snapshot;
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::types::types::BuckTypes;
+
+/// Committed keyspace read back from disk, along with the absolute deadline
+/// of every key that has one.
+pub type Keyspace = (BTreeMap<String, BuckTypes>, HashMap<String, u64>);
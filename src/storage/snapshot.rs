@@ -0,0 +1,172 @@
+//! snapshot.rs
+//!
+//! Point-in-time snapshots of the committed data, written by `SAVE` and read
+//! back by `LOAD`.
+//!
+//! A snapshot file is laid out as
+//!
+//! ```text
+//! | magic "BUCK" | version (u8) | entry count (u32, BE) | entries... | crc32 (u32, BE) |
+//! ```
+//!
+//! where each entry is a key, its type-tagged value and its deadline: a `0`
+//! byte if it has none, or a `1` byte followed by the absolute deadline in
+//! milliseconds since the epoch (u64, BE). The trailing checksum covers every
+//! byte before it. A snapshot is only ever loaded as a whole: any mismatch
+//! in the header, the checksum or the entries rejects the file without
+//! touching the database.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::encoding::encoding::{encode_length, encode_string, encode_type, take_string, take_type};
+use crate::encoding::errors::EncodingError;
+use crate::types::types::BuckTypes;
+
+use super::errors::StorageError;
+use super::Keyspace;
+
+const MAGIC: &[u8; 4] = b"BUCK";
+const VERSION: u8 = 3;
+const HEADER_LEN: usize = 9;
+const CHECKSUM_LEN: usize = 4;
+
+/// Encode the whole keyspace as a snapshot, along with the deadlines of
+/// its keys. Deadlines of keys that are not in `data` are left out.
+pub fn encode_snapshot(data: &BTreeMap<String, BuckTypes>, expires: &HashMap<String, u64>) -> Vec<u8> {
+    let mut encoded = Vec::new();
+    encoded.extend(MAGIC);
+    encoded.push(VERSION);
+    encoded.extend(encode_length(data.len()));
+
+    for (key, value) in data {
+        encoded.extend(encode_string(key));
+        encoded.extend(encode_type(value));
+
+        match expires.get(key) {
+            Some(deadline) => {
+                encoded.push(1);
+                encoded.extend(deadline.to_be_bytes());
+            }
+            None => encoded.push(0),
+        }
+    }
+
+    let checksum = crc32fast::hash(&encoded);
+    encoded.extend(checksum.to_be_bytes());
+
+    encoded
+}
+
+/// Decode a snapshot, verifying it completely before returning any data.
+///
+/// ## Returns
+///
+/// The keyspace and the deadlines of its keys.
+pub fn decode_snapshot(bytes: &[u8]) -> Result<Keyspace, StorageError> {
+    if bytes.len() < HEADER_LEN + CHECKSUM_LEN {
+        return Err(StorageError::Corrupted("snapshot is too short".to_owned()));
+    }
+
+    if &bytes[0..4] != MAGIC {
+        return Err(StorageError::Corrupted("not a buck snapshot".to_owned()));
+    }
+
+    let version = bytes[4];
+    if version != VERSION {
+        return Err(StorageError::Corrupted(format!(
+            "unsupported snapshot version: {}",
+            bytes[4]
+        )));
+    }
+
+    let (body, checksum) = bytes.split_at(bytes.len() - CHECKSUM_LEN);
+    if crc32fast::hash(body) != u32::from_be_bytes(checksum.try_into().unwrap()) {
+        return Err(StorageError::Corrupted("checksum mismatch".to_owned()));
+    }
+
+    let count = u32::from_be_bytes(body[5..9].try_into().unwrap()) as usize;
+    let mut entries = &body[HEADER_LEN..];
+    let mut data = BTreeMap::new();
+    let mut expires = HashMap::new();
+
+    for _ in 0..count {
+        let key = take_string(&mut entries)?;
+        let value = take_type(&mut entries)?;
+
+        let (has_deadline, rest) = entries.split_first().ok_or_else(|| {
+            EncodingError::UnexpectedEndOf("Unexpected end of snapshot".to_owned())
+        })?;
+        entries = rest;
+
+        match *has_deadline {
+            0 => {}
+            1 => {
+                expires.insert(key.clone(), take_deadline(&mut entries)?);
+            }
+            flag => return Err(StorageError::Corrupted(format!("unknown deadline flag {}", flag))),
+        }
+
+        data.insert(key, value);
+    }
+
+    if !entries.is_empty() {
+        return Err(StorageError::Corrupted(format!(
+            "{} trailing bytes after the last entry",
+            entries.len()
+        )));
+    }
+
+    Ok((data, expires))
+}
+
+/// Read an absolute deadline off the front of `bytes`.
+pub(crate) fn take_deadline(bytes: &mut &[u8]) -> Result<u64, StorageError> {
+    if bytes.len() < 8 {
+        return Err(EncodingError::UnexpectedEndOf("Unexpected end of deadline".to_owned()).into());
+    }
+
+    let (deadline, rest) = bytes.split_at(8);
+    *bytes = rest;
+
+    Ok(u64::from_be_bytes(deadline.try_into().unwrap()))
+}
+
+/// Write a snapshot to `path`.
+///
+/// The snapshot is written to a temporary file next to `path` first and then
+/// renamed over it, so a crash never leaves a half-written snapshot behind.
+pub fn save<P: AsRef<Path>>(
+    path: P,
+    data: &BTreeMap<String, BuckTypes>,
+    expires: &HashMap<String, u64>,
+) -> Result<(), StorageError> {
+    let path = path.as_ref();
+    let tmp_path = with_suffix(path, ".tmp");
+
+    let mut file = File::create(&tmp_path)?;
+    file.write_all(&encode_snapshot(data, expires))?;
+    file.sync_all()?;
+
+    fs::rename(&tmp_path, path)?;
+
+    Ok(())
+}
+
+/// `path` with `suffix` appended to its file name, so that `dump.bdb`
+/// becomes `dump.bdb.tmp` instead of replacing its extension.
+pub(crate) fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+
+    PathBuf::from(name)
+}
+
+/// Read and verify the snapshot at `path`, returning its keyspace and deadlines.
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Keyspace, StorageError> {
+    let bytes = fs::read(path)?;
+
+    decode_snapshot(&bytes)
+}
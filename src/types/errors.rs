@@ -1,9 +1,34 @@
 use std::fmt;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub enum BuckTypeError {
     UnknownCommand(String),
     ListIsEmpty,
+    IndexOutOfRange(i64),
+    NotAnInteger(String),
+    NotAFloat(String),
+    Overflow,
+    ScoreIsNan(String),
+    StringTooLong,
+    CountTooLarge(i64),
+    InvalidUtf8,
+}
+
+impl BuckTypeError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            BuckTypeError::UnknownCommand(_) => "UNKNOWN_COMMAND",
+            BuckTypeError::ListIsEmpty => "LIST_IS_EMPTY",
+            BuckTypeError::IndexOutOfRange(_) => "INDEX_OUT_OF_RANGE",
+            BuckTypeError::NotAnInteger(_) => "NOT_AN_INTEGER",
+            BuckTypeError::NotAFloat(_) => "NOT_A_FLOAT",
+            BuckTypeError::Overflow => "OVERFLOW",
+            BuckTypeError::ScoreIsNan(_) => "SCORE_IS_NAN",
+            BuckTypeError::StringTooLong => "STRING_TOO_LONG",
+            BuckTypeError::CountTooLarge(_) => "COUNT_TOO_LARGE",
+            BuckTypeError::InvalidUtf8 => "INVALID_UTF8",
+        }
+    }
 }
 
 impl fmt::Display for BuckTypeError {
@@ -13,6 +38,26 @@ impl fmt::Display for BuckTypeError {
                 write!(f, "[Error] Unknown command: {}", command)
             }
             BuckTypeError::ListIsEmpty => write!(f, "[Error] List is empty"),
+            BuckTypeError::IndexOutOfRange(index) => {
+                write!(f, "[Error] Index out of range: {}", index)
+            }
+            BuckTypeError::NotAnInteger(key) => {
+                write!(f, "[Error] Value is not an integer: {}", key)
+            }
+            BuckTypeError::NotAFloat(key) => write!(f, "[Error] Value is not a float: {}", key),
+            BuckTypeError::Overflow => {
+                write!(f, "[Error] Increment would overflow")
+            }
+            BuckTypeError::ScoreIsNan(member) => {
+                write!(f, "[Error] Resulting score is not a number: {}", member)
+            }
+            BuckTypeError::StringTooLong => {
+                write!(f, "[Error] String exceeds the maximum length of 512MB")
+            }
+            BuckTypeError::CountTooLarge(count) => {
+                write!(f, "[Error] Count is out of range: {}", count)
+            }
+            BuckTypeError::InvalidUtf8 => write!(f, "[Error] Resulting string is not valid UTF-8"),
         }
     }
 }
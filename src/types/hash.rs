@@ -1,7 +1,6 @@
 use std::collections::HashMap;
 
-use crate::parser::errors::BuckParserError;
-
+use super::errors::BuckTypeError;
 use super::types::BuckTypes;
 
 #[derive(Debug, Clone, Default, PartialEq)]
@@ -18,50 +17,115 @@ impl BuckHash {
         self.data.insert(key, value);
     }
 
-    pub fn remove(&mut self, key: &[&str]) {
+    /// Remove the given fields, ignoring the ones that do not exist.
+    ///
+    /// ## Returns
+    ///
+    /// The number of fields that were removed.
+    pub fn remove(&mut self, key: &[&str]) -> usize {
+        let mut removed = 0;
+
         for item in key {
-            self.data.remove(*item);
+            if self.data.remove(*item).is_some() {
+                removed += 1;
+            }
         }
+
+        removed
     }
 
     pub fn len(&self) -> usize {
         self.data.len()
     }
 
-    pub fn increment_value(&mut self, key: &str) -> Result<(), BuckParserError> {
-        match self.data.get(key) {
-            Some(value) => {
-                if let BuckTypes::Integer(value) = value {
-                    self.data
-                        .insert(key.to_owned(), BuckTypes::Integer(value + 1));
-                    return Ok(());
-                }
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
 
-                return Err(BuckParserError::HashValueIsNotInteger(key.to_owned()));
-            }
-            None => {
-                self.data.insert(key.to_owned(), BuckTypes::Integer(1));
-                return Ok(());
-            }
+    /// Add `by` to the integer stored at `key`, a missing field counts as `0`.
+    ///
+    /// ## Returns
+    ///
+    /// The value after the increment.
+    pub fn increment_value(&mut self, key: &str, by: i64) -> Result<i64, BuckTypeError> {
+        let current = match self.data.get(key) {
+            Some(BuckTypes::Integer(value)) => *value,
+            Some(_) => return Err(BuckTypeError::NotAnInteger(key.to_owned())),
+            None => 0,
+        };
+
+        let value = current.checked_add(by).ok_or(BuckTypeError::Overflow)?;
+        self.data.insert(key.to_owned(), BuckTypes::Integer(value));
+
+        Ok(value)
+    }
+
+    /// Add `by` to the number stored at `key`, a missing field counts as `0`.
+    ///
+    /// The field holds a float afterwards, even if it held an integer.
+    ///
+    /// ## Returns
+    ///
+    /// The value after the increment.
+    pub fn increment_float(&mut self, key: &str, by: f64) -> Result<f64, BuckTypeError> {
+        let current = match self.data.get(key) {
+            Some(BuckTypes::Float(value)) => *value,
+            Some(BuckTypes::Integer(value)) => *value as f64,
+            Some(_) => return Err(BuckTypeError::NotAFloat(key.to_owned())),
+            None => 0.0,
+        };
+
+        let value = current + by;
+        if !value.is_finite() {
+            return Err(BuckTypeError::Overflow);
         }
+
+        self.data.insert(key.to_owned(), BuckTypes::Float(value));
+
+        Ok(value)
     }
 
-    pub fn hset(&mut self, fields: HashMap<String, BuckTypes>) {
+    /// Set the given fields, overwriting the ones that already exist.
+    ///
+    /// ## Returns
+    ///
+    /// The number of fields that were newly added.
+    pub fn hset(&mut self, fields: HashMap<String, BuckTypes>) -> usize {
+        let mut added = 0;
+
         for (key, value) in fields {
-            self.data.insert(key, value);
+            if self.data.insert(key, value).is_none() {
+                added += 1;
+            }
         }
-    }
 
-    pub fn hget(&self, key: &str) -> Option<&BuckTypes> {
-        let result = self.data.get(key);
+        added
+    }
 
-        match result {
-            Some(value) => Some(value),
-            None => None,
+    /// Set `key` to `value`, only if the field does not exist yet.
+    ///
+    /// ## Returns
+    ///
+    /// `true` if the field was set.
+    pub fn set_if_absent(&mut self, key: &str, value: BuckTypes) -> bool {
+        if self.data.contains_key(key) {
+            return false;
         }
+
+        self.data.insert(key.to_owned(), value);
+
+        true
+    }
+
+    pub fn hget(&self, key: &str) -> Option<&BuckTypes> {
+        self.data.get(key)
     }
 
-    pub fn hget_all(&self, key: &str) -> Option<BuckTypes> {
-        unimplemented!("hget_all")
+    /// All fields and their values, sorted by field.
+    pub fn hget_all(&self) -> Vec<(&String, &BuckTypes)> {
+        let mut fields: Vec<(&String, &BuckTypes)> = self.data.iter().collect();
+        fields.sort_by(|a, b| a.0.cmp(b.0));
+
+        fields
     }
 }
@@ -1,10 +1,24 @@
+use std::collections::{HashSet, VecDeque};
 use std::fmt;
 
 use super::{errors::BuckTypeError, types::BuckTypes};
 
+/// One end of a list, as in `LMOVE source destination LEFT|RIGHT LEFT|RIGHT`.
+///
+/// `Left` is the head and `Right` is the tail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListSide {
+    Left,
+    Right,
+}
+
+/// A list of values, ordered from head to tail.
+///
+/// Indices follow the usual convention for list commands: `0` is the head,
+/// and negative indices count from the tail, `-1` being the last element.
 #[derive(Debug, Clone, PartialEq, Default)]
 pub struct BuckList {
-    pub data: Vec<BuckTypes>,
+    pub data: VecDeque<BuckTypes>,
 }
 
 impl BuckList {
@@ -12,21 +26,153 @@ impl BuckList {
         Default::default()
     }
 
+    /// Append `value` at the tail.
     pub fn push(&mut self, value: BuckTypes) {
-        self.data.push(value);
+        self.push_back(value);
     }
 
-    pub fn pop(&mut self) -> Result<Option<BuckTypes>, BuckTypeError> {
-        if self.data.is_empty() {
-            return Err(BuckTypeError::ListIsEmpty);
-        }
+    pub fn push_front(&mut self, value: BuckTypes) {
+        self.data.push_front(value);
+    }
+
+    pub fn push_back(&mut self, value: BuckTypes) {
+        self.data.push_back(value);
+    }
+
+    pub fn pop_front(&mut self) -> Option<BuckTypes> {
+        self.data.pop_front()
+    }
 
-        Ok(self.data.pop())
+    pub fn pop_back(&mut self) -> Option<BuckTypes> {
+        self.data.pop_back()
     }
 
     pub fn len(&self) -> usize {
         self.data.len()
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Resolve a possibly negative index to a position in the list.
+    fn position(&self, index: i64) -> Option<usize> {
+        let len = self.data.len() as i64;
+        let index = if index < 0 { len + index } else { index };
+
+        match (0..len).contains(&index) {
+            true => Some(index as usize),
+            false => None,
+        }
+    }
+
+    /// Resolve an inclusive `start..=stop` range, clamped to the list.
+    ///
+    /// ## Returns
+    ///
+    /// `None` if the range selects no element.
+    fn bounds(&self, start: i64, stop: i64) -> Option<(usize, usize)> {
+        let len = self.data.len() as i64;
+        let start = if start < 0 { (len + start).max(0) } else { start };
+        let stop = if stop < 0 { len + stop } else { stop.min(len - 1) };
+
+        if start > stop || start >= len {
+            return None;
+        }
+
+        Some((start as usize, stop as usize))
+    }
+
+    pub fn get(&self, index: i64) -> Option<&BuckTypes> {
+        self.position(index).and_then(|pos| self.data.get(pos))
+    }
+
+    /// Replace the element at `index` with `value`.
+    pub fn set(&mut self, index: i64, value: BuckTypes) -> Result<(), BuckTypeError> {
+        match self.position(index) {
+            Some(pos) => {
+                self.data[pos] = value;
+                Ok(())
+            }
+            None => Err(BuckTypeError::IndexOutOfRange(index)),
+        }
+    }
+
+    /// The elements from `start` to `stop`, both inclusive.
+    pub fn range(&self, start: i64, stop: i64) -> Vec<BuckTypes> {
+        match self.bounds(start, stop) {
+            Some((start, stop)) => self.data.range(start..=stop).cloned().collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Insert `value` right before or right after the first `pivot`.
+    ///
+    /// ## Returns
+    ///
+    /// The new length of the list, or `None` if `pivot` is not in the list.
+    pub fn insert(&mut self, pivot: &BuckTypes, value: BuckTypes, before: bool) -> Option<usize> {
+        let pos = self.data.iter().position(|item| item == pivot)?;
+        let pos = if before { pos } else { pos + 1 };
+
+        self.data.insert(pos, value);
+        Some(self.data.len())
+    }
+
+    /// Remove occurrences of `value`.
+    ///
+    /// A positive `count` removes up to `count` of them from head to tail,
+    /// a negative one from tail to head, and `0` removes all of them.
+    ///
+    /// ## Returns
+    ///
+    /// The number of removed elements.
+    pub fn remove(&mut self, count: i64, value: &BuckTypes) -> usize {
+        let limit = match count {
+            0 => usize::MAX,
+            n => n.unsigned_abs() as usize,
+        };
+
+        let matches: HashSet<usize> = match count < 0 {
+            true => self
+                .data
+                .iter()
+                .enumerate()
+                .rev()
+                .filter(|(_, item)| *item == value)
+                .map(|(pos, _)| pos)
+                .take(limit)
+                .collect(),
+            false => self
+                .data
+                .iter()
+                .enumerate()
+                .filter(|(_, item)| *item == value)
+                .map(|(pos, _)| pos)
+                .take(limit)
+                .collect(),
+        };
+
+        let mut pos = 0;
+        self.data.retain(|_| {
+            let keep = !matches.contains(&pos);
+            pos += 1;
+            keep
+        });
+
+        matches.len()
+    }
+
+    /// Keep only the elements from `start` to `stop`, both inclusive.
+    pub fn trim(&mut self, start: i64, stop: i64) {
+        match self.bounds(start, stop) {
+            Some((start, stop)) => {
+                self.data.truncate(stop + 1);
+                self.data.drain(..start);
+            }
+            None => self.data.clear(),
+        }
+    }
 }
 
 impl fmt::Display for BuckList {
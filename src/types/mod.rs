@@ -2,4 +2,7 @@ pub mod errors;
 pub mod hash;
 pub mod list;
 pub mod sets;
+pub mod sorted_set;
+pub mod string;
+#[allow(clippy::module_inception)]
 pub mod types;
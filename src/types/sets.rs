@@ -1,4 +1,14 @@
-use std::{collections::HashSet, fmt, hash::{Hash, Hasher}};
+use std::{
+    collections::{hash_map::RandomState, HashSet},
+    fmt,
+    hash::{BuildHasher, Hash, Hasher},
+};
+
+use crate::types::errors::BuckTypeError;
+
+/// Most members `SRANDMEMBER` returns for a negative count, the same bound
+/// as `MAX_ARRAY_LEN` puts on a request.
+pub const MAX_RANDOM_MEMBERS: u64 = 1024 * 1024;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Setable {
@@ -9,9 +19,17 @@ pub enum Setable {
     Empty,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub struct EqFloat(pub f64);
 
+// Equality is defined on the bit pattern so that it agrees with `Hash`.
+// Otherwise `0.0` and `-0.0` would be equal but hash differently.
+impl PartialEq for EqFloat {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.to_bits() == other.0.to_bits()
+    }
+}
+
 impl Eq for EqFloat {}
 
 impl Hash for EqFloat {
@@ -56,29 +74,154 @@ impl BuckSets {
         self.data.len()
     }
 
-    pub fn intersection(&self, other: &[BuckSets]) -> Self {
-        let mut result = BuckSets::new();
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Members of `self` that are in every one of `others`.
+    ///
+    /// The smallest of the sets drives the lookups, so the cost depends on
+    /// its size and not on the size of `self`.
+    pub fn intersection(&self, others: &[&BuckSets]) -> Self {
+        BuckSets {
+            data: self.common_members(others).cloned().collect(),
+        }
+    }
+
+    /// Number of members of `self` that are in every one of `others`, like
+    /// `intersection`. A `limit` other than `0` stops the count as soon as
+    /// it is reached, without looking at the remaining members.
+    pub fn intersection_card(&self, others: &[&BuckSets], limit: usize) -> usize {
+        let members = self.common_members(others);
+
+        match limit {
+            0 => members.count(),
+            limit => members.take(limit).count(),
+        }
+    }
+
+    /// Members of the smallest of the sets that are in all of the others.
+    fn common_members<'a>(&'a self, others: &[&'a BuckSets]) -> impl Iterator<Item = &'a Setable> {
+        let mut sets: Vec<&BuckSets> = Vec::with_capacity(others.len() + 1);
+        sets.push(self);
+        sets.extend_from_slice(others);
+        sets.sort_by_key(|set| set.len());
+
+        let smallest = sets.remove(0);
 
-        for item in &self.data {
-            let mut is_in_all = true;
-            for set in other {
-                if !set.data.contains(item) {
-                    is_in_all = false;
-                    break;
-                }
-            }
+        smallest
+            .data
+            .iter()
+            .filter(move |item| sets.iter().all(|set| set.data.contains(*item)))
+    }
+
+    /// Members that are in `self` or in any of `others`.
+    pub fn union(&self, others: &[&BuckSets]) -> Self {
+        let mut result = self.clone();
 
-            if is_in_all {
-                result.data.insert(item.clone());
-            }
+        for set in others {
+            result.data.extend(set.data.iter().cloned());
         }
 
         result
     }
 
+    /// Members of `self` that are in none of `others`.
+    pub fn difference(&self, others: &[&BuckSets]) -> Self {
+        let data = self
+            .data
+            .iter()
+            .filter(|item| !others.iter().any(|set| set.data.contains(item)))
+            .cloned()
+            .collect();
+
+        BuckSets { data }
+    }
+
     pub fn is_member(&self, value: &Setable) -> bool {
         self.data.contains(value)
     }
+
+    /// Random members, like `SRANDMEMBER key count`.
+    ///
+    /// A positive `count` returns up to `count` distinct members, a negative
+    /// one returns exactly `-count` members that may repeat.
+    ///
+    /// ## Returns
+    ///
+    /// `CountTooLarge` if `-count` is above `MAX_RANDOM_MEMBERS`.
+    pub fn random_members(&self, count: i64) -> Result<Vec<Setable>, BuckTypeError> {
+        if count < 0 && count.unsigned_abs() > MAX_RANDOM_MEMBERS {
+            return Err(BuckTypeError::CountTooLarge(count));
+        }
+
+        let members: Vec<&Setable> = self.data.iter().collect();
+        if members.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut rng = MemberRng::new();
+
+        if count < 0 {
+            return Ok((0..count.unsigned_abs())
+                .map(|_| members[rng.below(members.len())].clone())
+                .collect());
+        }
+
+        let count = (count as usize).min(members.len());
+        Ok(rng.pick(members, count).into_iter().cloned().collect())
+    }
+
+    /// Remove and return up to `count` random members.
+    pub fn pop_random(&mut self, count: usize) -> Vec<Setable> {
+        let count = count.min(self.data.len());
+        let members: Vec<Setable> = self.data.iter().cloned().collect();
+        let popped: Vec<Setable> = MemberRng::new().pick(members, count);
+
+        for member in &popped {
+            self.data.remove(member);
+        }
+
+        popped
+    }
+}
+
+/// xorshift64*, seeded from the standard library's random hash keys.
+///
+/// Plenty for picking random members, and it needs no extra dependency.
+struct MemberRng(u64);
+
+impl MemberRng {
+    fn new() -> Self {
+        // the state must never be zero
+        MemberRng(RandomState::new().hash_one(0u64) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.0 = x;
+
+        x.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// A random index below `n`, which must not be zero.
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    /// `count` distinct items out of `items`, with a partial Fisher-Yates shuffle.
+    fn pick<T>(&mut self, mut items: Vec<T>, count: usize) -> Vec<T> {
+        for i in 0..count {
+            let j = i + self.below(items.len() - i);
+            items.swap(i, j);
+        }
+
+        items.truncate(count);
+        items
+    }
 }
 
 impl fmt::Display for Setable {
@@ -0,0 +1,443 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap};
+
+use super::errors::BuckTypeError;
+
+/// A score, ordered with `f64::total_cmp` so that it can be kept in a `BTreeSet`.
+///
+/// `NaN` is never stored, and `-0.0` is stored as `0.0`.
+#[derive(Debug, Clone, Copy)]
+struct Score(f64);
+
+impl PartialEq for Score {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Score {}
+
+impl PartialOrd for Score {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Score {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// One end of a score range, as in `ZRANGE key (1 5 BYSCORE`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScoreBound {
+    Inclusive(f64),
+    Exclusive(f64),
+}
+
+impl ScoreBound {
+    /// Whether `score` is on the inner side of this bound, used as a minimum.
+    fn above_min(&self, score: f64) -> bool {
+        match self {
+            ScoreBound::Inclusive(min) => score >= *min,
+            ScoreBound::Exclusive(min) => score > *min,
+        }
+    }
+
+    /// Whether `score` is on the inner side of this bound, used as a maximum.
+    fn below_max(&self, score: f64) -> bool {
+        match self {
+            ScoreBound::Inclusive(max) => score <= *max,
+            ScoreBound::Exclusive(max) => score < *max,
+        }
+    }
+}
+
+/// One end of a lexicographical range, as in `ZRANGE key [a (c BYLEX`.
+///
+/// `Min` and `Max`, written `-` and `+`, are below and above every member.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LexBound {
+    Min,
+    Max,
+    Inclusive(String),
+    Exclusive(String),
+}
+
+impl LexBound {
+    fn above_min(&self, member: &str) -> bool {
+        match self {
+            LexBound::Min => true,
+            LexBound::Max => false,
+            LexBound::Inclusive(min) => member >= min.as_str(),
+            LexBound::Exclusive(min) => member > min.as_str(),
+        }
+    }
+
+    fn below_max(&self, member: &str) -> bool {
+        match self {
+            LexBound::Min => false,
+            LexBound::Max => true,
+            LexBound::Inclusive(max) => member <= max.as_str(),
+            LexBound::Exclusive(max) => member < max.as_str(),
+        }
+    }
+}
+
+/// What `ZRANGE` selects members by.
+///
+/// Score and lex ranges are always stored as `(min, max)`, even when the
+/// command was given them the other way around with `REV`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RangeBy {
+    Index(i64, i64),
+    Score(ScoreBound, ScoreBound),
+    Lex(LexBound, LexBound),
+}
+
+/// A `ZRANGE` query, see `BuckSortedSet::range`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ZRangeSpec {
+    pub by: RangeBy,
+    /// Walk from the highest score down.
+    pub rev: bool,
+    /// Skip `offset` matching members and return at most `count` of the rest.
+    pub limit: Option<(usize, usize)>,
+    pub with_scores: bool,
+}
+
+/// The flags of `ZADD`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ZAddFlags {
+    /// Only add new members.
+    pub nx: bool,
+    /// Only update existing members.
+    pub xx: bool,
+    /// Only update a member if the new score is greater.
+    pub gt: bool,
+    /// Only update a member if the new score is less.
+    pub lt: bool,
+    /// Count changed members in the reply, not only added ones.
+    pub ch: bool,
+}
+
+/// How `ZUNIONSTORE` and `ZINTERSTORE` combine the scores of a member.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Aggregate {
+    #[default]
+    Sum,
+    Min,
+    Max,
+}
+
+impl Aggregate {
+    fn apply(&self, a: f64, b: f64) -> f64 {
+        match self {
+            // `inf + -inf` counts as 0
+            Aggregate::Sum => nan_to_zero(a + b),
+            Aggregate::Min => a.min(b),
+            Aggregate::Max => a.max(b),
+        }
+    }
+}
+
+fn nan_to_zero(score: f64) -> f64 {
+    match score.is_nan() {
+        true => 0.0,
+        false => score,
+    }
+}
+
+/// Members with a score, ordered by score and then by member.
+///
+/// Members are kept twice: in a map for finding the score of a member, and
+/// in a B-tree for walking them in order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BuckSortedSet {
+    scores: HashMap<String, f64>,
+    ordered: BTreeSet<(Score, String)>,
+}
+
+impl BuckSortedSet {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.scores.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+
+    pub fn score(&self, member: &str) -> Option<f64> {
+        self.scores.get(member).copied()
+    }
+
+    /// Members and their scores, from the lowest score up.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&String, f64)> {
+        self.ordered.iter().map(|(score, member)| (member, score.0))
+    }
+
+    /// Set the score of `member`, adding it if needed. `score` must not be `NaN`.
+    pub fn insert(&mut self, member: String, score: f64) {
+        // `-0.0` would sort before `0.0`
+        let score = score + 0.0;
+
+        if let Some(previous) = self.scores.insert(member.clone(), score) {
+            self.ordered.remove(&(Score(previous), member.clone()));
+        }
+
+        self.ordered.insert((Score(score), member));
+    }
+
+    /// Add `member`, or update its score, as allowed by `flags`.
+    ///
+    /// ## Returns
+    ///
+    /// Whether the member was added, and whether it was added or its score changed.
+    pub fn add(&mut self, member: String, score: f64, flags: &ZAddFlags) -> (bool, bool) {
+        match self.score(&member) {
+            None if flags.xx => (false, false),
+            None => {
+                self.insert(member, score);
+                (true, true)
+            }
+            Some(current) => {
+                let skip = flags.nx
+                    || flags.gt && score <= current
+                    || flags.lt && score >= current
+                    || score == current;
+
+                if skip {
+                    return (false, false);
+                }
+
+                self.insert(member, score);
+                (false, true)
+            }
+        }
+    }
+
+    /// Remove the given members, ignoring the ones that do not exist.
+    ///
+    /// ## Returns
+    ///
+    /// The number of members that were removed.
+    pub fn remove(&mut self, members: &[String]) -> usize {
+        let mut removed = 0;
+
+        for member in members {
+            if let Some(score) = self.scores.remove(member) {
+                self.ordered.remove(&(Score(score), member.clone()));
+                removed += 1;
+            }
+        }
+
+        removed
+    }
+
+    /// Add `by` to the score of `member`, a missing member counts as `0`.
+    ///
+    /// ## Returns
+    ///
+    /// The new score.
+    pub fn increment(&mut self, member: &str, by: f64) -> Result<f64, BuckTypeError> {
+        let score = self.score(member).unwrap_or(0.0) + by;
+
+        if score.is_nan() {
+            return Err(BuckTypeError::ScoreIsNan(member.to_owned()));
+        }
+
+        self.insert(member.to_owned(), score);
+
+        Ok(score)
+    }
+
+    /// The position of `member`, counting from the lowest score or, with
+    /// `reverse`, from the highest.
+    pub fn rank(&self, member: &str, reverse: bool) -> Option<usize> {
+        let score = self.score(member)?;
+        let below = self
+            .ordered
+            .range(..(Score(score), member.to_owned()))
+            .count();
+
+        match reverse {
+            true => Some(self.len() - 1 - below),
+            false => Some(below),
+        }
+    }
+
+    /// The members selected by `spec`, with their scores.
+    pub fn range(&self, spec: &ZRangeSpec) -> Vec<(String, f64)> {
+        let (offset, count) = spec.limit.unwrap_or((0, usize::MAX));
+
+        let selected: Vec<(&String, f64)> = match &spec.by {
+            RangeBy::Index(start, stop) => match self.bounds(*start, *stop) {
+                Some((start, stop)) => self
+                    .walk(spec.rev)
+                    .skip(start)
+                    .take(stop - start + 1)
+                    .collect(),
+                None => Vec::new(),
+            },
+            RangeBy::Score(min, max) => self
+                .select(
+                    |score, _| min.above_min(score),
+                    |score, _| max.below_max(score),
+                    spec.rev,
+                )
+                .skip(offset)
+                .take(count)
+                .collect(),
+            RangeBy::Lex(min, max) => self
+                .select(
+                    |_, member| min.above_min(member),
+                    |_, member| max.below_max(member),
+                    spec.rev,
+                )
+                .skip(offset)
+                .take(count)
+                .collect(),
+        };
+
+        selected
+            .into_iter()
+            .map(|(member, score)| (member.clone(), score))
+            .collect()
+    }
+
+    /// The number of members with a score between `min` and `max`.
+    pub fn count(&self, min: &ScoreBound, max: &ScoreBound) -> usize {
+        self.select(
+            |score, _| min.above_min(score),
+            |score, _| max.below_max(score),
+            false,
+        )
+        .count()
+    }
+
+    /// Remove and return up to `count` members with the lowest scores or,
+    /// with `max`, the highest.
+    pub fn pop(&mut self, count: usize, max: bool) -> Vec<(String, f64)> {
+        let popped: Vec<(String, f64)> = self
+            .walk(max)
+            .take(count)
+            .map(|(member, score)| (member.clone(), score))
+            .collect();
+
+        for (member, score) in &popped {
+            self.scores.remove(member);
+            self.ordered.remove(&(Score(*score), member.clone()));
+        }
+
+        popped
+    }
+
+    /// The union of `sets`, each given with the weight its scores are multiplied by.
+    pub fn union(sets: &[(&BuckSortedSet, f64)], aggregate: Aggregate) -> BuckSortedSet {
+        let mut scores: HashMap<&String, f64> = HashMap::new();
+
+        for (set, weight) in sets {
+            for (member, score) in set.iter() {
+                let score = nan_to_zero(score * weight);
+
+                scores
+                    .entry(member)
+                    .and_modify(|current| *current = aggregate.apply(*current, score))
+                    .or_insert(score);
+            }
+        }
+
+        Self::from_scores(scores)
+    }
+
+    /// The intersection of `sets`, weighted like `union`.
+    pub fn intersection(sets: &[(&BuckSortedSet, f64)], aggregate: Aggregate) -> BuckSortedSet {
+        // drive the intersection from the smallest set
+        let smallest = match sets.iter().min_by_key(|(set, _)| set.len()) {
+            Some((set, _)) => set,
+            None => return BuckSortedSet::new(),
+        };
+
+        let scores = smallest.scores.keys().filter_map(|member| {
+            let mut combined: Option<f64> = None;
+
+            for (set, weight) in sets {
+                let score = nan_to_zero(set.score(member)? * weight);
+
+                combined = Some(match combined {
+                    Some(current) => aggregate.apply(current, score),
+                    None => score,
+                });
+            }
+
+            combined.map(|score| (member, score))
+        });
+
+        Self::from_scores(scores.collect())
+    }
+
+    fn from_scores(scores: HashMap<&String, f64>) -> BuckSortedSet {
+        let mut set = BuckSortedSet::new();
+
+        for (member, score) in scores {
+            set.insert(member.clone(), score);
+        }
+
+        set
+    }
+
+    /// Walk the members from the lowest score, or from the highest with `reverse`.
+    fn walk(&self, reverse: bool) -> Box<dyn Iterator<Item = (&String, f64)> + '_> {
+        match reverse {
+            true => Box::new(self.iter().rev()),
+            false => Box::new(self.iter()),
+        }
+    }
+
+    /// The members between a minimum and a maximum, in the order of `walk`.
+    fn select<'a>(
+        &'a self,
+        above_min: impl Fn(f64, &str) -> bool + 'a,
+        below_max: impl Fn(f64, &str) -> bool + 'a,
+        reverse: bool,
+    ) -> Box<dyn Iterator<Item = (&'a String, f64)> + 'a> {
+        match reverse {
+            true => Box::new(
+                self.walk(true)
+                    .skip_while(move |(member, score)| !below_max(*score, member))
+                    .take_while(move |(member, score)| above_min(*score, member)),
+            ),
+            false => Box::new(
+                self.walk(false)
+                    .skip_while(move |(member, score)| !above_min(*score, member))
+                    .take_while(move |(member, score)| below_max(*score, member)),
+            ),
+        }
+    }
+
+    /// Resolve an inclusive `start..=stop` range of positions, like `BuckList` does.
+    fn bounds(&self, start: i64, stop: i64) -> Option<(usize, usize)> {
+        let len = self.len() as i64;
+        let start = if start < 0 {
+            (len + start).max(0)
+        } else {
+            start
+        };
+        let stop = if stop < 0 {
+            len + stop
+        } else {
+            stop.min(len - 1)
+        };
+
+        if start > stop || start >= len {
+            return None;
+        }
+
+        Some((start as usize, stop as usize))
+    }
+}
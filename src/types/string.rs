@@ -0,0 +1,88 @@
+use crate::clock::Expiry;
+
+use super::errors::BuckTypeError;
+use super::types::BuckTypes;
+
+/// The longest string `APPEND` and `SETRANGE` may produce, in bytes.
+pub const MAX_STRING_LENGTH: usize = 512 * 1024 * 1024;
+
+/// The options of `SET`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SetOptions {
+    /// Only set the key if it does not exist.
+    pub nx: bool,
+    /// Only set the key if it already exists.
+    pub xx: bool,
+    /// Reply with the old value instead of `OK`.
+    pub get: bool,
+    /// The timeout of the key, which otherwise loses any it had.
+    pub expiry: Option<Expiry>,
+    /// Keep the timeout the key already has.
+    pub keep_ttl: bool,
+}
+
+/// The text of a value that string commands can work on.
+///
+/// Numbers and booleans are read as they are printed, so a key set with
+/// `SET key 10` has a length of `2`.
+///
+/// ## Returns
+///
+/// `None` for lists, sets, hashes and sorted sets.
+pub fn as_text(value: &BuckTypes) -> Option<String> {
+    match value {
+        BuckTypes::String(text) | BuckTypes::Unknown(text) => Some(text.clone()),
+        BuckTypes::Integer(_) | BuckTypes::Float(_) | BuckTypes::Boolean(_) => {
+            Some(value.to_string())
+        }
+        _ => None,
+    }
+}
+
+/// The bytes of `text` from `start` to `end`, both inclusive.
+///
+/// Negative offsets count from the end of the string, and out of range
+/// offsets are clamped to it. A range that cuts a character in half gets
+/// a replacement character in its place.
+pub fn get_range(text: &str, start: i64, end: i64) -> String {
+    let bytes = text.as_bytes();
+    let len = bytes.len() as i64;
+
+    let start = match start {
+        start if start < 0 => (len + start).max(0),
+        start => start,
+    };
+    let end = match end {
+        end if end < 0 => len + end,
+        end => end.min(len - 1),
+    };
+
+    if start > end {
+        return String::new();
+    }
+
+    String::from_utf8_lossy(&bytes[start as usize..=end as usize]).into_owned()
+}
+
+/// Overwrite the bytes of `text` starting at `offset` with `value`.
+///
+/// A string that is too short is padded with zero bytes first.
+///
+/// ## Returns
+///
+/// The new string, or an error if it would be longer than
+/// `MAX_STRING_LENGTH` or would not be valid UTF-8.
+pub fn set_range(text: &str, offset: usize, value: &str) -> Result<String, BuckTypeError> {
+    let end = offset
+        .checked_add(value.len())
+        .filter(|end| *end <= MAX_STRING_LENGTH)
+        .ok_or(BuckTypeError::StringTooLong)?;
+
+    let mut bytes = text.as_bytes().to_vec();
+    if bytes.len() < end {
+        bytes.resize(end, 0);
+    }
+    bytes[offset..end].copy_from_slice(value.as_bytes());
+
+    String::from_utf8(bytes).map_err(|_| BuckTypeError::InvalidUtf8)
+}
@@ -21,6 +21,7 @@
 
 use std::collections::{HashMap, HashSet};
 use std::fmt;
+use std::mem;
 
 use crate::parser::errors::BuckParserError;
 use crate::parser::parse::get_value_type;
@@ -28,6 +29,7 @@ use crate::parser::parse::get_value_type;
 use super::hash::BuckHash;
 use super::list::BuckList;
 use super::sets::{BuckSets, Setable};
+use super::sorted_set::BuckSortedSet;
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum BuckTypes {
@@ -38,6 +40,7 @@ pub enum BuckTypes {
     List(BuckList),
     Hash(BuckHash),
     Sets(BuckSets),
+    SortedSet(BuckSortedSet),
     Unknown(String),
 }
 
@@ -47,7 +50,7 @@ pub fn parse_list(list_input: &str) -> Result<BuckList, BuckParserError> {
     // expect input -> value1,value2, ...
     for value in list_input.split(',') {
         let value = get_value_type(value)?;
-        list.data.push(value);
+        list.push(value);
     }
 
     Ok(list)
@@ -58,21 +61,25 @@ pub fn parse_hash(hash_input: &str) -> Result<BuckHash, BuckParserError> {
 
     // expect input -> key1:value1,key2:value2, ...
     let mut hash = HashMap::new();
+    if hash_input.is_empty() {
+        return Ok(BuckHash { data: hash });
+    }
+
     for part in hash_input.split(',') {
-        let kv: Vec<&str> = part.splitn(2, ':').collect();
+        // a part without `:` is a key with no value
+        let (key, value) = part.split_once(':').unwrap_or((part, ""));
 
-        if kv[0].is_empty() {
-            let value = kv[1].replace(':', "");
+        if key.is_empty() {
+            let value = value.replace(':', "");
             return Err(BuckParserError::HashKeyIsEmpty(value));
         }
 
-        if kv[1].is_empty() {
-            let key = kv[0].replace(':', "");
-            return Err(BuckParserError::HashValueIsEmpty(key));
+        if value.is_empty() {
+            return Err(BuckParserError::HashValueIsEmpty(key.to_owned()));
         }
 
-        let value = get_value_type(kv[1])?;
-        hash.insert(kv[0].to_owned(), value);
+        let value = get_value_type(value)?;
+        hash.insert(key.to_owned(), value);
     }
 
     Ok(BuckHash { data: hash })
@@ -135,7 +142,80 @@ impl fmt::Display for BuckTypes {
 
                 write!(f, "{}", set_string)
             }
+            BuckTypes::SortedSet(zval) => {
+                let mut zset_string = String::new();
+                for (i, (member, score)) in zval.iter().enumerate() {
+                    zset_string.push_str(&format!("{}: {} ({})\n", i, member, score));
+                }
+
+                write!(f, "{}", zset_string)
+            }
             BuckTypes::Unknown(uval) => write!(f, "{}", uval),
         }
     }
 }
+
+impl BuckTypes {
+    /// The name of this type, as reported by `TYPE`.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            BuckTypes::String(_) => "string",
+            BuckTypes::Integer(_) => "integer",
+            BuckTypes::Float(_) => "float",
+            BuckTypes::Boolean(_) => "boolean",
+            BuckTypes::List(_) => "list",
+            BuckTypes::Hash(_) => "hash",
+            BuckTypes::Sets(_) => "sets",
+            BuckTypes::SortedSet(_) => "zset",
+            BuckTypes::Unknown(_) => "unknown",
+        }
+    }
+
+    /// Rough number of bytes this value occupies in memory.
+    ///
+    /// This is not exact: it counts the inline size of every value plus the
+    /// heap bytes of its strings and collections, and ignores allocator
+    /// slack and hash table overhead. It is good enough to enforce a
+    /// `maxmemory` budget.
+    pub fn estimated_size(&self) -> usize {
+        let inline = mem::size_of::<BuckTypes>();
+
+        match self {
+            BuckTypes::String(s) | BuckTypes::Unknown(s) => inline + s.len(),
+            BuckTypes::Integer(_) | BuckTypes::Float(_) | BuckTypes::Boolean(_) => inline,
+            BuckTypes::List(list) => {
+                inline + list.data.iter().map(BuckTypes::estimated_size).sum::<usize>()
+            }
+            BuckTypes::Hash(hash) => {
+                let fields: usize = hash
+                    .data
+                    .iter()
+                    .map(|(field, value)| mem::size_of::<String>() + field.len() + value.estimated_size())
+                    .sum();
+
+                inline + fields
+            }
+            BuckTypes::Sets(set) => {
+                let members: usize = set
+                    .data
+                    .iter()
+                    .map(|member| match member {
+                        Setable::String(s) => mem::size_of::<Setable>() + s.len(),
+                        _ => mem::size_of::<Setable>(),
+                    })
+                    .sum();
+
+                inline + members
+            }
+            // every member is kept in both the score map and the ordered tree
+            BuckTypes::SortedSet(zset) => {
+                let members: usize = zset
+                    .iter()
+                    .map(|(member, _)| 2 * (mem::size_of::<(String, f64)>() + member.len()))
+                    .sum();
+
+                inline + members
+            }
+        }
+    }
+}
@@ -0,0 +1,306 @@
+#[cfg(test)]
+mod aof_tests {
+    use std::collections::HashMap;
+    use std::fs::{self, OpenOptions};
+    use std::io::Write;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    use buck::clock::ManualClock;
+    use buck::databases;
+    use buck::engine::BuckDB;
+    use buck::storage::aof::{frame_record, AppendOnlyLog, LogEntry};
+    use buck::storage::errors::StorageError;
+    use buck::types::types::BuckTypes;
+
+    fn aof_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("buck-aof-{}-{}.aof", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        path
+    }
+
+    fn append_raw(path: &PathBuf, bytes: &[u8]) {
+        let mut file = OpenOptions::new().create(true).append(true).open(path).unwrap();
+        file.write_all(bytes).unwrap();
+    }
+
+    #[test]
+    fn test_commit_is_replayed_on_open() {
+        let path = aof_path("replay");
+
+        let mut db = BuckDB::open(&path).unwrap();
+        db.insert("k1".to_owned(), BuckTypes::Integer(1)).unwrap();
+        db.insert("k2".to_owned(), BuckTypes::String("two".to_owned())).unwrap();
+        db.commit().unwrap();
+
+        db.insert("k1".to_owned(), BuckTypes::Float(1.5)).unwrap();
+        db.insert("k3".to_owned(), BuckTypes::Boolean(true)).unwrap();
+        db.commit().unwrap();
+
+        // uncommitted data is not written to the log
+        db.insert("k4".to_owned(), BuckTypes::Integer(4)).unwrap();
+
+        let reopened = BuckDB::open(&path).unwrap();
+        assert_eq!(reopened.data.len(), 3);
+        assert_eq!(reopened.data.get("k1"), Some(&BuckTypes::Float(1.5)));
+        assert_eq!(reopened.data.get("k2"), Some(&BuckTypes::String("two".to_owned())));
+        assert_eq!(reopened.data.get("k3"), Some(&BuckTypes::Boolean(true)));
+        assert_eq!(reopened.data.get("k4"), None);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_torn_record_is_truncated() {
+        let path = aof_path("torn");
+        let aof = AppendOnlyLog::new(&path);
+
+        aof.append(&[LogEntry::Set("k1".to_owned(), BuckTypes::Integer(1), None)]).unwrap();
+        let complete_len = fs::metadata(&path).unwrap().len();
+
+        // crash after writing only part of the second record
+        let record = frame_record(&[LogEntry::Set("k2".to_owned(), BuckTypes::Integer(2), None)]);
+        for cut in 1..record.len() {
+            append_raw(&path, &record[..cut]);
+
+            let (data, _) = aof.replay().unwrap();
+            assert_eq!(data.len(), 1);
+            assert_eq!(data.get("k1"), Some(&BuckTypes::Integer(1)));
+            assert_eq!(fs::metadata(&path).unwrap().len(), complete_len);
+        }
+
+        // the log keeps working after the torn tail is dropped
+        aof.append(&[LogEntry::Set("k2".to_owned(), BuckTypes::Integer(2), None)]).unwrap();
+        let (data, _) = aof.replay().unwrap();
+        assert_eq!(data.len(), 2);
+        assert_eq!(data.get("k2"), Some(&BuckTypes::Integer(2)));
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_final_record_with_bad_checksum_is_truncated() {
+        let path = aof_path("checksum");
+        let aof = AppendOnlyLog::new(&path);
+
+        aof.append(&[LogEntry::Set("k1".to_owned(), BuckTypes::Integer(1), None)]).unwrap();
+        let complete_len = fs::metadata(&path).unwrap().len();
+
+        let mut record = frame_record(&[LogEntry::Set("k2".to_owned(), BuckTypes::Integer(2), None)]);
+        let last = record.len() - 1;
+        record[last] ^= 0xff;
+        append_raw(&path, &record);
+
+        let (data, _) = aof.replay().unwrap();
+        assert_eq!(data.len(), 1);
+        assert_eq!(fs::metadata(&path).unwrap().len(), complete_len);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_corrupted_record_in_the_middle_is_rejected() {
+        let path = aof_path("corrupted");
+
+        let mut first = frame_record(&[LogEntry::Set("k1".to_owned(), BuckTypes::Integer(1), None)]);
+        let last = first.len() - 1;
+        first[last] ^= 0xff;
+        append_raw(&path, &first);
+        append_raw(&path, &frame_record(&[LogEntry::Set("k2".to_owned(), BuckTypes::Integer(2), None)]));
+
+        let result = AppendOnlyLog::new(&path).replay();
+        assert!(matches!(result, Err(StorageError::Corrupted(_))));
+        assert!(BuckDB::open(&path).is_err());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_broken_length_in_the_middle_is_rejected() {
+        let path = aof_path("length");
+
+        let mut first = frame_record(&[LogEntry::Set("k1".to_owned(), BuckTypes::Integer(1), None)]);
+        first[0..4].copy_from_slice(&u32::MAX.to_be_bytes());
+        append_raw(&path, &first);
+        append_raw(&path, &frame_record(&[LogEntry::Set("k2".to_owned(), BuckTypes::Integer(2), None)]));
+        let len = fs::metadata(&path).unwrap().len();
+
+        // the records after it are not thrown away as a torn tail
+        let result = AppendOnlyLog::new(&path).replay();
+        assert!(matches!(result, Err(StorageError::Corrupted(_))));
+        assert_eq!(fs::metadata(&path).unwrap().len(), len);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_missing_log_starts_empty() {
+        let path = aof_path("missing");
+
+        let db = BuckDB::open(&path).unwrap();
+        assert!(db.data.is_empty());
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_updates_and_removes_are_replayed() {
+        let path = aof_path("updates");
+
+        let mut db = BuckDB::open(&path).unwrap();
+        db.insert("a".to_owned(), BuckTypes::Integer(1)).unwrap();
+        db.insert("b".to_owned(), BuckTypes::Integer(1)).unwrap();
+        db.commit().unwrap();
+
+        // committed keys are changed in place, not staged
+        db.update("a", BuckTypes::Integer(2)).unwrap();
+        db.commit().unwrap();
+        db.remove("b").unwrap();
+        db.commit().unwrap();
+        db.incr_by("a", 1).unwrap();
+        db.commit().unwrap();
+
+        let reopened = BuckDB::open(&path).unwrap();
+        assert_eq!(reopened.data.get("a"), Some(&BuckTypes::Integer(3)));
+        assert_eq!(reopened.data.get("b"), None);
+
+        // a rolled back change is not logged
+        db.remove("a").unwrap();
+        db.abort().unwrap();
+
+        let reopened = BuckDB::open(&path).unwrap();
+        assert_eq!(reopened.data.get("a"), Some(&BuckTypes::Integer(3)));
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_flush_and_move_are_replayed() {
+        let path = aof_path("flush");
+
+        let mut dbs = databases::new(2);
+        dbs[0] = BuckDB::open(&path).unwrap();
+        dbs[0].insert("a".to_owned(), BuckTypes::Integer(1)).unwrap();
+        dbs[0].insert("b".to_owned(), BuckTypes::Integer(2)).unwrap();
+        dbs[0].commit().unwrap();
+
+        assert_eq!(databases::move_key(&mut dbs, "a", 0, 1), Ok(true));
+        dbs[0].commit().unwrap();
+        assert_eq!(BuckDB::open(&path).unwrap().data.keys().collect::<Vec<_>>(), vec!["b"]);
+
+        dbs[0].insert("c".to_owned(), BuckTypes::Integer(3)).unwrap();
+        dbs[0].commit().unwrap();
+        dbs[0].flush_db().unwrap();
+        dbs[0].commit().unwrap();
+        assert!(BuckDB::open(&path).unwrap().data.is_empty());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_delete_entry() {
+        let path = aof_path("delete");
+        let aof = AppendOnlyLog::new(&path);
+
+        aof.append(&[
+            LogEntry::Set("k1".to_owned(), BuckTypes::Integer(1), None),
+            LogEntry::Set("k2".to_owned(), BuckTypes::Integer(2), None),
+        ])
+        .unwrap();
+        aof.append(&[LogEntry::Delete("k1".to_owned()), LogEntry::Delete("k3".to_owned())]).unwrap();
+
+        let (data, _) = aof.replay().unwrap();
+        assert_eq!(data.keys().collect::<Vec<_>>(), vec!["k2"]);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_deadlines_survive_restart() {
+        let path = aof_path("deadlines");
+        let clock = ManualClock::new(1_000_000);
+
+        let mut db = BuckDB::open(&path).unwrap();
+        db.clock = Arc::new(clock.clone());
+        for key in ["session", "token", "gone"] {
+            db.insert(key.to_owned(), BuckTypes::Integer(1)).unwrap();
+        }
+        db.commit().unwrap();
+
+        db.expire("session", 5_000).unwrap();
+        db.expire("token", 5_000).unwrap();
+        db.commit().unwrap();
+
+        db.persist("token").unwrap();
+        db.expire("gone", 0).unwrap();
+        db.commit().unwrap();
+
+        let mut reopened = BuckDB::open(&path).unwrap();
+        reopened.clock = Arc::new(clock.clone());
+        assert_eq!(reopened.expires, HashMap::from([("session".to_owned(), 1_005_000)]));
+        assert_eq!(reopened.pttl("session"), 5_000);
+        assert_eq!(reopened.pttl("token"), -1);
+        assert_eq!(reopened.pttl("gone"), -2);
+
+        // a deadline that passed while the database was closed still applies
+        clock.advance(5_000);
+        assert!(reopened.get("session").is_err());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_swapped_data_survives_restart() {
+        let path = aof_path("swapdb");
+
+        let mut dbs = databases::new(2);
+        dbs[0] = BuckDB::open(&path).unwrap();
+        dbs[0].insert("a".to_owned(), BuckTypes::Integer(0)).unwrap();
+        dbs[0].commit().unwrap();
+        dbs[1].insert("b".to_owned(), BuckTypes::Integer(1)).unwrap();
+        dbs[1].expire("b", 60_000).unwrap();
+        dbs[1].commit().unwrap();
+        // not committed, so not logged
+        dbs[1].insert("c".to_owned(), BuckTypes::Integer(2)).unwrap();
+
+        databases::swap(&mut dbs, 0, 1).unwrap();
+        assert_eq!(dbs[0].aof.as_ref().map(|aof| aof.path()), Some(path.as_path()));
+        assert!(dbs[1].aof.is_none());
+
+        let reopened = BuckDB::open(&path).unwrap();
+        assert_eq!(reopened.data.keys().collect::<Vec<_>>(), vec!["b"]);
+        assert!(reopened.expires.contains_key("b"));
+
+        // later commits land in the same log
+        dbs[0].commit().unwrap();
+        let reopened = BuckDB::open(&path).unwrap();
+        assert_eq!(reopened.data.keys().collect::<Vec<_>>(), vec!["b", "c"]);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_failed_swap_keeps_both_logs() {
+        let path = aof_path("swapdb-failed");
+        let dir = std::env::temp_dir().join(format!("buck-aof-swapdb-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let mut dbs = databases::new(2);
+        dbs[0] = BuckDB::open(&path).unwrap();
+        dbs[1] = BuckDB::open(dir.join("second.aof")).unwrap();
+        dbs[0].insert("a".to_owned(), BuckTypes::Integer(0)).unwrap();
+        dbs[0].commit().unwrap();
+        dbs[1].insert("b".to_owned(), BuckTypes::Integer(1)).unwrap();
+        dbs[1].commit().unwrap();
+
+        // the second log can not be rewritten, so the first is put back
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(databases::swap(&mut dbs, 0, 1).is_err());
+        assert_eq!(dbs[0].data.keys().collect::<Vec<_>>(), vec!["a"]);
+
+        let reopened = BuckDB::open(&path).unwrap();
+        assert_eq!(reopened.data.keys().collect::<Vec<_>>(), vec!["a"]);
+
+        fs::remove_file(&path).unwrap();
+    }
+}
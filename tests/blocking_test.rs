@@ -0,0 +1,288 @@
+mod common;
+
+#[cfg(test)]
+mod blocking_tests {
+    use std::io::{Read, Write};
+    use std::net::{SocketAddr, TcpListener, TcpStream};
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use buck::blocking::BlockedPop;
+    use buck::engine::BuckDB;
+    use buck::errors::{BuckEngineError, BuckError};
+    use buck::parser::parse::parse_query;
+    use buck::parser::query::BuckQuery;
+    use buck::reply::BuckReply;
+    use buck::server::serve;
+    use buck::session::BuckSession;
+    use buck::types::list::ListSide;
+    use buck::types::types::BuckTypes;
+
+    use crate::common::run_session;
+
+    fn pop(keys: &[&str], side: ListSide) -> BlockedPop {
+        BlockedPop {
+            keys: keys.iter().map(|key| key.to_string()).collect(),
+            side,
+            target: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_blocking_queries() {
+        assert_eq!(
+            parse_query("BLPOP a b 0"),
+            Ok(BuckQuery::BLPop(vec!["a".to_owned(), "b".to_owned()], None))
+        );
+        assert_eq!(
+            parse_query("BRPOP a 1.5"),
+            Ok(BuckQuery::BRPop(vec!["a".to_owned()], Some(Duration::from_millis(1500))))
+        );
+        assert_eq!(
+            parse_query("LMOVE a b left RIGHT"),
+            Ok(BuckQuery::LMove("a".to_owned(), "b".to_owned(), ListSide::Left, ListSide::Right))
+        );
+        assert_eq!(
+            parse_query("BLMOVE a b RIGHT LEFT 2"),
+            Ok(BuckQuery::BLMove(
+                "a".to_owned(),
+                "b".to_owned(),
+                ListSide::Right,
+                ListSide::Left,
+                Some(Duration::from_secs(2))
+            ))
+        );
+
+        for input in ["BLPOP", "BLPOP a", "BLPOP a -1", "BLPOP a x", "LMOVE a b UP LEFT", "BLMOVE a b LEFT LEFT"] {
+            assert!(parse_query(input).is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_blocking_commands_try_once() {
+        let mut db = BuckDB::new();
+        let mut session = BuckSession::new();
+
+        // nothing to pop, the caller decides whether to wait
+        assert_eq!(run_session(&mut db, &mut session, "BLPOP a b 0"), Ok(BuckReply::Nil));
+
+        run_session(&mut db, &mut session, "RPUSH b 1 2").unwrap();
+        assert_eq!(
+            run_session(&mut db, &mut session, "BLPOP a b 0"),
+            Ok(BuckReply::Array(vec![BuckReply::Bulk("b".to_owned()), BuckReply::Integer(1)]))
+        );
+        assert_eq!(
+            run_session(&mut db, &mut session, "BRPOP a b 0"),
+            Ok(BuckReply::Array(vec![BuckReply::Bulk("b".to_owned()), BuckReply::Integer(2)]))
+        );
+    }
+
+    #[test]
+    fn test_lmove() {
+        let mut db = BuckDB::new();
+        let mut session = BuckSession::new();
+        run_session(&mut db, &mut session, "RPUSH jobs 1 2 3").unwrap();
+
+        assert_eq!(
+            run_session(&mut db, &mut session, "LMOVE jobs working LEFT RIGHT"),
+            Ok(BuckReply::Integer(1))
+        );
+        assert_eq!(db.l_range("working", 0, -1), Ok(vec![BuckTypes::Integer(1)]));
+
+        // the same list rotates
+        run_session(&mut db, &mut session, "LMOVE jobs jobs RIGHT LEFT").unwrap();
+        assert_eq!(
+            db.l_range("jobs", 0, -1),
+            Ok(vec![BuckTypes::Integer(3), BuckTypes::Integer(2)])
+        );
+
+        assert_eq!(run_session(&mut db, &mut session, "LMOVE missing jobs LEFT LEFT"), Ok(BuckReply::Nil));
+
+        // nothing is popped if the destination is not a list
+        run_session(&mut db, &mut session, "INSERT str \"text\"").unwrap();
+        assert_eq!(
+            run_session(&mut db, &mut session, "LMOVE jobs str LEFT LEFT"),
+            Err(BuckError::Engine(BuckEngineError::TypeNotSupported("str".to_owned())))
+        );
+        assert_eq!(db.l_range("jobs", 0, -1).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_waiters_are_served_in_order() {
+        let mut db = BuckDB::new();
+        let mut session = BuckSession::new();
+
+        let first = db.block(pop(&["a", "q"], ListSide::Left));
+        let second = db.block(pop(&["q"], ListSide::Left));
+        let third = db.block(pop(&["q"], ListSide::Right));
+        assert_eq!(db.blocked.waiting_on("q"), 3);
+
+        run_session(&mut db, &mut session, "RPUSH q 1 2").unwrap();
+
+        assert_eq!(
+            db.blocked.take_served(first),
+            Some(Ok(("q".to_owned(), BuckTypes::Integer(1))))
+        );
+        assert_eq!(
+            db.blocked.take_served(second),
+            Some(Ok(("q".to_owned(), BuckTypes::Integer(2))))
+        );
+
+        // a served client is not waiting on its other keys anymore
+        assert_eq!(db.blocked.waiting_on("a"), 0);
+        assert!(db.blocked.is_blocked(third));
+        assert!(db.get("q").is_err());
+
+        run_session(&mut db, &mut session, "LPUSH q 3").unwrap();
+        assert_eq!(
+            db.blocked.take_served(third),
+            Some(Ok(("q".to_owned(), BuckTypes::Integer(3))))
+        );
+        assert_eq!(db.blocked.waiting_on("q"), 0);
+    }
+
+    #[test]
+    fn test_unblocked_client_is_skipped() {
+        let mut db = BuckDB::new();
+        let mut session = BuckSession::new();
+
+        let gone = db.block(pop(&["q"], ListSide::Left));
+        let waiting = db.block(pop(&["q"], ListSide::Left));
+        db.blocked.unblock(gone);
+
+        run_session(&mut db, &mut session, "RPUSH q 1").unwrap();
+
+        assert_eq!(db.blocked.take_served(gone), None);
+        assert!(db.blocked.take_served(waiting).is_some());
+    }
+
+    #[test]
+    fn test_blmove_waiter_moves_element() {
+        let mut db = BuckDB::new();
+        let mut session = BuckSession::new();
+
+        let id = db.block(BlockedPop {
+            keys: vec!["jobs".to_owned()],
+            side: ListSide::Right,
+            target: Some(("working".to_owned(), ListSide::Left)),
+        });
+
+        run_session(&mut db, &mut session, "RPUSH jobs 1 2").unwrap();
+
+        assert_eq!(
+            db.blocked.take_served(id),
+            Some(Ok(("jobs".to_owned(), BuckTypes::Integer(2))))
+        );
+        assert_eq!(db.l_range("jobs", 0, -1), Ok(vec![BuckTypes::Integer(1)]));
+        assert_eq!(db.l_range("working", 0, -1), Ok(vec![BuckTypes::Integer(2)]));
+    }
+
+    #[test]
+    fn test_waiters_ignore_keys_of_other_types() {
+        let mut db = BuckDB::new();
+        let mut session = BuckSession::new();
+
+        let id = db.block(pop(&["q"], ListSide::Left));
+        run_session(&mut db, &mut session, "INSERT q 1").unwrap();
+
+        assert!(db.blocked.is_blocked(id));
+        assert_eq!(db.get("q"), Ok(&BuckTypes::Integer(1)));
+    }
+
+    fn start_server() -> (SocketAddr, Arc<Mutex<Vec<BuckDB>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let db = Arc::new(Mutex::new(vec![BuckDB::new()]));
+
+        let shared = Arc::clone(&db);
+        thread::spawn(move || serve(listener, shared));
+
+        (addr, db)
+    }
+
+    fn send(stream: &mut TcpStream, request: &str) {
+        stream.write_all(format!("{}\r\n", request).as_bytes()).unwrap();
+    }
+
+    fn receive(stream: &mut TcpStream, expected: &str) {
+        let mut reply = vec![0u8; expected.len()];
+        stream.read_exact(&mut reply).unwrap();
+
+        assert_eq!(String::from_utf8_lossy(&reply), expected);
+    }
+
+    fn wait_for_waiters(db: &Mutex<Vec<BuckDB>>, key: &str, n: usize) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+
+        while db.lock().unwrap()[0].blocked.waiting_on(key) != n {
+            assert!(Instant::now() < deadline, "clients never blocked on {}", key);
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn test_server_wakes_waiters_in_order() {
+        let (addr, db) = start_server();
+
+        let mut first = TcpStream::connect(addr).unwrap();
+        send(&mut first, "BLPOP q 5");
+        wait_for_waiters(&db, "q", 1);
+
+        let mut second = TcpStream::connect(addr).unwrap();
+        send(&mut second, "BLPOP other q 5");
+        wait_for_waiters(&db, "q", 2);
+
+        let mut producer = TcpStream::connect(addr).unwrap();
+        send(&mut producer, "RPUSH q 1 2 3");
+        receive(&mut producer, ":3\r\n");
+
+        receive(&mut first, "*2\r\n$1\r\nq\r\n:1\r\n");
+        receive(&mut second, "*2\r\n$1\r\nq\r\n:2\r\n");
+
+        // the element nobody waited for stays in the list
+        send(&mut producer, "LRANGE q 0 -1");
+        receive(&mut producer, "*1\r\n:3\r\n");
+    }
+
+    #[test]
+    fn test_server_blmove_and_timeout() {
+        let (addr, db) = start_server();
+
+        let mut worker = TcpStream::connect(addr).unwrap();
+        let started = Instant::now();
+        send(&mut worker, "BRPOP q 0.1");
+        receive(&mut worker, "$-1\r\n");
+        assert!(started.elapsed() >= Duration::from_millis(100));
+        assert_eq!(db.lock().unwrap()[0].blocked.waiting_on("q"), 0);
+
+        send(&mut worker, "BLMOVE jobs working RIGHT LEFT 5");
+        wait_for_waiters(&db, "jobs", 1);
+
+        let mut producer = TcpStream::connect(addr).unwrap();
+        send(&mut producer, "LPUSH jobs 7");
+        receive(&mut producer, ":1\r\n");
+        receive(&mut worker, ":7\r\n");
+
+        send(&mut producer, "LRANGE working 0 -1");
+        receive(&mut producer, "*1\r\n:7\r\n");
+    }
+
+    #[test]
+    fn test_server_forgets_disconnected_waiter() {
+        let (addr, db) = start_server();
+
+        let mut gone = TcpStream::connect(addr).unwrap();
+        send(&mut gone, "BLPOP q 0");
+        wait_for_waiters(&db, "q", 1);
+        drop(gone);
+        wait_for_waiters(&db, "q", 0);
+
+        let mut producer = TcpStream::connect(addr).unwrap();
+        send(&mut producer, "RPUSH q 1");
+        receive(&mut producer, ":1\r\n");
+
+        send(&mut producer, "LPOP q");
+        receive(&mut producer, ":1\r\n");
+    }
+}
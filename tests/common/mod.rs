@@ -0,0 +1,31 @@
+//! Helpers shared by the integration tests.
+//!
+//! Every test file is its own crate and uses only some of them.
+#![allow(dead_code)]
+
+use std::sync::Arc;
+
+use buck::clock::ManualClock;
+use buck::engine::BuckDB;
+use buck::errors::BuckError;
+use buck::parser::parse::parse_query;
+use buck::reply::BuckReply;
+use buck::session::BuckSession;
+
+/// Parse `input` and run it against `db`.
+pub fn run(db: &mut BuckDB, input: &str) -> Result<BuckReply, BuckError> {
+    parse_query(input)?.execute(input, db)
+}
+
+/// Parse `input` and run it against `db` as a client of `session`.
+pub fn run_session(db: &mut BuckDB, session: &mut BuckSession, input: &str) -> Result<BuckReply, BuckError> {
+    session.execute(parse_query(input)?, input, db)
+}
+
+/// An empty database, and the clock it reads time from.
+pub fn db_with_clock() -> (BuckDB, ManualClock) {
+    let clock = ManualClock::new(1_000_000);
+    let db = BuckDB::with_clock(Arc::new(clock.clone()));
+
+    (db, clock)
+}
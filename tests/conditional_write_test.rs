@@ -0,0 +1,158 @@
+mod common;
+
+#[cfg(test)]
+mod conditional_write_tests {
+    use buck::clock::Expiry;
+    use buck::engine::BuckDB;
+    use buck::errors::{BuckEngineError, BuckError};
+    use buck::parser::parse::parse_query;
+    use buck::parser::query::BuckQuery;
+    use buck::reply::BuckReply;
+    use buck::types::string::SetOptions;
+    use buck::types::types::BuckTypes;
+
+    use crate::common::{db_with_clock, run};
+
+    #[test]
+    fn test_parse_set() {
+        assert_eq!(
+            parse_query("SET lock \"worker 1\" nx px 30000"),
+            Ok(BuckQuery::Set(
+                "lock".to_owned(),
+                BuckTypes::String("worker 1".to_owned()),
+                SetOptions { nx: true, expiry: Some(Expiry::After(30_000)), ..Default::default() }
+            ))
+        );
+        assert_eq!(
+            parse_query("SET key 1 XX GET KEEPTTL"),
+            Ok(BuckQuery::Set(
+                "key".to_owned(),
+                BuckTypes::Integer(1),
+                SetOptions { xx: true, get: true, keep_ttl: true, ..Default::default() }
+            ))
+        );
+        // `INSERT` still takes the rest of the line as its value
+        assert_eq!(
+            parse_query("INSERT key hello world"),
+            Ok(BuckQuery::Insert("key".to_owned(), BuckTypes::Unknown("hello world".to_owned())))
+        );
+
+        for input in [
+            "SET", "SET key", "SET \"\" 1", "SET key 1 NX XX", "SET key 1 EX", "SET key 1 EX 0",
+            "SET key 1 EX 10 PX 10", "SET key 1 EX 10 KEEPTTL", "SET key 1 SOON",
+            "SET key hello world",
+        ] {
+            assert!(parse_query(input).is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_set_nx_and_xx() {
+        let mut db = BuckDB::new();
+
+        assert_eq!(run(&mut db, "SET lock a XX"), Ok(BuckReply::Nil));
+        assert!(db.get("lock").is_err());
+
+        assert_eq!(run(&mut db, "SET lock a NX"), Ok(BuckReply::ok()));
+        assert_eq!(run(&mut db, "SET lock b NX"), Ok(BuckReply::Nil));
+        assert_eq!(db.get("lock"), Ok(&BuckTypes::Unknown("a".to_owned())));
+
+        assert_eq!(run(&mut db, "SET lock b XX"), Ok(BuckReply::ok()));
+        assert_eq!(db.get("lock"), Ok(&BuckTypes::Unknown("b".to_owned())));
+
+        // a committed key counts as existing too
+        db.commit().unwrap();
+        assert_eq!(run(&mut db, "SET lock c NX"), Ok(BuckReply::Nil));
+        assert!(db.uncommitted_data.is_empty());
+    }
+
+    #[test]
+    fn test_set_get() {
+        let mut db = BuckDB::new();
+
+        assert_eq!(run(&mut db, "SET key 1 GET"), Ok(BuckReply::Nil));
+        assert_eq!(run(&mut db, "SET key 2 GET"), Ok(BuckReply::Integer(1)));
+        // the old value is returned even if nothing was set
+        assert_eq!(run(&mut db, "SET key 3 NX GET"), Ok(BuckReply::Integer(2)));
+        assert_eq!(db.get("key"), Ok(&BuckTypes::Integer(2)));
+
+        run(&mut db, "RPUSH list 1").unwrap();
+        assert_eq!(
+            run(&mut db, "SET list 1 GET"),
+            Err(BuckError::Engine(BuckEngineError::TypeNotSupported("list".to_owned())))
+        );
+        // without `GET`, any value is overwritten
+        assert_eq!(run(&mut db, "SET list 1"), Ok(BuckReply::ok()));
+    }
+
+    #[test]
+    fn test_set_timeouts() {
+        let (mut db, clock) = db_with_clock();
+
+        run(&mut db, "SET session abc EX 10").unwrap();
+        assert_eq!(db.pttl("session"), 10_000);
+
+        // a plain `SET` drops the timeout, `KEEPTTL` keeps it
+        run(&mut db, "SET session abc PX 500").unwrap();
+        run(&mut db, "SET session def KEEPTTL").unwrap();
+        assert_eq!(db.pttl("session"), 500);
+        run(&mut db, "SET session ghi").unwrap();
+        assert_eq!(db.pttl("session"), -1);
+
+        run(&mut db, "SET session abc PXAT 1000300").unwrap();
+        clock.advance(300);
+        assert!(db.get("session").is_err());
+
+        // a lock that expired can be taken again
+        run(&mut db, "SET lock a NX PX 100").unwrap();
+        assert_eq!(run(&mut db, "SET lock b NX PX 100"), Ok(BuckReply::Nil));
+        clock.advance(100);
+        assert_eq!(run(&mut db, "SET lock b NX PX 100"), Ok(BuckReply::ok()));
+    }
+
+    #[test]
+    fn test_compare_and_swap() {
+        let mut db = BuckDB::new();
+
+        // create if absent
+        assert_eq!(db.compare_and_swap("job", None, BuckTypes::Integer(1)), Ok(true));
+        assert_eq!(db.compare_and_swap("job", None, BuckTypes::Integer(2)), Ok(false));
+        assert_eq!(db.get("job"), Ok(&BuckTypes::Integer(1)));
+
+        assert_eq!(
+            db.compare_and_swap("job", Some(&BuckTypes::Integer(2)), BuckTypes::Integer(3)),
+            Ok(false)
+        );
+        // values of a different type are never equal
+        assert_eq!(
+            db.compare_and_swap("job", Some(&BuckTypes::Float(1.0)), BuckTypes::Integer(3)),
+            Ok(false)
+        );
+        assert_eq!(
+            db.compare_and_swap("job", Some(&BuckTypes::Integer(1)), BuckTypes::Integer(3)),
+            Ok(true)
+        );
+        assert_eq!(db.get("job"), Ok(&BuckTypes::Integer(3)));
+
+        // the swap is staged like any other write
+        db.commit().unwrap();
+        db.compare_and_swap("job", Some(&BuckTypes::Integer(3)), BuckTypes::Integer(4)).unwrap();
+        db.abort().unwrap();
+        assert_eq!(db.get("job"), Ok(&BuckTypes::Integer(3)));
+    }
+
+    #[test]
+    fn test_compare_and_swap_keeps_the_deadline() {
+        let (mut db, clock) = db_with_clock();
+
+        db.compare_and_swap("lock", None, BuckTypes::String("a".to_owned())).unwrap();
+        db.expire("lock", 1_000).unwrap();
+
+        let owner = BuckTypes::String("a".to_owned());
+        assert_eq!(db.compare_and_swap("lock", Some(&owner), BuckTypes::String("b".to_owned())), Ok(true));
+        assert_eq!(db.pttl("lock"), 1_000);
+
+        clock.advance(1_000);
+        assert_eq!(db.compare_and_swap("lock", None, BuckTypes::String("c".to_owned())), Ok(true));
+    }
+}
@@ -0,0 +1,123 @@
+mod common;
+
+#[cfg(test)]
+mod counter_tests {
+    use buck::engine::BuckDB;
+    use buck::errors::BuckError;
+    use buck::parser::parse::parse_query;
+    use buck::parser::query::BuckQuery;
+    use buck::reply::BuckReply;
+    use buck::types::errors::BuckTypeError;
+    use buck::types::types::BuckTypes;
+
+    use crate::common::run;
+
+    #[test]
+    fn test_parse_counter_queries() {
+        assert_eq!(parse_query("INCR hits"), Ok(BuckQuery::IncrBy("hits".to_owned(), 1)));
+        assert_eq!(parse_query("decr hits"), Ok(BuckQuery::DecrBy("hits".to_owned(), 1)));
+        assert_eq!(parse_query("INCRBY hits -5"), Ok(BuckQuery::IncrBy("hits".to_owned(), -5)));
+        assert_eq!(parse_query("DECRBY hits 5"), Ok(BuckQuery::DecrBy("hits".to_owned(), 5)));
+        assert_eq!(
+            parse_query("INCRBYFLOAT price 0.25"),
+            Ok(BuckQuery::IncrByFloat("price".to_owned(), 0.25))
+        );
+
+        for input in [
+            "INCR", "INCR a b", "INCR ''", "INCRBY a", "INCRBY a 1.5", "INCRBY a 1 2",
+            "DECRBY a x", "INCRBYFLOAT a", "INCRBYFLOAT a x",
+        ] {
+            assert!(parse_query(input).is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_incr_and_decr() {
+        let mut db = BuckDB::new();
+        run(&mut db, "INSERT hits 10").unwrap();
+
+        assert_eq!(run(&mut db, "INCR hits"), Ok(BuckReply::Integer(11)));
+        assert_eq!(run(&mut db, "INCRBY hits 9"), Ok(BuckReply::Integer(20)));
+        assert_eq!(run(&mut db, "DECR hits"), Ok(BuckReply::Integer(19)));
+        assert_eq!(run(&mut db, "DECRBY hits -1"), Ok(BuckReply::Integer(20)));
+        assert_eq!(db.get("hits"), Ok(&BuckTypes::Integer(20)));
+    }
+
+    #[test]
+    fn test_missing_key_starts_at_zero() {
+        let mut db = BuckDB::new();
+
+        assert_eq!(run(&mut db, "DECR fresh"), Ok(BuckReply::Integer(-1)));
+        assert_eq!(run(&mut db, "INCRBYFLOAT other 1.5"), Ok(BuckReply::Float(1.5)));
+
+        // the new keys only exist inside the current transaction
+        assert!(db.data.is_empty());
+        db.abort().unwrap();
+        assert!(db.get("fresh").is_err());
+
+        run(&mut db, "INCR fresh").unwrap();
+        db.commit().unwrap();
+        assert_eq!(db.get("fresh"), Ok(&BuckTypes::Integer(1)));
+    }
+
+    #[test]
+    fn test_overflow_is_an_error() {
+        let mut db = BuckDB::new();
+        run(&mut db, "INSERT max 9223372036854775807").unwrap();
+        run(&mut db, "INSERT min -9223372036854775808").unwrap();
+        db.commit().unwrap();
+
+        let overflow = Err(BuckError::Type(BuckTypeError::Overflow));
+        assert_eq!(run(&mut db, "INCR max"), overflow);
+        assert_eq!(run(&mut db, "DECR min"), overflow);
+        assert_eq!(run(&mut db, "DECRBY zero -9223372036854775808"), overflow);
+        assert_eq!(run(&mut db, "INCRBYFLOAT max inf"), overflow);
+
+        // nothing was changed or created
+        assert!(db.uncommitted_data.is_empty());
+        assert_eq!(db.get("max"), Ok(&BuckTypes::Integer(i64::MAX)));
+        assert!(db.get("zero").is_err());
+    }
+
+    #[test]
+    fn test_incr_by_float() {
+        let mut db = BuckDB::new();
+        run(&mut db, "INSERT price 10").unwrap();
+
+        assert_eq!(run(&mut db, "INCRBYFLOAT price 0.5"), Ok(BuckReply::Float(10.5)));
+        assert_eq!(run(&mut db, "INCRBYFLOAT price -10.5"), Ok(BuckReply::Float(0.0)));
+        assert_eq!(db.get("price"), Ok(&BuckTypes::Float(0.0)));
+
+        // a float is not an integer anymore
+        assert_eq!(
+            run(&mut db, "INCR price"),
+            Err(BuckError::Type(BuckTypeError::NotAnInteger("price".to_owned())))
+        );
+    }
+
+    #[test]
+    fn test_counters_check_the_type() {
+        let mut db = BuckDB::new();
+        run(&mut db, "INSERT str \"10\"").unwrap();
+        run(&mut db, "RPUSH list 1").unwrap();
+
+        assert_eq!(
+            run(&mut db, "INCR str"),
+            Err(BuckError::Type(BuckTypeError::NotAnInteger("str".to_owned())))
+        );
+        assert_eq!(
+            run(&mut db, "INCRBYFLOAT list 1"),
+            Err(BuckError::Type(BuckTypeError::NotAFloat("list".to_owned())))
+        );
+    }
+
+    #[test]
+    fn test_counters_keep_the_deadline() {
+        let mut db = BuckDB::new();
+        run(&mut db, "INSERT hits 1").unwrap();
+        run(&mut db, "PEXPIRE hits 100000").unwrap();
+
+        run(&mut db, "INCR hits").unwrap();
+        assert!(db.pttl("hits") > 0);
+    }
+}
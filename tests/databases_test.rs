@@ -0,0 +1,229 @@
+#[cfg(test)]
+mod databases_tests {
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    use buck::clock::ManualClock;
+    use buck::databases;
+    use buck::engine::BuckDB;
+    use buck::errors::{BuckEngineError, BuckError};
+    use buck::parser::parse::parse_query;
+    use buck::parser::query::BuckQuery;
+    use buck::reply::BuckReply;
+    use buck::server::serve;
+    use buck::session::BuckSession;
+    use buck::types::types::BuckTypes;
+
+    fn run(databases: &mut [BuckDB], session: &mut BuckSession, input: &str) -> Result<BuckReply, BuckError> {
+        session.execute_in(parse_query(input)?, input, databases)
+    }
+
+    fn int(value: i64) -> BuckReply {
+        BuckReply::Integer(value)
+    }
+
+    #[test]
+    fn test_parse_database_queries() {
+        assert_eq!(parse_query("SELECT 3"), Ok(BuckQuery::Select(3)));
+        assert_eq!(parse_query("MOVE key 1"), Ok(BuckQuery::Move("key".to_owned(), 1)));
+        assert_eq!(parse_query("SWAPDB 0 1"), Ok(BuckQuery::SwapDb(0, 1)));
+        assert_eq!(parse_query("FLUSHALL"), Ok(BuckQuery::FlushAll));
+
+        for input in [
+            "SELECT", "SELECT -1", "SELECT a", "SELECT 1 2", "MOVE key", "MOVE \"\" 1", "MOVE key -1",
+            "SWAPDB 0", "SWAPDB 0 x", "FLUSHALL ASYNC",
+        ] {
+            assert!(parse_query(input).is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_select_isolates_keyspaces() {
+        let mut dbs = databases::new(3);
+        let mut session = BuckSession::new();
+
+        run(&mut dbs, &mut session, "MSET a 1 b 2").unwrap();
+        assert_eq!(run(&mut dbs, &mut session, "SELECT 1"), Ok(BuckReply::ok()));
+        assert_eq!(session.selected(), 1);
+        assert_eq!(run(&mut dbs, &mut session, "GET a"), Ok(BuckReply::Nil));
+        assert_eq!(run(&mut dbs, &mut session, "DBSIZE"), Ok(int(0)));
+
+        run(&mut dbs, &mut session, "INSERT a 10").unwrap();
+        assert_eq!(
+            run(&mut dbs, &mut session, "SELECT 3"),
+            Err(BuckError::Engine(BuckEngineError::InvalidDatabase(3)))
+        );
+        assert_eq!(session.selected(), 1);
+
+        // every write is committed in the database it went to
+        assert_eq!(dbs[0].data.len(), 2);
+        assert_eq!(dbs[1].data.get("a"), Some(&BuckTypes::Integer(10)));
+
+        // `FLUSHDB` only empties the selected database
+        run(&mut dbs, &mut session, "SELECT 2").unwrap();
+        run(&mut dbs, &mut session, "INSERT c 3").unwrap();
+        run(&mut dbs, &mut session, "FLUSHDB").unwrap();
+        assert_eq!(dbs[1].db_size(), Ok(1));
+        assert_eq!(dbs[2].db_size(), Ok(0));
+    }
+
+    #[test]
+    fn test_move() {
+        let clock = ManualClock::new(1_000_000);
+        let mut dbs: Vec<BuckDB> = (0..2).map(|_| BuckDB::with_clock(Arc::new(clock.clone()))).collect();
+        let mut session = BuckSession::new();
+
+        run(&mut dbs, &mut session, "MSET a 1 b 2").unwrap();
+        run(&mut dbs, &mut session, "PEXPIRE a 100").unwrap();
+        dbs[1].insert("b".to_owned(), BuckTypes::Integer(20)).unwrap();
+
+        assert_eq!(run(&mut dbs, &mut session, "MOVE a 1"), Ok(int(1)));
+        assert!(dbs[0].get("a").is_err());
+        assert_eq!(dbs[1].get("a"), Ok(&BuckTypes::Integer(1)));
+        assert_eq!(dbs[1].pttl("a"), 100);
+
+        // nothing is moved over an existing key, from a missing one or in place
+        assert_eq!(run(&mut dbs, &mut session, "MOVE b 1"), Ok(int(0)));
+        assert_eq!(run(&mut dbs, &mut session, "MOVE missing 1"), Ok(int(0)));
+        assert_eq!(run(&mut dbs, &mut session, "MOVE b 0"), Ok(int(0)));
+        assert_eq!(
+            run(&mut dbs, &mut session, "MOVE b 2"),
+            Err(BuckError::Engine(BuckEngineError::InvalidDatabase(2)))
+        );
+        assert_eq!(dbs[0].get("b"), Ok(&BuckTypes::Integer(2)));
+
+        // the move is committed in both databases
+        assert!(!dbs[0].data.contains_key("a"));
+        assert_eq!(dbs[1].data.get("a"), Some(&BuckTypes::Integer(1)));
+    }
+
+    #[test]
+    fn test_transaction_commits_the_databases_it_wrote_to() {
+        let mut dbs = databases::new(3);
+        let mut session = BuckSession::new();
+        dbs[2].insert("other".to_owned(), BuckTypes::Integer(1)).unwrap();
+
+        run(&mut dbs, &mut session, "INSERT a 1").unwrap();
+        run(&mut dbs, &mut session, "MULTI").unwrap();
+        run(&mut dbs, &mut session, "MOVE a 1").unwrap();
+        run(&mut dbs, &mut session, "SELECT 1").unwrap();
+        run(&mut dbs, &mut session, "INCR a").unwrap();
+
+        assert_eq!(
+            run(&mut dbs, &mut session, "EXEC"),
+            Ok(BuckReply::Array(vec![int(1), BuckReply::ok(), int(2)]))
+        );
+        assert_eq!(session.selected(), 1);
+        assert!(dbs[0].get("a").is_err());
+        assert_eq!(dbs[1].data.get("a"), Some(&BuckTypes::Integer(2)));
+
+        // a database the transaction never touched keeps its staged writes staged
+        assert!(dbs[2].data.is_empty());
+        assert_eq!(dbs[2].uncommitted_data.len(), 1);
+    }
+
+    #[test]
+    fn test_swapdb() {
+        let mut dbs = databases::new(2);
+        let mut watcher = BuckSession::new();
+        let mut other = BuckSession::new();
+
+        run(&mut dbs, &mut other, "INSERT a 0").unwrap();
+        run(&mut dbs, &mut other, "SELECT 1").unwrap();
+        run(&mut dbs, &mut other, "INSERT a 1").unwrap();
+        dbs[1].memory.maxmemory = Some(1 << 30);
+
+        run(&mut dbs, &mut watcher, "WATCH a").unwrap();
+        assert_eq!(run(&mut dbs, &mut other, "SWAPDB 0 1"), Ok(BuckReply::ok()));
+
+        // clients stay on their index and see the other data
+        assert_eq!(run(&mut dbs, &mut watcher, "GET a"), Ok(int(1)));
+        assert_eq!(run(&mut dbs, &mut other, "GET a"), Ok(int(0)));
+
+        // the configuration stays with the index
+        assert_eq!(dbs[0].memory.maxmemory, None);
+        assert_eq!(dbs[1].memory.maxmemory, Some(1 << 30));
+
+        // the swap changed what the watched key holds
+        run(&mut dbs, &mut watcher, "MULTI").unwrap();
+        run(&mut dbs, &mut watcher, "INSERT b 1").unwrap();
+        assert_eq!(
+            run(&mut dbs, &mut watcher, "EXEC"),
+            Err(BuckError::Engine(BuckEngineError::WatchConflict("a".to_owned())))
+        );
+        assert_eq!(run(&mut dbs, &mut other, "SWAPDB 0 0"), Ok(BuckReply::ok()));
+        assert!(run(&mut dbs, &mut other, "SWAPDB 0 2").is_err());
+    }
+
+    #[test]
+    fn test_flushall() {
+        let mut dbs = databases::new(3);
+        let mut session = BuckSession::new();
+
+        for index in 0..3 {
+            run(&mut dbs, &mut session, &format!("SELECT {}", index)).unwrap();
+            run(&mut dbs, &mut session, "MSET a 1 b 2").unwrap();
+        }
+
+        assert_eq!(run(&mut dbs, &mut session, "FLUSHALL"), Ok(BuckReply::ok()));
+        assert!(dbs.iter().all(|db| db.db_size() == Ok(0)));
+
+        // the removals are committed in every database
+        for db in dbs.iter_mut() {
+            let _ = db.abort();
+            assert!(db.data.is_empty());
+            assert_eq!(db.db_size(), Ok(0));
+        }
+    }
+
+    #[test]
+    fn test_single_database() {
+        let mut db = BuckDB::new();
+        let mut session = BuckSession::new();
+        db.insert("a".to_owned(), BuckTypes::Integer(1)).unwrap();
+
+        // a lone database is a set of one
+        let mut run_one = |input: &str| session.execute(parse_query(input).unwrap(), input, &mut db);
+        assert_eq!(run_one("SELECT 0"), Ok(BuckReply::ok()));
+        assert_eq!(run_one("SELECT 1"), Err(BuckError::Engine(BuckEngineError::InvalidDatabase(1))));
+        assert_eq!(run_one("MOVE a 0"), Ok(int(0)));
+        assert_eq!(run_one("FLUSHALL"), Ok(BuckReply::ok()));
+
+        let input = "SELECT 0";
+        assert_eq!(
+            parse_query(input).unwrap().execute(input, &mut db).unwrap_err().code(),
+            "SESSION_REQUIRED"
+        );
+    }
+
+    #[test]
+    fn test_server_clients_select_on_their_own() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let dbs = Arc::new(Mutex::new(databases::new(2)));
+
+        let shared = Arc::clone(&dbs);
+        thread::spawn(move || serve(listener, shared));
+
+        let send = |stream: &mut TcpStream, request: &str, expected: &str| {
+            stream.write_all(format!("{}\r\n", request).as_bytes()).unwrap();
+
+            let mut reply = vec![0u8; expected.len()];
+            stream.read_exact(&mut reply).unwrap();
+            assert_eq!(String::from_utf8_lossy(&reply), expected);
+        };
+
+        let mut first = TcpStream::connect(addr).unwrap();
+        let mut second = TcpStream::connect(addr).unwrap();
+
+        send(&mut first, "SELECT 1", "+OK\r\n");
+        send(&mut first, "SET tenant 1", "+OK\r\n");
+        send(&mut second, "GET tenant", "$-1\r\n");
+        send(&mut second, "DBSIZE", ":0\r\n");
+        send(&mut first, "DBSIZE", ":1\r\n");
+
+        assert_eq!(dbs.lock().unwrap()[1].db_size(), Ok(1));
+    }
+}
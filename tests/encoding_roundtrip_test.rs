@@ -0,0 +1,214 @@
+#[cfg(test)]
+mod encoding_roundtrip_tests {
+    use std::collections::{HashMap, HashSet};
+
+    use proptest::prelude::*;
+
+    use buck::encoding::encoding::{
+        encode_set, encode_type, take_set, take_type, TypeTag, FORMAT_VERSION,
+    };
+    use buck::encoding::errors::EncodingError;
+    use buck::types::hash::BuckHash;
+    use buck::types::list::BuckList;
+    use buck::types::sets::{BuckSets, EqFloat, Setable};
+use buck::types::sorted_set::BuckSortedSet;
+    use buck::types::types::BuckTypes;
+
+    fn setable() -> impl Strategy<Value = Setable> {
+        prop_oneof![
+            any::<String>().prop_map(Setable::String),
+            any::<i64>().prop_map(Setable::Integer),
+            any::<f64>()
+                .prop_filter("NaN", |f| !f.is_nan())
+                .prop_map(|f| Setable::Float(EqFloat(f))),
+            any::<bool>().prop_map(Setable::Boolean),
+            Just(Setable::Empty),
+        ]
+    }
+
+    fn buck_set() -> impl Strategy<Value = BuckSets> {
+        prop::collection::hash_set(setable(), 0..16).prop_map(|data| BuckSets { data })
+    }
+
+    fn sorted_set() -> impl Strategy<Value = BuckSortedSet> {
+        let score = any::<f64>().prop_filter("NaN", |f| !f.is_nan());
+
+        prop::collection::vec((any::<String>(), score), 0..16).prop_map(|members| {
+            let mut zset = BuckSortedSet::new();
+            for (member, score) in members {
+                zset.insert(member, score);
+            }
+            zset
+        })
+    }
+
+    fn scalar() -> impl Strategy<Value = BuckTypes> {
+        prop_oneof![
+            any::<bool>().prop_map(BuckTypes::Boolean),
+            any::<i64>().prop_map(BuckTypes::Integer),
+            // NaN never compares equal to itself
+            any::<f64>()
+                .prop_filter("NaN", |f| !f.is_nan())
+                .prop_map(BuckTypes::Float),
+            any::<String>().prop_map(BuckTypes::String),
+            any::<String>().prop_map(BuckTypes::Unknown),
+            buck_set().prop_map(BuckTypes::Sets),
+            sorted_set().prop_map(BuckTypes::SortedSet),
+        ]
+    }
+
+    fn buck_type() -> impl Strategy<Value = BuckTypes> {
+        scalar().prop_recursive(4, 64, 8, |inner| {
+            prop_oneof![
+                prop::collection::vec(inner.clone(), 0..8).prop_map(|items| {
+                    let mut list = BuckList::new();
+                    for item in items {
+                        list.push(item);
+                    }
+                    BuckTypes::List(list)
+                }),
+                prop::collection::hash_map(any::<String>(), inner, 0..8)
+                    .prop_map(|data| BuckTypes::Hash(BuckHash { data })),
+            ]
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn test_type_round_trip(value in buck_type()) {
+            let encoded = encode_type(&value);
+            let mut bytes = encoded.as_slice();
+
+            prop_assert_eq!(take_type(&mut bytes), Ok(value));
+            prop_assert!(bytes.is_empty());
+        }
+
+        #[test]
+        fn test_set_round_trip(set in buck_set()) {
+            let encoded = encode_set(&set);
+            let mut bytes = encoded.as_slice();
+
+            prop_assert_eq!(take_set(&mut bytes), Ok(set));
+            prop_assert!(bytes.is_empty());
+        }
+
+        #[test]
+        fn test_set_encoding_ignores_insertion_order(items in prop::collection::vec(setable(), 0..16)) {
+            let forward = BuckSets { data: items.iter().cloned().collect::<HashSet<_>>() };
+            let backward = BuckSets { data: items.into_iter().rev().collect::<HashSet<_>>() };
+
+            prop_assert_eq!(encode_set(&forward), encode_set(&backward));
+        }
+
+        #[test]
+        fn test_arbitrary_bytes_never_panic(bytes in prop::collection::vec(any::<u8>(), 0..64)) {
+            let _ = take_set(&mut bytes.as_slice());
+            let _ = take_type(&mut bytes.as_slice());
+
+            let mut versioned = vec![FORMAT_VERSION];
+            versioned.extend(bytes);
+            let _ = take_set(&mut versioned.as_slice());
+            let _ = take_type(&mut versioned.as_slice());
+        }
+
+        #[test]
+        fn test_truncated_type_is_rejected(value in buck_type()) {
+            let encoded = encode_type(&value);
+
+            for cut in 0..encoded.len() {
+                let mut bytes = &encoded[..cut];
+                prop_assert!(take_type(&mut bytes).is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn test_nested_list_in_hash_in_list() {
+        let mut inner = BuckList::new();
+        inner.push(BuckTypes::Integer(1));
+        inner.push(BuckTypes::String("two".to_owned()));
+
+        let mut hash = BuckHash::new();
+        hash.insert("list".to_owned(), BuckTypes::List(inner));
+        hash.insert("flag".to_owned(), BuckTypes::Boolean(true));
+
+        let mut outer = BuckList::new();
+        outer.push(BuckTypes::Hash(hash));
+        outer.push(BuckTypes::Unknown("raw".to_owned()));
+        let value = BuckTypes::List(outer);
+
+        let encoded = encode_type(&value);
+        let mut bytes = encoded.as_slice();
+        assert_eq!(take_type(&mut bytes), Ok(value));
+        assert!(bytes.is_empty());
+    }
+
+    #[test]
+    fn test_hash_encoding_is_deterministic() {
+        let fields: Vec<(String, BuckTypes)> = (0..32)
+            .map(|i| (format!("field{}", i), BuckTypes::Integer(i)))
+            .collect();
+
+        let forward = BuckHash {
+            data: fields.iter().cloned().collect::<HashMap<_, _>>(),
+        };
+        let backward = BuckHash {
+            data: fields.into_iter().rev().collect::<HashMap<_, _>>(),
+        };
+
+        assert_eq!(
+            encode_type(&BuckTypes::Hash(forward)),
+            encode_type(&BuckTypes::Hash(backward))
+        );
+    }
+
+    #[test]
+    fn test_set_uses_shared_type_tags() {
+        let set = BuckSets {
+            data: HashSet::from([Setable::String("a".to_owned()), Setable::Boolean(true)]),
+        };
+
+        assert_eq!(
+            encode_set(&set),
+            vec![
+                FORMAT_VERSION,
+                0x00, 0x00, 0x00, 0x02,
+                TypeTag::Boolean as u8, 0x01,
+                TypeTag::String as u8, 0x61, 0x00, 0x00,
+            ]
+        );
+
+        let value = encode_type(&BuckTypes::Boolean(true));
+        assert_eq!(value, vec![FORMAT_VERSION, TypeTag::Boolean as u8, 0x01]);
+    }
+
+    #[test]
+    fn test_unsupported_version_is_rejected() {
+        let mut encoded = encode_set(&BuckSets::new());
+        encoded[0] = 0x7f;
+        assert_eq!(
+            take_set(&mut encoded.as_slice()),
+            Err(EncodingError::UnsupportedVersion(0x7f))
+        );
+
+        let mut encoded = encode_type(&BuckTypes::Integer(1));
+        encoded[0] = 0x00;
+        assert_eq!(
+            take_type(&mut encoded.as_slice()),
+            Err(EncodingError::UnsupportedVersion(0x00))
+        );
+    }
+
+    #[test]
+    fn test_unknown_tag_is_rejected() {
+        let mut bytes: &[u8] = &[FORMAT_VERSION, 0xee, 0x00];
+        assert_eq!(take_type(&mut bytes), Err(EncodingError::InvalidTag(0xee)));
+
+        // a hash is not a valid set member
+        let mut bytes: &[u8] = &[FORMAT_VERSION, 0x00, 0x00, 0x00, 0x01, TypeTag::Hash as u8];
+        assert_eq!(take_set(&mut bytes), Err(EncodingError::InvalidTag(TypeTag::Hash as u8)));
+
+        let mut bytes: &[u8] = &[];
+        assert!(take_type(&mut bytes).is_err());
+    }
+}
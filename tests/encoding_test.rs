@@ -74,8 +74,8 @@ mod encoding_tests {
     fn test_integer_encoding() {
         use buck::encoding::encoding::encode_integer;
 
-        assert_eq!(encode_integer(std::i64::MIN), [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
-        assert_eq!(encode_integer(std::i64::MAX), [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]);
+        assert_eq!(encode_integer(i64::MIN), [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+        assert_eq!(encode_integer(i64::MAX), [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]);
         assert_eq!(encode_integer(-1024), [0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfc, 0x00]);
         assert_eq!(encode_integer(-42), [0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xd6]);
         assert_eq!(encode_integer(-1), [0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]);
@@ -89,8 +89,8 @@ mod encoding_tests {
     fn test_decode_integer() {
         use buck::encoding::encoding::decode_integer;
 
-        assert_eq!(decode_integer([0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]), std::i64::MIN);
-        assert_eq!(decode_integer([0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]), std::i64::MAX);
+        assert_eq!(decode_integer([0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]), i64::MIN);
+        assert_eq!(decode_integer([0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]), i64::MAX);
         assert_eq!(decode_integer([0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfc, 0x00]), -1024);
         assert_eq!(decode_integer([0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xd6]), -42);
         assert_eq!(decode_integer([0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]), -1);
@@ -250,14 +250,16 @@ mod encoding_tests {
         set.data.insert(Setable::String("a".to_owned()));
     
         assert_eq!(encode_set(&set), vec![
+            0x01,                       // format version
             0x00, 0x00, 0x00, 0x01,     // length of the set
-            0x01, 0x61, 0x00, 0x00,     // string type  `a`
+            0x04, 0x61, 0x00, 0x00,     // string type  `a`
         ]);
 
         let mut set = BuckSets::new();
         set.data.insert(Setable::Integer(123));
 
         assert_eq!(encode_set(&set), vec![
+            0x01,                             // format version
             0x00, 0x00, 0x00, 0x01,           // length of the set
             0x03, 0x80, 0x00, 0x00,           // integer type
             0x00, 0x00, 0x00, 0x00, 0x7b,     // integer value `123`
@@ -267,24 +269,27 @@ mod encoding_tests {
         set.data.insert(Setable::Boolean(true));
 
         assert_eq!(encode_set(&set), vec![
+            0x01,                       // format version
             0x00, 0x00, 0x00, 0x01,     // length of the set
-            0x02, 0x01,                 // boolean type `true`
+            0x01, 0x01,                 // boolean type `true`
         ]);
 
         let mut set = BuckSets::new();
         set.data.insert(Setable::Boolean(false));
 
         assert_eq!(encode_set(&set), vec![
+            0x01,                       // format version
             0x00, 0x00, 0x00, 0x01,     // length of the set
-            0x02, 0x00,                 // boolean type `false`
+            0x01, 0x00,                 // boolean type `false`
         ]);
 
         let mut set = BuckSets::new();
         set.data.insert(Setable::Float(EqFloat(1.0)));
 
         assert_eq!(encode_set(&set), vec![
+            0x01,                           // format version
             0x00, 0x00, 0x00, 0x01,         // length of the set
-            0x04, 0xbf, 0xf0, 0x00,         // float type
+            0x02, 0xbf, 0xf0, 0x00,         // float type
             0x00, 0x00, 0x00, 0x00, 0x00,   // value `1.0`
         ]);
 
@@ -294,6 +299,6 @@ mod encoding_tests {
         set.data.insert(Setable::String("b".to_owned()));
         set.data.insert(Setable::String("c".to_owned()));
 
-        assert_eq!(encode_set(&set).len(), 16);
+        assert_eq!(encode_set(&set).len(), 17);
     }
 }
@@ -0,0 +1,253 @@
+mod common;
+
+#[cfg(test)]
+mod eviction_tests {
+    use buck::clock::ManualClock;
+    use buck::databases;
+    use buck::engine::BuckDB;
+    use buck::errors::BuckEngineError;
+    use buck::eviction::{parse_memory, EvictionPolicy};
+    use buck::log::BuckLog;
+    use buck::parser::parse::parse_query;
+    use buck::parser::query::BuckQuery;
+    use buck::session::BuckSession;
+    use buck::types::list::BuckList;
+    use buck::types::types::BuckTypes;
+
+    use crate::common::db_with_clock;
+
+    /// Insert `k0..kn`, one millisecond apart, and return the size of one key.
+    fn fill(db: &mut BuckDB, clock: &ManualClock, n: i64) -> usize {
+        for i in 0..n {
+            db.insert(format!("k{}", i), BuckTypes::Integer(i)).unwrap();
+            clock.advance(1);
+        }
+        db.commit().unwrap();
+
+        db.used_memory() / n as usize
+    }
+
+    fn keys(db: &BuckDB) -> Vec<String> {
+        db.data.keys().chain(db.uncommitted_data.keys()).cloned().collect()
+    }
+
+    #[test]
+    fn test_estimated_size_grows_with_content() {
+        let small = BuckTypes::String("a".to_owned());
+        let large = BuckTypes::String("a".repeat(100));
+        assert_eq!(large.estimated_size() - small.estimated_size(), 99);
+
+        let mut list = BuckList::new();
+        let empty = BuckTypes::List(list.clone()).estimated_size();
+        list.push(large.clone());
+        assert_eq!(BuckTypes::List(list).estimated_size(), empty + large.estimated_size());
+    }
+
+    #[test]
+    fn test_used_memory_tracks_writes() {
+        let (mut db, clock) = db_with_clock();
+        assert_eq!(db.used_memory(), 0);
+
+        let size = fill(&mut db, &clock, 2);
+        assert_eq!(db.used_memory(), size * 2);
+
+        // staged and committed copies are both counted until the commit
+        db.insert("k0".to_owned(), BuckTypes::Integer(7)).unwrap();
+        assert!(db.used_memory() > size * 2);
+        db.commit().unwrap();
+        assert_eq!(db.used_memory(), size * 2);
+
+        db.remove("k0").unwrap();
+        assert_eq!(db.used_memory(), size);
+
+        db.expire("k1", 0).unwrap();
+        assert_eq!(db.used_memory(), 0);
+    }
+
+    #[test]
+    fn test_noeviction_rejects_writes() {
+        let (mut db, clock) = db_with_clock();
+        let size = fill(&mut db, &clock, 3);
+        db.set_maxmemory(Some(size * 3 - 1));
+
+        assert_eq!(
+            db.insert("k3".to_owned(), BuckTypes::Integer(3)),
+            Err(BuckEngineError::OutOfMemory)
+        );
+        assert_eq!(
+            db.l_push("list".to_owned(), BuckTypes::Integer(1)),
+            Err(BuckEngineError::OutOfMemory)
+        );
+        assert_eq!(keys(&db).len(), 3);
+
+        // reads and deletes are still allowed
+        assert_eq!(db.get("k0"), Ok(&BuckTypes::Integer(0)));
+        db.remove("k0").unwrap();
+        db.insert("k3".to_owned(), BuckTypes::Integer(3)).unwrap();
+    }
+
+    #[test]
+    fn test_databases_share_one_budget() {
+        let mut dbs = databases::new(2);
+        let mut session = BuckSession::new();
+
+        for key in ["k0", "k1"] {
+            dbs[0].insert(key.to_owned(), BuckTypes::Integer(0)).unwrap();
+        }
+        dbs[0].commit().unwrap();
+        let size = dbs[0].used_memory() / 2;
+
+        // the budget is set for every database at once
+        let input = format!("CONFIG SET maxmemory {}", size * 3 - 1);
+        session.execute_in(parse_query(&input).unwrap(), &input, &mut dbs).unwrap();
+        assert!(dbs.iter().all(|db| db.memory.maxmemory == Some(size * 3 - 1)));
+
+        // and covers the keys of both
+        dbs[1].insert("k2".to_owned(), BuckTypes::Integer(0)).unwrap();
+        assert_eq!(dbs[1].memory.total_memory(), size * 3);
+        assert_eq!(
+            dbs[1].insert("k3".to_owned(), BuckTypes::Integer(0)),
+            Err(BuckEngineError::OutOfMemory)
+        );
+
+        // a database that goes away takes its keys out of the budget
+        dbs.pop();
+        assert_eq!(dbs[0].memory.total_memory(), size * 2);
+    }
+
+    #[test]
+    fn test_allkeys_lru_evicts_least_recently_used() {
+        let (mut db, clock) = db_with_clock();
+        let size = fill(&mut db, &clock, 4);
+        db.set_maxmemory(Some(size * 4 - 1));
+        db.set_eviction_policy(EvictionPolicy::AllKeysLru);
+
+        db.touch("k0");
+        clock.advance(1);
+
+        db.insert("k4".to_owned(), BuckTypes::Integer(4)).unwrap();
+        assert!(db.get("k1").is_err());
+        assert!(db.get("k0").is_ok());
+
+        // reads through queries count as accesses too
+        let query = "GET k2";
+        parse_query(query).unwrap().execute(query, &mut db).unwrap();
+        clock.advance(1);
+
+        db.insert("k5".to_owned(), BuckTypes::Integer(5)).unwrap();
+        assert!(db.get("k3").is_err());
+        assert!(db.get("k2").is_ok());
+    }
+
+    #[test]
+    fn test_allkeys_lfu_evicts_least_frequently_used() {
+        let (mut db, clock) = db_with_clock();
+        let size = fill(&mut db, &clock, 3);
+        db.set_maxmemory(Some(size * 3 - 1));
+        db.set_eviction_policy(EvictionPolicy::AllKeysLfu);
+
+        for _ in 0..3 {
+            db.touch("k0");
+        }
+        db.touch("k2");
+
+        db.insert("k3".to_owned(), BuckTypes::Integer(3)).unwrap();
+        assert!(db.get("k1").is_err());
+
+        let access = db.memory.access_of("k0").unwrap();
+        assert_eq!(access.frequency, 4);
+    }
+
+    #[test]
+    fn test_allkeys_random_never_evicts_written_key() {
+        let (mut db, clock) = db_with_clock();
+        let size = fill(&mut db, &clock, 8);
+        db.set_maxmemory(Some(size * 4));
+        db.set_eviction_policy(EvictionPolicy::AllKeysRandom);
+
+        // keys are evicted until the budget is met, then the write goes through
+        db.insert("new".to_owned(), BuckTypes::Integer(0)).unwrap();
+
+        assert!(db.get("new").is_ok());
+        assert_eq!(keys(&db).len(), 5);
+
+        db.insert("newer".to_owned(), BuckTypes::Integer(0)).unwrap();
+        assert!(db.get("newer").is_ok());
+        assert!(db.used_memory() <= size * 4 + db.memory.size_of("newer").unwrap());
+    }
+
+    #[test]
+    fn test_volatile_lru_only_evicts_keys_with_timeout() {
+        let (mut db, clock) = db_with_clock();
+        let size = fill(&mut db, &clock, 4);
+        db.set_maxmemory(Some(size * 4 - 1));
+        db.set_eviction_policy(EvictionPolicy::VolatileLru);
+
+        db.expire("k3", 60_000).unwrap();
+        db.expire("k2", 60_000).unwrap();
+        db.touch("k2");
+
+        db.insert("k4".to_owned(), BuckTypes::Integer(4)).unwrap();
+        assert!(db.get("k3").is_err());
+        assert!(!db.expires.contains_key("k3"));
+
+        db.insert("k5".to_owned(), BuckTypes::Integer(5)).unwrap();
+        assert!(db.get("k2").is_err());
+
+        // nothing left that may be evicted
+        assert_eq!(
+            db.insert("k6".to_owned(), BuckTypes::Integer(6)),
+            Err(BuckEngineError::OutOfMemory)
+        );
+        assert!(db.get("k0").is_ok());
+    }
+
+    #[test]
+    fn test_config_queries() {
+        let (mut db, _) = db_with_clock();
+
+        assert_eq!(
+            parse_query("CONFIG SET maxmemory 1mb"),
+            Ok(BuckQuery::ConfigSet("maxmemory".to_owned(), "1mb".to_owned()))
+        );
+        assert_eq!(
+            parse_query("config get MAXMEMORY-POLICY"),
+            Ok(BuckQuery::ConfigGet("maxmemory-policy".to_owned()))
+        );
+        assert!(parse_query("CONFIG maxmemory").is_err());
+        assert!(parse_query("CONFIG SET maxmemory").is_err());
+
+        assert_eq!(db.config_set("maxmemory", "1mb"), Ok(BuckLog::ConfigSetOk));
+        assert_eq!(db.memory.maxmemory, Some(1 << 20));
+        assert_eq!(
+            db.config_get("maxmemory"),
+            Ok(BuckLog::ConfigOk("maxmemory".to_owned(), "1048576".to_owned()))
+        );
+
+        db.config_set("maxmemory-policy", "allkeys-lfu").unwrap();
+        assert_eq!(db.memory.policy, EvictionPolicy::AllKeysLfu);
+
+        db.config_set("maxmemory", "0").unwrap();
+        assert_eq!(db.memory.maxmemory, None);
+
+        assert!(db.config_set("maxmemory-policy", "sometimes").is_err());
+        assert!(db.config_set("maxmemory", "lots").is_err());
+        assert!(db.config_get("appendonly").is_err());
+
+        assert_eq!(parse_memory("512kb"), Some(512 * 1024));
+        assert_eq!(parse_memory("2G"), Some(2 << 30));
+        assert_eq!(parse_memory("100"), Some(100));
+        assert_eq!(parse_memory("1tb"), None);
+    }
+
+    #[test]
+    fn test_out_of_memory_through_query() {
+        let (mut db, clock) = db_with_clock();
+        let size = fill(&mut db, &clock, 1);
+        db.set_maxmemory(Some(size - 1));
+
+        let query = "INSERT other 1";
+        let result = parse_query(query).unwrap().execute(query, &mut db);
+        assert_eq!(result, Err(BuckEngineError::OutOfMemory.into()));
+    }
+}
@@ -0,0 +1,183 @@
+mod common;
+
+#[cfg(test)]
+mod expire_tests {
+    use std::collections::HashMap;
+
+    use buck::errors::BuckEngineError;
+    use buck::log::BuckLog;
+    use buck::parser::parse::parse_query;
+    use buck::parser::query::BuckQuery;
+    use buck::reply::BuckReply;
+    use buck::types::types::BuckTypes;
+
+    use crate::common::{db_with_clock, run};
+
+    #[test]
+    fn test_expired_key_is_invisible() {
+        let (mut db, clock) = db_with_clock();
+
+        db.insert("session".to_owned(), BuckTypes::String("abc".to_owned())).unwrap();
+        db.l_push("queue".to_owned(), BuckTypes::Integer(1)).unwrap();
+        db.commit().unwrap();
+
+        assert_eq!(db.expire("session", 1_000), Ok(BuckLog::IntegerOk(1)));
+        assert_eq!(db.expire("queue", 1_000), Ok(BuckLog::IntegerOk(1)));
+
+        clock.advance(999);
+        assert_eq!(db.get("session"), Ok(&BuckTypes::String("abc".to_owned())));
+        assert_eq!(db.get_collections_length("queue".to_owned()), Ok(1));
+
+        clock.advance(1);
+        assert_eq!(
+            db.get("session"),
+            Err(BuckEngineError::KeyNotFound("session".to_owned()))
+        );
+        assert_eq!(
+            db.type_of("session"),
+            Err(BuckEngineError::KeyNotFound("session".to_owned()))
+        );
+        assert!(db.get_collections_length("queue".to_owned()).is_err());
+
+        // still stored until it is accessed or swept
+        assert!(db.data.contains_key("session"));
+    }
+
+    #[test]
+    fn test_lazy_deletion_on_access() {
+        let (mut db, clock) = db_with_clock();
+
+        db.insert("k".to_owned(), BuckTypes::Integer(1)).unwrap();
+        db.expire("k", 10).unwrap();
+        clock.advance(10);
+
+        assert!(db.expire_if_needed("k"));
+        assert!(!db.uncommitted_data.contains_key("k"));
+        assert!(db.expires.is_empty());
+
+        // writing to an expired key starts from scratch
+        db.insert("list".to_owned(), BuckTypes::Integer(0)).unwrap();
+        db.expire("list", 10).unwrap();
+        clock.advance(10);
+        db.h_set(
+            "list".to_owned(),
+            HashMap::from([("f".to_owned(), BuckTypes::Integer(1))]),
+        )
+        .unwrap();
+        assert_eq!(db.type_of("list"), Ok("hash".to_owned()));
+        assert_eq!(db.pttl("list"), -1);
+    }
+
+    #[test]
+    fn test_active_expire_cycle() {
+        let (mut db, clock) = db_with_clock();
+
+        for i in 0..10 {
+            db.insert(format!("k{}", i), BuckTypes::Integer(i)).unwrap();
+        }
+        db.commit().unwrap();
+
+        for i in 0..6 {
+            db.expire(&format!("k{}", i), 100).unwrap();
+        }
+
+        assert_eq!(db.active_expire_cycle(100), 0);
+
+        clock.advance(100);
+        assert_eq!(db.active_expire_cycle(4), 4);
+        assert_eq!(db.active_expire_cycle(4), 2);
+        assert_eq!(db.active_expire_cycle(4), 0);
+
+        assert_eq!(db.data.len(), 4);
+        assert!(db.expires.is_empty());
+    }
+
+    #[test]
+    fn test_ttl_and_persist() {
+        let (mut db, clock) = db_with_clock();
+
+        assert_eq!(db.expire("missing", 100), Ok(BuckLog::IntegerOk(0)));
+        assert_eq!(db.pttl("missing"), -2);
+
+        db.insert("k".to_owned(), BuckTypes::Integer(1)).unwrap();
+        assert_eq!(db.pttl("k"), -1);
+
+        db.expire("k", 2_500).unwrap();
+        clock.advance(500);
+        assert_eq!(db.pttl("k"), 2_000);
+
+        assert_eq!(db.persist("k"), Ok(BuckLog::IntegerOk(1)));
+        assert_eq!(db.persist("k"), Ok(BuckLog::IntegerOk(0)));
+        assert_eq!(db.pttl("k"), -1);
+
+        clock.advance(10_000);
+        assert_eq!(db.get("k"), Ok(&BuckTypes::Integer(1)));
+
+        // overwriting a key clears its timeout
+        db.expire("k", 100).unwrap();
+        db.insert("k".to_owned(), BuckTypes::Integer(2)).unwrap();
+        assert_eq!(db.pttl("k"), -1);
+
+        // a timeout in the past deletes the key
+        assert_eq!(db.expire("k", 0), Ok(BuckLog::IntegerOk(1)));
+        assert!(db.get("k").is_err());
+    }
+
+    #[test]
+    fn test_expire_queries() {
+        let (mut db, clock) = db_with_clock();
+
+        assert_eq!(parse_query("EXPIRE k 10"), Ok(BuckQuery::Expire("k".to_owned(), 10)));
+        assert_eq!(parse_query("pexpire k 10"), Ok(BuckQuery::PExpire("k".to_owned(), 10)));
+        assert_eq!(parse_query("TTL k"), Ok(BuckQuery::Ttl("k".to_owned())));
+        assert_eq!(parse_query("PTTL k"), Ok(BuckQuery::PTtl("k".to_owned())));
+        assert_eq!(parse_query("PERSIST k"), Ok(BuckQuery::Persist("k".to_owned())));
+        assert!(parse_query("EXPIRE k").is_err());
+        assert!(parse_query("EXPIRE k soon").is_err());
+
+        run(&mut db, "INSERT k 1").unwrap();
+        assert_eq!(run(&mut db, "EXPIRE k 10").unwrap(), BuckReply::Integer(1));
+        clock.advance(1_400);
+        assert_eq!(run(&mut db, "TTL k").unwrap(), BuckReply::Integer(9));
+        assert_eq!(run(&mut db, "PTTL k").unwrap(), BuckReply::Integer(8_600));
+
+        clock.advance(8_600);
+        assert_eq!(run(&mut db, "TTL k").unwrap(), BuckReply::Integer(-2));
+        assert!(db.expires.is_empty());
+
+        // timeouts too far out to count down stay positive
+        run(&mut db, "INSERT k 1").unwrap();
+        run(&mut db, "EXPIRE k 9223372036854775807").unwrap();
+        assert!(matches!(run(&mut db, "TTL k").unwrap(), BuckReply::Integer(ttl) if ttl > 0));
+        run(&mut db, "SET k 1 PXAT 18446744073709551615").unwrap();
+        assert_eq!(run(&mut db, "PTTL k").unwrap(), BuckReply::Integer(i64::MAX));
+        assert_eq!(run(&mut db, "TTL k").unwrap(), BuckReply::Integer(i64::MAX / 1000));
+    }
+
+    #[test]
+    fn test_abort_restores_timeouts() {
+        let (mut db, _clock) = db_with_clock();
+
+        db.insert("a".to_owned(), BuckTypes::Integer(1)).unwrap();
+        db.insert("b".to_owned(), BuckTypes::Integer(2)).unwrap();
+        db.commit().unwrap();
+        db.expire("b", 1_000).unwrap();
+        db.commit().unwrap();
+
+        assert_eq!(db.expire("a", 500), Ok(BuckLog::IntegerOk(1)));
+        assert_eq!(db.persist("b"), Ok(BuckLog::IntegerOk(1)));
+        assert_eq!(db.pttl("a"), 500);
+        assert_eq!(db.pttl("b"), -1);
+
+        db.abort().unwrap();
+        assert_eq!(db.pttl("a"), -1);
+        assert_eq!(db.pttl("b"), 1_000);
+
+        // a staged key takes its timeout with it
+        db.insert("c".to_owned(), BuckTypes::Integer(3)).unwrap();
+        db.expire("c", 100).unwrap();
+        db.abort().unwrap();
+        assert_eq!(db.pttl("c"), -2);
+        assert!(db.expires_backup.is_empty());
+    }
+}
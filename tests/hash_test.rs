@@ -0,0 +1,336 @@
+mod common;
+
+#[cfg(test)]
+mod hash_tests {
+    use std::collections::{HashMap, HashSet};
+
+    use buck::engine::BuckDB;
+    use buck::errors::{BuckEngineError, BuckError};
+    use buck::parser::parse::parse_query;
+    use buck::parser::query::BuckQuery;
+    use buck::reply::BuckReply;
+    use buck::scan::glob_match;
+    use buck::types::errors::BuckTypeError;
+    use buck::types::types::BuckTypes;
+
+    use crate::common::run;
+
+    fn bulk(value: &str) -> BuckReply {
+        BuckReply::Bulk(value.to_owned())
+    }
+
+    /// `h = {a: 1, b: "two", c: true}`, committed.
+    fn db_with_hash() -> BuckDB {
+        let mut db = BuckDB::new();
+
+        run(&mut db, "HSET h a:1 b:\"two\" c:true").unwrap();
+        db.commit().unwrap();
+
+        db
+    }
+
+    /// One `HSCAN` iteration, returning every field it saw, in order.
+    fn scan_all(db: &mut BuckDB, key: &str, options: &str) -> Vec<String> {
+        let mut cursor = "0".to_owned();
+        let mut fields = Vec::new();
+
+        loop {
+            let reply = run(db, &format!("HSCAN {} {} {}", key, cursor, options)).unwrap();
+
+            let (next, items) = match reply {
+                BuckReply::Array(mut page) if page.len() == 2 => match (page.remove(0), page.remove(0)) {
+                    (BuckReply::Bulk(next), BuckReply::Array(items)) => (next, items),
+                    other => panic!("unexpected page {:?}", other),
+                },
+                other => panic!("unexpected reply {:?}", other),
+            };
+
+            for pair in items.chunks(2) {
+                match &pair[0] {
+                    BuckReply::Bulk(field) => fields.push(field.clone()),
+                    other => panic!("unexpected field {:?}", other),
+                }
+            }
+
+            if next == "0" {
+                return fields;
+            }
+            cursor = next;
+        }
+    }
+
+    #[test]
+    fn test_parse_hash_queries() {
+        assert_eq!(
+            parse_query("HGET h a"),
+            Ok(BuckQuery::HGet("h".to_owned(), "a".to_owned()))
+        );
+        assert_eq!(
+            parse_query("HDEL h a b"),
+            Ok(BuckQuery::HDel("h".to_owned(), vec!["a".to_owned(), "b".to_owned()]))
+        );
+        assert_eq!(parse_query("HGETALL h"), Ok(BuckQuery::HGetAll("h".to_owned())));
+        assert_eq!(
+            parse_query("HSETNX h a \"some text\""),
+            Ok(BuckQuery::HSetNx(
+                "h".to_owned(),
+                "a".to_owned(),
+                BuckTypes::String("some text".to_owned())
+            ))
+        );
+        assert_eq!(
+            parse_query("HINCRBY h a -5"),
+            Ok(BuckQuery::HIncrBy("h".to_owned(), "a".to_owned(), -5))
+        );
+        assert_eq!(
+            parse_query("HINCRBYFLOAT h a 0.5"),
+            Ok(BuckQuery::HIncrByFloat("h".to_owned(), "a".to_owned(), 0.5))
+        );
+        assert_eq!(
+            parse_query("HSCAN h 42 count 5 match f*"),
+            Ok(BuckQuery::HScan("h".to_owned(), 42, Some("f*".to_owned()), 5))
+        );
+        assert_eq!(
+            parse_query("HSCAN h 0"),
+            Ok(BuckQuery::HScan("h".to_owned(), 0, None, 10))
+        );
+
+        for input in ["HGET h", "HMGET h", "HINCRBY h a 1.5", "HSCAN h -1", "HSCAN h 0 COUNT", "HSCAN h 0 LIMIT 1"] {
+            assert!(parse_query(input).is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_hset_counts_new_fields() {
+        let mut db = BuckDB::new();
+
+        assert_eq!(run(&mut db, "HSET h a:1 b:2"), Ok(BuckReply::Integer(2)));
+        // `a` is overwritten, only `c` is new
+        assert_eq!(run(&mut db, "HSET h a:3 c:4"), Ok(BuckReply::Integer(1)));
+        assert_eq!(run(&mut db, "HGET h a"), Ok(BuckReply::Integer(3)));
+
+        // a committed hash is staged before it changes
+        db.commit().unwrap();
+        run(&mut db, "HSET h d:5").unwrap();
+        assert_eq!(run(&mut db, "HLEN h"), Ok(BuckReply::Integer(4)));
+
+        db.abort().unwrap();
+        assert_eq!(run(&mut db, "HLEN h"), Ok(BuckReply::Integer(3)));
+    }
+
+    #[test]
+    fn test_read_fields() {
+        let mut db = db_with_hash();
+
+        assert_eq!(run(&mut db, "HGET h b"), Ok(bulk("two")));
+        assert_eq!(run(&mut db, "HGET h x"), Ok(BuckReply::Nil));
+        assert_eq!(run(&mut db, "HGET missing a"), Ok(BuckReply::Nil));
+        assert_eq!(
+            run(&mut db, "HMGET h c x a"),
+            Ok(BuckReply::Array(vec![
+                BuckReply::Boolean(true),
+                BuckReply::Nil,
+                BuckReply::Integer(1),
+            ]))
+        );
+        assert_eq!(
+            run(&mut db, "HGETALL h"),
+            Ok(BuckReply::Map(vec![
+                (bulk("a"), BuckReply::Integer(1)),
+                (bulk("b"), bulk("two")),
+                (bulk("c"), BuckReply::Boolean(true)),
+            ]))
+        );
+        assert_eq!(run(&mut db, "HGETALL missing"), Ok(BuckReply::Map(Vec::new())));
+        assert_eq!(
+            run(&mut db, "HKEYS h"),
+            Ok(BuckReply::Array(vec![bulk("a"), bulk("b"), bulk("c")]))
+        );
+        assert_eq!(
+            run(&mut db, "HVALS h"),
+            Ok(BuckReply::Array(vec![BuckReply::Integer(1), bulk("two"), BuckReply::Boolean(true)]))
+        );
+        assert_eq!(run(&mut db, "HEXISTS h a"), Ok(BuckReply::Integer(1)));
+        assert_eq!(run(&mut db, "HEXISTS h x"), Ok(BuckReply::Integer(0)));
+        assert_eq!(run(&mut db, "HSTRLEN h b"), Ok(BuckReply::Integer(3)));
+        assert_eq!(run(&mut db, "HSTRLEN h c"), Ok(BuckReply::Integer(4)));
+        assert_eq!(run(&mut db, "HSTRLEN h x"), Ok(BuckReply::Integer(0)));
+        assert_eq!(run(&mut db, "HLEN missing"), Ok(BuckReply::Integer(0)));
+
+        // reading never stages anything
+        assert!(db.uncommitted_data.is_empty());
+    }
+
+    #[test]
+    fn test_hdel_deletes_empty_hash() {
+        let mut db = db_with_hash();
+
+        assert_eq!(run(&mut db, "HDEL h a x"), Ok(BuckReply::Integer(1)));
+        assert_eq!(run(&mut db, "HDEL h a"), Ok(BuckReply::Integer(0)));
+        assert_eq!(run(&mut db, "HDEL missing a"), Ok(BuckReply::Integer(0)));
+
+        assert_eq!(run(&mut db, "HDEL h b c"), Ok(BuckReply::Integer(2)));
+        assert!(db.get("h").is_err());
+
+        db.commit().unwrap();
+        assert!(!db.data.contains_key("h"));
+    }
+
+    #[test]
+    fn test_hsetnx() {
+        let mut db = db_with_hash();
+
+        assert_eq!(run(&mut db, "HSETNX h a 100"), Ok(BuckReply::Integer(0)));
+        assert_eq!(run(&mut db, "HGET h a"), Ok(BuckReply::Integer(1)));
+        assert!(db.uncommitted_data.is_empty());
+
+        assert_eq!(run(&mut db, "HSETNX h d 100"), Ok(BuckReply::Integer(1)));
+        assert_eq!(run(&mut db, "HGET h d"), Ok(BuckReply::Integer(100)));
+
+        assert_eq!(run(&mut db, "HSETNX new a 1"), Ok(BuckReply::Integer(1)));
+        assert_eq!(run(&mut db, "HLEN new"), Ok(BuckReply::Integer(1)));
+    }
+
+    #[test]
+    fn test_hincrby() {
+        let mut db = db_with_hash();
+
+        assert_eq!(run(&mut db, "HINCRBY h a 9"), Ok(BuckReply::Integer(10)));
+        assert_eq!(run(&mut db, "HINCRBY h a -15"), Ok(BuckReply::Integer(-5)));
+        assert_eq!(run(&mut db, "HINCRBY h x 2"), Ok(BuckReply::Integer(2)));
+        assert_eq!(run(&mut db, "HINCRBY counters hits 1"), Ok(BuckReply::Integer(1)));
+
+        db.commit().unwrap();
+
+        assert_eq!(
+            run(&mut db, "HINCRBY h b 1"),
+            Err(BuckError::Type(BuckTypeError::NotAnInteger("b".to_owned())))
+        );
+        assert!(db.uncommitted_data.is_empty());
+
+        run(&mut db, "HSET h big:9223372036854775807").unwrap();
+        assert_eq!(
+            run(&mut db, "HINCRBY h big 1"),
+            Err(BuckError::Type(BuckTypeError::Overflow))
+        );
+        assert_eq!(run(&mut db, "HGET h big"), Ok(BuckReply::Integer(i64::MAX)));
+    }
+
+    #[test]
+    fn test_hincrbyfloat() {
+        let mut db = db_with_hash();
+
+        // an integer field turns into a float
+        assert_eq!(run(&mut db, "HINCRBYFLOAT h a 0.5"), Ok(BuckReply::Float(1.5)));
+        assert_eq!(run(&mut db, "HGET h a"), Ok(BuckReply::Float(1.5)));
+        assert_eq!(run(&mut db, "HINCRBYFLOAT h x -2.25"), Ok(BuckReply::Float(-2.25)));
+
+        assert_eq!(
+            run(&mut db, "HINCRBYFLOAT h c 1"),
+            Err(BuckError::Type(BuckTypeError::NotAFloat("c".to_owned())))
+        );
+        assert_eq!(
+            run(&mut db, "HINCRBYFLOAT h a inf"),
+            Err(BuckError::Type(BuckTypeError::Overflow))
+        );
+        assert_eq!(run(&mut db, "HGET h a"), Ok(BuckReply::Float(1.5)));
+    }
+
+    #[test]
+    fn test_hash_commands_check_the_type() {
+        let mut db = BuckDB::new();
+        run(&mut db, "RPUSH list 1").unwrap();
+
+        for input in [
+            "HGET list a", "HMGET list a", "HGETALL list", "HDEL list a", "HLEN list",
+            "HSETNX list a 1", "HINCRBY list a 1", "HSCAN list 0", "HSET list a:1",
+        ] {
+            assert_eq!(
+                run(&mut db, input),
+                Err(BuckError::Engine(BuckEngineError::TypeNotSupported("list".to_owned()))),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_hscan_visits_every_field_once() {
+        let mut db = BuckDB::new();
+        let fields: HashMap<String, BuckTypes> =
+            (0..200).map(|i| (format!("f{}", i), BuckTypes::Integer(i))).collect();
+        db.h_set("big".to_owned(), fields.clone()).unwrap();
+
+        let seen = scan_all(&mut db, "big", "COUNT 7");
+        let unique: HashSet<&String> = seen.iter().collect();
+
+        assert_eq!(seen.len(), 200);
+        assert_eq!(unique, fields.keys().collect());
+
+        assert_eq!(
+            run(&mut db, "HSCAN missing 0"),
+            Ok(BuckReply::Array(vec![bulk("0"), BuckReply::Array(Vec::new())]))
+        );
+    }
+
+    #[test]
+    fn test_hscan_survives_changes_between_pages() {
+        let mut db = BuckDB::new();
+        let fields: HashMap<String, BuckTypes> =
+            (0..50).map(|i| (format!("f{}", i), BuckTypes::Integer(i))).collect();
+        db.h_set("h".to_owned(), fields).unwrap();
+
+        let (cursor, first) = db.h_scan("h", 0, None, 10).unwrap();
+        assert_ne!(cursor, 0);
+
+        // fields added or removed in between do not shift the ones left to visit
+        let added: HashMap<String, BuckTypes> =
+            (0..50).map(|i| (format!("g{}", i), BuckTypes::Integer(i))).collect();
+        db.h_set("h".to_owned(), added).unwrap();
+        let visited: Vec<&str> = first.iter().map(|(field, _)| field.as_str()).collect();
+        db.h_del("h", &visited).unwrap();
+
+        let mut seen: HashSet<String> = first.into_iter().map(|(field, _)| field).collect();
+        let mut cursor = cursor;
+        while cursor != 0 {
+            let (next, page) = db.h_scan("h", cursor, None, 10).unwrap();
+
+            for (field, _) in page {
+                assert!(seen.insert(field.clone()), "{} returned twice", field);
+            }
+            cursor = next;
+        }
+
+        for i in 0..50 {
+            assert!(seen.contains(&format!("f{}", i)), "f{} never returned", i);
+        }
+    }
+
+    #[test]
+    fn test_hscan_match() {
+        let mut db = BuckDB::new();
+        run(&mut db, "HSET h name:1 nick:2 age:3 nope:4").unwrap();
+
+        let mut seen = scan_all(&mut db, "h", "MATCH n[ai]* COUNT 1");
+        seen.sort();
+
+        assert_eq!(seen, vec!["name".to_owned(), "nick".to_owned()]);
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*", ""));
+        assert!(glob_match("user:*", "user:42"));
+        assert!(glob_match("h?llo", "hallo"));
+        assert!(!glob_match("h?llo", "hllo"));
+        assert!(glob_match("h*l*o", "heeellllo"));
+        assert!(glob_match("h[ae]llo", "hello"));
+        assert!(!glob_match("h[^e]llo", "hello"));
+        assert!(glob_match("h[a-c]llo", "hbllo"));
+        assert!(glob_match("a\\*b", "a*b"));
+        assert!(!glob_match("a\\*b", "axb"));
+        assert!(glob_match("[", "["));
+        assert!(!glob_match("abc", "abcd"));
+        assert!(!glob_match("", "a"));
+    }
+}
@@ -79,7 +79,7 @@ mod hash_method_tests {
             HashMap::from([
                 ("model".to_string(), BuckTypes::Unknown("Deimos".to_string())),
                 ("brand".to_string(), BuckTypes::Unknown("Ergonom".to_string())),
-                ("type".to_string(), BuckTypes::Unknown("Enduro bikes".to_string())),
+                ("type".to_string(), BuckTypes::String("Enduro bikes".to_string())),
                 ("price".to_string(), BuckTypes::Integer(4972)),
             ]),
         );
@@ -0,0 +1,188 @@
+mod common;
+
+#[cfg(test)]
+mod key_command_tests {
+    use std::sync::Arc;
+
+    use buck::clock::ManualClock;
+    use buck::engine::BuckDB;
+    use buck::errors::{BuckEngineError, BuckError};
+    use buck::parser::parse::parse_query;
+    use buck::parser::query::BuckQuery;
+    use buck::reply::BuckReply;
+    use buck::types::types::BuckTypes;
+
+    use crate::common::run;
+
+    /// The value the shards hold for `key`, if any of them has it.
+    fn sharded(db: &BuckDB, key: &str) -> Option<BuckTypes> {
+        db.shards.iter().find_map(|shard| shard.get(key).ok())
+    }
+
+    #[test]
+    fn test_parse_key_commands() {
+        assert_eq!(
+            parse_query("EXISTS a b a"),
+            Ok(BuckQuery::Exists(vec!["a".to_owned(), "b".to_owned(), "a".to_owned()]))
+        );
+        assert_eq!(
+            parse_query("RENAMENX a b"),
+            Ok(BuckQuery::RenameNx("a".to_owned(), "b".to_owned()))
+        );
+        assert_eq!(
+            parse_query("COPY a b replace"),
+            Ok(BuckQuery::Copy("a".to_owned(), "b".to_owned(), true))
+        );
+        assert_eq!(parse_query("DBSIZE"), Ok(BuckQuery::DbSize));
+        assert_eq!(parse_query("flushdb"), Ok(BuckQuery::FlushDb));
+        assert_eq!(parse_query("RANDOMKEY"), Ok(BuckQuery::RandomKey));
+
+        for input in [
+            "EXISTS", "UNLINK", "TOUCH \"\"", "RENAME a", "RENAME a b c", "RENAME a ''", "COPY a",
+            "COPY a b KEEP", "COPY a b REPLACE REPLACE", "DBSIZE now", "FLUSHDB ASYNC",
+        ] {
+            assert!(parse_query(input).is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_exists_touch_and_unlink() {
+        let mut db = BuckDB::new();
+        run(&mut db, "MSET a 1 b 2").unwrap();
+        db.commit().unwrap();
+        run(&mut db, "INSERT c 3").unwrap();
+
+        assert_eq!(run(&mut db, "EXISTS a c missing a"), Ok(BuckReply::Integer(3)));
+        assert_eq!(run(&mut db, "TOUCH a missing"), Ok(BuckReply::Integer(1)));
+
+        // unlike `DEL`, missing keys are skipped
+        assert_eq!(run(&mut db, "UNLINK a c missing"), Ok(BuckReply::Integer(2)));
+        assert_eq!(run(&mut db, "EXISTS a b c"), Ok(BuckReply::Integer(1)));
+
+        db.abort().unwrap();
+        assert_eq!(run(&mut db, "EXISTS a b c"), Ok(BuckReply::Integer(2)));
+    }
+
+    #[test]
+    fn test_rename() {
+        let mut db = BuckDB::new();
+        run(&mut db, "MSET a 1 b 2").unwrap();
+        db.commit().unwrap();
+
+        assert_eq!(run(&mut db, "RENAME a b"), Ok(BuckReply::ok()));
+        assert!(db.get("a").is_err());
+        assert_eq!(db.get("b"), Ok(&BuckTypes::Integer(1)));
+        assert_eq!(run(&mut db, "RENAMENX b a"), Ok(BuckReply::Integer(1)));
+
+        run(&mut db, "INSERT b 3").unwrap();
+        assert_eq!(run(&mut db, "RENAMENX a b"), Ok(BuckReply::Integer(0)));
+        assert_eq!(run(&mut db, "RENAME a a"), Ok(BuckReply::ok()));
+        assert_eq!(db.get("a"), Ok(&BuckTypes::Integer(1)));
+        assert_eq!(
+            run(&mut db, "RENAME missing a"),
+            Err(BuckError::Engine(BuckEngineError::KeyNotFound("missing".to_owned())))
+        );
+
+        // the renames were staged, so they can be rolled back
+        db.abort().unwrap();
+        assert_eq!(db.get("a"), Ok(&BuckTypes::Integer(1)));
+        assert_eq!(db.get("b"), Ok(&BuckTypes::Integer(2)));
+    }
+
+    #[test]
+    fn test_copy() {
+        let mut db = BuckDB::new();
+        run(&mut db, "RPUSH list 1 2").unwrap();
+        run(&mut db, "INSERT other 1").unwrap();
+
+        assert_eq!(run(&mut db, "COPY list copied"), Ok(BuckReply::Integer(1)));
+        assert_eq!(run(&mut db, "COPY list other"), Ok(BuckReply::Integer(0)));
+        assert_eq!(run(&mut db, "COPY missing other REPLACE"), Ok(BuckReply::Integer(0)));
+        assert_eq!(run(&mut db, "COPY list list REPLACE"), Ok(BuckReply::Integer(0)));
+        assert_eq!(run(&mut db, "COPY list other REPLACE"), Ok(BuckReply::Integer(1)));
+
+        // the copy is a value of its own
+        run(&mut db, "RPUSH copied 3").unwrap();
+        assert_eq!(db.l_range("list", 0, -1).unwrap().len(), 2);
+        assert_eq!(db.l_range("copied", 0, -1).unwrap().len(), 3);
+        assert_eq!(db.type_of("other"), Ok("list".to_owned()));
+    }
+
+    #[test]
+    fn test_timeouts_follow_the_value() {
+        let clock = ManualClock::new(1_000_000);
+        let mut db = BuckDB::with_clock(Arc::new(clock.clone()));
+        run(&mut db, "MSET a 1 b 2").unwrap();
+        run(&mut db, "PEXPIRE a 100").unwrap();
+        run(&mut db, "PEXPIRE b 500").unwrap();
+
+        run(&mut db, "COPY a c").unwrap();
+        run(&mut db, "RENAME a b").unwrap();
+        assert_eq!(db.pttl("a"), -2);
+        assert_eq!(db.pttl("b"), 100);
+        assert_eq!(db.pttl("c"), 100);
+
+        clock.advance(100);
+        assert_eq!(run(&mut db, "EXISTS b c"), Ok(BuckReply::Integer(0)));
+        assert_eq!(run(&mut db, "RENAME b d"), Err(BuckError::Engine(BuckEngineError::KeyNotFound("b".to_owned()))));
+        assert_eq!(run(&mut db, "DBSIZE"), Ok(BuckReply::Integer(0)));
+    }
+
+    #[test]
+    fn test_dbsize_flushdb_and_randomkey() {
+        let mut db = BuckDB::new();
+        assert_eq!(run(&mut db, "RANDOMKEY"), Ok(BuckReply::Nil));
+
+        run(&mut db, "MSET a 1 b 2 c 3").unwrap();
+        db.commit().unwrap();
+        run(&mut db, "MSET c 4 d 5").unwrap();
+        assert_eq!(run(&mut db, "DBSIZE"), Ok(BuckReply::Integer(4)));
+
+        for _ in 0..10 {
+            match run(&mut db, "RANDOMKEY") {
+                Ok(BuckReply::Bulk(key)) => assert!(["a", "b", "c", "d"].contains(&key.as_str())),
+                reply => panic!("unexpected reply {:?}", reply),
+            }
+        }
+
+        assert_eq!(run(&mut db, "FLUSHDB"), Ok(BuckReply::ok()));
+        assert_eq!(run(&mut db, "DBSIZE"), Ok(BuckReply::Integer(0)));
+        assert_eq!(run(&mut db, "RANDOMKEY"), Ok(BuckReply::Nil));
+
+        // the flush is staged like a `REMOVE` of every key
+        db.abort().unwrap();
+        assert_eq!(run(&mut db, "DBSIZE"), Ok(BuckReply::Integer(3)));
+        assert_eq!(db.get("c"), Ok(&BuckTypes::Integer(3)));
+
+        run(&mut db, "FLUSHDB").unwrap();
+        db.commit().unwrap();
+        assert!(db.data.is_empty());
+        assert_eq!(db.used_memory(), 0);
+    }
+
+    #[test]
+    fn test_shards_stay_in_sync() {
+        let mut db = BuckDB::new();
+        db.enable_sharding(4).unwrap();
+        run(&mut db, "MSET a 1 b 2 c 3").unwrap();
+        db.commit().unwrap();
+
+        run(&mut db, "RENAME a renamed").unwrap();
+        run(&mut db, "COPY b copied").unwrap();
+        run(&mut db, "UNLINK c").unwrap();
+
+        assert_eq!(sharded(&db, "a"), None);
+        assert_eq!(sharded(&db, "renamed"), Some(BuckTypes::Integer(1)));
+        assert_eq!(sharded(&db, "copied"), Some(BuckTypes::Integer(2)));
+        assert_eq!(sharded(&db, "c"), None);
+
+        db.abort().unwrap();
+        assert_eq!(sharded(&db, "a"), Some(BuckTypes::Integer(1)));
+        assert_eq!(sharded(&db, "renamed"), None);
+
+        run(&mut db, "FLUSHDB").unwrap();
+        assert!(["a", "b", "c"].iter().all(|key| sharded(&db, key).is_none()));
+        db.abort().unwrap();
+        assert_eq!(sharded(&db, "c"), Some(BuckTypes::Integer(3)));
+    }
+}
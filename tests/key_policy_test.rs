@@ -0,0 +1,228 @@
+mod common;
+
+#[cfg(test)]
+mod key_policy_tests {
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    use buck::databases;
+    use buck::engine::BuckDB;
+    use buck::errors::BuckError;
+    use buck::parser::errors::BuckParserError;
+    use buck::parser::key_policy::{KeyCharset, KeyPolicy};
+    use buck::parser::parse::parse_query;
+    use buck::parser::query::BuckQuery;
+    use buck::reply::BuckReply;
+    use buck::server::serve;
+    use buck::session::BuckSession;
+    use buck::types::types::BuckTypes;
+
+    use crate::common::run;
+
+    fn code(result: Result<BuckReply, BuckError>) -> &'static str {
+        result.unwrap_err().code()
+    }
+
+    #[test]
+    fn test_parse_any_key() {
+        assert_eq!(
+            parse_query("SET user:42 1"),
+            Ok(BuckQuery::Set("user:42".to_owned(), BuckTypes::Integer(1), Default::default()))
+        );
+        assert_eq!(
+            parse_query("GET session_abc order-2026 1st ключ"),
+            Ok(BuckQuery::Get(vec![
+                "session_abc".to_owned(),
+                "order-2026".to_owned(),
+                "1st".to_owned(),
+                "ключ".to_owned(),
+            ]))
+        );
+
+        // a key with whitespace is quoted, like a string value
+        assert_eq!(
+            parse_query("INSERT \"my key\" \"hello world\""),
+            Ok(BuckQuery::Insert("my key".to_owned(), BuckTypes::String("hello world".to_owned())))
+        );
+        assert_eq!(
+            parse_query("HGET 'my hash' \"a field\""),
+            Ok(BuckQuery::HGet("my hash".to_owned(), "a field".to_owned()))
+        );
+        assert_eq!(
+            parse_query("LSET \"my list\" 0 \"a b\""),
+            Ok(BuckQuery::LSet("my list".to_owned(), 0, BuckTypes::String("a b".to_owned())))
+        );
+        assert_eq!(
+            parse_query("RENAME \"a b\" 'c d'"),
+            Ok(BuckQuery::Rename("a b".to_owned(), "c d".to_owned()))
+        );
+
+        for input in ["GET \"\"", "TYPE a b", "INCR ''", "MSET '' 1", "HSET hash :1", "EXPIRE \"a 10"] {
+            assert!(parse_query(input).is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_keys_with_whitespace() {
+        let mut db = BuckDB::new();
+        run(&mut db, "MSET \"my key\" 1 my 2").unwrap();
+        run(&mut db, "RPUSH 'a list' 1 2").unwrap();
+
+        assert_eq!(run(&mut db, "GET \"my key\" my"), Ok(BuckReply::Array(vec![
+            BuckReply::Integer(1),
+            BuckReply::Integer(2),
+        ])));
+        assert_eq!(run(&mut db, "LEN \"a list\""), Ok(BuckReply::Integer(2)));
+        assert_eq!(db.get("my key"), Ok(&BuckTypes::Integer(1)));
+        assert!(db.get("key").is_err());
+    }
+
+    #[test]
+    fn test_query_keys() {
+        let keys = |input: &str| -> Vec<String> {
+            parse_query(input).unwrap().keys().into_iter().map(String::from).collect()
+        };
+
+        assert_eq!(keys("SET a 1"), vec!["a"]);
+        assert_eq!(keys("MSET a 1 b 2"), vec!["a", "b"]);
+        assert_eq!(keys("LMOVE a b LEFT RIGHT"), vec!["a", "b"]);
+        assert_eq!(keys("ZUNIONSTORE d 2 a b"), vec!["d", "a", "b"]);
+        assert_eq!(keys("HGET hash field"), vec!["hash"]);
+
+        // patterns and bounds are not keys
+        assert!(keys("KEYS user:*").is_empty());
+        assert!(keys("RANGE a z").is_empty());
+        assert!(keys("PREFIX user:").is_empty());
+    }
+
+    #[test]
+    fn test_charset() {
+        let charset = KeyCharset::parse("a-z0-9:_-").unwrap();
+        assert!("user:42_a-b".chars().all(|c| charset.contains(c)));
+        assert!(!charset.contains('A'));
+        assert!(!charset.contains(' '));
+        assert_eq!(charset.to_string(), "a-z0-9:_-");
+
+        assert!(KeyCharset::parse("-").unwrap().contains('-'));
+        assert_eq!(KeyCharset::parse("z-a"), None);
+        assert_eq!(KeyCharset::parse(""), None);
+    }
+
+    #[test]
+    fn test_policy() {
+        let policy = KeyPolicy {
+            max_length: Some(12),
+            charset: KeyCharset::parse("a-z0-9:"),
+            prefix: Some("app:".to_owned()),
+        };
+
+        assert_eq!(policy.check("app:user:42"), Ok(()));
+        assert_eq!(
+            policy.check("app:user:4200"),
+            Err(BuckParserError::KeyPolicyViolation("app:user:4200 is longer than 12 bytes".to_owned()))
+        );
+        assert_eq!(
+            policy.check("user:42"),
+            Err(BuckParserError::KeyPolicyViolation("user:42 does not start with app:".to_owned()))
+        );
+        assert_eq!(
+            policy.check("app:User"),
+            Err(BuckParserError::KeyPolicyViolation("app:User contains 'U', which is not in a-z0-9:".to_owned()))
+        );
+        assert!(policy.check_all(["app:a", "app:b"]).is_ok());
+        assert!(policy.check_all(["app:a", "b"]).is_err());
+        assert_eq!(KeyPolicy::default().check(""), Ok(()));
+    }
+
+    #[test]
+    fn test_config_key_policy() {
+        let mut db = BuckDB::new();
+        run(&mut db, "INSERT legacy 1").unwrap();
+
+        run(&mut db, "CONFIG SET key-prefix tenant:").unwrap();
+        run(&mut db, "CONFIG SET key-max-length 16").unwrap();
+        run(&mut db, "CONFIG SET key-charset a-z0-9:_").unwrap();
+
+        assert_eq!(run(&mut db, "SET tenant:user_1 1"), Ok(BuckReply::ok()));
+        assert_eq!(code(run(&mut db, "SET user_1 1")), "KEY_POLICY_VIOLATION");
+        assert_eq!(code(run(&mut db, "SET tenant:user-1 1")), "KEY_POLICY_VIOLATION");
+        assert_eq!(code(run(&mut db, "SET tenant:a_long_name 1")), "KEY_POLICY_VIOLATION");
+
+        // every key a query names is checked, reads included, before anything runs
+        assert_eq!(code(run(&mut db, "MSET tenant:a 1 b 2")), "KEY_POLICY_VIOLATION");
+        assert_eq!(code(run(&mut db, "GET legacy")), "KEY_POLICY_VIOLATION");
+        assert!(db.get("tenant:a").is_err());
+        assert_eq!(run(&mut db, "KEYS *"), Ok(BuckReply::Array(vec![
+            BuckReply::Bulk("legacy".to_owned()),
+            BuckReply::Bulk("tenant:user_1".to_owned()),
+        ])));
+
+        assert_eq!(
+            run(&mut db, "CONFIG GET key-charset"),
+            Ok(BuckReply::Map(vec![(
+                BuckReply::Bulk("key-charset".to_owned()),
+                BuckReply::Bulk("a-z0-9:_".to_owned()),
+            )]))
+        );
+        assert_eq!(code(run(&mut db, "CONFIG SET key-charset z-a")), "INVALID_CONFIG");
+        assert_eq!(code(run(&mut db, "CONFIG SET key-max-length -1")), "INVALID_CONFIG");
+
+        // each rule is turned off on its own
+        run(&mut db, "CONFIG SET key-prefix \"\"").unwrap();
+        run(&mut db, "CONFIG SET key-max-length 0").unwrap();
+        assert_eq!(run(&mut db, "GET legacy"), Ok(BuckReply::Integer(1)));
+        assert_eq!(code(run(&mut db, "GET Legacy")), "KEY_POLICY_VIOLATION");
+        run(&mut db, "CONFIG SET key-charset ''").unwrap();
+        assert_eq!(db.key_policy, KeyPolicy::default());
+    }
+
+    #[test]
+    fn test_policy_per_database() {
+        let mut dbs = databases::new(2);
+        let mut session = BuckSession::new();
+        dbs[1].key_policy.prefix = Some("tenant:".to_owned());
+
+        let mut run_in = |input: &str| session.execute_in(parse_query(input)?, input, &mut dbs);
+        run_in("MSET a 1 tenant:b 2").unwrap();
+
+        // a key only moves where the destination accepts it
+        assert_eq!(code(run_in("MOVE a 1")), "KEY_POLICY_VIOLATION");
+        assert_eq!(run_in("MOVE tenant:b 1"), Ok(BuckReply::Integer(1)));
+
+        // a queued query that breaks the policy fails the transaction
+        run_in("SELECT 1").unwrap();
+        run_in("MULTI").unwrap();
+        run_in("INSERT tenant:c 1").unwrap();
+        run_in("INSERT c 1").unwrap();
+        assert_eq!(code(run_in("EXEC")), "KEY_POLICY_VIOLATION");
+        assert_eq!(run_in("EXISTS tenant:b tenant:c"), Ok(BuckReply::Integer(1)));
+    }
+
+    #[test]
+    fn test_server_keys_over_resp() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let dbs = Arc::new(Mutex::new(databases::new(1)));
+
+        let shared = Arc::clone(&dbs);
+        thread::spawn(move || serve(listener, shared));
+
+        let send = |stream: &mut TcpStream, request: &str, expected: &str| {
+            stream.write_all(request.as_bytes()).unwrap();
+
+            let mut reply = vec![0u8; expected.len()];
+            stream.read_exact(&mut reply).unwrap();
+            assert_eq!(String::from_utf8_lossy(&reply), expected);
+        };
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        send(&mut stream, "*3\r\n$3\r\nSET\r\n$7\r\nuser:42\r\n$2\r\nok\r\n", "+OK\r\n");
+        send(&mut stream, "*3\r\n$3\r\nSET\r\n$6\r\nmy key\r\n$8\r\nsay \"hi\"\r\n", "+OK\r\n");
+        send(&mut stream, "*2\r\n$3\r\nGET\r\n$6\r\nmy key\r\n", "$8\r\nsay \"hi\"\r\n");
+        send(&mut stream, "*2\r\n$3\r\nGET\r\n$7\r\nuser:42\r\n", "$2\r\nok\r\n");
+
+        assert_eq!(dbs.lock().unwrap()[0].db_size(), Ok(2));
+    }
+}
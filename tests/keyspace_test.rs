@@ -0,0 +1,200 @@
+mod common;
+
+#[cfg(test)]
+mod keyspace_tests {
+    use std::collections::{HashMap, HashSet};
+    use std::sync::Arc;
+
+    use buck::clock::ManualClock;
+    use buck::engine::BuckDB;
+    use buck::parser::parse::parse_query;
+    use buck::parser::query::BuckQuery;
+    use buck::reply::BuckReply;
+    use buck::scan;
+    use buck::types::types::BuckTypes;
+
+    use crate::common::run;
+
+    fn keys(names: &[&str]) -> BuckReply {
+        BuckReply::Array(names.iter().map(|name| BuckReply::Bulk(name.to_string())).collect())
+    }
+
+    /// Scan the whole database, running `between` after every page.
+    fn scan_all(db: &mut BuckDB, count: usize, mut between: impl FnMut(&mut BuckDB)) -> Vec<String> {
+        let mut cursor = 0;
+        let mut seen = Vec::new();
+
+        loop {
+            let (next, page) = db.scan(cursor, None, count, None).unwrap();
+            seen.extend(page);
+            between(db);
+
+            if next == 0 {
+                return seen;
+            }
+            cursor = next;
+        }
+    }
+
+    #[test]
+    fn test_parse_keyspace_queries() {
+        assert_eq!(parse_query("KEYS user*"), Ok(BuckQuery::Keys("user*".to_owned())));
+        assert_eq!(
+            parse_query("SCAN 0"),
+            Ok(BuckQuery::Scan(0, None, 10, None))
+        );
+        assert_eq!(
+            parse_query("SCAN 42 TYPE List COUNT 5 MATCH a*"),
+            Ok(BuckQuery::Scan(42, Some("a*".to_owned()), 5, Some("list".to_owned())))
+        );
+
+        for input in [
+            "KEYS", "KEYS  ", "SCAN", "SCAN x", "SCAN -1", "SCAN 0 COUNT 0", "SCAN 0 TYPE",
+            "SCAN 0 MATCH", "SCAN 0 LIMIT 1",
+        ] {
+            assert!(parse_query(input).is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_keys() {
+        let mut db = BuckDB::new();
+        run(&mut db, "MSET user1 1 user2 2 admin 3 user10 4").unwrap();
+        db.commit().unwrap();
+        run(&mut db, "INSERT user3 5").unwrap();
+        run(&mut db, "REMOVE user2").unwrap();
+
+        // staged writes are part of the keyspace, and keys come out in order
+        assert_eq!(run(&mut db, "KEYS *"), Ok(keys(&["admin", "user1", "user10", "user3"])));
+        assert_eq!(run(&mut db, "KEYS user?"), Ok(keys(&["user1", "user3"])));
+        assert_eq!(run(&mut db, "KEYS [au]*[0n]"), Ok(keys(&["admin", "user10"])));
+        assert_eq!(run(&mut db, "KEYS nothing"), Ok(keys(&[])));
+    }
+
+    #[test]
+    fn test_expired_keys_are_left_out() {
+        let clock = ManualClock::new(1_000_000);
+        let mut db = BuckDB::with_clock(Arc::new(clock.clone()));
+        run(&mut db, "MSET a 1 b 2").unwrap();
+        run(&mut db, "PEXPIRE a 100").unwrap();
+
+        clock.advance(100);
+        assert_eq!(run(&mut db, "KEYS *"), Ok(keys(&["b"])));
+        assert_eq!(db.scan(0, None, 10, None), Ok((0, vec!["b".to_owned()])));
+    }
+
+    #[test]
+    fn test_scan_match_and_type() {
+        let mut db = BuckDB::new();
+        run(&mut db, "MSET a1 1 a2 \"two\" b1 3").unwrap();
+        run(&mut db, "RPUSH a3 1").unwrap();
+
+        let (cursor, mut found) = db.scan(0, Some("a*"), 100, None).unwrap();
+        found.sort();
+        assert_eq!(cursor, 0);
+        assert_eq!(found, vec!["a1", "a2", "a3"]);
+
+        assert_eq!(db.scan(0, None, 100, Some("list")), Ok((0, vec!["a3".to_owned()])));
+        assert_eq!(db.scan(0, Some("b*"), 100, Some("integer")), Ok((0, vec!["b1".to_owned()])));
+
+        let reply = run(&mut db, "SCAN 0 MATCH b* COUNT 100").unwrap();
+        assert_eq!(
+            reply,
+            BuckReply::Array(vec![BuckReply::Bulk("0".to_owned()), keys(&["b1"])])
+        );
+    }
+
+    #[test]
+    fn test_scan_returns_every_key_once() {
+        let mut db = BuckDB::new();
+        for i in 0..100 {
+            db.insert(format!("key{}", i), BuckTypes::Integer(i)).unwrap();
+        }
+        db.commit().unwrap();
+
+        let seen = scan_all(&mut db, 7, |_| {});
+        let unique: HashSet<&String> = seen.iter().collect();
+
+        assert_eq!(seen.len(), 100);
+        assert_eq!(unique.len(), 100);
+    }
+
+    #[test]
+    fn test_scan_survives_writes_between_calls() {
+        let mut db = BuckDB::new();
+        for i in 0..100 {
+            db.insert(format!("stable{}", i), BuckTypes::Integer(i)).unwrap();
+            db.insert(format!("doomed{}", i), BuckTypes::Integer(i)).unwrap();
+        }
+        db.commit().unwrap();
+
+        let mut round = 0;
+        let seen = scan_all(&mut db, 5, |db| {
+            // add and remove keys, and commit some of it, while the scan is going on
+            db.insert(format!("new{}", round), BuckTypes::Integer(round)).unwrap();
+            db.remove(&format!("doomed{}", round)).unwrap();
+            if round % 3 == 0 {
+                db.commit().unwrap();
+            }
+            round += 1;
+        });
+
+        let seen: HashSet<String> = seen.into_iter().collect();
+        for i in 0..100 {
+            assert!(seen.contains(&format!("stable{}", i)), "stable{} was skipped", i);
+        }
+    }
+
+    #[test]
+    fn test_scan_follows_rollback_and_expiry() {
+        let clock = ManualClock::new(1_000_000);
+        let mut db = BuckDB::with_clock(Arc::new(clock.clone()));
+        for i in 0..20 {
+            db.insert(format!("key{}", i), BuckTypes::Integer(i)).unwrap();
+        }
+        db.commit().unwrap();
+
+        db.insert("staged".to_owned(), BuckTypes::Integer(0)).unwrap();
+        db.remove("key0").unwrap();
+        db.abort().unwrap();
+        db.expire("key1", 10).unwrap();
+        db.commit().unwrap();
+        clock.advance(10);
+
+        let mut seen = scan_all(&mut db, 3, |_| {});
+        seen.sort();
+        assert_eq!(seen, db.keys("*").unwrap());
+        assert_eq!(seen.len(), 19);
+
+        // an expired key that is deleted for good stays gone
+        db.active_expire_cycle(100);
+        assert_eq!(scan_all(&mut db, 100, |_| {}).len(), 19);
+    }
+
+    #[test]
+    fn test_unordered_scan_matches_ordered_scan() {
+        let entries: HashMap<String, usize> = (0..50).map(|i| (format!("field{}", i), i)).collect();
+        let mut ordered: Vec<(u64, &String, &usize)> =
+            entries.iter().map(|(name, value)| (scan::position(name), name, value)).collect();
+        ordered.sort();
+
+        for count in 1..8 {
+            let mut cursor = 0;
+            let mut seen = Vec::new();
+
+            loop {
+                let (next, page) = scan::scan(&entries, cursor, None, count);
+                let start = ordered.iter().position(|(hash, _, _)| *hash >= cursor).unwrap();
+                assert_eq!((next, page.clone()), scan::scan_ordered(ordered[start..].to_vec(), None, count));
+
+                seen.extend(page);
+                if next == 0 {
+                    break;
+                }
+                cursor = next;
+            }
+
+            assert_eq!(seen.len(), 50, "count {}", count);
+        }
+    }
+}
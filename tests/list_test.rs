@@ -0,0 +1,224 @@
+mod common;
+
+#[cfg(test)]
+mod list_tests {
+    use buck::engine::BuckDB;
+    use buck::errors::{BuckEngineError, BuckError};
+    use buck::parser::parse::parse_query;
+    use buck::parser::query::BuckQuery;
+    use buck::reply::BuckReply;
+    use buck::types::errors::BuckTypeError;
+    use buck::types::list::BuckList;
+    use buck::types::types::BuckTypes;
+
+    use crate::common::run;
+
+    fn int(i: i64) -> BuckTypes {
+        BuckTypes::Integer(i)
+    }
+
+    fn ints(values: &[i64]) -> Vec<BuckTypes> {
+        values.iter().map(|i| int(*i)).collect()
+    }
+
+    fn list_of(values: &[i64]) -> BuckList {
+        let mut list = BuckList::new();
+        for value in values {
+            list.push(int(*value));
+        }
+        list
+    }
+
+    #[test]
+    fn test_push_head_and_tail() {
+        let mut db = BuckDB::new();
+
+        assert_eq!(run(&mut db, "LPUSH l 1 2 3"), Ok(BuckReply::Integer(3)));
+        assert_eq!(run(&mut db, "RPUSH l 4 5"), Ok(BuckReply::Integer(5)));
+
+        // each LPUSH value goes to the head, so they end up reversed
+        assert_eq!(db.l_range("l", 0, -1), Ok(ints(&[3, 2, 1, 4, 5])));
+    }
+
+    #[test]
+    fn test_pop_with_count() {
+        let mut db = BuckDB::new();
+        run(&mut db, "RPUSH l 1 2 3 4 5").unwrap();
+
+        assert_eq!(run(&mut db, "LPOP l"), Ok(BuckReply::Integer(1)));
+        assert_eq!(run(&mut db, "RPOP l"), Ok(BuckReply::Integer(5)));
+        assert_eq!(
+            run(&mut db, "LPOP l 2"),
+            Ok(BuckReply::Array(vec![BuckReply::Integer(2), BuckReply::Integer(3)]))
+        );
+
+        // a count larger than the list pops what is left
+        assert_eq!(
+            run(&mut db, "RPOP l 10"),
+            Ok(BuckReply::Array(vec![BuckReply::Integer(4)]))
+        );
+        assert_eq!(run(&mut db, "LPOP l"), Ok(BuckReply::Nil));
+        assert_eq!(run(&mut db, "LPOP l 3"), Ok(BuckReply::Nil));
+    }
+
+    #[test]
+    fn test_range_and_index() {
+        let list = list_of(&[0, 1, 2, 3, 4]);
+
+        assert_eq!(list.range(0, -1), ints(&[0, 1, 2, 3, 4]));
+        assert_eq!(list.range(-2, -1), ints(&[3, 4]));
+        assert_eq!(list.range(1, 2), ints(&[1, 2]));
+        assert_eq!(list.range(-100, 100), ints(&[0, 1, 2, 3, 4]));
+        assert_eq!(list.range(3, 1), ints(&[]));
+        assert_eq!(list.range(5, 10), ints(&[]));
+
+        assert_eq!(list.get(0), Some(&int(0)));
+        assert_eq!(list.get(-1), Some(&int(4)));
+        assert_eq!(list.get(5), None);
+        assert_eq!(list.get(-6), None);
+    }
+
+    #[test]
+    fn test_set() {
+        let mut db = BuckDB::new();
+        run(&mut db, "RPUSH l 1 2 3").unwrap();
+
+        assert_eq!(run(&mut db, "LSET l -1 9"), Ok(BuckReply::Status("OK".to_owned())));
+        assert_eq!(run(&mut db, "LINDEX l 2"), Ok(BuckReply::Integer(9)));
+        assert_eq!(run(&mut db, "LINDEX l 3"), Ok(BuckReply::Nil));
+
+        assert_eq!(
+            run(&mut db, "LSET l 3 0"),
+            Err(BuckError::Type(BuckTypeError::IndexOutOfRange(3)))
+        );
+        assert_eq!(
+            run(&mut db, "LSET missing 0 0"),
+            Err(BuckError::Engine(BuckEngineError::KeyNotFound("missing".to_owned())))
+        );
+    }
+
+    #[test]
+    fn test_insert() {
+        let mut db = BuckDB::new();
+        run(&mut db, "RPUSH l 1 2 3").unwrap();
+
+        assert_eq!(run(&mut db, "LINSERT l BEFORE 2 7"), Ok(BuckReply::Integer(4)));
+        assert_eq!(run(&mut db, "LINSERT l after 3 8"), Ok(BuckReply::Integer(5)));
+        assert_eq!(db.l_range("l", 0, -1), Ok(ints(&[1, 7, 2, 3, 8])));
+
+        assert_eq!(run(&mut db, "LINSERT l BEFORE 42 0"), Ok(BuckReply::Integer(-1)));
+        assert_eq!(run(&mut db, "LINSERT missing BEFORE 1 0"), Ok(BuckReply::Integer(0)));
+        assert!(run(&mut db, "LINSERT l SIDEWAYS 1 0").is_err());
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut list = list_of(&[1, 2, 1, 3, 1, 2, 1]);
+        assert_eq!(list.remove(2, &int(1)), 2);
+        assert_eq!(list.data, ints(&[2, 3, 1, 2, 1]));
+
+        let mut list = list_of(&[1, 2, 1, 3, 1, 2, 1]);
+        assert_eq!(list.remove(-2, &int(1)), 2);
+        assert_eq!(list.data, ints(&[1, 2, 1, 3, 2]));
+
+        let mut list = list_of(&[1, 2, 1, 3, 1, 2, 1]);
+        assert_eq!(list.remove(0, &int(1)), 4);
+        assert_eq!(list.data, ints(&[2, 3, 2]));
+
+        let mut db = BuckDB::new();
+        run(&mut db, "RPUSH l 1 2 1").unwrap();
+        assert_eq!(run(&mut db, "LREM l 0 1"), Ok(BuckReply::Integer(2)));
+        assert_eq!(run(&mut db, "LREM l 0 5"), Ok(BuckReply::Integer(0)));
+        assert_eq!(run(&mut db, "LREM missing 0 1"), Ok(BuckReply::Integer(0)));
+    }
+
+    #[test]
+    fn test_trim() {
+        let mut list = list_of(&[0, 1, 2, 3, 4]);
+        list.trim(1, -2);
+        assert_eq!(list.data, ints(&[1, 2, 3]));
+
+        list.trim(-100, 100);
+        assert_eq!(list.data, ints(&[1, 2, 3]));
+
+        list.trim(2, 0);
+        assert!(list.is_empty());
+
+        let mut db = BuckDB::new();
+        run(&mut db, "RPUSH l 1 2 3").unwrap();
+        assert_eq!(run(&mut db, "LTRIM l 0 0"), Ok(BuckReply::Status("OK".to_owned())));
+        assert_eq!(db.l_range("l", 0, -1), Ok(ints(&[1])));
+    }
+
+    #[test]
+    fn test_empty_list_is_deleted() {
+        let mut db = BuckDB::new();
+
+        run(&mut db, "RPUSH l 1 2").unwrap();
+        run(&mut db, "LPOP l 2").unwrap();
+        assert!(db.get("l").is_err());
+
+        run(&mut db, "RPUSH l 1 2").unwrap();
+        db.commit().unwrap();
+        run(&mut db, "LTRIM l 5 10").unwrap();
+        assert!(db.get("l").is_err());
+        db.commit().unwrap();
+        assert!(!db.data.contains_key("l"));
+    }
+
+    #[test]
+    fn test_changes_to_committed_list_are_rolled_back() {
+        let mut db = BuckDB::new();
+        run(&mut db, "RPUSH l 1 2 3").unwrap();
+        db.commit().unwrap();
+
+        run(&mut db, "LPUSH l 0").unwrap();
+        run(&mut db, "LSET l 1 9").unwrap();
+        run(&mut db, "RPOP l").unwrap();
+        assert_eq!(db.l_range("l", 0, -1), Ok(ints(&[0, 9, 2])));
+
+        // the committed copy is untouched until the commit
+        assert_eq!(db.data.get("l"), Some(&BuckTypes::List(list_of(&[1, 2, 3]))));
+
+        db.abort().unwrap();
+        assert_eq!(db.l_range("l", 0, -1), Ok(ints(&[1, 2, 3])));
+    }
+
+    #[test]
+    fn test_wrong_type() {
+        let mut db = BuckDB::new();
+        run(&mut db, "INSERT s \"text\"").unwrap();
+
+        for input in ["LPUSH s 1", "RPOP s", "LRANGE s 0 -1", "LINDEX s 0", "LTRIM s 0 1"] {
+            assert_eq!(
+                run(&mut db, input),
+                Err(BuckError::Engine(BuckEngineError::TypeNotSupported("s".to_owned()))),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_list_queries() {
+        assert_eq!(
+            parse_query("RPUSH l 1 2"),
+            Ok(BuckQuery::RPush("l".to_owned(), ints(&[1, 2])))
+        );
+        assert_eq!(parse_query("LPOP l"), Ok(BuckQuery::LPop("l".to_owned(), None)));
+        assert_eq!(parse_query("RPOP l 2"), Ok(BuckQuery::RPop("l".to_owned(), Some(2))));
+        assert_eq!(parse_query("LRANGE l 0 -1"), Ok(BuckQuery::LRange("l".to_owned(), 0, -1)));
+        assert_eq!(parse_query("LINDEX l -2"), Ok(BuckQuery::LIndex("l".to_owned(), -2)));
+        assert_eq!(parse_query("LSET l 0 1"), Ok(BuckQuery::LSet("l".to_owned(), 0, int(1))));
+        assert_eq!(
+            parse_query("LINSERT l AFTER 1 2"),
+            Ok(BuckQuery::LInsert("l".to_owned(), false, int(1), int(2)))
+        );
+        assert_eq!(parse_query("LREM l -1 3"), Ok(BuckQuery::LRem("l".to_owned(), -1, int(3))));
+        assert_eq!(parse_query("LTRIM l 1 2"), Ok(BuckQuery::LTrim("l".to_owned(), 1, 2)));
+
+        assert!(parse_query("LPOP l -1").is_err());
+        assert!(parse_query("LRANGE l 0").is_err());
+        assert!(parse_query("LINDEX l x").is_err());
+    }
+}
@@ -0,0 +1,203 @@
+#[cfg(test)]
+mod mvcc_tests {
+    use std::sync::Arc;
+
+    use buck::clock::ManualClock;
+    use buck::engine::BuckDB;
+    use buck::errors::BuckEngineError;
+    use buck::types::types::BuckTypes;
+
+    fn int(n: i64) -> BuckTypes {
+        BuckTypes::Integer(n)
+    }
+
+    #[test]
+    fn test_view_does_not_see_staged_writes() {
+        let mut db = BuckDB::new();
+        db.insert("a".to_owned(), int(1)).unwrap();
+        db.commit().unwrap();
+
+        db.insert("a".to_owned(), int(2)).unwrap();
+        db.insert("b".to_owned(), int(3)).unwrap();
+
+        let view = db.read_view();
+        assert_eq!(db.get_at(&view, "a"), Ok(&int(1)));
+        assert_eq!(
+            db.get_at(&view, "b"),
+            Err(BuckEngineError::KeyNotFound("b".to_owned()))
+        );
+
+        // the writer still reads its own staged data
+        assert_eq!(db.get("a"), Ok(&int(2)));
+    }
+
+    #[test]
+    fn test_view_is_pinned_across_commits() {
+        let mut db = BuckDB::new();
+        db.insert("a".to_owned(), int(1)).unwrap();
+        db.commit().unwrap();
+        let first = db.read_view();
+
+        db.insert("a".to_owned(), int(2)).unwrap();
+        db.insert("b".to_owned(), int(20)).unwrap();
+        db.commit().unwrap();
+        let second = db.read_view();
+        assert!(second.seq() > first.seq());
+
+        db.remove("a").unwrap();
+        db.commit().unwrap();
+        let third = db.read_view();
+
+        assert_eq!(db.get_at(&first, "a"), Ok(&int(1)));
+        assert!(db.get_at(&first, "b").is_err());
+
+        assert_eq!(db.get_at(&second, "a"), Ok(&int(2)));
+        assert_eq!(db.get_at(&second, "b"), Ok(&int(20)));
+
+        assert!(db.get_at(&third, "a").is_err());
+        assert_eq!(db.get_at(&third, "b"), Ok(&int(20)));
+    }
+
+    #[test]
+    fn test_in_place_changes_are_invisible_until_commit() {
+        let mut db = BuckDB::new();
+        db.insert("a".to_owned(), int(1)).unwrap();
+        db.insert("b".to_owned(), int(2)).unwrap();
+        db.commit().unwrap();
+
+        // in the committed state these change `data` directly
+        db.update("a", int(10)).unwrap();
+        db.remove("b").unwrap();
+
+        let view = db.read_view();
+        assert_eq!(db.get_at(&view, "a"), Ok(&int(1)));
+        assert_eq!(db.get_at(&view, "b"), Ok(&int(2)));
+
+        db.commit().unwrap();
+        assert_eq!(db.get_at(&view, "a"), Ok(&int(1)));
+        assert_eq!(db.get_at(&view, "b"), Ok(&int(2)));
+
+        let view = db.read_view();
+        assert_eq!(db.get_at(&view, "a"), Ok(&int(10)));
+        assert!(db.get_at(&view, "b").is_err());
+    }
+
+    #[test]
+    fn test_rollback_is_invisible_to_views() {
+        let mut db = BuckDB::new();
+        db.insert("a".to_owned(), int(1)).unwrap();
+        db.commit().unwrap();
+        let view = db.read_view();
+        let seq = db.commit_seq;
+
+        db.update("a", int(10)).unwrap();
+        db.abort().unwrap();
+
+        assert_eq!(db.commit_seq, seq);
+        assert_eq!(db.get_at(&view, "a"), Ok(&int(1)));
+        assert_eq!(db.versions.version_count(), 0);
+    }
+
+    #[test]
+    fn test_no_versions_without_views() {
+        let mut db = BuckDB::new();
+
+        for i in 0..10 {
+            db.insert("a".to_owned(), int(i)).unwrap();
+            db.commit().unwrap();
+        }
+
+        assert_eq!(db.versions.version_count(), 0);
+    }
+
+    #[test]
+    fn test_versions_are_collected_when_views_are_dropped() {
+        let mut db = BuckDB::new();
+        db.insert("a".to_owned(), int(0)).unwrap();
+        db.commit().unwrap();
+
+        let old = db.read_view();
+        for i in 1..=3 {
+            db.insert("a".to_owned(), int(i)).unwrap();
+            db.commit().unwrap();
+        }
+        let middle = db.read_view();
+        for i in 4..=6 {
+            db.insert("a".to_owned(), int(i)).unwrap();
+            db.commit().unwrap();
+        }
+
+        assert_eq!(db.get_at(&old, "a"), Ok(&int(0)));
+        assert_eq!(db.get_at(&middle, "a"), Ok(&int(3)));
+
+        // versions 1 and 2 were never visible to any view
+        assert_eq!(db.versions.version_count(), 2);
+
+        drop(old);
+        assert_eq!(db.collect_garbage(), 1);
+        assert_eq!(db.get_at(&middle, "a"), Ok(&int(3)));
+
+        drop(middle);
+        assert_eq!(db.collect_garbage(), 1);
+        assert_eq!(db.versions.version_count(), 0);
+        assert_eq!(db.get("a"), Ok(&int(6)));
+    }
+
+    #[test]
+    fn test_cloned_view_keeps_versions_alive() {
+        let mut db = BuckDB::new();
+        db.insert("a".to_owned(), int(1)).unwrap();
+        db.commit().unwrap();
+
+        let view = db.read_view();
+        let copy = view.clone();
+        drop(view);
+
+        db.insert("a".to_owned(), int(2)).unwrap();
+        db.commit().unwrap();
+        assert_eq!(db.get_at(&copy, "a"), Ok(&int(1)));
+
+        drop(copy);
+        db.collect_garbage();
+        assert_eq!(db.versions.version_count(), 0);
+    }
+
+    #[test]
+    fn test_view_keeps_expired_key() {
+        let clock = ManualClock::new(1_000);
+        let mut db = BuckDB::with_clock(Arc::new(clock.clone()));
+        db.insert("a".to_owned(), int(1)).unwrap();
+        db.commit().unwrap();
+        db.expire("a", 10).unwrap();
+
+        let view = db.read_view();
+        clock.advance(10);
+        assert_eq!(db.active_expire_cycle(10), 1);
+
+        assert!(db.get("a").is_err());
+        assert_eq!(db.get_at(&view, "a"), Ok(&int(1)));
+    }
+
+    #[test]
+    fn test_view_survives_load() {
+        let path = std::env::temp_dir().join(format!("buck_mvcc_{}.snapshot", std::process::id()));
+
+        let mut db = BuckDB::new();
+        db.insert("a".to_owned(), int(1)).unwrap();
+        db.commit().unwrap();
+        db.save(&path).unwrap();
+
+        db.insert("a".to_owned(), int(2)).unwrap();
+        db.insert("b".to_owned(), int(3)).unwrap();
+        db.commit().unwrap();
+
+        let view = db.read_view();
+        db.load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(db.get("a"), Ok(&int(1)));
+        assert!(db.get("b").is_err());
+        assert_eq!(db.get_at(&view, "a"), Ok(&int(2)));
+        assert_eq!(db.get_at(&view, "b"), Ok(&int(3)));
+    }
+}
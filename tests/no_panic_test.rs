@@ -0,0 +1,274 @@
+mod common;
+
+#[cfg(test)]
+mod no_panic_tests {
+    use std::panic::{self, AssertUnwindSafe};
+
+    use proptest::prelude::*;
+
+    use buck::engine::BuckDB;
+    use buck::errors::{BuckEngineError, BuckError};
+    use buck::parser::errors::BuckParserError;
+    use buck::parser::parse::parse_query;
+    use buck::reply::BuckReply;
+    use buck::session::BuckSession;
+    use buck::types::errors::BuckTypeError;
+    use buck::types::list::BuckList;
+    use buck::types::types::BuckTypes;
+
+    use crate::common::run_session;
+
+    /// A database holding one key of every type, plus an empty list.
+    fn populated_db() -> BuckDB {
+        let mut db = BuckDB::new();
+
+        for input in [
+            "INSERT str \"text\"",
+            "INSERT int 10",
+            "INSERT float 1.5",
+            "INSERT bool true",
+            "INSERT list [1,2,3]",
+            "INSERT set (1,2,3)",
+            "INSERT hash {a:1,b:2}",
+            "ZADD zset 1 a 2 b",
+        ] {
+            parse_query(input).unwrap().execute(input, &mut db).unwrap();
+        }
+        db.insert("empty".to_owned(), BuckTypes::List(BuckList::new())).unwrap();
+
+        db
+    }
+
+    /// Every command, fed the inputs it is expected to reject.
+    const FAILURE_INPUTS: &[&str] = &[
+        // no arguments, or the wrong number of them
+        "", " ", "GET", "INSERT", "INSERT a", "UPDATE", "UPDATE a", "REMOVE", "TYPE", "LPUSH",
+        "LPUSH l", "LPOP", "SADD", "SADD s", "SREM", "SREM s", "SINTER", "SINTER  ", "LEN",
+        "HSET", "HSET h", "SHARD", "SHARD x", "SAVE", "LOAD", "EXPIRE", "EXPIRE a", "PEXPIRE a x",
+        "TTL", "PTTL", "PERSIST", "CONFIG", "CONFIG GET", "CONFIG SET maxmemory", "WATCH",
+        // invalid keys and values
+        "GET 1a", "INSERT 1a 1", "INSERT a \"", "INSERT a '", "INSERT a {a}", "INSERT a {:1}",
+        "INSERT a {a:}", "INSERT a [", "INSERT a ()", "SADD s 5..1", "SADD s 1..x",
+        "SADD s 1..2..3", "HSET h 1:1", "EXPIRE a 99999999999999999999", "CONFIG SET nope 1",
+        "CONFIG SET maxmemory lots", "CONFIG SET maxmemory-policy nope", "NOSUCHCOMMAND",
+        // missing keys
+        "GET missing", "REMOVE missing", "UPDATE missing 1", "TYPE missing", "LPOP missing",
+        "SREM missing 1", "SINTER missing set", "LEN missing", "EXPIRE missing 1",
+        "TTL missing", "PERSIST missing", "LOAD /nonexistent/buck.snapshot",
+        // wrong types
+        "LPUSH str 1", "LPOP str", "LPOP set", "SADD list 1", "SREM hash 1", "SINTER str set",
+        "SINTER set list", "LEN int", "LEN str", "HSET list a:1",
+        // empty collections and out of range indices
+        "LPOP empty", "LPOP empty", "RPOP empty 3", "LEN empty", "LINDEX list 99",
+        "LSET list 99 1", "LSET list -99 1", "LSET missing 0 1", "LRANGE list 5 1",
+        "LTRIM list 9 -9", "LREM missing 0 1", "LINSERT list BEFORE 99 1",
+        "LINSERT list SIDEWAYS 1 1", "LPOP list -1", "LRANGE list a b",
+        // blocking commands only try once outside of the server
+        "BLPOP", "BLPOP empty", "BLPOP missing 0", "BRPOP str 1", "BLPOP list -1",
+        "BLMOVE missing list LEFT LEFT 0", "LMOVE list str LEFT LEFT", "LMOVE list list UP DOWN",
+        // set commands
+        "SISMEMBER", "SISMEMBER set", "SMISMEMBER set", "SMEMBERS", "SCARD str", "SUNION",
+        "SDIFF str", "SINTERCARD", "SINTERCARD x set", "SINTERCARD 5 set", "SINTERCARD 1 set LIMIT x",
+        "SINTERSTORE set", "SUNIONSTORE list set", "SRANDMEMBER set -100",
+        "SRANDMEMBER set -9223372036854775808", "SRANDMEMBER set -100000000000", "SPOP set 99",
+        "SPOP set -1", "SMOVE set str 1", "SMOVE missing set 1", "SMOVE set set 1.5",
+        // hash commands
+        "HGET", "HGET hash", "HGET str a", "HMGET hash", "HDEL hash", "HDEL list a", "HGETALL",
+        "HKEYS set", "HVALS int", "HLEN str", "HSETNX hash a", "HSETNX 1a a 1", "HEXISTS hash 1a",
+        "HSTRLEN missing a", "HINCRBY hash a x", "HINCRBY hash a 9223372036854775807",
+        "HINCRBY str a 1", "HINCRBYFLOAT hash a inf", "HINCRBYFLOAT hash a nan", "HSCAN hash",
+        "HSCAN hash x", "HSCAN hash 0 COUNT 0", "HSCAN hash 0 MATCH", "HSCAN list 0",
+        "HSCAN hash 0 MATCH [", "HSCAN hash 0 MATCH \\",
+        // sorted set commands
+        "ZADD", "ZADD zset 1", "ZADD zset x a", "ZADD zset nan a", "ZADD zset NX XX 1 a",
+        "ZADD list 1 a", "ZREM zset", "ZSCORE zset", "ZRANK hash a", "ZRANGE zset 0",
+        "ZRANGE zset a b", "ZRANGE zset 0 -1 LIMIT 0 1", "ZRANGE zset a b BYLEX",
+        "ZRANGE zset - + BYLEX WITHSCORES", "ZRANGEBYSCORE zset 1", "ZRANGEBYLEX zset - + WITHSCORES", "ZINCRBY zset nan a",
+        "ZINCRBY str 1 a", "ZPOPMIN zset -1", "ZPOPMAX set", "ZCOUNT zset a b", "ZCARD list",
+        "ZUNIONSTORE out", "ZUNIONSTORE out 0 zset", "ZUNIONSTORE out 3 zset",
+        "ZINTERSTORE out 1 zset WEIGHTS", "ZINTERSTORE out 1 str", "ZINTERSTORE out 1 zset AGGREGATE x",
+        // counters
+        "INCR", "INCR str", "INCR list", "INCR float", "DECRBY int x", "INCRBY int 1.5",
+        "DECRBY int -9223372036854775808", "INCRBY int 9223372036854775807", "INCRBYFLOAT hash 1",
+        "INCRBYFLOAT float inf", "INCRBYFLOAT float nan",
+        // string commands
+        "APPEND", "APPEND list x", "STRLEN hash", "GETRANGE str 0", "GETRANGE str -99999 99999",
+        "SETRANGE str -1 x", "SETRANGE str 99999999999999 x", "SETRANGE set 0 x", "GETSET",
+        "GETSET list 1", "GETDEL zset", "GETEX str EX", "GETEX str EX 0", "GETEX str EX 1 PX 1",
+        "GETEX str EX 99999999999999999", "GETEX list PERSIST", "MGET", "MSET a", "MSET a 1 b",
+        "MSETNX 1a 1", "MSET a \"", "MSET a [1,",
+        // conditional writes
+        "SET", "SET a", "SET a 1 NX XX", "SET a 1 EX", "SET a 1 EX -1", "SET a 1 PX 1 KEEPTTL",
+        "SET list 1 GET", "SET a 1 EXAT 99999999999999999999", "SET a \"b", "SET a 1 GET GET",
+        // keyspace
+        "KEYS", "KEYS [", "KEYS \\", "SCAN", "SCAN x", "SCAN 0 COUNT 0", "SCAN 0 TYPE",
+        "SCAN 0 MATCH [^", "SCAN 0 TYPE nope", "SCAN 99999999999999999999",
+        // key ranges
+        "RANGE", "RANGE a", "RANGE z a", "RANGE (a (a", "RANGE ( (", "RANGE [ [", "RANGE a z LIMIT",
+        "RANGE a z LIMIT -1", "RANGE + -", "PREFIX", "PREFIX a b",
+        // key commands
+        "EXISTS", "TOUCH", "UNLINK", "UNLINK 1a", "RENAME", "RENAME missing a", "RENAME str str",
+        "RENAMENX str list", "COPY", "COPY str", "COPY str str", "COPY str list NOW", "DBSIZE x",
+        "FLUSHDB", "RANDOMKEY", "RANDOMKEY",
+        // databases
+        "SELECT", "SELECT 1", "SELECT 99999999999999999999", "MOVE str 1", "MOVE str 0", "SWAPDB 0 1",
+        "SWAPDB 0 0", "FLUSHALL x", "FLUSHALL",
+        // transactions in the wrong state
+        "COMMIT", "COMMIT", "ROLLBACK", "ROLLBACK", "UNWATCH", "SHARD 0",
+    ];
+
+    #[test]
+    fn test_failure_inputs_do_not_panic() {
+        for input in FAILURE_INPUTS {
+            let mut db = populated_db();
+            let mut session = BuckSession::new();
+
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                run_session(&mut db, &mut session, input)
+            }));
+
+            assert!(result.is_ok(), "`{}` panicked", input);
+        }
+    }
+
+    #[test]
+    fn test_failure_inputs_in_sequence_do_not_panic() {
+        // the same inputs against one database, so that each one sees the
+        // state the previous ones left behind
+        let mut db = populated_db();
+        let mut session = BuckSession::new();
+
+        for input in FAILURE_INPUTS {
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                run_session(&mut db, &mut session, input)
+            }));
+
+            assert!(result.is_ok(), "`{}` panicked", input);
+        }
+    }
+
+    #[test]
+    fn test_failure_inputs_inside_transaction_do_not_panic() {
+        let mut db = populated_db();
+        let mut session = BuckSession::new();
+
+        for input in FAILURE_INPUTS {
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                let _ = run_session(&mut db, &mut session, "MULTI");
+                let _ = run_session(&mut db, &mut session, input);
+                run_session(&mut db, &mut session, "EXEC")
+            }));
+
+            assert!(result.is_ok(), "`{}` panicked inside MULTI", input);
+        }
+    }
+
+    #[test]
+    fn test_errors_are_propagated() {
+        let mut db = populated_db();
+        let mut session = BuckSession::new();
+
+        assert_eq!(run_session(&mut db, &mut session, "GET missing"), Ok(BuckReply::Nil));
+        assert_eq!(
+            run_session(&mut db, &mut session, "LSET list 10 1"),
+            Err(BuckError::Type(BuckTypeError::IndexOutOfRange(10)))
+        );
+        assert_eq!(
+            run_session(&mut db, &mut session, "LSET missing 0 1"),
+            Err(BuckError::Engine(BuckEngineError::KeyNotFound("missing".to_owned())))
+        );
+        assert_eq!(
+            run_session(&mut db, &mut session, "LPOP str"),
+            Err(BuckError::Engine(BuckEngineError::TypeNotSupported("str".to_owned())))
+        );
+        assert_eq!(
+            run_session(&mut db, &mut session, "INSERT a {a}"),
+            Err(BuckError::Parser(BuckParserError::HashValueIsEmpty("a".to_owned())))
+        );
+        assert_eq!(
+            run_session(&mut db, &mut session, "NOSUCHCOMMAND"),
+            Err(BuckError::Parser(BuckParserError::InvalidQueryCommand(
+                "NOSUCHCOMMAND".to_owned()
+            )))
+        );
+    }
+
+    #[test]
+    fn test_error_codes() {
+        let errors = [
+            (BuckError::from(BuckEngineError::KeyNotFound("k".to_owned())), "KEY_NOT_FOUND"),
+            (BuckError::from(BuckEngineError::OutOfMemory), "OUT_OF_MEMORY"),
+            (BuckError::from(BuckEngineError::InvalidDatabase(16)), "INVALID_DATABASE"),
+            (BuckError::from(BuckTypeError::ListIsEmpty), "LIST_IS_EMPTY"),
+            (BuckError::from(BuckTypeError::NotAnInteger("f".to_owned())), "NOT_AN_INTEGER"),
+            (BuckError::from(BuckTypeError::Overflow), "OVERFLOW"),
+            (BuckError::from(BuckTypeError::ScoreIsNan("m".to_owned())), "SCORE_IS_NAN"),
+            (BuckError::from(BuckTypeError::StringTooLong), "STRING_TOO_LONG"),
+            (BuckError::from(BuckParserError::InvalidKey("1".to_owned())), "INVALID_KEY"),
+            (
+                BuckError::from(BuckParserError::KeyPolicyViolation("k is too long".to_owned())),
+                "KEY_POLICY_VIOLATION",
+            ),
+            // a type error raised by the engine keeps its own code
+            (
+                BuckError::from(BuckEngineError::from(BuckTypeError::ListIsEmpty)),
+                "LIST_IS_EMPTY",
+            ),
+        ];
+
+        for (error, code) in errors {
+            assert_eq!(error.code(), code);
+            assert!(error.to_string().starts_with("[Error] "), "{}", error);
+        }
+    }
+
+    fn command_line() -> impl Strategy<Value = String> {
+        let commands = prop::sample::select(vec![
+            "GET", "INSERT", "SET", "UPDATE", "REMOVE", "TYPE", "LPUSH", "RPUSH", "LPOP", "RPOP",
+            "LRANGE", "LINDEX", "LSET", "LINSERT", "LREM", "LTRIM", "LMOVE",
+            "BLPOP", "BRPOP", "BLMOVE", "SADD", "SREM",
+            "SINTER", "SUNION", "SDIFF", "SINTERCARD", "SINTERSTORE", "SUNIONSTORE", "SDIFFSTORE",
+            "SISMEMBER", "SMISMEMBER", "SMEMBERS", "SCARD", "SRANDMEMBER", "SPOP", "SMOVE", "LEN", "HSET",
+            "HGET", "HMGET", "HGETALL", "HDEL", "HEXISTS", "HKEYS", "HVALS", "HLEN", "HSETNX",
+            "HINCRBY", "HINCRBYFLOAT", "HSTRLEN", "HSCAN", "ZADD", "ZREM", "ZSCORE", "ZRANK",
+            "ZREVRANK", "ZRANGE", "ZRANGEBYSCORE", "ZRANGEBYLEX", "ZINCRBY", "ZPOPMIN", "ZPOPMAX", "ZCOUNT", "ZCARD",
+            "ZUNIONSTORE", "ZINTERSTORE", "INCR", "DECR", "INCRBY", "DECRBY", "INCRBYFLOAT", "APPEND", "STRLEN", "GETRANGE", "SETRANGE",
+            "GETSET", "GETDEL", "GETEX", "MGET", "MSET", "MSETNX", "KEYS", "SCAN", "RANGE", "PREFIX", "EXISTS", "TOUCH",
+            "RENAME", "RENAMENX", "COPY", "UNLINK", "DBSIZE", "FLUSHDB", "RANDOMKEY", "SELECT",
+            "MOVE", "SWAPDB", "FLUSHALL", "EXPIRE", "PEXPIRE", "TTL", "PTTL", "PERSIST", "CONFIG",
+            "MULTI", "EXEC", "DISCARD", "WATCH", "UNWATCH", "PING",
+        ]);
+        let args = prop::collection::vec(
+            prop_oneof![
+                prop::sample::select(vec![
+                    "str", "int", "list", "set", "hash", "empty", "missing", "0", "-1", "1..3",
+                    "\"", "[]", "()", "{}", "{a:}", "get", "maxmemory", "a:1", "BEFORE", "LEFT",
+                    "-100", "99", "MATCH", "COUNT", "*", "zset", "(1", "+inf", "BYSCORE", "WITHSCORES", "PX", "PERSIST", "NX", "KEEPTTL", "TYPE", "REV", "LIMIT", "(", "REPLACE",
+                ])
+                .prop_map(str::to_owned),
+                "[ -~]{0,8}",
+            ],
+            0..4,
+        );
+
+        (commands, args).prop_map(|(command, args)| {
+            std::iter::once(command.to_owned()).chain(args).collect::<Vec<_>>().join(" ")
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn test_random_commands_do_not_panic(inputs in prop::collection::vec(command_line(), 1..16)) {
+            let mut db = populated_db();
+            let mut session = BuckSession::new();
+
+            for input in &inputs {
+                let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                    run_session(&mut db, &mut session, input)
+                }));
+
+                prop_assert!(result.is_ok(), "`{}` panicked", input);
+            }
+        }
+    }
+}
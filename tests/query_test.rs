@@ -7,6 +7,7 @@ mod query_tests {
     use buck::types::types::BuckTypes;
 
     #[test]
+    #[allow(clippy::excessive_precision)]
     fn test_parse_insert() {
         let query = "INSERT key 1";
         let result = parse_query(query);
@@ -116,9 +117,13 @@ mod query_tests {
             ))
         );
 
-        let invalid_number_key = "INSERT 1 2";
-        let result = parse_query(invalid_number_key);
-        assert_eq!(result, Err(BuckParserError::InvalidKey("1".to_owned())));
+        let number_key = "INSERT 1 2";
+        let result = parse_query(number_key);
+        assert_eq!(result, Ok(Insert("1".to_owned(), BuckTypes::Integer(2))));
+
+        let empty_key = "INSERT \"\" 2";
+        let result = parse_query(empty_key);
+        assert_eq!(result, Err(BuckParserError::InvalidKey("\"\"".to_owned())));
     }
 
     #[test]
@@ -149,12 +154,17 @@ mod query_tests {
             Err(BuckParserError::InvalidQueryCommand(query.to_owned()))
         );
 
-        let invalid_number_key = "GET 1 2";
-        let result = parse_query(invalid_number_key);
-        assert_eq!(result, Err(BuckParserError::InvalidKey("1, 2".to_owned())));
+        let number_keys = "GET 1 2";
+        let result = parse_query(number_keys);
+        assert_eq!(result, Ok(Get(vec!["1".to_owned(), "2".to_owned()])));
+
+        let empty_key = "GET key ''";
+        let result = parse_query(empty_key);
+        assert_eq!(result, Err(BuckParserError::InvalidKey("''".to_owned())));
     }
 
     #[test]
+    #[allow(clippy::excessive_precision)]
     fn test_parse_update() {
         let query = "UPDATE key 1";
         let result = parse_query(query);
@@ -353,13 +363,16 @@ mod query_tests {
             ))
         );
 
-        let invalid_number_key = "UPDATE 1 2";
-        let result = parse_query(invalid_number_key);
-        assert_eq!(result, Err(BuckParserError::InvalidKey("1".to_owned())));
+        let number_key = "UPDATE 1 2";
+        let result = parse_query(number_key);
+        assert_eq!(result, Ok(Update("1".to_owned(), BuckTypes::Integer(2))));
 
         let invalid_query = "UPDATE 1 2 3 4 5 6 7 8 9 10";
         let result = parse_query(invalid_query);
-        assert_eq!(result, Err(BuckParserError::InvalidKey("1".to_owned())));
+        assert_eq!(
+            result,
+            Err(BuckParserError::UpdateValueContainsSpace("2 3 4 5 6 7 8 9 10".to_owned()))
+        );
 
         let invalid_hash_query = "UPDATE key {key1:1, key2:}";
         let result = parse_query(invalid_hash_query);
@@ -401,8 +414,12 @@ mod query_tests {
             Err(BuckParserError::InvalidQueryCommand(query.to_owned()))
         );
 
-        let invalid_number_key = "REMOVE 1 2";
-        let result = parse_query(invalid_number_key);
-        assert_eq!(result, Err(BuckParserError::InvalidKey("1, 2".to_owned())));
+        let number_keys = "REMOVE 1 2";
+        let result = parse_query(number_keys);
+        assert_eq!(result, Ok(Remove(vec!["1".to_owned(), "2".to_owned()])));
+
+        let empty_key = "REMOVE key ''";
+        let result = parse_query(empty_key);
+        assert_eq!(result, Err(BuckParserError::InvalidKey("''".to_owned())));
     }
 }
@@ -0,0 +1,181 @@
+mod common;
+
+#[cfg(test)]
+mod range_tests {
+    use std::ops::Bound;
+    use std::sync::Arc;
+
+    use buck::clock::ManualClock;
+    use buck::engine::BuckDB;
+    use buck::parser::parse::parse_query;
+    use buck::parser::query::BuckQuery;
+    use buck::range::prefix_end;
+    use buck::reply::BuckReply;
+    use buck::types::types::BuckTypes;
+
+    use crate::common::run;
+
+    fn entries(pairs: &[(&str, i64)]) -> BuckReply {
+        BuckReply::Array(
+            pairs
+                .iter()
+                .flat_map(|(key, value)| [BuckReply::Bulk(key.to_string()), BuckReply::Integer(*value)])
+                .collect(),
+        )
+    }
+
+    fn keys(db: &BuckDB, start: Bound<&str>, end: Bound<&str>) -> Vec<String> {
+        db.range(start, end).unwrap().map(|(key, _)| key.clone()).collect()
+    }
+
+    /// `evt2026101800` to `evt2026101823`, one per hour, committed.
+    fn db_with_events() -> BuckDB {
+        let mut db = BuckDB::new();
+
+        for hour in 0..24 {
+            db.insert(format!("evt20261018{:02}", hour), BuckTypes::Integer(hour)).unwrap();
+        }
+        db.insert("other".to_owned(), BuckTypes::Integer(-1)).unwrap();
+        db.commit().unwrap();
+
+        db
+    }
+
+    #[test]
+    fn test_parse_range_queries() {
+        assert_eq!(
+            parse_query("RANGE a z"),
+            Ok(BuckQuery::Range(
+                Bound::Included("a".to_owned()),
+                Bound::Included("z".to_owned()),
+                None,
+                false
+            ))
+        );
+        assert_eq!(
+            parse_query("RANGE (a + rev LIMIT 5"),
+            Ok(BuckQuery::Range(Bound::Excluded("a".to_owned()), Bound::Unbounded, Some(5), true))
+        );
+        assert_eq!(
+            parse_query("RANGE - [z"),
+            Ok(BuckQuery::Range(Bound::Unbounded, Bound::Included("z".to_owned()), None, false))
+        );
+        assert_eq!(parse_query("PREFIX evt2026"), Ok(BuckQuery::Prefix("evt2026".to_owned())));
+
+        for input in [
+            "RANGE", "RANGE a", "RANGE a z LIMIT", "RANGE a z LIMIT -1", "RANGE a z SIDEWAYS",
+            "PREFIX", "PREFIX a b",
+        ] {
+            assert!(parse_query(input).is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_range() {
+        let mut db = db_with_events();
+
+        assert_eq!(
+            run(&mut db, "RANGE evt2026101803 evt2026101805"),
+            Ok(entries(&[("evt2026101803", 3), ("evt2026101804", 4), ("evt2026101805", 5)]))
+        );
+        assert_eq!(
+            run(&mut db, "RANGE (evt2026101803 (evt2026101805"),
+            Ok(entries(&[("evt2026101804", 4)]))
+        );
+        assert_eq!(
+            run(&mut db, "RANGE evt2026101822 + LIMIT 2"),
+            Ok(entries(&[("evt2026101822", 22), ("evt2026101823", 23)]))
+        );
+        assert_eq!(
+            run(&mut db, "RANGE - + REV LIMIT 2"),
+            Ok(entries(&[("other", -1), ("evt2026101823", 23)]))
+        );
+
+        // a range that is empty or backwards is not an error
+        assert_eq!(run(&mut db, "RANGE z a"), Ok(entries(&[])));
+        assert_eq!(run(&mut db, "RANGE (a (a"), Ok(entries(&[])));
+        assert_eq!(run(&mut db, "RANGE a (a"), Ok(entries(&[])));
+    }
+
+    #[test]
+    fn test_prefix() {
+        let mut db = db_with_events();
+
+        let reply = run(&mut db, "PREFIX evt202610181").unwrap();
+        let expected: Vec<(String, i64)> = (10..20).map(|hour| (format!("evt20261018{}", hour), hour)).collect();
+        let expected: Vec<(&str, i64)> = expected.iter().map(|(key, hour)| (key.as_str(), *hour)).collect();
+        assert_eq!(reply, entries(&expected));
+
+        assert_eq!(run(&mut db, "PREFIX evt2027"), Ok(entries(&[])));
+        assert_eq!(db.prefix("").unwrap().count(), 25);
+
+        assert_eq!(prefix_end("abc"), Some("abd".to_owned()));
+        assert_eq!(prefix_end("a\u{10ffff}"), Some("b".to_owned()));
+        assert_eq!(prefix_end("\u{d7ff}"), Some("\u{e000}".to_owned()));
+        assert_eq!(prefix_end("\u{10ffff}"), None);
+    }
+
+    #[test]
+    fn test_range_merges_staged_writes() {
+        let mut db = BuckDB::new();
+        run(&mut db, "MSET b 1 d 1 f 1").unwrap();
+        db.commit().unwrap();
+
+        run(&mut db, "MSET a 2 d 2 e 2 g 2").unwrap();
+        run(&mut db, "REMOVE f").unwrap();
+
+        assert_eq!(
+            run(&mut db, "RANGE - +"),
+            Ok(entries(&[("a", 2), ("b", 1), ("d", 2), ("e", 2), ("g", 2)]))
+        );
+        assert_eq!(
+            run(&mut db, "RANGE c + REV"),
+            Ok(entries(&[("g", 2), ("e", 2), ("d", 2)]))
+        );
+
+        // nothing staged is seen once it was rolled back
+        db.abort().unwrap();
+        assert_eq!(run(&mut db, "RANGE - +"), Ok(entries(&[("b", 1), ("d", 1), ("f", 1)])));
+    }
+
+    #[test]
+    fn test_range_from_both_ends() {
+        let mut db = BuckDB::new();
+        for key in ["a", "c", "e", "g"] {
+            db.insert(key.to_owned(), BuckTypes::Integer(1)).unwrap();
+        }
+        db.commit().unwrap();
+        for key in ["b", "c", "f", "h"] {
+            db.insert(key.to_owned(), BuckTypes::Integer(2)).unwrap();
+        }
+
+        let mut range = db.range(Bound::Unbounded, Bound::Unbounded).unwrap();
+        let mut seen = Vec::new();
+
+        // alternate ends until they meet
+        while let Some((key, _)) = range.next() {
+            seen.push(key.clone());
+
+            match range.next_back() {
+                Some((key, _)) => seen.push(key.clone()),
+                None => break,
+            }
+        }
+
+        assert_eq!(seen, vec!["a", "h", "b", "g", "c", "f", "e"]);
+        assert_eq!(range.next(), None);
+        assert_eq!(range.next_back(), None);
+    }
+
+    #[test]
+    fn test_range_skips_expired_keys() {
+        let clock = ManualClock::new(1_000_000);
+        let mut db = BuckDB::with_clock(Arc::new(clock.clone()));
+        run(&mut db, "MSET a 1 b 2 c 3").unwrap();
+        run(&mut db, "PEXPIRE b 10").unwrap();
+
+        clock.advance(10);
+        assert_eq!(keys(&db, Bound::Unbounded, Bound::Unbounded), vec!["a", "c"]);
+        assert_eq!(keys(&db, Bound::Included("b"), Bound::Included("b")), Vec::<String>::new());
+    }
+}
@@ -0,0 +1,162 @@
+mod common;
+
+#[cfg(test)]
+mod reply_tests {
+    use std::collections::HashMap;
+
+    use buck::engine::BuckDB;
+    use buck::errors::{BuckEngineError, BuckError};
+    use buck::log::BuckLog;
+    use buck::protocol::resp::RespValue;
+    use buck::reply::BuckReply;
+    use buck::types::sets::{BuckSets, Setable};
+    use buck::types::types::BuckTypes;
+
+    use crate::common::run;
+
+    fn bulk(s: &str) -> BuckReply {
+        BuckReply::Bulk(s.to_owned())
+    }
+
+    #[test]
+    fn test_get_returns_typed_values() {
+        let mut db = BuckDB::new();
+        db.insert("s".to_owned(), BuckTypes::String("hello".to_owned())).unwrap();
+        db.insert("i".to_owned(), BuckTypes::Integer(42)).unwrap();
+        db.insert("f".to_owned(), BuckTypes::Float(1.5)).unwrap();
+        db.insert("b".to_owned(), BuckTypes::Boolean(true)).unwrap();
+
+        assert_eq!(run(&mut db, "GET s"), Ok(bulk("hello")));
+        assert_eq!(run(&mut db, "GET i"), Ok(BuckReply::Integer(42)));
+        assert_eq!(run(&mut db, "GET f"), Ok(BuckReply::Float(1.5)));
+        assert_eq!(run(&mut db, "GET b"), Ok(BuckReply::Boolean(true)));
+        assert_eq!(run(&mut db, "GET missing"), Ok(BuckReply::Nil));
+    }
+
+    #[test]
+    fn test_get_many_keys_returns_array() {
+        let mut db = BuckDB::new();
+        db.insert("a".to_owned(), BuckTypes::Integer(1)).unwrap();
+        db.insert("c".to_owned(), BuckTypes::Integer(3)).unwrap();
+
+        assert_eq!(
+            run(&mut db, "GET a b c"),
+            Ok(BuckReply::Array(vec![
+                BuckReply::Integer(1),
+                BuckReply::Nil,
+                BuckReply::Integer(3),
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_collections_convert_to_nested_replies() {
+        let mut db = BuckDB::new();
+        db.r_push("list".to_owned(), BuckTypes::Integer(1)).unwrap();
+        db.r_push("list".to_owned(), BuckTypes::String("two".to_owned())).unwrap();
+        db.h_set(
+            "hash".to_owned(),
+            HashMap::from([
+                ("y".to_owned(), BuckTypes::Integer(2)),
+                ("x".to_owned(), BuckTypes::Boolean(false)),
+            ]),
+        )
+        .unwrap();
+
+        assert_eq!(
+            run(&mut db, "GET list"),
+            Ok(BuckReply::Array(vec![BuckReply::Integer(1), bulk("two")]))
+        );
+        assert_eq!(
+            run(&mut db, "GET hash"),
+            Ok(BuckReply::Map(vec![
+                (bulk("x"), BuckReply::Boolean(false)),
+                (bulk("y"), BuckReply::Integer(2)),
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_sinter_returns_the_intersection() {
+        let mut db = BuckDB::new();
+        let sets = [("s1", ["a", "b", "c"]), ("s2", ["b", "c", "d"]), ("s3", ["c", "b", "e"])];
+
+        for (key, members) in sets {
+            let members: Vec<Setable> = members
+                .iter()
+                .map(|m| Setable::String(m.to_string()))
+                .collect();
+
+            let mut set = BuckSets::new();
+            set.insert(&members);
+            db.insert(key.to_owned(), BuckTypes::Sets(set)).unwrap();
+        }
+
+        assert_eq!(
+            run(&mut db, "SINTER s1 s2 s3"),
+            Ok(BuckReply::Set(vec![bulk("b"), bulk("c")]))
+        );
+        assert_eq!(run(&mut db, "SINTER s1 missing"), Ok(BuckReply::Set(vec![])));
+    }
+
+    #[test]
+    fn test_scalar_replies() {
+        let mut db = BuckDB::new();
+
+        assert_eq!(run(&mut db, "INSERT a 1"), Ok(BuckReply::ok()));
+        assert_eq!(run(&mut db, "PING"), Ok(BuckReply::Status("PONG".to_owned())));
+        assert_eq!(run(&mut db, "TYPE a"), Ok(BuckReply::Status("integer".to_owned())));
+        assert_eq!(run(&mut db, "TTL a"), Ok(BuckReply::Integer(-1)));
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(BuckReply::Nil.to_string(), "(nil)");
+        assert_eq!(BuckReply::ok().to_string(), "OK");
+        assert_eq!(BuckReply::Integer(3).to_string(), "(integer) 3");
+        assert_eq!(bulk("hi").to_string(), "\"hi\"");
+        assert_eq!(BuckReply::Array(vec![]).to_string(), "(empty array)");
+        assert_eq!(
+            BuckReply::Array(vec![
+                bulk("a"),
+                BuckReply::Array(vec![BuckReply::Integer(1), BuckReply::Integer(2)]),
+            ])
+            .to_string(),
+            "1) \"a\"\n2) 1) (integer) 1\n   2) (integer) 2"
+        );
+        assert_eq!(
+            BuckReply::Map(vec![(bulk("f"), BuckReply::Boolean(true))]).to_string(),
+            "1# \"f\" => (boolean) true"
+        );
+    }
+
+    #[test]
+    fn test_log_displays_as_its_reply() {
+        let log = BuckLog::LengthOk(4);
+
+        assert_eq!(log.to_string(), BuckReply::Integer(4).to_string());
+        assert_eq!(BuckLog::InsertOk("INSERT a 1".to_owned()).to_string(), "OK");
+    }
+
+    #[test]
+    fn test_reply_to_resp() {
+        assert_eq!(RespValue::from(BuckReply::Nil), RespValue::BulkString(None));
+        assert_eq!(
+            RespValue::from(BuckReply::Float(2.5)),
+            RespValue::BulkString(Some("2.5".to_owned()))
+        );
+        assert_eq!(
+            RespValue::from(BuckReply::Map(vec![(bulk("f"), BuckReply::Integer(1))])),
+            RespValue::Array(Some(vec![
+                RespValue::BulkString(Some("f".to_owned())),
+                RespValue::Integer(1),
+            ]))
+        );
+        assert_eq!(
+            RespValue::from(BuckReply::from(&BuckError::from(BuckEngineError::KeyNotFound(
+                "k".to_owned()
+            )))),
+            RespValue::Error("KEY_NOT_FOUND Key not found: k".to_owned())
+        );
+    }
+}
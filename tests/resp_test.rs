@@ -0,0 +1,256 @@
+#[cfg(test)]
+mod resp_tests {
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    use buck::engine::BuckDB;
+    use buck::log::BuckLog;
+    use buck::protocol::errors::RespError;
+    use buck::parser::parse::parse_request;
+    use buck::parser::query::BuckQuery;
+    use buck::protocol::resp::{
+        encode, take_request, take_value, RespValue, MAX_ARRAY_LEN, MAX_BULK_LEN, MAX_INLINE_LEN,
+    };
+    use buck::server::serve;
+    use buck::types::types::BuckTypes;
+
+    fn request(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_encode_values() {
+        assert_eq!(encode(&RespValue::SimpleString("OK".to_owned())), b"+OK\r\n");
+        assert_eq!(encode(&RespValue::Error("ERR oops".to_owned())), b"-ERR oops\r\n");
+        assert_eq!(encode(&RespValue::Integer(-42)), b":-42\r\n");
+        assert_eq!(encode(&RespValue::BulkString(None)), b"$-1\r\n");
+        assert_eq!(
+            encode(&RespValue::BulkString(Some("foo".to_owned()))),
+            b"$3\r\nfoo\r\n"
+        );
+        assert_eq!(
+            encode(&RespValue::Array(Some(vec![
+                RespValue::Integer(1),
+                RespValue::BulkString(Some("a".to_owned())),
+            ]))),
+            b"*2\r\n:1\r\n$1\r\na\r\n"
+        );
+    }
+
+    #[test]
+    fn test_take_value_round_trip() {
+        let value = RespValue::Array(Some(vec![
+            RespValue::SimpleString("OK".to_owned()),
+            RespValue::Integer(7),
+            RespValue::BulkString(Some("hello world".to_owned())),
+            RespValue::BulkString(None),
+        ]));
+
+        let encoded = encode(&value);
+        let mut bytes = encoded.as_slice();
+        assert_eq!(take_value(&mut bytes), Ok(Some(value)));
+        assert!(bytes.is_empty());
+    }
+
+    #[test]
+    fn test_take_request() {
+        let mut bytes: &[u8] = b"*3\r\n$3\r\nSET\r\n$3\r\nkey\r\n$5\r\nvalue\r\n*1\r\n$4\r\nPING\r\n";
+        assert_eq!(
+            take_request(&mut bytes),
+            Ok(Some(vec!["SET".to_owned(), "key".to_owned(), "value".to_owned()]))
+        );
+        assert_eq!(take_request(&mut bytes), Ok(Some(vec!["PING".to_owned()])));
+        assert!(bytes.is_empty());
+
+        // inline commands
+        let mut bytes: &[u8] = b"GET key\r\n";
+        assert_eq!(
+            take_request(&mut bytes),
+            Ok(Some(vec!["GET".to_owned(), "key".to_owned()]))
+        );
+
+        let mut bytes: &[u8] = b"SET  \"my key\" 'it s' \"a \\\"b\\\"\" \"\"\r\n";
+        assert_eq!(
+            take_request(&mut bytes),
+            Ok(Some(request(&["SET", "my key", "it s", "a \"b\"", ""])))
+        );
+
+        let mut bytes: &[u8] = b"SET key \"value\r\n";
+        assert_eq!(take_request(&mut bytes), Err(RespError::UnbalancedQuotes));
+
+        // partial requests are left in the buffer
+        let mut bytes: &[u8] = b"*2\r\n$3\r\nGET\r\n$3\r\nke";
+        assert_eq!(take_request(&mut bytes), Ok(None));
+        assert_eq!(bytes.len(), 19);
+
+        let mut bytes: &[u8] = b"*1\r\n:1\r\n";
+        assert_eq!(take_request(&mut bytes), Err(RespError::NotAnArray));
+
+        let mut bytes: &[u8] = b"*1\r\n$-5\r\n";
+        assert!(take_request(&mut bytes).is_err());
+
+        // nested arrays are refused, however deep they go
+        let mut bytes: &[u8] = b"*1\r\n*1\r\n$4\r\nPING\r\n";
+        assert_eq!(take_request(&mut bytes), Err(RespError::NotAnArray));
+
+        let deep = b"*1\r\n".repeat(1_000_000);
+        assert_eq!(take_request(&mut deep.as_slice()), Err(RespError::NotAnArray));
+    }
+
+    #[test]
+    fn test_request_lines_are_bounded() {
+        let mut line = format!("GET {}", "k".repeat(MAX_INLINE_LEN - 4)).into_bytes();
+        assert_eq!(take_request(&mut line.as_slice()), Ok(None));
+
+        line.extend(b"\r\n");
+        assert!(matches!(take_request(&mut line.as_slice()), Ok(Some(args)) if args.len() == 2));
+
+        // a longer line is refused before its end arrives
+        line.truncate(MAX_INLINE_LEN);
+        line.extend(b"kk");
+        assert_eq!(take_request(&mut line.as_slice()), Err(RespError::LineTooLong));
+
+        let mut length = b"*1\r\n$".to_vec();
+        length.extend(b"0".repeat(MAX_INLINE_LEN + 2));
+        assert_eq!(take_request(&mut length.as_slice()), Err(RespError::LineTooLong));
+    }
+
+    #[test]
+    fn test_lengths_are_bounded() {
+        let mut bytes: &[u8] = b"*9223372036854775807\r\n";
+        assert_eq!(
+            take_value(&mut bytes),
+            Err(RespError::InvalidLength("9223372036854775807".to_owned()))
+        );
+
+        let mut bytes: &[u8] = b"$9223372036854775807\r\nabc\r\n";
+        assert!(take_value(&mut bytes).is_err());
+
+        let too_long = format!("${}\r\n", MAX_BULK_LEN + 1);
+        assert!(take_value(&mut too_long.as_bytes()).is_err());
+        let too_many = format!("*{}\r\n", MAX_ARRAY_LEN + 1);
+        assert!(take_value(&mut too_many.as_bytes()).is_err());
+
+        // a large array that is not fully buffered yet only waits for more
+        let mut bytes: &[u8] = b"*1000000\r\n$3\r\nGET\r\n";
+        assert_eq!(take_value(&mut bytes), Ok(None));
+        assert_eq!(bytes.len(), 19);
+    }
+
+    #[test]
+    fn test_parse_request() {
+        let set = |key: &str, value: BuckTypes| {
+            Ok(BuckQuery::Set(key.to_owned(), value, Default::default()))
+        };
+        let string = |value: &str| BuckTypes::String(value.to_owned());
+
+        // every argument is taken as it was sent
+        assert_eq!(parse_request(&request(&["SET", "key", ""])), set("key", string("")));
+        assert_eq!(parse_request(&request(&["SET", "key", "foo bar"])), set("key", string("foo bar")));
+        assert_eq!(parse_request(&request(&["SET", "key", "'foo'"])), set("key", string("'foo'")));
+        assert_eq!(
+            parse_request(&request(&["SET", "my key", "it's \"hi\" there"])),
+            set("my key", string("it's \"hi\" there"))
+        );
+        assert_eq!(parse_request(&request(&["SET", "key", "[1, 2]"])), set("key", string("[1, 2]")));
+
+        // numbers are only numbers if they read back the same
+        assert_eq!(parse_request(&request(&["SET", "key", "42"])), set("key", BuckTypes::Integer(42)));
+        assert_eq!(parse_request(&request(&["SET", "key", "1.5"])), set("key", BuckTypes::Float(1.5)));
+        assert_eq!(parse_request(&request(&["SET", "key", "007"])), set("key", string("007")));
+        assert_eq!(parse_request(&request(&["SET", "key", "1_000"])), set("key", string("1_000")));
+
+        assert_eq!(
+            parse_request(&request(&["RPUSH", "list", "a b", "", "3"])),
+            Ok(BuckQuery::RPush(
+                "list".to_owned(),
+                vec![string("a b"), string(""), BuckTypes::Integer(3)]
+            ))
+        );
+        assert_eq!(
+            parse_request(&request(&["HGET", "hash", "a field"])),
+            Ok(BuckQuery::HGet("hash".to_owned(), "a field".to_owned()))
+        );
+
+        // an argument too many is not joined to the value
+        assert!(parse_request(&request(&["GETSET", "key", "a", "b"])).is_err());
+        assert!(parse_request(&request(&["SET", "", "1"])).is_err());
+        assert!(parse_request(&request(&["DBSIZE", "a", "b"])).is_err());
+        assert!(parse_request(&[]).is_err());
+    }
+
+    #[test]
+    fn test_log_replies() {
+        assert_eq!(
+            RespValue::from(BuckLog::InsertOk("key".to_owned())),
+            RespValue::SimpleString("OK".to_owned())
+        );
+        assert_eq!(RespValue::from(BuckLog::LengthOk(3)), RespValue::Integer(3));
+        assert_eq!(
+            RespValue::from(BuckLog::ListPopOk("1".to_owned())),
+            RespValue::BulkString(Some("1".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_server_round_trip() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let db = Arc::new(Mutex::new(vec![BuckDB::new()]));
+
+        thread::spawn(move || serve(listener, db));
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        let mut send = |request: &[u8], expected: &[u8]| {
+            stream.write_all(request).unwrap();
+
+            let mut reply = vec![0u8; expected.len()];
+            stream.read_exact(&mut reply).unwrap();
+            assert_eq!(String::from_utf8_lossy(&reply), String::from_utf8_lossy(expected));
+        };
+
+        send(b"*1\r\n$4\r\nPING\r\n", b"+PONG\r\n");
+        send(b"*3\r\n$3\r\nSET\r\n$3\r\nnum\r\n$2\r\n42\r\n", b"+OK\r\n");
+        send(b"*2\r\n$4\r\nTYPE\r\n$3\r\nnum\r\n", b"+integer\r\n");
+        send(b"*1\r\n$5\r\nHELLO\r\n", b"-INVALID_COMMAND Invalid query command: HELLO\r\n");
+        send(b"*3\r\n$3\r\nSET\r\n$5\r\nempty\r\n$0\r\n\r\n", b"+OK\r\n");
+        send(b"*2\r\n$3\r\nGET\r\n$5\r\nempty\r\n", b"$0\r\n\r\n");
+        send(b"*3\r\n$3\r\nSET\r\n$1\r\nq\r\n$7\r\n\"hi\" 'x\r\n", b"+OK\r\n");
+        send(b"*2\r\n$3\r\nGET\r\n$1\r\nq\r\n", b"$7\r\n\"hi\" 'x\r\n");
+        send(b"GET \"q\"\r\n", b"$7\r\n\"hi\" 'x\r\n");
+        send(b"QUIT\r\n", b"+OK\r\n");
+    }
+
+    #[test]
+    fn test_error_codes_on_the_wire() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let db = Arc::new(Mutex::new(vec![BuckDB::new()]));
+
+        thread::spawn(move || serve(listener, db));
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        let mut send = |request: &[u8], expected: &[u8]| {
+            stream.write_all(request).unwrap();
+
+            let mut reply = vec![0u8; expected.len()];
+            stream.read_exact(&mut reply).unwrap();
+            assert_eq!(String::from_utf8_lossy(&reply), String::from_utf8_lossy(expected));
+        };
+
+        // the first word of an error is the code of the error
+        send(b"*3\r\n$3\r\nSET\r\n$3\r\nnum\r\n$2\r\n42\r\n", b"+OK\r\n");
+        send(b"*3\r\n$5\r\nLPUSH\r\n$3\r\nnum\r\n$1\r\n1\r\n", b"-WRONG_TYPE Type not supported: num\r\n");
+        send(b"*1\r\n$5\r\nMULTI\r\n", b"+OK\r\n");
+        send(b"*1\r\n$5\r\nMULTI\r\n", b"-NESTED_TRANSACTION Transactions can not be nested\r\n");
+
+        // a protocol error ends the connection
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(b"*1\r\n$x\r\n").unwrap();
+        let mut reply = String::new();
+        stream.read_to_string(&mut reply).unwrap();
+        assert_eq!(reply, "-INVALID_INTEGER Protocol error: invalid integer x\r\n");
+    }
+}
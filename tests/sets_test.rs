@@ -0,0 +1,280 @@
+mod common;
+
+#[cfg(test)]
+mod sets_tests {
+    use buck::engine::BuckDB;
+    use buck::errors::{BuckEngineError, BuckError};
+    use buck::parser::parse::parse_query;
+    use buck::parser::query::BuckQuery;
+    use buck::reply::BuckReply;
+    use buck::session::BuckSession;
+    use buck::types::sets::{BuckSets, Setable};
+    use buck::types::types::BuckTypes;
+
+    use crate::common::run;
+
+    fn set_of(members: &[i64]) -> BuckSets {
+        let members: Vec<Setable> = members.iter().map(|m| Setable::Integer(*m)).collect();
+
+        let mut set = BuckSets::new();
+        set.insert(&members);
+        set
+    }
+
+    fn members(values: &[i64]) -> BuckReply {
+        BuckReply::Set(values.iter().map(|v| BuckReply::Integer(*v)).collect())
+    }
+
+    fn keys(keys: &[&str]) -> Vec<String> {
+        keys.iter().map(|key| key.to_string()).collect()
+    }
+
+    /// `a = {1, 2, 3, 4}`, `b = {3, 4, 5}` and `c = {4, 5, 6}`, all committed.
+    fn db_with_sets() -> BuckDB {
+        let mut db = BuckDB::new();
+
+        run(&mut db, "SADD a 1..5").unwrap();
+        run(&mut db, "SADD b 3 4 5").unwrap();
+        run(&mut db, "SADD c 4 5 6").unwrap();
+        db.commit().unwrap();
+
+        db
+    }
+
+    #[test]
+    fn test_sadd_creates_and_extends_sets() {
+        let mut db = BuckDB::new();
+
+        run(&mut db, "SADD s 1 2").unwrap();
+        run(&mut db, "SADD s 2 3").unwrap();
+        assert_eq!(run(&mut db, "SMEMBERS s"), Ok(members(&[1, 2, 3])));
+
+        // a committed set is staged before it changes
+        db.commit().unwrap();
+        run(&mut db, "SADD s 4").unwrap();
+        run(&mut db, "SREM s 1").unwrap();
+        assert_eq!(run(&mut db, "SMEMBERS s"), Ok(members(&[2, 3, 4])));
+        assert_eq!(db.data.get("s"), Some(&BuckTypes::Sets(set_of(&[1, 2, 3]))));
+
+        db.abort().unwrap();
+        assert_eq!(run(&mut db, "SMEMBERS s"), Ok(members(&[1, 2, 3])));
+
+        // removing the last member deletes the set
+        run(&mut db, "SREM s 1 2 3").unwrap();
+        assert!(db.get("s").is_err());
+    }
+
+    #[test]
+    fn test_membership() {
+        let mut db = db_with_sets();
+
+        assert_eq!(run(&mut db, "SISMEMBER a 1"), Ok(BuckReply::Integer(1)));
+        assert_eq!(run(&mut db, "SISMEMBER a 9"), Ok(BuckReply::Integer(0)));
+        assert_eq!(run(&mut db, "SISMEMBER missing 1"), Ok(BuckReply::Integer(0)));
+        assert_eq!(
+            run(&mut db, "SMISMEMBER b 3 9 5"),
+            Ok(BuckReply::Array(vec![
+                BuckReply::Integer(1),
+                BuckReply::Integer(0),
+                BuckReply::Integer(1)
+            ]))
+        );
+
+        assert_eq!(run(&mut db, "SCARD a"), Ok(BuckReply::Integer(4)));
+        assert_eq!(run(&mut db, "SCARD missing"), Ok(BuckReply::Integer(0)));
+        assert_eq!(run(&mut db, "SMEMBERS missing"), Ok(members(&[])));
+    }
+
+    #[test]
+    fn test_algebra() {
+        let mut db = db_with_sets();
+
+        assert_eq!(run(&mut db, "SINTER a b c"), Ok(members(&[4])));
+        assert_eq!(run(&mut db, "SINTER a missing"), Ok(members(&[])));
+        assert_eq!(run(&mut db, "SUNION a c missing"), Ok(members(&[1, 2, 3, 4, 5, 6])));
+        assert_eq!(run(&mut db, "SDIFF a b"), Ok(members(&[1, 2])));
+        assert_eq!(run(&mut db, "SDIFF a b c missing"), Ok(members(&[1, 2])));
+        assert_eq!(run(&mut db, "SDIFF missing a"), Ok(members(&[])));
+
+        assert_eq!(run(&mut db, "SINTERCARD 2 a b"), Ok(BuckReply::Integer(2)));
+        assert_eq!(run(&mut db, "SINTERCARD 2 a b LIMIT 1"), Ok(BuckReply::Integer(1)));
+        assert_eq!(run(&mut db, "SINTERCARD 1 a limit 0"), Ok(BuckReply::Integer(4)));
+
+        run(&mut db, "INSERT str \"text\"").unwrap();
+        for input in ["SINTER a str", "SUNION str", "SDIFF a str", "SCARD str", "SISMEMBER str 1"] {
+            assert_eq!(
+                run(&mut db, input),
+                Err(BuckError::Engine(BuckEngineError::TypeNotSupported("str".to_owned()))),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_intersection_does_not_depend_on_order() {
+        let small = set_of(&[2, 3]);
+        let large = set_of(&(0..100).collect::<Vec<i64>>());
+        let other = set_of(&[1, 2, 3, 4]);
+
+        let expected = set_of(&[2, 3]);
+        assert_eq!(large.intersection(&[&small, &other]), expected);
+        assert_eq!(small.intersection(&[&large, &other]), expected);
+        assert_eq!(other.intersection(&[&large, &small]), expected);
+        assert_eq!(large.intersection(&[]), large);
+
+        assert_eq!(large.intersection_card(&[&small, &other], 0), 2);
+        assert_eq!(large.intersection_card(&[&small, &other], 1), 1);
+        assert_eq!(other.intersection_card(&[&large], 10), 4);
+
+        assert_eq!(small.union(&[&other]), set_of(&[1, 2, 3, 4]));
+        assert_eq!(other.difference(&[&small]), set_of(&[1, 4]));
+    }
+
+    #[test]
+    fn test_store_variants() {
+        let mut db = db_with_sets();
+
+        assert_eq!(run(&mut db, "SINTERSTORE dest a b"), Ok(BuckReply::Integer(2)));
+        assert_eq!(run(&mut db, "SMEMBERS dest"), Ok(members(&[3, 4])));
+
+        assert_eq!(run(&mut db, "SUNIONSTORE dest b c"), Ok(BuckReply::Integer(4)));
+        assert_eq!(run(&mut db, "SMEMBERS dest"), Ok(members(&[3, 4, 5, 6])));
+
+        // the destination may be one of the sources
+        assert_eq!(run(&mut db, "SDIFFSTORE a a b"), Ok(BuckReply::Integer(2)));
+        assert_eq!(run(&mut db, "SMEMBERS a"), Ok(members(&[1, 2])));
+
+        // any value is overwritten, and an empty result deletes it
+        run(&mut db, "INSERT str \"text\"").unwrap();
+        assert_eq!(run(&mut db, "SUNIONSTORE str c"), Ok(BuckReply::Integer(3)));
+        assert_eq!(run(&mut db, "TYPE str"), Ok(BuckReply::Status("sets".to_owned())));
+        assert_eq!(run(&mut db, "SINTERSTORE str a c"), Ok(BuckReply::Integer(0)));
+        assert!(db.get("str").is_err());
+    }
+
+    #[test]
+    fn test_store_is_part_of_the_transaction() {
+        let mut db = db_with_sets();
+        let mut session = BuckSession::new();
+
+        for input in ["MULTI", "SINTERSTORE dest a b", "DISCARD"] {
+            session.execute(parse_query(input).unwrap(), input, &mut db).unwrap();
+        }
+        assert!(db.get("dest").is_err());
+
+        run(&mut db, "SUNIONSTORE dest a").unwrap();
+        assert!(db.uncommitted_data.contains_key("dest"));
+        db.abort().unwrap();
+        assert!(db.get("dest").is_err());
+    }
+
+    #[test]
+    fn test_random_members() {
+        let mut db = db_with_sets();
+        let all = set_of(&[1, 2, 3, 4]);
+
+        let reply = run(&mut db, "SRANDMEMBER a").unwrap();
+        assert!(matches!(reply, BuckReply::Integer(1..=4)), "{:?}", reply);
+        assert_eq!(run(&mut db, "SRANDMEMBER missing"), Ok(BuckReply::Nil));
+        assert_eq!(run(&mut db, "SRANDMEMBER missing 3"), Ok(BuckReply::Array(vec![])));
+
+        let picked = db.s_rand_member("a", 3).unwrap();
+        let distinct = {
+            let mut set = BuckSets::new();
+            set.insert(&picked);
+            set
+        };
+        assert_eq!(picked.len(), 3);
+        assert_eq!(distinct.len(), 3);
+        assert!(picked.iter().all(|member| all.is_member(member)));
+
+        assert_eq!(db.s_rand_member("a", 10).unwrap().len(), 4);
+
+        // a negative count may repeat members
+        let picked = db.s_rand_member("a", -10).unwrap();
+        assert_eq!(picked.len(), 10);
+        assert!(picked.iter().all(|member| all.is_member(member)));
+
+        // but not more of them than a reply can hold
+        for count in [i64::MIN, -100_000_000_000] {
+            assert_eq!(db.s_rand_member("a", count).unwrap_err().code(), "COUNT_TOO_LARGE");
+        }
+
+        // reading does not stage anything
+        assert!(db.uncommitted_data.is_empty());
+    }
+
+    #[test]
+    fn test_spop() {
+        let mut db = db_with_sets();
+
+        let popped = db.s_pop("a", 3).unwrap();
+        assert_eq!(popped.len(), 3);
+        assert_eq!(run(&mut db, "SCARD a"), Ok(BuckReply::Integer(1)));
+        let left = db.s_members("a").unwrap();
+        assert!(popped.iter().all(|member| !left.is_member(member)));
+
+        let reply = run(&mut db, "SPOP a").unwrap();
+        assert!(matches!(reply, BuckReply::Integer(1..=4)), "{:?}", reply);
+        assert!(db.get("a").is_err());
+
+        assert_eq!(run(&mut db, "SPOP a"), Ok(BuckReply::Nil));
+        assert_eq!(run(&mut db, "SPOP b 5"), Ok(members(&[3, 4, 5])));
+
+        db.abort().unwrap();
+        assert_eq!(run(&mut db, "SCARD a"), Ok(BuckReply::Integer(4)));
+    }
+
+    #[test]
+    fn test_smove() {
+        let mut db = db_with_sets();
+
+        assert_eq!(run(&mut db, "SMOVE a b 1"), Ok(BuckReply::Integer(1)));
+        assert_eq!(run(&mut db, "SMEMBERS a"), Ok(members(&[2, 3, 4])));
+        assert_eq!(run(&mut db, "SMEMBERS b"), Ok(members(&[1, 3, 4, 5])));
+
+        assert_eq!(run(&mut db, "SMOVE a b 9"), Ok(BuckReply::Integer(0)));
+        assert_eq!(run(&mut db, "SMOVE a new 2"), Ok(BuckReply::Integer(1)));
+        assert_eq!(run(&mut db, "SMEMBERS new"), Ok(members(&[2])));
+        assert_eq!(run(&mut db, "SMOVE a a 3"), Ok(BuckReply::Integer(1)));
+
+        run(&mut db, "INSERT str \"text\"").unwrap();
+        assert_eq!(
+            run(&mut db, "SMOVE a str 3"),
+            Err(BuckError::Engine(BuckEngineError::TypeNotSupported("str".to_owned())))
+        );
+        assert_eq!(run(&mut db, "SMEMBERS a"), Ok(members(&[3, 4])));
+    }
+
+    #[test]
+    fn test_parse_set_queries() {
+        assert_eq!(parse_query("SINTER a b"), Ok(BuckQuery::SInter(keys(&["a", "b"]))));
+        assert_eq!(parse_query("SUNION a"), Ok(BuckQuery::SUnion(keys(&["a"]))));
+        assert_eq!(
+            parse_query("SDIFFSTORE d a b"),
+            Ok(BuckQuery::SDiffStore("d".to_owned(), keys(&["a", "b"])))
+        );
+        assert_eq!(
+            parse_query("SINTERCARD 2 a b LIMIT 3"),
+            Ok(BuckQuery::SInterCard(keys(&["a", "b"]), 3))
+        );
+        assert_eq!(
+            parse_query("SISMEMBER a \"x y\""),
+            Ok(BuckQuery::SIsMember("a".to_owned(), BuckTypes::String("x y".to_owned())))
+        );
+        assert_eq!(parse_query("SRANDMEMBER a -2"), Ok(BuckQuery::SRandMember("a".to_owned(), Some(-2))));
+        assert_eq!(parse_query("SPOP a"), Ok(BuckQuery::SPop("a".to_owned(), None)));
+        assert_eq!(
+            parse_query("SMOVE a b 1"),
+            Ok(BuckQuery::SMove("a".to_owned(), "b".to_owned(), BuckTypes::Integer(1)))
+        );
+
+        for input in [
+            "SINTER", "SINTERSTORE d", "SINTERCARD 0 a", "SINTERCARD 3 a b", "SINTERCARD 1 a LIMIT",
+            "SISMEMBER a", "SPOP a -1", "SMOVE a b", "SMEMBERS ''",
+        ] {
+            assert!(parse_query(input).is_err(), "{}", input);
+        }
+    }
+}
@@ -0,0 +1,132 @@
+#[cfg(test)]
+mod shared_tests {
+    use std::collections::HashMap;
+    use std::sync::{Arc, Barrier};
+    use std::thread;
+
+    use buck::errors::BuckEngineError;
+    use buck::sharding::hash::calculate_hash;
+    use buck::sharding::shared::SharedBuckDB;
+    use buck::types::types::BuckTypes;
+
+    const THREADS: usize = 8;
+    const OPS: usize = 500;
+    const LISTS: usize = 4;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_shared_db_is_send_and_sync() {
+        assert_send_sync::<SharedBuckDB>();
+    }
+
+    #[test]
+    fn test_keys_are_routed_by_hash() {
+        let db = SharedBuckDB::new(4);
+
+        for i in 0..32 {
+            let key = format!("key{}", i);
+            assert_eq!(db.partition_of(&key), calculate_hash(key.as_str()) as usize % 4);
+
+            db.insert(key.clone(), BuckTypes::Integer(i)).unwrap();
+            assert!(db.read(&key, |partition| partition.uncommitted_data.contains_key(&key)));
+        }
+
+        assert_eq!(db.len(), 32);
+    }
+
+    #[test]
+    fn test_basic_operations() {
+        let db = SharedBuckDB::new(3);
+
+        db.insert("a".to_owned(), BuckTypes::Integer(1)).unwrap();
+        db.l_push("list".to_owned(), BuckTypes::Integer(1)).unwrap();
+        db.h_set(
+            "hash".to_owned(),
+            HashMap::from([("f".to_owned(), BuckTypes::Boolean(true))]),
+        )
+        .unwrap();
+        db.commit().unwrap();
+
+        assert_eq!(db.get("a"), Ok(BuckTypes::Integer(1)));
+        assert_eq!(db.type_of("list"), Ok("list".to_owned()));
+        assert_eq!(db.get_collections_length("hash"), Ok(1));
+
+        // a range only takes the read lock, so it runs alongside other readers
+        let range = db.read("list", |_| db.l_range("list", 0, -1));
+        assert_eq!(range, Ok(vec![BuckTypes::Integer(1)]));
+
+        db.update("a", BuckTypes::Integer(2)).unwrap();
+        db.rollback().unwrap();
+        assert_eq!(db.get("a"), Ok(BuckTypes::Integer(1)));
+
+        db.remove("a").unwrap();
+        assert_eq!(db.get("a"), Err(BuckEngineError::KeyNotFound("a".to_owned())));
+        assert_eq!(db.len(), 2);
+    }
+
+    #[test]
+    fn test_concurrent_mixed_workload() {
+        let db = Arc::new(SharedBuckDB::new(8));
+        let barrier = Arc::new(Barrier::new(THREADS));
+
+        let handles: Vec<_> = (0..THREADS)
+            .map(|t| {
+                let db = Arc::clone(&db);
+                let barrier = Arc::clone(&barrier);
+
+                thread::spawn(move || {
+                    barrier.wait();
+
+                    for i in 0..OPS {
+                        let key = format!("t{}k{}", t, i);
+                        let value = (t * OPS + i) as i64;
+
+                        db.insert(key.clone(), BuckTypes::Integer(value)).unwrap();
+                        assert_eq!(db.get(&key), Ok(BuckTypes::Integer(value)));
+
+                        db.l_push(format!("list{}", i % LISTS), BuckTypes::Integer(value))
+                            .unwrap();
+
+                        db.h_set(
+                            "hash".to_owned(),
+                            HashMap::from([(key.clone(), BuckTypes::Integer(value))]),
+                        )
+                        .unwrap();
+
+                        // reads of other threads' keys must never see a torn value
+                        if let Ok(other) = db.get(&format!("t{}k{}", (t + 1) % THREADS, i)) {
+                            let expected = (((t + 1) % THREADS) * OPS + i) as i64;
+                            assert_eq!(other, BuckTypes::Integer(expected));
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        db.commit().unwrap();
+
+        let total = THREADS * OPS;
+
+        // every insert is there, with its own value
+        for t in 0..THREADS {
+            for i in 0..OPS {
+                let expected = BuckTypes::Integer((t * OPS + i) as i64);
+                assert_eq!(db.get(&format!("t{}k{}", t, i)), Ok(expected));
+            }
+        }
+
+        // no push and no field got lost
+        let pushed: usize = (0..LISTS)
+            .map(|i| db.get_collections_length(&format!("list{}", i)).unwrap())
+            .sum();
+        assert_eq!(pushed, total);
+        assert_eq!(db.get_collections_length("hash"), Ok(total));
+
+        assert_eq!(db.len(), total + LISTS + 1);
+    }
+}
@@ -0,0 +1,189 @@
+#[cfg(test)]
+mod snapshot_tests {
+    use std::collections::{BTreeMap, HashMap};
+    use std::fs;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    use buck::clock::ManualClock;
+    use buck::engine::BuckDB;
+    use buck::log::BuckLog;
+    use buck::parser::parse::parse_query;
+    use buck::parser::query::BuckQuery;
+    use buck::storage::errors::StorageError;
+    use buck::storage::snapshot::{decode_snapshot, encode_snapshot};
+    use buck::types::types::BuckTypes;
+
+    fn snapshot_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("buck-snapshot-{}-{}.bdb", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        path
+    }
+
+    fn sample_db() -> BuckDB {
+        let mut db = BuckDB::new();
+        db.insert("int".to_owned(), BuckTypes::Integer(-7)).unwrap();
+        db.insert("float".to_owned(), BuckTypes::Float(2.5)).unwrap();
+        db.insert("bool".to_owned(), BuckTypes::Boolean(false)).unwrap();
+        db.insert("string".to_owned(), BuckTypes::String("hello\0world".to_owned())).unwrap();
+        db.insert("unknown".to_owned(), BuckTypes::Unknown("raw".to_owned())).unwrap();
+        db.l_push("list".to_owned(), BuckTypes::Integer(1)).unwrap();
+        db.l_push("list".to_owned(), BuckTypes::String("two".to_owned())).unwrap();
+        db.h_set(
+            "hash".to_owned(),
+            HashMap::from([("field".to_owned(), BuckTypes::Float(0.5))]),
+        )
+        .unwrap();
+        db.commit().unwrap();
+
+        db
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let path = snapshot_path("roundtrip");
+        let mut db = sample_db();
+
+        // staged writes are not part of the snapshot
+        db.insert("staged".to_owned(), BuckTypes::Integer(1)).unwrap();
+        assert_eq!(db.save(&path), Ok(BuckLog::BackupOk));
+
+        let mut restored = BuckDB::new();
+        restored.insert("other".to_owned(), BuckTypes::Integer(0)).unwrap();
+        assert_eq!(restored.load(&path), Ok(BuckLog::LoadOk));
+
+        assert_eq!(restored.data, sample_db().data);
+        assert!(restored.uncommitted_data.is_empty());
+        assert!(restored.get("staged").is_err());
+        assert!(restored.get("other").is_err());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_decode_rejects_damaged_snapshots() {
+        let data = sample_db().data;
+        let encoded = encode_snapshot(&data, &HashMap::new());
+        assert_eq!(decode_snapshot(&encoded), Ok((data, HashMap::new())));
+
+        // every truncation is rejected
+        for cut in 0..encoded.len() {
+            assert!(decode_snapshot(&encoded[..cut]).is_err());
+        }
+
+        // every single flipped byte is rejected
+        for i in 0..encoded.len() {
+            let mut damaged = encoded.clone();
+            damaged[i] ^= 0x01;
+            assert!(decode_snapshot(&damaged).is_err(), "flipped byte {}", i);
+        }
+
+        let mut wrong_version = encoded.clone();
+        wrong_version[4] = 0xff;
+        assert!(matches!(
+            decode_snapshot(&wrong_version),
+            Err(StorageError::Corrupted(_))
+        ));
+
+        assert_eq!(
+            decode_snapshot(&encode_snapshot(&BTreeMap::new(), &HashMap::new())),
+            Ok((BTreeMap::new(), HashMap::new()))
+        );
+    }
+
+    #[test]
+    fn test_failed_load_leaves_database_untouched() {
+        let path = snapshot_path("damaged");
+        let db = sample_db();
+        db.save(&path).unwrap();
+
+        let mut bytes = fs::read(&path).unwrap();
+        bytes.truncate(bytes.len() - 1);
+        fs::write(&path, &bytes).unwrap();
+
+        let mut target = BuckDB::new();
+        target.insert("keep".to_owned(), BuckTypes::Integer(1)).unwrap();
+        target.commit().unwrap();
+
+        assert!(target.load(&path).is_err());
+        assert_eq!(target.data.len(), 1);
+        assert_eq!(target.get("keep"), Ok(&BuckTypes::Integer(1)));
+
+        assert!(target.load(snapshot_path("missing")).is_err());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_parse_save_load() {
+        assert_eq!(
+            parse_query("SAVE dump.bdb"),
+            Ok(BuckQuery::Save("dump.bdb".to_owned()))
+        );
+        assert_eq!(
+            parse_query("load \"backups/my dump.bdb\""),
+            Ok(BuckQuery::Load("backups/my dump.bdb".to_owned()))
+        );
+        assert!(parse_query("SAVE").is_err());
+        assert!(parse_query("LOAD  ").is_err());
+
+        // a path may not leave the data directory
+        for input in ["SAVE /tmp/dump.bdb", "LOAD ../dump.bdb", "SAVE backups/../../dump.bdb"] {
+            assert_eq!(parse_query(input).unwrap_err().code(), "INVALID_PATH", "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_save_in_data_directory() {
+        let dir = std::env::temp_dir().join(format!("buck-dir-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let mut db = sample_db();
+        db.dir = dir.clone();
+        parse_query("SAVE dump.bdb").unwrap().execute("SAVE dump.bdb", &mut db).unwrap();
+
+        // the temporary file keeps the extension of the snapshot
+        assert!(dir.join("dump.bdb").exists());
+        assert!(!dir.join("dump.tmp").exists());
+        assert!(!dir.join("dump.bdb.tmp").exists());
+
+        let mut restored = BuckDB::new();
+        restored.dir = dir.clone();
+        parse_query("LOAD dump.bdb").unwrap().execute("LOAD dump.bdb", &mut restored).unwrap();
+        assert_eq!(restored.data, db.data);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_save_and_load_keep_deadlines() {
+        let path = snapshot_path("deadlines");
+        let aof_path = snapshot_path("deadlines-aof");
+        let clock = ManualClock::new(1_000_000);
+
+        let mut db = sample_db();
+        db.clock = Arc::new(clock.clone());
+        db.expire("int", 1_000).unwrap();
+        db.commit().unwrap();
+
+        // an uncommitted timeout is not part of the snapshot
+        db.expire("float", 1_000).unwrap();
+        db.save(&path).unwrap();
+
+        let mut restored = BuckDB::open(&aof_path).unwrap();
+        restored.clock = Arc::new(clock.clone());
+        restored.expire("bool", 1_000).unwrap();
+        restored.load(&path).unwrap();
+        assert_eq!(restored.expires, HashMap::from([("int".to_owned(), 1_001_000)]));
+
+        // the rewritten log keeps the deadline as well
+        let mut reopened = BuckDB::open(&aof_path).unwrap();
+        reopened.clock = Arc::new(clock.clone());
+        assert_eq!(reopened.pttl("int"), 1_000);
+        assert_eq!(reopened.pttl("float"), -1);
+
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(&aof_path).unwrap();
+    }
+}
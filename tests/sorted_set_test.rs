@@ -0,0 +1,432 @@
+mod common;
+
+#[cfg(test)]
+mod sorted_set_tests {
+    use buck::encoding::encoding::{encode_type, take_type};
+    use buck::engine::BuckDB;
+    use buck::errors::{BuckEngineError, BuckError};
+    use buck::parser::parse::parse_query;
+    use buck::parser::query::BuckQuery;
+    use buck::reply::BuckReply;
+    use buck::types::errors::BuckTypeError;
+    use buck::types::sorted_set::{
+        Aggregate, BuckSortedSet, LexBound, RangeBy, ScoreBound, ZAddFlags, ZRangeSpec,
+    };
+    use buck::types::types::BuckTypes;
+
+    use crate::common::run;
+
+    fn members(members: &[&str]) -> BuckReply {
+        BuckReply::Array(
+            members
+                .iter()
+                .map(|m| BuckReply::Bulk(m.to_string()))
+                .collect(),
+        )
+    }
+
+    fn scored(members: &[(&str, f64)]) -> BuckReply {
+        BuckReply::Array(
+            members
+                .iter()
+                .flat_map(|(m, s)| [BuckReply::Bulk(m.to_string()), BuckReply::Float(*s)])
+                .collect(),
+        )
+    }
+
+    /// `board = {a: 1, b: 2, c: 3, d: 4, e: 5}`, committed.
+    fn db_with_board() -> BuckDB {
+        let mut db = BuckDB::new();
+
+        run(&mut db, "ZADD board 1 a 2 b 3 c 4 d 5 e").unwrap();
+        db.commit().unwrap();
+
+        db
+    }
+
+    #[test]
+    fn test_parse_sorted_set_queries() {
+        assert_eq!(
+            parse_query("ZADD z xx gt ch 1.5 a -inf b"),
+            Ok(BuckQuery::ZAdd(
+                "z".to_owned(),
+                ZAddFlags {
+                    xx: true,
+                    gt: true,
+                    ch: true,
+                    ..Default::default()
+                },
+                vec![(1.5, "a".to_owned()), (f64::NEG_INFINITY, "b".to_owned())],
+            ))
+        );
+        assert_eq!(
+            parse_query("ZADD z NX 1 a 2 b"),
+            Ok(BuckQuery::ZAdd(
+                "z".to_owned(),
+                ZAddFlags {
+                    nx: true,
+                    ..Default::default()
+                },
+                vec![(1.0, "a".to_owned()), (2.0, "b".to_owned())],
+            ))
+        );
+        assert_eq!(
+            parse_query("ZRANGE z (5 1 BYSCORE REV LIMIT 1 -1 WITHSCORES"),
+            Ok(BuckQuery::ZRange(
+                "z".to_owned(),
+                ZRangeSpec {
+                    by: RangeBy::Score(ScoreBound::Inclusive(1.0), ScoreBound::Exclusive(5.0)),
+                    rev: true,
+                    limit: Some((1, usize::MAX)),
+                    with_scores: true,
+                }
+            ))
+        );
+        assert_eq!(
+            parse_query("ZRANGEBYSCORE z -inf +inf"),
+            Ok(BuckQuery::ZRange(
+                "z".to_owned(),
+                ZRangeSpec {
+                    by: RangeBy::Score(
+                        ScoreBound::Inclusive(f64::NEG_INFINITY),
+                        ScoreBound::Inclusive(f64::INFINITY)
+                    ),
+                    rev: false,
+                    limit: None,
+                    with_scores: false,
+                }
+            ))
+        );
+        assert_eq!(
+            parse_query("ZRANGE z - (c BYLEX"),
+            Ok(BuckQuery::ZRange(
+                "z".to_owned(),
+                ZRangeSpec {
+                    by: RangeBy::Lex(LexBound::Min, LexBound::Exclusive("c".to_owned())),
+                    rev: false,
+                    limit: None,
+                    with_scores: false,
+                }
+            ))
+        );
+        assert_eq!(
+            parse_query("ZUNIONSTORE out 2 a b WEIGHTS 2 0.5 AGGREGATE max"),
+            Ok(BuckQuery::ZUnionStore(
+                "out".to_owned(),
+                vec!["a".to_owned(), "b".to_owned()],
+                Some(vec![2.0, 0.5]),
+                Aggregate::Max
+            ))
+        );
+
+        for input in [
+            "ZADD z",
+            "ZADD z 1",
+            "ZADD z nan a",
+            "ZADD z NX XX 1 a",
+            "ZADD z GT LT 1 a",
+            "ZADD z NX GT 1 a",
+            "ZRANGE z 0 -1 LIMIT 0 1",
+            "ZRANGE z a b BYLEX",
+            "ZRANGE z [a [b BYLEX WITHSCORES",
+            "ZRANGE z 0 1 BYSCORE BYLEX",
+            "ZRANGE z 0",
+            "ZUNIONSTORE out 0 a",
+            "ZUNIONSTORE out 2 a",
+            "ZINTERSTORE out 1 a WEIGHTS",
+            "ZINTERSTORE out 1 a AGGREGATE avg",
+            "ZCOUNT z 1",
+            "ZINCRBY z x a",
+        ] {
+            assert!(parse_query(input).is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_zadd_flags() {
+        let mut db = BuckDB::new();
+
+        assert_eq!(run(&mut db, "ZADD z 1 a 2 b"), Ok(BuckReply::Integer(2)));
+        // an update is not counted without CH
+        assert_eq!(run(&mut db, "ZADD z 5 a 3 c"), Ok(BuckReply::Integer(1)));
+        assert_eq!(
+            run(&mut db, "ZADD z CH 6 a 3 c 4 d"),
+            Ok(BuckReply::Integer(2))
+        );
+
+        assert_eq!(run(&mut db, "ZADD z NX 0 a 0 e"), Ok(BuckReply::Integer(1)));
+        assert_eq!(
+            run(&mut db, "ZADD z XX CH 7 a 0 f"),
+            Ok(BuckReply::Integer(1))
+        );
+        assert_eq!(run(&mut db, "ZSCORE z f"), Ok(BuckReply::Nil));
+
+        assert_eq!(
+            run(&mut db, "ZADD z GT CH 1 a 8 b"),
+            Ok(BuckReply::Integer(1))
+        );
+        assert_eq!(
+            run(&mut db, "ZADD z LT CH 9 a 1 b"),
+            Ok(BuckReply::Integer(1))
+        );
+        assert_eq!(run(&mut db, "ZSCORE z a"), Ok(BuckReply::Float(7.0)));
+        assert_eq!(run(&mut db, "ZSCORE z b"), Ok(BuckReply::Float(1.0)));
+
+        // `XX` never creates the key
+        assert_eq!(run(&mut db, "ZADD other XX 1 a"), Ok(BuckReply::Integer(0)));
+        assert!(db.get("other").is_err());
+    }
+
+    #[test]
+    fn test_zrange_by_index() {
+        let mut db = db_with_board();
+
+        assert_eq!(
+            run(&mut db, "ZRANGE board 0 -1"),
+            Ok(members(&["a", "b", "c", "d", "e"]))
+        );
+        assert_eq!(run(&mut db, "ZRANGE board -2 99"), Ok(members(&["d", "e"])));
+        assert_eq!(
+            run(&mut db, "ZRANGE board 0 1 REV"),
+            Ok(members(&["e", "d"]))
+        );
+        assert_eq!(run(&mut db, "ZRANGE board 3 1"), Ok(members(&[])));
+        assert_eq!(
+            run(&mut db, "ZRANGE board 0 1 WITHSCORES"),
+            Ok(scored(&[("a", 1.0), ("b", 2.0)]))
+        );
+        assert_eq!(run(&mut db, "ZRANGE missing 0 -1"), Ok(members(&[])));
+    }
+
+    #[test]
+    fn test_zrange_by_score() {
+        let mut db = db_with_board();
+
+        assert_eq!(
+            run(&mut db, "ZRANGE board 2 4 BYSCORE"),
+            Ok(members(&["b", "c", "d"]))
+        );
+        assert_eq!(
+            run(&mut db, "ZRANGE board (2 (4 BYSCORE"),
+            Ok(members(&["c"]))
+        );
+        assert_eq!(
+            run(&mut db, "ZRANGEBYSCORE board -inf +inf LIMIT 1 2"),
+            Ok(members(&["b", "c"]))
+        );
+        // a negative count takes every member after the offset
+        assert_eq!(
+            run(&mut db, "ZRANGEBYSCORE board -inf +inf LIMIT 0 -1"),
+            Ok(members(&["a", "b", "c", "d", "e"]))
+        );
+        assert_eq!(
+            run(&mut db, "ZRANGE board +inf -inf BYSCORE REV LIMIT 3 -5"),
+            Ok(members(&["b", "a"]))
+        );
+        assert_eq!(
+            run(&mut db, "ZRANGE board +inf 3 BYSCORE REV"),
+            Ok(members(&["e", "d", "c"]))
+        );
+        assert_eq!(
+            run(
+                &mut db,
+                "ZRANGE board (5 -inf BYSCORE REV LIMIT 1 1 WITHSCORES"
+            ),
+            Ok(scored(&[("c", 3.0)]))
+        );
+        assert_eq!(run(&mut db, "ZCOUNT board (1 3"), Ok(BuckReply::Integer(2)));
+        assert_eq!(
+            run(&mut db, "ZCOUNT board 10 +inf"),
+            Ok(BuckReply::Integer(0))
+        );
+    }
+
+    #[test]
+    fn test_zrange_by_lex() {
+        let mut db = BuckDB::new();
+        run(&mut db, "ZADD names 0 alice 0 bob 0 carol 0 dave").unwrap();
+
+        assert_eq!(
+            run(&mut db, "ZRANGE names - +  BYLEX"),
+            Ok(members(&["alice", "bob", "carol", "dave"]))
+        );
+        assert_eq!(
+            run(&mut db, "ZRANGE names [bob (dave BYLEX"),
+            Ok(members(&["bob", "carol"]))
+        );
+        assert_eq!(
+            run(&mut db, "ZRANGE names + (bob BYLEX REV"),
+            Ok(members(&["dave", "carol"]))
+        );
+        assert_eq!(
+            run(&mut db, "ZRANGE names - + BYLEX LIMIT 3 5"),
+            Ok(members(&["dave"]))
+        );
+        assert_eq!(
+            run(&mut db, "ZRANGEBYLEX names - + LIMIT 1 -1"),
+            Ok(members(&["bob", "carol", "dave"]))
+        );
+        assert_eq!(
+            run(&mut db, "ZRANGEBYLEX names (alice [carol"),
+            Ok(members(&["bob", "carol"]))
+        );
+    }
+
+    #[test]
+    fn test_rank_and_score() {
+        let mut db = db_with_board();
+        // ties are ordered by member
+        run(&mut db, "ZADD board 3 bb").unwrap();
+
+        assert_eq!(run(&mut db, "ZRANK board a"), Ok(BuckReply::Integer(0)));
+        assert_eq!(run(&mut db, "ZRANK board bb"), Ok(BuckReply::Integer(2)));
+        assert_eq!(run(&mut db, "ZRANK board c"), Ok(BuckReply::Integer(3)));
+        assert_eq!(run(&mut db, "ZREVRANK board e"), Ok(BuckReply::Integer(0)));
+        assert_eq!(run(&mut db, "ZREVRANK board a"), Ok(BuckReply::Integer(5)));
+        assert_eq!(run(&mut db, "ZRANK board x"), Ok(BuckReply::Nil));
+        assert_eq!(run(&mut db, "ZRANK missing a"), Ok(BuckReply::Nil));
+        assert_eq!(run(&mut db, "ZSCORE board c"), Ok(BuckReply::Float(3.0)));
+        assert_eq!(run(&mut db, "ZCARD board"), Ok(BuckReply::Integer(6)));
+        assert_eq!(run(&mut db, "LEN board"), Ok(BuckReply::Integer(6)));
+        assert_eq!(
+            run(&mut db, "TYPE board"),
+            Ok(BuckReply::Status("zset".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_zincrby() {
+        let mut db = db_with_board();
+
+        assert_eq!(
+            run(&mut db, "ZINCRBY board 10 a"),
+            Ok(BuckReply::Float(11.0))
+        );
+        assert_eq!(run(&mut db, "ZREVRANK board a"), Ok(BuckReply::Integer(0)));
+        assert_eq!(
+            run(&mut db, "ZINCRBY board -0.5 new"),
+            Ok(BuckReply::Float(-0.5))
+        );
+        assert_eq!(run(&mut db, "ZINCRBY fresh 2 a"), Ok(BuckReply::Float(2.0)));
+
+        run(&mut db, "ZADD board +inf top").unwrap();
+        db.commit().unwrap();
+        assert_eq!(
+            run(&mut db, "ZINCRBY board -inf top"),
+            Err(BuckError::Type(BuckTypeError::ScoreIsNan("top".to_owned())))
+        );
+        assert!(db.uncommitted_data.is_empty());
+    }
+
+    #[test]
+    fn test_zpop_and_zrem() {
+        let mut db = db_with_board();
+
+        assert_eq!(run(&mut db, "ZPOPMIN board"), Ok(scored(&[("a", 1.0)])));
+        assert_eq!(
+            run(&mut db, "ZPOPMAX board 2"),
+            Ok(scored(&[("e", 5.0), ("d", 4.0)]))
+        );
+        assert_eq!(run(&mut db, "ZPOPMIN missing"), Ok(scored(&[])));
+
+        assert_eq!(run(&mut db, "ZREM board b x"), Ok(BuckReply::Integer(1)));
+        assert_eq!(run(&mut db, "ZREM board x"), Ok(BuckReply::Integer(0)));
+
+        // removing the last member deletes the sorted set
+        assert_eq!(run(&mut db, "ZPOPMAX board 10"), Ok(scored(&[("c", 3.0)])));
+        assert!(db.get("board").is_err());
+
+        // aborting brings the committed sorted set back
+        db.abort().unwrap();
+        assert_eq!(run(&mut db, "ZCARD board"), Ok(BuckReply::Integer(5)));
+    }
+
+    #[test]
+    fn test_zunionstore_and_zinterstore() {
+        let mut db = BuckDB::new();
+        run(&mut db, "ZADD x 1 a 2 b 3 c").unwrap();
+        run(&mut db, "ZADD y 10 b 20 c 30 d").unwrap();
+
+        assert_eq!(
+            run(&mut db, "ZUNIONSTORE out 2 x y"),
+            Ok(BuckReply::Integer(4))
+        );
+        assert_eq!(
+            run(&mut db, "ZRANGE out 0 -1 WITHSCORES"),
+            Ok(scored(&[("a", 1.0), ("b", 12.0), ("c", 23.0), ("d", 30.0)]))
+        );
+
+        assert_eq!(
+            run(&mut db, "ZINTERSTORE out 2 x y WEIGHTS 2 0.5 AGGREGATE MIN"),
+            Ok(BuckReply::Integer(2))
+        );
+        assert_eq!(
+            run(&mut db, "ZRANGE out 0 -1 WITHSCORES"),
+            Ok(scored(&[("b", 4.0), ("c", 6.0)]))
+        );
+
+        assert_eq!(
+            run(
+                &mut db,
+                "ZUNIONSTORE out 2 x missing AGGREGATE MAX WEIGHTS -1 1"
+            ),
+            Ok(BuckReply::Integer(3))
+        );
+        assert_eq!(run(&mut db, "ZRANGE out 0 0"), Ok(members(&["c"])));
+
+        // an empty result deletes the destination
+        assert_eq!(
+            run(&mut db, "ZINTERSTORE out 2 x missing"),
+            Ok(BuckReply::Integer(0))
+        );
+        assert!(db.get("out").is_err());
+
+        run(&mut db, "INSERT str \"text\"").unwrap();
+        assert_eq!(
+            run(&mut db, "ZUNIONSTORE out 2 x str"),
+            Err(BuckError::Engine(BuckEngineError::TypeNotSupported(
+                "str".to_owned()
+            )))
+        );
+    }
+
+    #[test]
+    fn test_sorted_set_commands_check_the_type() {
+        let mut db = BuckDB::new();
+        run(&mut db, "RPUSH list 1").unwrap();
+
+        for input in [
+            "ZADD list 1 a",
+            "ZREM list a",
+            "ZSCORE list a",
+            "ZRANK list a",
+            "ZRANGE list 0 -1",
+            "ZINCRBY list 1 a",
+            "ZPOPMIN list",
+            "ZCOUNT list 0 1",
+            "ZCARD list",
+        ] {
+            assert_eq!(
+                run(&mut db, input),
+                Err(BuckError::Engine(BuckEngineError::TypeNotSupported(
+                    "list".to_owned()
+                ))),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_sorted_set_encoding_roundtrip() {
+        let mut zset = BuckSortedSet::new();
+        zset.insert("a".to_owned(), -0.0);
+        zset.insert("b".to_owned(), f64::INFINITY);
+        zset.insert("c".to_owned(), -1.5);
+
+        let value = BuckTypes::SortedSet(zset);
+        let encoded = encode_type(&value);
+
+        assert_eq!(take_type(&mut encoded.as_slice()), Ok(value));
+    }
+}
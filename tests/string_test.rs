@@ -0,0 +1,225 @@
+mod common;
+
+#[cfg(test)]
+mod string_tests {
+    use std::sync::Arc;
+
+    use buck::clock::{Expiry, ManualClock};
+    use buck::engine::BuckDB;
+    use buck::errors::{BuckEngineError, BuckError};
+    use buck::parser::parse::{get_value_type, parse_query};
+    use buck::parser::query::BuckQuery;
+    use buck::reply::BuckReply;
+    use buck::types::errors::BuckTypeError;
+    use buck::types::string::{get_range, set_range};
+    use buck::types::types::BuckTypes;
+
+    use crate::common::run;
+
+    fn bulk(text: &str) -> Result<BuckReply, BuckError> {
+        Ok(BuckReply::Bulk(text.to_owned()))
+    }
+
+    #[test]
+    fn test_parse_string_queries() {
+        assert_eq!(
+            parse_query("APPEND greeting \" world\""),
+            Ok(BuckQuery::Append("greeting".to_owned(), " world".to_owned()))
+        );
+        assert_eq!(
+            parse_query("SETRANGE greeting 6 Redis"),
+            Ok(BuckQuery::SetRange("greeting".to_owned(), 6, "Redis".to_owned()))
+        );
+        assert_eq!(
+            parse_query("GETRANGE greeting 0 -1"),
+            Ok(BuckQuery::GetRange("greeting".to_owned(), 0, -1))
+        );
+        assert_eq!(
+            parse_query("GETEX session PX 500"),
+            Ok(BuckQuery::GetEx("session".to_owned(), Some(Expiry::After(500))))
+        );
+        assert_eq!(
+            parse_query("GETEX session exat 1700000000"),
+            Ok(BuckQuery::GetEx("session".to_owned(), Some(Expiry::At(1_700_000_000_000))))
+        );
+        assert_eq!(
+            parse_query("GETEX session PERSIST"),
+            Ok(BuckQuery::GetEx("session".to_owned(), Some(Expiry::Persist)))
+        );
+        assert_eq!(
+            parse_query("MSET a 1 b \"two words\" c [1, 2]"),
+            Ok(BuckQuery::MSet(vec![
+                ("a".to_owned(), BuckTypes::Integer(1)),
+                ("b".to_owned(), BuckTypes::String("two words".to_owned())),
+                ("c".to_owned(), get_value_type("[1, 2]").unwrap()),
+            ]))
+        );
+        assert_eq!(
+            parse_query("MGET a b"),
+            Ok(BuckQuery::MGet(vec!["a".to_owned(), "b".to_owned()]))
+        );
+
+        for input in [
+            "APPEND", "APPEND a", "STRLEN", "GETRANGE a 0", "GETRANGE a x 1", "SETRANGE a -1 x",
+            "SETRANGE a 0", "GETSET a", "GETDEL \"\"", "GETEX a EX", "GETEX a EX 0", "GETEX a PX -1",
+            "GETEX a EX 1 PX 1", "GETEX a KEEPTTL", "GETEX a EX 99999999999999999",
+            "MSET", "MSET a", "MSET a 1 b", "MSETNX \"\" 1", "MGET",
+        ] {
+            assert!(parse_query(input).is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_append_and_strlen() {
+        let mut db = BuckDB::new();
+
+        assert_eq!(run(&mut db, "APPEND greeting hello"), Ok(BuckReply::Integer(5)));
+        assert_eq!(run(&mut db, "APPEND greeting \" world\""), Ok(BuckReply::Integer(11)));
+        assert_eq!(run(&mut db, "GET greeting"), bulk("hello world"));
+        assert_eq!(run(&mut db, "STRLEN greeting"), Ok(BuckReply::Integer(11)));
+        assert_eq!(run(&mut db, "STRLEN missing"), Ok(BuckReply::Integer(0)));
+
+        // lengths are in bytes
+        assert_eq!(run(&mut db, "APPEND greeting é"), Ok(BuckReply::Integer(13)));
+
+        // numbers are read as they are printed
+        run(&mut db, "INSERT count 10").unwrap();
+        assert_eq!(run(&mut db, "STRLEN count"), Ok(BuckReply::Integer(2)));
+        assert_eq!(run(&mut db, "APPEND count 5"), Ok(BuckReply::Integer(3)));
+        assert_eq!(db.get("count"), Ok(&BuckTypes::String("105".to_owned())));
+    }
+
+    #[test]
+    fn test_getrange() {
+        let mut db = BuckDB::new();
+        run(&mut db, "INSERT text \"This is a string\"").unwrap();
+
+        assert_eq!(run(&mut db, "GETRANGE text 0 3"), bulk("This"));
+        assert_eq!(run(&mut db, "GETRANGE text -3 -1"), bulk("ing"));
+        assert_eq!(run(&mut db, "GETRANGE text 0 -1"), bulk("This is a string"));
+        assert_eq!(run(&mut db, "GETRANGE text 10 100"), bulk("string"));
+        assert_eq!(run(&mut db, "GETRANGE text 5 2"), bulk(""));
+        assert_eq!(run(&mut db, "GETRANGE text -100 1"), bulk("Th"));
+        assert_eq!(run(&mut db, "GETRANGE missing 0 -1"), bulk(""));
+
+        assert_eq!(get_range("", 0, -1), "");
+        assert_eq!(get_range("héllo", 0, 1), "h\u{fffd}");
+    }
+
+    #[test]
+    fn test_setrange() {
+        let mut db = BuckDB::new();
+        run(&mut db, "INSERT greeting \"Hello World\"").unwrap();
+
+        assert_eq!(run(&mut db, "SETRANGE greeting 6 Redis"), Ok(BuckReply::Integer(11)));
+        assert_eq!(run(&mut db, "GET greeting"), bulk("Hello Redis"));
+
+        // a missing key is padded with zero bytes
+        assert_eq!(run(&mut db, "SETRANGE padded 3 abc"), Ok(BuckReply::Integer(6)));
+        assert_eq!(run(&mut db, "GET padded"), bulk("\0\0\0abc"));
+
+        // an empty value never creates the key
+        assert_eq!(run(&mut db, "SETRANGE other 5 \"\""), Ok(BuckReply::Integer(0)));
+        assert!(db.get("other").is_err());
+
+        assert_eq!(
+            run(&mut db, "SETRANGE greeting 536870912 x"),
+            Err(BuckError::Type(BuckTypeError::StringTooLong))
+        );
+        assert_eq!(set_range("é", 0, "a"), Err(BuckTypeError::InvalidUtf8));
+        assert_eq!(set_range("é", 0, "è"), Ok("è".to_owned()));
+    }
+
+    #[test]
+    fn test_getset_and_getdel() {
+        let mut db = BuckDB::new();
+
+        assert_eq!(run(&mut db, "GETSET counter 1"), Ok(BuckReply::Nil));
+        assert_eq!(run(&mut db, "GETSET counter 0"), Ok(BuckReply::Integer(1)));
+        assert_eq!(db.get("counter"), Ok(&BuckTypes::Integer(0)));
+
+        assert_eq!(run(&mut db, "GETDEL counter"), Ok(BuckReply::Integer(0)));
+        assert!(db.get("counter").is_err());
+        assert_eq!(run(&mut db, "GETDEL counter"), Ok(BuckReply::Nil));
+    }
+
+    #[test]
+    fn test_getex() {
+        let clock = ManualClock::new(1_000_000);
+        let mut db = BuckDB::with_clock(Arc::new(clock.clone()));
+        run(&mut db, "INSERT session \"abc\"").unwrap();
+
+        assert_eq!(run(&mut db, "GETEX session"), bulk("abc"));
+        assert_eq!(db.pttl("session"), -1);
+
+        assert_eq!(run(&mut db, "GETEX session PX 1500"), bulk("abc"));
+        assert_eq!(db.pttl("session"), 1_500);
+
+        assert_eq!(run(&mut db, "GETEX session PXAT 1000200"), bulk("abc"));
+        assert_eq!(db.pttl("session"), 200);
+
+        assert_eq!(run(&mut db, "GETEX session PERSIST"), bulk("abc"));
+        assert_eq!(db.pttl("session"), -1);
+
+        // a deadline in the past deletes the key once it was read
+        assert_eq!(run(&mut db, "GETEX session EXAT 1"), bulk("abc"));
+        assert!(db.get("session").is_err());
+        assert_eq!(run(&mut db, "GETEX session EX 10"), Ok(BuckReply::Nil));
+    }
+
+    #[test]
+    fn test_mset_and_mget() {
+        let mut db = BuckDB::new();
+        run(&mut db, "RPUSH list 1").unwrap();
+
+        assert_eq!(run(&mut db, "MSET a 1 b \"two words\""), Ok(BuckReply::ok()));
+        assert_eq!(
+            run(&mut db, "MGET a b missing list"),
+            Ok(BuckReply::Array(vec![
+                BuckReply::Integer(1),
+                BuckReply::Bulk("two words".to_owned()),
+                BuckReply::Nil,
+                BuckReply::Nil,
+            ]))
+        );
+        // a single key is still an array
+        assert_eq!(run(&mut db, "MGET a"), Ok(BuckReply::Array(vec![BuckReply::Integer(1)])));
+    }
+
+    #[test]
+    fn test_msetnx_is_all_or_nothing() {
+        let mut db = BuckDB::new();
+        run(&mut db, "INSERT b 2").unwrap();
+
+        assert_eq!(run(&mut db, "MSETNX a 1 b 3"), Ok(BuckReply::Integer(0)));
+        assert!(db.get("a").is_err());
+        assert_eq!(db.get("b"), Ok(&BuckTypes::Integer(2)));
+
+        assert_eq!(run(&mut db, "MSETNX a 1 c 3"), Ok(BuckReply::Integer(1)));
+        assert_eq!(db.get("a"), Ok(&BuckTypes::Integer(1)));
+        assert_eq!(db.get("c"), Ok(&BuckTypes::Integer(3)));
+    }
+
+    #[test]
+    fn test_string_commands_check_the_type() {
+        let mut db = BuckDB::new();
+        run(&mut db, "RPUSH list 1").unwrap();
+        db.commit().unwrap();
+
+        for input in [
+            "APPEND list x", "STRLEN list", "GETRANGE list 0 1", "SETRANGE list 0 x",
+            "GETSET list 1", "GETDEL list", "GETEX list PERSIST",
+        ] {
+            assert_eq!(
+                run(&mut db, input),
+                Err(BuckError::Engine(BuckEngineError::TypeNotSupported("list".to_owned()))),
+                "{}",
+                input
+            );
+        }
+
+        // nothing was staged or removed
+        assert!(db.uncommitted_data.is_empty());
+        assert!(db.data.contains_key("list"));
+    }
+}
@@ -0,0 +1,404 @@
+mod common;
+
+#[cfg(test)]
+mod transaction_tests {
+    use std::sync::Arc;
+
+    use buck::clock::ManualClock;
+    use buck::engine::{BuckDB, TransactionStatus};
+    use buck::errors::{BuckEngineError, BuckError};
+        use buck::parser::parse::parse_query;
+    use buck::parser::query::BuckQuery;
+use buck::reply::BuckReply;
+    use buck::session::BuckSession;
+    use buck::types::types::BuckTypes;
+
+    use crate::common::run_session;
+
+    fn committed_db() -> BuckDB {
+        let mut db = BuckDB::new();
+        db.insert("a".to_owned(), BuckTypes::Integer(1)).unwrap();
+        db.insert("b".to_owned(), BuckTypes::Integer(2)).unwrap();
+        db.commit().unwrap();
+
+        db
+    }
+
+    #[test]
+    fn test_transaction_aliases() {
+        assert_eq!(parse_query("MULTI"), Ok(BuckQuery::Begin));
+        assert_eq!(parse_query("begin"), Ok(BuckQuery::Begin));
+        assert_eq!(parse_query("EXEC"), Ok(BuckQuery::Commit));
+        assert_eq!(parse_query("commit"), Ok(BuckQuery::Commit));
+        assert_eq!(parse_query("DISCARD"), Ok(BuckQuery::Rollback));
+        assert_eq!(parse_query("rollback"), Ok(BuckQuery::Rollback));
+        assert_eq!(
+            parse_query("WATCH a b"),
+            Ok(BuckQuery::Watch(vec!["a".to_owned(), "b".to_owned()]))
+        );
+        assert_eq!(parse_query("UNWATCH"), Ok(BuckQuery::Unwatch));
+        assert!(parse_query("WATCH").is_err());
+    }
+
+    #[test]
+    fn test_multi_queues_until_exec() {
+        let mut db = committed_db();
+        let mut session = BuckSession::new();
+
+        assert_eq!(run_session(&mut db, &mut session, "MULTI"), Ok(BuckReply::ok()));
+        assert_eq!(run_session(&mut db, &mut session, "INSERT c 3"), Ok(BuckReply::Status("QUEUED".to_owned())));
+        assert_eq!(run_session(&mut db, &mut session, "UPDATE a 10"), Ok(BuckReply::Status("QUEUED".to_owned())));
+        assert!(session.in_transaction());
+
+        // nothing has run yet
+        assert!(db.get("c").is_err());
+        assert_eq!(db.get("a"), Ok(&BuckTypes::Integer(1)));
+
+        assert_eq!(
+            run_session(&mut db, &mut session, "EXEC"),
+            Ok(BuckReply::Array(vec![BuckReply::ok(), BuckReply::ok()]))
+        );
+        assert!(!session.in_transaction());
+
+        // applied and committed
+        assert_eq!(db.status, TransactionStatus::Committed);
+        assert_eq!(db.data.get("c"), Some(&BuckTypes::Integer(3)));
+        assert_eq!(db.data.get("a"), Some(&BuckTypes::Integer(10)));
+    }
+
+    #[test]
+    fn test_discard_drops_queue() {
+        let mut db = committed_db();
+        let mut session = BuckSession::new();
+
+        run_session(&mut db, &mut session, "BEGIN").unwrap();
+        run_session(&mut db, &mut session, "INSERT c 3").unwrap();
+        assert_eq!(run_session(&mut db, &mut session, "DISCARD"), Ok(BuckReply::ok()));
+
+        assert!(!session.in_transaction());
+        assert!(db.get("c").is_err());
+        assert!(db.uncommitted_data.is_empty());
+    }
+
+    #[test]
+    fn test_failed_exec_applies_nothing() {
+        let mut db = committed_db();
+        let mut session = BuckSession::new();
+
+        run_session(&mut db, &mut session, "MULTI").unwrap();
+        run_session(&mut db, &mut session, "DEL a").unwrap();
+        run_session(&mut db, &mut session, "INSERT c 3").unwrap();
+        run_session(&mut db, &mut session, "UPDATE missing 4").unwrap();
+
+        assert_eq!(
+            run_session(&mut db, &mut session, "EXEC"),
+            Err(BuckError::Engine(BuckEngineError::KeyNotFound("missing".to_owned())))
+        );
+
+        assert_eq!(db.get("a"), Ok(&BuckTypes::Integer(1)));
+        assert!(db.get("c").is_err());
+        assert_eq!(db.data.len(), 2);
+        assert_eq!(db.status, TransactionStatus::Committed);
+    }
+
+    #[test]
+    fn test_nesting_and_watch_inside_multi_are_rejected() {
+        let mut db = committed_db();
+        let mut session = BuckSession::new();
+
+        run_session(&mut db, &mut session, "MULTI").unwrap();
+        assert_eq!(
+            run_session(&mut db, &mut session, "MULTI"),
+            Err(BuckError::Engine(BuckEngineError::NestedTransaction))
+        );
+        assert_eq!(
+            run_session(&mut db, &mut session, "WATCH a"),
+            Err(BuckError::Engine(BuckEngineError::WatchInsideTransaction))
+        );
+
+        // the transaction is still open and usable
+        run_session(&mut db, &mut session, "INSERT c 3").unwrap();
+        assert!(run_session(&mut db, &mut session, "EXEC").is_ok());
+        assert_eq!(db.get("c"), Ok(&BuckTypes::Integer(3)));
+    }
+
+    #[test]
+    fn test_queries_outside_multi_commit_right_away() {
+        let mut db = committed_db();
+        let mut session = BuckSession::new();
+
+        run_session(&mut db, &mut session, "UPDATE a 10").unwrap();
+        run_session(&mut db, &mut session, "DEL b").unwrap();
+        run_session(&mut db, &mut session, "INSERT c 3").unwrap();
+        assert_eq!(db.status, TransactionStatus::Committed);
+        assert_eq!(db.data.get("a"), Some(&BuckTypes::Integer(10)));
+        assert_eq!(db.data.get("b"), None);
+        assert_eq!(db.data.get("c"), Some(&BuckTypes::Integer(3)));
+
+        // there is nothing to commit or roll back without MULTI
+        for input in ["EXEC", "COMMIT", "DISCARD", "ROLLBACK"] {
+            assert_eq!(run_session(&mut db, &mut session, input).unwrap_err().code(), "NO_TRANSACTION");
+        }
+
+        // a commit makes the changes permanent
+        db.remove("a").unwrap();
+        db.commit().unwrap();
+        db.abort().unwrap();
+        assert!(db.get("a").is_err());
+    }
+
+    #[test]
+    fn test_exec_leaves_writes_staged_on_the_database() {
+        let mut db = committed_db();
+        let mut bob = BuckSession::new();
+
+        db.insert("x".to_owned(), BuckTypes::Integer(1)).unwrap();
+
+        // a failed EXEC only rolls back what the queue changed
+        run_session(&mut db, &mut bob, "MULTI").unwrap();
+        run_session(&mut db, &mut bob, "INSERT c 3").unwrap();
+        run_session(&mut db, &mut bob, "UPDATE missing 4").unwrap();
+        assert!(run_session(&mut db, &mut bob, "EXEC").is_err());
+
+        assert_eq!(db.get("x"), Ok(&BuckTypes::Integer(1)));
+        assert!(db.get("c").is_err());
+
+        // and a successful EXEC only commits what the queue changed
+        run_session(&mut db, &mut bob, "MULTI").unwrap();
+        run_session(&mut db, &mut bob, "INSERT d 4").unwrap();
+        assert!(run_session(&mut db, &mut bob, "EXEC").is_ok());
+        assert_eq!(db.data.get("d"), Some(&BuckTypes::Integer(4)));
+        assert_eq!(db.data.get("x"), None);
+        assert_eq!(db.uncommitted_data.get("x"), Some(&BuckTypes::Integer(1)));
+    }
+
+    #[test]
+    fn test_closed_session_keeps_its_writes() {
+        let mut db = committed_db();
+        let mut alice = BuckSession::new();
+        let mut bob = BuckSession::new();
+
+        run_session(&mut db, &mut alice, "RPUSH q 1").unwrap();
+        run_session(&mut db, &mut bob, "RPUSH q 2").unwrap();
+        run_session(&mut db, &mut bob, "LPOP q").unwrap();
+        run_session(&mut db, &mut bob, "MULTI").unwrap();
+        run_session(&mut db, &mut bob, "DEL a").unwrap();
+
+        // only the queued query is dropped, the popped element stays popped
+        bob.close(&mut db);
+        assert_eq!(db.l_range("q", 0, -1), Ok(vec![BuckTypes::Integer(2)]));
+        assert_eq!(db.data.get("a"), Some(&BuckTypes::Integer(1)));
+        assert_eq!(db.status, TransactionStatus::Committed);
+    }
+
+    #[test]
+    fn test_other_clients_only_read_committed_writes() {
+        let mut db = committed_db();
+        let mut alice = BuckSession::new();
+        let mut bob = BuckSession::new();
+
+        run_session(&mut db, &mut alice, "MULTI").unwrap();
+        run_session(&mut db, &mut alice, "INSERT k 1").unwrap();
+        run_session(&mut db, &mut alice, "UPDATE a 10").unwrap();
+        assert_eq!(run_session(&mut db, &mut bob, "GET k"), Ok(BuckReply::Nil));
+
+        // a failed EXEC leaves nothing behind to read
+        run_session(&mut db, &mut alice, "UPDATE missing 1").unwrap();
+        assert!(run_session(&mut db, &mut alice, "EXEC").is_err());
+        assert_eq!(run_session(&mut db, &mut bob, "GET k"), Ok(BuckReply::Nil));
+        assert_eq!(run_session(&mut db, &mut bob, "GET a"), Ok(BuckReply::Integer(1)));
+
+        run_session(&mut db, &mut alice, "MULTI").unwrap();
+        run_session(&mut db, &mut alice, "INSERT k 1").unwrap();
+        run_session(&mut db, &mut alice, "EXEC").unwrap();
+        assert_eq!(run_session(&mut db, &mut bob, "GET k"), Ok(BuckReply::Integer(1)));
+        assert!(db.uncommitted_data.is_empty());
+    }
+
+    #[test]
+    fn test_rollback_keeps_shards_in_sync() {
+        let mut db = BuckDB::new();
+        db.enable_sharding(4).unwrap();
+        db.insert("a".to_owned(), BuckTypes::Integer(1)).unwrap();
+        db.commit().unwrap();
+
+        db.remove("a").unwrap();
+        db.insert("b".to_owned(), BuckTypes::Integer(2)).unwrap();
+        db.abort().unwrap();
+
+        assert!(db.shards.iter().any(|shard| shard.get("a").is_ok()));
+        assert!(db.shards.iter().all(|shard| shard.get("b").is_err()));
+    }
+
+    #[test]
+    fn test_watch_conflict_on_write_by_other_client() {
+        let mut db = committed_db();
+        let mut alice = BuckSession::new();
+        let mut bob = BuckSession::new();
+
+        run_session(&mut db, &mut alice, "WATCH a").unwrap();
+        run_session(&mut db, &mut alice, "MULTI").unwrap();
+        run_session(&mut db, &mut alice, "INSERT a 100").unwrap();
+
+        run_session(&mut db, &mut bob, "UPDATE a 50").unwrap();
+
+        assert_eq!(
+            run_session(&mut db, &mut alice, "EXEC"),
+            Err(BuckError::Engine(BuckEngineError::WatchConflict("a".to_owned())))
+        );
+        assert_eq!(db.get("a"), Ok(&BuckTypes::Integer(50)));
+    }
+
+    #[test]
+    fn test_watch_conflict_on_delete() {
+        let mut db = committed_db();
+        let mut alice = BuckSession::new();
+
+        run_session(&mut db, &mut alice, "WATCH a").unwrap();
+        db.remove("a").unwrap();
+        run_session(&mut db, &mut alice, "MULTI").unwrap();
+        run_session(&mut db, &mut alice, "INSERT c 3").unwrap();
+
+        assert!(matches!(
+            run_session(&mut db, &mut alice, "EXEC"),
+            Err(BuckError::Engine(BuckEngineError::WatchConflict(_)))
+        ));
+        assert!(db.get("c").is_err());
+    }
+
+    #[test]
+    fn test_watch_conflict_on_creation_of_missing_key() {
+        let mut db = committed_db();
+        let mut alice = BuckSession::new();
+
+        run_session(&mut db, &mut alice, "WATCH fresh").unwrap();
+        db.insert("fresh".to_owned(), BuckTypes::Integer(1)).unwrap();
+        run_session(&mut db, &mut alice, "MULTI").unwrap();
+
+        assert!(matches!(
+            run_session(&mut db, &mut alice, "EXEC"),
+            Err(BuckError::Engine(BuckEngineError::WatchConflict(_)))
+        ));
+    }
+
+    #[test]
+    fn test_watch_conflict_on_expiration() {
+        let clock = ManualClock::new(1_000);
+        let mut db = BuckDB::with_clock(Arc::new(clock.clone()));
+        db.insert("a".to_owned(), BuckTypes::Integer(1)).unwrap();
+        db.commit().unwrap();
+
+        // a new timeout is a change
+        let mut alice = BuckSession::new();
+        run_session(&mut db, &mut alice, "WATCH a").unwrap();
+        db.expire("a", 100).unwrap();
+        run_session(&mut db, &mut alice, "MULTI").unwrap();
+        assert!(run_session(&mut db, &mut alice, "EXEC").is_err());
+
+        // and so is the key expiring, even if nobody read it
+        run_session(&mut db, &mut alice, "WATCH a").unwrap();
+        clock.advance(100);
+        run_session(&mut db, &mut alice, "MULTI").unwrap();
+        assert!(matches!(
+            run_session(&mut db, &mut alice, "EXEC"),
+            Err(BuckError::Engine(BuckEngineError::WatchConflict(_)))
+        ));
+    }
+
+    #[test]
+    fn test_watch_conflict_on_rollback_of_staged_write() {
+        let mut db = committed_db();
+        let mut alice = BuckSession::new();
+
+        db.insert("a".to_owned(), BuckTypes::Integer(5)).unwrap();
+        run_session(&mut db, &mut alice, "WATCH a").unwrap();
+        db.abort().unwrap();
+
+        run_session(&mut db, &mut alice, "MULTI").unwrap();
+        assert!(run_session(&mut db, &mut alice, "EXEC").is_err());
+    }
+
+    #[test]
+    fn test_no_conflict_when_watched_keys_are_untouched() {
+        let mut db = committed_db();
+        let mut alice = BuckSession::new();
+
+        run_session(&mut db, &mut alice, "WATCH a").unwrap();
+        db.insert("b".to_owned(), BuckTypes::Integer(20)).unwrap();
+        db.get("a").unwrap();
+
+        run_session(&mut db, &mut alice, "MULTI").unwrap();
+        run_session(&mut db, &mut alice, "UPDATE a 10").unwrap();
+        assert!(run_session(&mut db, &mut alice, "EXEC").is_ok());
+        assert_eq!(db.data.get("a"), Some(&BuckTypes::Integer(10)));
+    }
+
+    #[test]
+    fn test_unwatch_and_exec_release_watches() {
+        let mut db = committed_db();
+        let mut alice = BuckSession::new();
+
+        run_session(&mut db, &mut alice, "WATCH a").unwrap();
+        assert_eq!(run_session(&mut db, &mut alice, "UNWATCH"), Ok(BuckReply::ok()));
+        db.insert("a".to_owned(), BuckTypes::Integer(5)).unwrap();
+        run_session(&mut db, &mut alice, "MULTI").unwrap();
+        assert!(run_session(&mut db, &mut alice, "EXEC").is_ok());
+
+        // EXEC clears the watches, even when it fails
+        run_session(&mut db, &mut alice, "WATCH a").unwrap();
+        db.insert("a".to_owned(), BuckTypes::Integer(6)).unwrap();
+        run_session(&mut db, &mut alice, "MULTI").unwrap();
+        assert!(run_session(&mut db, &mut alice, "EXEC").is_err());
+
+        db.insert("a".to_owned(), BuckTypes::Integer(7)).unwrap();
+        run_session(&mut db, &mut alice, "MULTI").unwrap();
+        assert!(run_session(&mut db, &mut alice, "EXEC").is_ok());
+        assert_eq!(db.key_version("a"), 0);
+    }
+
+    #[test]
+    fn test_first_exec_wins() {
+        let mut db = committed_db();
+        let mut alice = BuckSession::new();
+        let mut bob = BuckSession::new();
+
+        for session in [&mut alice, &mut bob] {
+            run_session(&mut db, session, "WATCH a").unwrap();
+            run_session(&mut db, session, "MULTI").unwrap();
+        }
+        run_session(&mut db, &mut alice, "UPDATE a 10").unwrap();
+        run_session(&mut db, &mut bob, "UPDATE a 20").unwrap();
+
+        assert!(run_session(&mut db, &mut alice, "EXEC").is_ok());
+        assert!(matches!(
+            run_session(&mut db, &mut bob, "EXEC"),
+            Err(BuckError::Engine(BuckEngineError::WatchConflict(_)))
+        ));
+        assert_eq!(db.get("a"), Ok(&BuckTypes::Integer(10)));
+    }
+
+    #[test]
+    fn test_closed_session_releases_watches() {
+        let mut db = committed_db();
+        let mut alice = BuckSession::new();
+
+        run_session(&mut db, &mut alice, "WATCH a b").unwrap();
+        db.insert("a".to_owned(), BuckTypes::Integer(5)).unwrap();
+        assert_ne!(db.key_version("a"), 0);
+
+        alice.close(&mut db);
+        assert_eq!(db.key_version("a"), 0);
+        assert!(!alice.in_transaction());
+    }
+
+    #[test]
+    fn test_watch_without_session() {
+        let mut db = committed_db();
+        let query = "WATCH a";
+
+        assert_eq!(
+            parse_query(query).unwrap().execute(query, &mut db),
+            Err(BuckError::Engine(BuckEngineError::SessionRequired("WATCH".to_owned())))
+        );
+    }
+}